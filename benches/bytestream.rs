@@ -15,7 +15,7 @@ fn make_input(target: usize) -> String {
     s
 }
 
-/// Eager decode: how long `from_str` (buffer -> chars + offsets + line_starts) takes.
+/// Decode: how long `from_str` (UTF-8 validation + line_starts) takes.
 fn decode(c: &mut Criterion) {
     let mut group = c.benchmark_group("bytestream/decode");
     for &size in &[16 * 1024usize, 1024 * 1024, 8 * 1024 * 1024] {
@@ -50,8 +50,51 @@ fn iterate(c: &mut Criterion) {
     group.finish();
 }
 
+/// Lazy vs. eager decoding: UTF-8 input is read straight from the raw bytes, other encodings
+/// are decoded into a second buffer up front.
+fn decode_encodings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bytestream/decode_encoding");
+    let input = make_input(1024 * 1024);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    for name in ["utf8", "latin1"] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let encoding = if name == "utf8" {
+                    Encoding::UTF8
+                } else {
+                    Encoding::Latin1
+                };
+                let mut stream = ByteStream::from_str(black_box(&input), encoding);
+                while !stream.eof() {
+                    black_box(stream.read_and_next());
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Iteration with a location lookup per character, as the tokenizer does for its tokens.
+/// Columns are counted on the fly from the raw bytes.
+fn iterate_with_location(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bytestream/location");
+    let input = make_input(1024 * 1024);
+    let mut stream = ByteStream::from_str(&input, Encoding::UTF8);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function(format!("{}KB", input.len() / 1024), |b| {
+        b.iter(|| {
+            stream.reset_stream();
+            while !stream.eof() {
+                black_box(stream.read_and_next());
+                black_box(stream.location());
+            }
+        });
+    });
+    group.finish();
+}
+
 /// Streaming append: feed the input in fixed-size chunks via append_str.
-/// Exposes the O(n^2) full re-decode on every append.
+/// Each append only validates the new bytes.
 fn append_chunks(c: &mut Criterion) {
     let mut group = c.benchmark_group("bytestream/append");
     let input = make_input(1024 * 1024);
@@ -76,5 +119,12 @@ fn append_chunks(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, decode, iterate, append_chunks);
+criterion_group!(
+    benches,
+    decode,
+    decode_encodings,
+    iterate,
+    iterate_with_location,
+    append_chunks
+);
 criterion_main!(benches);
@@ -5,19 +5,28 @@
 //! system libraries (skia-safe is statically linked). The page is rasterized into small
 //! cached tiles (`ExternalHandle::TileCache`) which we composite here, so there is no
 //! GPU texture-size limit and pages of any height can be captured.
+//!
+//! `--selector` and `--clip` capture part of the page instead of all of it, `--wait-for` delays
+//! the capture until an element exists, and `--dpr` captures at a device pixel ratio above 1.
+//! The image format follows the output extension (PNG, JPEG or WebP) unless `--format` is given.
+//!
+//! With `--pdf` the page is printed instead (`@media print`, `@page` size and margins, page
+//! breaks) and saved as a PDF. That needs a backend with vector output: build with
+//! `--features backend_cairo`; the Skia backend reports printing as unsupported.
 
-use clap::Parser;
-use gosub_engine::events::{EngineEvent, NavigationEvent, TabCommand};
+use clap::{Parser, ValueEnum};
+use gosub_engine::events::{ElementRect, EngineEvent, NavigationEvent, TabCommand};
 use gosub_engine::storage::{InMemorySessionStore, PartitionPolicy, SqliteLocalStore, StorageService};
-use gosub_engine::tab::{TabDefaults, TabId};
+use gosub_engine::tab::{TabDefaults, TabHandle, TabId};
 use gosub_engine::zone::{ZoneConfig, ZoneId, ZoneServices};
 use gosub_engine::DefaultRenderConfig;
 use gosub_engine::GosubEngine;
 use gosub_render_pipeline::render::backend::ExternalHandle;
-use gosub_render_pipeline::render::DefaultCompositor;
+use gosub_render_pipeline::render::{DefaultCompositor, DEVICE_PIXEL_RATIO};
 #[cfg(all(feature = "backend_skia", not(feature = "backend_cairo")))]
 use gosub_renderer_skia::{SkiaBackend, SkiaFontSystem};
-use image::ColorType;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, RgbaImage};
 
 #[cfg(feature = "backend_cairo")]
 use gosub_renderer_cairo::{CairoBackend, PangoFontSystem};
@@ -50,7 +59,7 @@ type AppConfig = DefaultRenderConfig<CairoBackend, PangoFontSystem>;
 struct Args {
     /// URL to capture (https:// is prepended if no scheme is given)
     url: String,
-    /// Output PNG path
+    /// Output path (PNG, JPEG or WebP; PDF with `--pdf`)
     #[arg(default_value = "screenshot.png")]
     output: String,
     /// Viewport width in CSS pixels
@@ -66,6 +75,81 @@ struct Args {
     /// decode and repaint before the capture
     #[arg(long, default_value = "0")]
     settle: u64,
+    /// Print the page to PDF instead of taking a screenshot (requires the Cairo backend)
+    #[arg(long)]
+    pdf: bool,
+    /// Capture only the border box of the first element matching this CSS selector
+    #[arg(long, conflicts_with = "clip")]
+    selector: Option<String>,
+    /// Capture only this region of the page, as `x,y,width,height` in CSS pixels
+    #[arg(long, value_parser = parse_clip)]
+    clip: Option<Clip>,
+    /// Wait (up to --render-timeout) until an element matching this CSS selector exists
+    #[arg(long)]
+    wait_for: Option<String>,
+    /// Image format; inferred from the output extension when omitted
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+    /// Encoding quality 1-100. Applies to JPEG; WebP is always written lossless
+    #[arg(long, default_value = "90", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Device pixel ratio to capture at (2 = HiDPI)
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..=4))]
+    dpr: u32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    /// Format implied by the output path's extension; PNG when it names none we know.
+    fn from_path(path: &str) -> Self {
+        let ext = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg") {
+            OutputFormat::Jpeg
+        } else if ext.eq_ignore_ascii_case("webp") {
+            OutputFormat::Webp
+        } else {
+            OutputFormat::Png
+        }
+    }
+}
+
+/// A capture region in CSS pixels.
+#[derive(Clone, Copy, Debug)]
+struct Clip {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+fn parse_clip(value: &str) -> Result<Clip, String> {
+    let parts: Vec<f64> = value
+        .split(',')
+        .map(|p| {
+            p.trim()
+                .parse::<f64>()
+                .map_err(|e| format!("invalid number {p:?}: {e}"))
+        })
+        .collect::<Result<_, _>>()?;
+    match parts.as_slice() {
+        [x, y, width, height] if *width > 0.0 && *height > 0.0 && *x >= 0.0 && *y >= 0.0 => Ok(Clip {
+            x: *x,
+            y: *y,
+            width: *width,
+            height: *height,
+        }),
+        [_, _, _, _] => Err("x and y must be non-negative, width and height positive".to_string()),
+        _ => Err("expected x,y,width,height".to_string()),
+    }
 }
 
 const DEFAULT_ZONE: uuid::Uuid = uuid!("f1234567-abcd-4000-8000-000000000003");
@@ -105,6 +189,10 @@ fn main() {
     let url = Url::parse(&url_str).expect("invalid URL");
 
     // ── Engine setup (CPU backend - no GPU) ───────────────────────────────────
+    // The backends rasterize tiles at this ratio; set it before any rendering starts, the way a
+    // windowed host reports its display's scale factor.
+    DEVICE_PIXEL_RATIO.store(args.dpr, std::sync::atomic::Ordering::Relaxed);
+
     #[cfg(all(feature = "backend_skia", not(feature = "backend_cairo")))]
     let backend = SkiaBackend::new();
     #[cfg(feature = "backend_cairo")]
@@ -224,11 +312,38 @@ fn main() {
         std::thread::sleep(Duration::from_millis(50));
     }
 
+    if let Some(selector) = &args.wait_for {
+        let deadline = Instant::now() + render_budget;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match query_elements(&tab, tab_id, &mut event_rx, selector, remaining) {
+                Ok(found) if !found.is_empty() => break,
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("--wait-for {selector:?}: {e}");
+                    std::process::exit(1);
+                }
+            }
+            if Instant::now() >= deadline {
+                eprintln!("Timeout waiting for {selector:?} ({}s)", args.render_timeout);
+                std::process::exit(1);
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        // Let the frame that rendered the element reach the compositor.
+        while rx_redraw.try_recv().is_ok() {}
+    }
+
     if args.settle > 0 {
         std::thread::sleep(Duration::from_secs(args.settle));
         while rx_redraw.try_recv().is_ok() {}
     }
 
+    if args.pdf {
+        print_to_pdf(&tab, tab_id, &mut event_rx, &output, render_budget);
+        return;
+    }
+
     let phase1_handle = compositor.frame_for(tab_id);
     let mut tile_cache_handle: Option<ExternalHandle> = match phase1_handle {
         Some(h @ ExternalHandle::TileCache { .. }) => Some(h),
@@ -259,31 +374,56 @@ fn main() {
         std::thread::sleep(Duration::from_millis(20));
     }
 
-    // ── Phase 3: composite tiles into a full-page PNG ────────────────────────
-    let (tiles, page_height_f) = match tile_cache_handle {
-        Some(ExternalHandle::TileCache { tiles, page_height, .. }) => (tiles, page_height),
+    // ── Phase 3: composite tiles into a full-page image ──────────────────────
+    let (tiles, page_height_f, dpr) = match tile_cache_handle {
+        Some(ExternalHandle::TileCache {
+            tiles,
+            page_height,
+            dpr,
+            ..
+        }) => (tiles, page_height, dpr.max(1)),
         _ => {
             eprintln!("No TileCache frame available — nothing was rendered.");
             std::process::exit(1);
         }
     };
 
-    let page_w = viewport_w;
-    let page_h = (page_height_f.ceil() as u32).max(1);
+    // Region to keep, in CSS pixels; resolved before compositing so a bad selector fails fast.
+    let region = match (&args.selector, args.clip) {
+        (Some(selector), _) => {
+            let found = match query_elements(&tab, tab_id, &mut event_rx, selector, Duration::from_secs(5)) {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!("--selector {selector:?}: {e}");
+                    std::process::exit(1);
+                }
+            };
+            match found.iter().find_map(|el| el.border_box) {
+                Some(rect) => Some(Clip {
+                    x: rect.x,
+                    y: rect.y,
+                    width: rect.width,
+                    height: rect.height,
+                }),
+                None => {
+                    eprintln!("No rendered element matches {selector:?}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        (None, clip) => clip,
+    };
 
-    eprintln!(
-        "Page size: {}×{} px. Compositing {} tile(s)…",
-        page_w,
-        page_h,
-        tiles.len()
-    );
+    let page_w = viewport_w * dpr;
+    let page_h = ((page_height_f * dpr as f32).ceil() as u32).max(1);
 
     // Fill with opaque white, then alpha-blend each tile (premultiplied).
     let mut pixels = vec![255u8; (page_w * page_h * 4) as usize];
 
     for tile in tiles.iter() {
-        let tx = tile.page_x as u32;
-        let ty = tile.page_y as u32;
+        // Tile positions are CSS pixels; tile sizes are already physical.
+        let tx = (tile.page_x * dpr as f32).round() as u32;
+        let ty = (tile.page_y * dpr as f32).round() as u32;
         if tx >= page_w || ty >= page_h {
             continue;
         }
@@ -336,6 +476,111 @@ fn main() {
         }
     }
 
-    image::save_buffer(&output, &pixels, page_w, page_h, ColorType::Rgba8).expect("save PNG");
-    eprintln!("Saved {output} ({}×{})", page_w, page_h);
+    let Some(page) = RgbaImage::from_raw(page_w, page_h, pixels) else {
+        eprintln!("Composited buffer does not match the page size");
+        std::process::exit(1);
+    };
+    let image = match region {
+        Some(clip) => {
+            let x = ((clip.x * dpr as f64).round() as u32).min(page_w.saturating_sub(1));
+            let y = ((clip.y * dpr as f64).round() as u32).min(page_h.saturating_sub(1));
+            let w = ((clip.width * dpr as f64).round() as u32).clamp(1, page_w - x);
+            let h = ((clip.height * dpr as f64).round() as u32).clamp(1, page_h - y);
+            image::imageops::crop_imm(&page, x, y, w, h).to_image()
+        }
+        None => page,
+    };
+
+    let format = args.format.unwrap_or_else(|| OutputFormat::from_path(&output));
+    let (out_w, out_h) = image.dimensions();
+    if let Err(e) = save_image(image, &output, format, args.quality) {
+        eprintln!("Failed to save {output}: {e}");
+        std::process::exit(1);
+    }
+    eprintln!("Saved {output} ({out_w}×{out_h})");
+}
+
+/// Encodes `image` (opaque RGBA) to `path` in `format`.
+fn save_image(image: RgbaImage, path: &str, format: OutputFormat, quality: u8) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Png => image.save_with_format(path, ImageFormat::Png)?,
+        // image's WebP encoder is lossless-only, so `quality` does not apply.
+        OutputFormat::Webp => image.save_with_format(path, ImageFormat::WebP)?,
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel; the composited page is opaque anyway.
+            let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(file, quality))?;
+        }
+    }
+    Ok(())
+}
+
+/// Asks the tab for the elements matching `selector` and waits up to `timeout` for the answer.
+fn query_elements(
+    tab: &TabHandle,
+    tab_id: TabId,
+    event_rx: &mut tokio::sync::broadcast::Receiver<EngineEvent>,
+    selector: &str,
+    timeout: Duration,
+) -> Result<Vec<ElementRect>, String> {
+    let tab_query = tab.clone();
+    let query = selector.to_string();
+    TOKIO_RT.spawn(async move {
+        let _ = tab_query.send(TabCommand::GetElementRects { selector: query }).await;
+    });
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        loop {
+            match event_rx.try_recv() {
+                Ok(EngineEvent::ElementRects {
+                    tab_id: tid,
+                    selector: answered,
+                    result,
+                }) if tid == tab_id && answered == selector => return result,
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Err("no answer from the engine".to_string())
+}
+
+/// Prints the loaded page to PDF and writes it to `output`. Exits the process on failure.
+fn print_to_pdf(
+    tab: &TabHandle,
+    tab_id: TabId,
+    event_rx: &mut tokio::sync::broadcast::Receiver<EngineEvent>,
+    output: &str,
+    timeout: Duration,
+) {
+    let tab_print = tab.clone();
+    TOKIO_RT.spawn(async move {
+        let _ = tab_print.send(TabCommand::PrintToPdf).await;
+    });
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        loop {
+            match event_rx.try_recv() {
+                Ok(EngineEvent::PdfPrinted { tab_id: tid, pdf }) if tid == tab_id => {
+                    std::fs::write(output, &pdf).expect("save PDF");
+                    eprintln!("Saved {output} ({} bytes)", pdf.len());
+                    return;
+                }
+                Ok(EngineEvent::PrintFailed { tab_id: tid, message }) if tid == tab_id => {
+                    eprintln!("Printing failed: {message}");
+                    std::process::exit(1);
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    eprintln!("Timeout waiting for the PDF ({}s)", timeout.as_secs());
+    std::process::exit(1);
 }
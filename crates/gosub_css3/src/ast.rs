@@ -1,11 +1,16 @@
+use core::slice;
 use cow_utils::CowUtils;
 use log::warn;
 
+use crate::layer::anonymous_layer;
+use crate::media::MediaQueryList;
 use crate::node::{Node as CssNode, NodeType};
+use crate::page::{self, PageRule};
 use crate::stylesheet::{
-    AttributeSelector, Combinator, CssDeclaration, CssRule, CssSelector, CssSelectorPart, CssStylesheet, CssValue,
-    FontFace, MatcherType,
+    AttributeSelector, Combinator, CssDeclaration, CssImport, CssPageRule, CssRule, CssSelector, CssSelectorPart,
+    CssStylesheet, CssValue, FontFace, MatcherType,
 };
+use crate::supports::supports;
 use gosub_interface::css3::CssOrigin;
 use gosub_shared::errors::{CssError, CssResult};
 
@@ -61,10 +66,99 @@ vs
     h4 { color: rebeccapurple; }
 */
 
-fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
+/// Converts a single (non-comma) node of a selector into a selector part
+fn selector_part(node: &CssNode) -> CssResult<CssSelectorPart> {
+    let part = match &*node.node_type {
+        NodeType::Ident { value } => CssSelectorPart::Type(value.clone()),
+        NodeType::ClassSelector { value } => CssSelectorPart::Class(value.clone()),
+        NodeType::Combinator { value } => {
+            let combinator = match value.as_str() {
+                ">" => Combinator::Child,
+                "+" => Combinator::NextSibling,
+                "~" => Combinator::SubsequentSibling,
+                " " => Combinator::Descendant,
+                "||" => Combinator::Column,
+                "|" => Combinator::Namespace,
+                _ => return Err(CssError::new(format!("Unknown combinator: {value}").as_str())),
+            };
+
+            CssSelectorPart::Combinator(combinator)
+        }
+        NodeType::IdSelector { value } => CssSelectorPart::Id(value.clone()),
+        NodeType::TypeSelector { value, .. } if value == "*" => CssSelectorPart::Universal,
+        NodeType::PseudoClassSelector { value } => match &*value.node_type {
+            NodeType::Ident { value } if value.eq_ignore_ascii_case("host") => CssSelectorPart::Host(None),
+            NodeType::Function { name, arguments } if name == "host" => {
+                CssSelectorPart::Host(Some(compound_argument(arguments.first())?))
+            }
+            _ => CssSelectorPart::PseudoClass(value.to_string()),
+        },
+        NodeType::PseudoElementSelector { value, argument } if value.eq_ignore_ascii_case("slotted") => {
+            CssSelectorPart::Slotted(compound_argument(argument.as_ref())?)
+        }
+        NodeType::PseudoElementSelector { value, .. } => CssSelectorPart::PseudoElement(value.to_string()),
+        NodeType::TypeSelector { value, .. } => CssSelectorPart::Type(value.clone()),
+        NodeType::AttributeSelector {
+            name,
+            value,
+            flags,
+            matcher,
+        } => {
+            let matcher = match matcher {
+                None => MatcherType::None,
+
+                Some(matcher) => {
+                    if let NodeType::Operator(op) = &*matcher.node_type {
+                        match op.as_str() {
+                            "=" => MatcherType::Equals,
+                            "~=" => MatcherType::Includes,
+                            "|=" => MatcherType::DashMatch,
+                            "^=" => MatcherType::PrefixMatch,
+                            "$=" => MatcherType::SuffixMatch,
+                            "*=" => MatcherType::SubstringMatch,
+                            _ => {
+                                warn!("Unsupported matcher: {matcher:?}");
+                                MatcherType::Equals
+                            }
+                        }
+                    } else {
+                        warn!("Unsupported matcher: {matcher:?}");
+                        MatcherType::Equals
+                    }
+                }
+            };
+
+            CssSelectorPart::Attribute(Box::new(AttributeSelector {
+                name: name.clone(),
+                matcher,
+                value: value.clone(),
+                case_insensitive: flags.eq_ignore_ascii_case("i"),
+            }))
+        }
+        _ => {
+            return Err(CssError::new(
+                format!("Unsupported selector part: {:?}", node.node_type).as_str(),
+            ));
+        }
+    };
+    Ok(part)
+}
+
+/// Converts the compound selector argument of `:host()` or `::slotted()` into selector parts
+fn compound_argument(argument: Option<&CssNode>) -> CssResult<Vec<CssSelectorPart>> {
+    let children = argument
+        .and_then(CssNode::as_selector)
+        .ok_or_else(|| CssError::new("Expected a compound selector argument"))?;
+    children.iter().map(selector_part).collect()
+}
+
+fn collect_rule(node: &CssNode, media: &[MediaQueryList], layers: &[String]) -> CssResult<Option<CssRule>> {
     let mut rule = CssRule {
         selectors: vec![],
         declarations: vec![],
+        media: media.to_vec(),
+        layers: layers.to_vec(),
+        location: node.location,
     };
 
     let Some((prelude, declarations)) = node.as_rule() else {
@@ -82,74 +176,11 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
             };
 
             for node in selector_children {
-                let part = match &*node.node_type {
-                    NodeType::Ident { value } => CssSelectorPart::Type(value.clone()),
-                    NodeType::ClassSelector { value } => CssSelectorPart::Class(value.clone()),
-                    NodeType::Combinator { value } => {
-                        let combinator = match value.as_str() {
-                            ">" => Combinator::Child,
-                            "+" => Combinator::NextSibling,
-                            "~" => Combinator::SubsequentSibling,
-                            " " => Combinator::Descendant,
-                            "||" => Combinator::Column,
-                            "|" => Combinator::Namespace,
-                            _ => return Err(CssError::new(format!("Unknown combinator: {value}").as_str())),
-                        };
-
-                        CssSelectorPart::Combinator(combinator)
-                    }
-                    NodeType::IdSelector { value } => CssSelectorPart::Id(value.clone()),
-                    NodeType::TypeSelector { value, .. } if value == "*" => CssSelectorPart::Universal,
-                    NodeType::PseudoClassSelector { value, .. } => CssSelectorPart::PseudoClass(value.to_string()),
-                    NodeType::PseudoElementSelector { value, .. } => CssSelectorPart::PseudoElement(value.to_string()),
-                    NodeType::TypeSelector { value, .. } => CssSelectorPart::Type(value.clone()),
-                    NodeType::AttributeSelector {
-                        name,
-                        value,
-                        flags,
-                        matcher,
-                    } => {
-                        let matcher = match matcher {
-                            None => MatcherType::None,
-
-                            Some(matcher) => {
-                                if let NodeType::Operator(op) = &*matcher.node_type {
-                                    match op.as_str() {
-                                        "=" => MatcherType::Equals,
-                                        "~=" => MatcherType::Includes,
-                                        "|=" => MatcherType::DashMatch,
-                                        "^=" => MatcherType::PrefixMatch,
-                                        "$=" => MatcherType::SuffixMatch,
-                                        "*=" => MatcherType::SubstringMatch,
-                                        _ => {
-                                            warn!("Unsupported matcher: {matcher:?}");
-                                            MatcherType::Equals
-                                        }
-                                    }
-                                } else {
-                                    warn!("Unsupported matcher: {matcher:?}");
-                                    MatcherType::Equals
-                                }
-                            }
-                        };
-
-                        CssSelectorPart::Attribute(Box::new(AttributeSelector {
-                            name: name.clone(),
-                            matcher,
-                            value: value.clone(),
-                            case_insensitive: flags.eq_ignore_ascii_case("i"),
-                        }))
-                    }
-                    NodeType::Comma => {
-                        selector.parts.push(vec![]);
-                        continue;
-                    }
-                    _ => {
-                        return Err(CssError::new(
-                            format!("Unsupported selector part: {:?}", node.node_type).as_str(),
-                        ));
-                    }
-                };
+                if let NodeType::Comma = &*node.node_type {
+                    selector.parts.push(vec![]);
+                    continue;
+                }
+                let part = selector_part(node)?;
                 if let Some(x) = selector.parts.last_mut() {
                     x.push(part);
                 } else {
@@ -201,21 +232,61 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
     Ok(Some(rule))
 }
 
-fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut Vec<FontFace>) -> CssResult<()> {
+fn collect_rules(
+    nodes: &[CssNode],
+    sheet: &mut CssStylesheet,
+    media: &[MediaQueryList],
+    layers: &[String],
+) -> CssResult<()> {
     for node in nodes {
         match &*node.node_type {
             NodeType::Rule { .. } => {
-                if let Some(rule) = collect_rule(node)? {
-                    rules.push(rule);
+                if let Some(rule) = collect_rule(node, media, layers)? {
+                    sheet.rules.push(rule);
                 }
             }
             NodeType::AtRule {
                 name,
+                prelude,
                 block: Some(block),
-                ..
             } if name.eq_ignore_ascii_case("layer") => {
+                // The rules are flattened into the sheet, remembering the layer they are in
+                let layer = layer_names(prelude.as_ref())
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(anonymous_layer);
+                let mut nested = layers.to_vec();
+                nested.push(layer);
+                declare_layer(sheet, &nested);
                 if let Some(children) = block.as_block() {
-                    collect_rules(children, rules, font_faces)?;
+                    collect_rules(children, sheet, media, &nested)?;
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude,
+                block: None,
+            } if name.eq_ignore_ascii_case("layer") => {
+                // `@layer a, b;` only declares the layers, fixing their order
+                for layer in layer_names(prelude.as_ref()) {
+                    let mut nested = layers.to_vec();
+                    nested.push(layer);
+                    declare_layer(sheet, &nested);
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude,
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("media") => {
+                // Nested `@media` blocks apply only when every enclosing query list matches.
+                let Some(list) = prelude.as_ref().and_then(MediaQueryList::from_node) else {
+                    continue;
+                };
+                let mut nested = media.to_vec();
+                nested.push(list);
+                if let Some(children) = block.as_block() {
+                    collect_rules(children, sheet, &nested, layers)?;
                 }
             }
             NodeType::AtRule {
@@ -224,17 +295,176 @@ fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut V
                 ..
             } if name.eq_ignore_ascii_case("font-face") => {
                 if let Some(children) = block.as_block() {
-                    if let Some(face) = collect_font_face(children) {
-                        font_faces.push(face);
+                    if let Some(mut face) = collect_font_face(children) {
+                        face.media = media.to_vec();
+                        sheet.font_faces.push(face);
                     }
                 }
             }
+            NodeType::AtRule {
+                name,
+                prelude,
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("page") => {
+                if let Some(children) = block.as_block() {
+                    sheet.page_rules.push(CssPageRule {
+                        rule: collect_page_rule(prelude.as_ref(), children),
+                        media: media.to_vec(),
+                    });
+                }
+            }
             _ => {}
         }
     }
     Ok(())
 }
 
+/// The layer names in the prelude of an `@layer` rule
+fn layer_names(prelude: Option<&CssNode>) -> Vec<String> {
+    let Some(NodeType::LayerList { layers }) = prelude.map(|p| &*p.node_type) else {
+        return Vec::new();
+    };
+    layers
+        .iter()
+        .filter_map(|layer| match &*layer.node_type {
+            NodeType::Ident { value } => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds the layer at `path` (outermost first) to the layers the sheet declares, unless it was
+/// declared before
+fn declare_layer(sheet: &mut CssStylesheet, path: &[String]) {
+    let name = path.join(".");
+    if !sheet.layer_order.contains(&name) {
+        sheet.layer_order.push(name);
+    }
+}
+
+/// Collects the `@import` rules at the start of the stylesheet. Imports may only be preceded by
+/// `@charset` and `@layer` statements; any later `@import` is invalid and ignored, as are imports
+/// whose `supports()` condition fails.
+fn collect_imports(nodes: &[CssNode], sheet: &mut CssStylesheet) {
+    for node in nodes {
+        let (name, prelude, block) = match &*node.node_type {
+            NodeType::AtRule { name, prelude, block } => (name, prelude, block),
+            NodeType::Comment { .. } | NodeType::Cdo | NodeType::Cdc => continue,
+            _ => return,
+        };
+        if name.eq_ignore_ascii_case("charset") {
+            continue;
+        }
+        if name.eq_ignore_ascii_case("layer") && block.is_none() {
+            for layer in layer_names(prelude.as_ref()) {
+                declare_layer(sheet, &[layer]);
+            }
+            continue;
+        }
+        if !name.eq_ignore_ascii_case("import") {
+            return;
+        }
+        let Some(NodeType::ImportList { children }) = prelude.as_ref().map(|p| &*p.node_type) else {
+            continue;
+        };
+
+        let mut import = CssImport {
+            url: String::new(),
+            layer: None,
+            media: None,
+            declared_layers: 0,
+            location: node.location,
+        };
+        let mut supported = true;
+        for child in children {
+            match &*child.node_type {
+                NodeType::String { value } => import.url.clone_from(value),
+                NodeType::Url { url } => import.url.clone_from(url),
+                NodeType::Ident { value } if value.eq_ignore_ascii_case("layer") => {
+                    import.layer = Some(anonymous_layer());
+                }
+                NodeType::Function { name, arguments } => {
+                    let raw = match arguments.first().map(|a| &*a.node_type) {
+                        Some(NodeType::Raw { value }) => value.trim(),
+                        _ => "",
+                    };
+                    if name.eq_ignore_ascii_case("layer") {
+                        import.layer = Some(if raw.is_empty() {
+                            anonymous_layer()
+                        } else {
+                            raw.to_string()
+                        });
+                    } else if name.eq_ignore_ascii_case("supports") {
+                        supported = supports(raw);
+                    }
+                }
+                NodeType::MediaQueryList { .. } => import.media = MediaQueryList::from_node(child),
+                _ => {}
+            }
+        }
+
+        if import.url.is_empty() {
+            continue;
+        }
+        if supported {
+            if let Some(layer) = &import.layer {
+                declare_layer(sheet, slice::from_ref(layer));
+            }
+            import.declared_layers = sheet.layer_order.len();
+            sheet.imports.push(import);
+        } else {
+            log::debug!("Skipping @import of {}: supports() condition fails", import.url);
+        }
+    }
+}
+
+/// Build a [`PageRule`] from an `@page` prelude (page selectors) and its declarations. Page-margin
+/// boxes (`@top-center` and friends) are not supported and are skipped.
+fn collect_page_rule(prelude: Option<&CssNode>, nodes: &[CssNode]) -> PageRule {
+    let mut rule = PageRule::default();
+
+    if let Some(NodeType::SelectorList { selectors }) = prelude.map(|p| &*p.node_type) {
+        for selector in selectors {
+            for part in selector.as_selector().into_iter().flatten() {
+                if let NodeType::PseudoClassSelector { value } = &*part.node_type {
+                    if let NodeType::Ident { value } = &*value.node_type {
+                        rule.pseudo_classes.push(value.cow_to_ascii_lowercase().into_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    for decl in nodes {
+        let Some((property, value_nodes, _important)) = decl.as_declaration() else {
+            continue;
+        };
+        let values: Vec<CssValue> = value_nodes
+            .iter()
+            .filter_map(|n| CssValue::parse_ast_node(n).ok())
+            .collect();
+        let single = || match values.as_slice() {
+            [value] => page::length(value),
+            _ => None,
+        };
+        match property.cow_to_ascii_lowercase().as_ref() {
+            "size" => rule.size = page::page_size(&values, page::DEFAULT_PAGE_SIZE),
+            "margin" => {
+                if let Some(edges) = page::margin_edges(&values) {
+                    rule.margin = edges.map(Some);
+                }
+            }
+            "margin-top" => rule.margin[0] = single(),
+            "margin-right" => rule.margin[1] = single(),
+            "margin-bottom" => rule.margin[2] = single(),
+            "margin-left" => rule.margin[3] = single(),
+            _ => {}
+        }
+    }
+
+    rule
+}
+
 /// Build a [`FontFace`] from the declarations inside an `@font-face` block. Requires a
 /// `font-family` and at least one `src: url(...)`; returns `None` otherwise.
 fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
@@ -298,6 +528,7 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
         family,
         sources,
         unicode_range,
+        media: vec![],
     })
 }
 
@@ -332,18 +563,23 @@ pub fn convert_ast_to_stylesheet(css_ast: &CssNode, origin: CssOrigin, url: &str
     let mut sheet = CssStylesheet {
         rules: vec![],
         font_faces: vec![],
+        page_rules: vec![],
+        layer_order: vec![],
+        imports: vec![],
         origin,
         url: url.to_string(),
         parse_log: vec![],
     };
 
-    collect_rules(children, &mut sheet.rules, &mut sheet.font_faces)?;
+    collect_imports(children, &mut sheet);
+    collect_rules(children, &mut sheet, &[], &[])?;
     Ok(sheet)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stylesheet::Specificity;
     use crate::Css3;
     use gosub_shared::config::ParserConfig;
 
@@ -374,6 +610,112 @@ mod tests {
         assert!(face.unicode_range.as_deref().unwrap_or("").contains("U+0000"));
     }
 
+    #[test]
+    fn import_rules_are_collected() {
+        let stylesheet = Css3::parse_str(
+            r#"
+            @charset "utf-8";
+            @layer base, utilities;
+            @import "a.css";
+            @import url(b.css) layer(base) print;
+            @import "c.css" supports(display: no-such-display);
+            @import "d.css" supports((display: grid) or (display: nope)) screen and (min-width: 600px);
+            h1 { color: red; }
+            @import "late.css";
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        let urls: Vec<&str> = stylesheet.imports.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(urls, ["a.css", "b.css", "d.css"]);
+        assert_eq!(stylesheet.imports[0].layer, None);
+        assert!(stylesheet.imports[0].media.is_none());
+        assert_eq!(stylesheet.imports[1].layer.as_deref(), Some("base"));
+        assert!(stylesheet.imports[1].media.is_some());
+        assert!(stylesheet.imports[2].media.is_some());
+        assert_eq!(stylesheet.rules.len(), 1);
+    }
+
+    fn parse(css: &str) -> CssStylesheet {
+        Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap()
+    }
+
+    #[test]
+    fn import_conditions_are_keyed_by_layer_and_media() {
+        use gosub_interface::css3::{CssStylesheet as _, ImportConditions};
+
+        let sheet = parse(
+            r#"
+            @import "a.css";
+            @import "b.css" layer(base) screen and (min-width: 600px), print;
+            @import "c.css" layer;
+            @import "c.css" layer;
+            "#,
+        );
+
+        assert!(sheet.import_conditions(0).is_empty());
+        assert_eq!(
+            sheet.import_conditions(1),
+            ImportConditions {
+                layer: Some("base".into()),
+                media: Some("screen and (min-width: 600px), print".into()),
+            }
+        );
+        // Every anonymous layer is a layer of its own
+        assert_ne!(sheet.import_conditions(2), sheet.import_conditions(3));
+    }
+
+    #[test]
+    fn imports_restrict_every_rule() {
+        use crate::media::{set_media_type, MediaType};
+        use gosub_interface::css3::CssStylesheet as _;
+
+        let importer = parse(r#"@import "x.css" print;"#);
+        let mut imported = parse(
+            r"
+            @font-face { font-family: Serif; src: url(serif.ttf); }
+            @page { margin: 1in; }
+            h1 { color: red; }
+            ",
+        );
+        importer.apply_import(0, &mut imported);
+
+        set_media_type(MediaType::Screen);
+        assert!(!imported.rules[0].applies());
+        assert!(imported.font_faces().is_empty());
+        assert!(imported.page_rules().is_empty());
+
+        set_media_type(MediaType::Print);
+        assert!(imported.rules[0].applies());
+        assert_eq!(imported.font_faces().len(), 1);
+        assert_eq!(imported.page_rules().len(), 1);
+        set_media_type(MediaType::Screen);
+    }
+
+    #[test]
+    fn layered_imports_nest_their_layers() {
+        use crate::layer::LayerOrder;
+        use gosub_interface::css3::CssStylesheet as _;
+
+        let importer = parse(r#"@layer theme; @import "x.css" layer(base); @layer late;"#);
+        let mut imported = parse("@layer inner { h1 { color: red; } } h2 { color: blue; }");
+        importer.apply_import(0, &mut imported);
+
+        assert_eq!(imported.rules[0].layers, ["base", "inner"]);
+        assert_eq!(imported.rules[1].layers, ["base"]);
+        assert_eq!(imported.layer_order, ["theme", "base", "base.inner"]);
+
+        // The imported sheet comes before its importer in the cascade
+        let order = LayerOrder::new([&imported, &importer]);
+        let rank = |name: &str| order.rank(&name.split('.').map(String::from).collect::<Vec<_>>());
+        assert!(rank("theme") < rank("base.inner"));
+        assert!(rank("base.inner") < rank("base"));
+        assert!(rank("base") < rank("late"));
+    }
+
     #[test]
     fn layer_rules_are_flattened() {
         let stylesheet = Css3::parse_str(
@@ -397,6 +739,9 @@ mod tests {
             stylesheet.rules[0].selectors[0].parts[0][0],
             CssSelectorPart::Type("h1".into())
         );
+        assert_eq!(stylesheet.rules[0].layers, ["base"]);
+        assert!(stylesheet.rules[1].layers.is_empty());
+        assert_eq!(stylesheet.rules[2].layers, ["utilities"]);
         assert_eq!(
             stylesheet.rules[1].selectors[0].parts[0][0],
             CssSelectorPart::Type("h2".into())
@@ -407,6 +752,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shadow_selectors_are_converted() {
+        let stylesheet = Css3::parse_str(
+            r"
+            :host { display: block; }
+            :host(.dark) p { color: white; }
+            ::slotted(span.x) { color: red; }
+            ",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        let parts: Vec<_> = stylesheet
+            .rules
+            .iter()
+            .map(|rule| &rule.selectors[0].parts[0])
+            .collect();
+        assert_eq!(parts[0], &vec![CssSelectorPart::Host(None)]);
+        assert_eq!(
+            parts[1],
+            &vec![
+                CssSelectorPart::Host(Some(vec![CssSelectorPart::Class("dark".into())])),
+                CssSelectorPart::Combinator(Combinator::Descendant),
+                CssSelectorPart::Type("p".into()),
+            ]
+        );
+        assert_eq!(
+            parts[2],
+            &vec![CssSelectorPart::Slotted(vec![
+                CssSelectorPart::Type("span".into()),
+                CssSelectorPart::Class("x".into()),
+            ])]
+        );
+
+        // `:host` counts as a class, `::slotted()` as an element, each plus its argument
+        assert_eq!(Specificity::from(parts[1].as_slice()), Specificity::new(0, 2, 1));
+        assert_eq!(Specificity::from(parts[2].as_slice()), Specificity::new(0, 1, 2));
+    }
+
     #[test]
     fn layer_ordering_declaration_is_ignored() {
         let stylesheet = Css3::parse_str(
@@ -0,0 +1,146 @@
+//! Cascade layers (`@layer`).
+//!
+//! Every stylesheet records the layers it declares, in order, in
+//! [`CssStylesheet::layer_order`] and every rule the layer it is in
+//! ([`CssRule::layers`](crate::stylesheet::CssRule::layers)). [`LayerOrder`] ranks the layers of
+//! the stylesheets that apply to an element: layers are ordered by their first declaration, the
+//! sublayers of a layer come before the rules directly in it, and rules outside any layer come
+//! after all layers. `!important` declarations reverse that order.
+
+use crate::stylesheet::CssStylesheet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Layer rank of the rules outside any layer, which win over every layer.
+pub const UNLAYERED: u32 = u32::MAX;
+
+/// A new name for an anonymous layer (`@layer { ... }` or `@import ... layer`). An anonymous
+/// layer cannot be referred to again, so each one gets a name no other layer has: `<` cannot
+/// appear in an identifier.
+pub(crate) fn anonymous_layer() -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    format!("<anonymous-{}>", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// The rank of each layer declared by a list of stylesheets, lowest precedence first.
+#[derive(Debug, Default)]
+pub struct LayerOrder {
+    ranks: HashMap<String, u32>,
+}
+
+impl LayerOrder {
+    /// Orders the layers declared by `sheets`, which are in cascade order.
+    pub fn new<'a>(sheets: impl IntoIterator<Item = &'a CssStylesheet>) -> Self {
+        // The position of each layer among its siblings and of its parents among theirs, by
+        // full (dotted) name
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut children: HashMap<String, u32> = HashMap::new();
+        for name in sheets.into_iter().flat_map(|sheet| &sheet.layer_order) {
+            let mut full = String::new();
+            let mut position = Vec::new();
+            for segment in name.split('.') {
+                let parent = full.clone();
+                if !full.is_empty() {
+                    full.push('.');
+                }
+                full.push_str(segment);
+
+                if let Some(known) = positions.get(&full) {
+                    position.clone_from(known);
+                    continue;
+                }
+                let count = children.entry(parent).or_default();
+                position.push(*count);
+                *count += 1;
+                positions.insert(full.clone(), position.clone());
+            }
+        }
+
+        // The rules directly in a layer come after those of its sublayers
+        let mut layers: Vec<(Vec<u32>, String)> = positions
+            .into_iter()
+            .map(|(name, mut position)| {
+                position.push(u32::MAX);
+                (position, name)
+            })
+            .collect();
+        layers.sort_unstable();
+
+        Self {
+            ranks: layers
+                .into_iter()
+                .zip(0..)
+                .map(|((_, name), rank)| (name, rank))
+                .collect(),
+        }
+    }
+
+    /// The rank of the layer a rule with the given
+    /// [`layers`](crate::stylesheet::CssRule::layers) is in, [`UNLAYERED`] for a rule outside any
+    /// layer.
+    #[must_use]
+    pub fn rank(&self, layers: &[String]) -> u32 {
+        if layers.is_empty() {
+            return UNLAYERED;
+        }
+        self.ranks.get(&layers.join(".")).copied().unwrap_or(UNLAYERED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::CssOrigin;
+    use gosub_shared::config::ParserConfig;
+
+    fn parse(css: &str) -> CssStylesheet {
+        Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap()
+    }
+
+    fn layers(names: &str) -> Vec<String> {
+        names.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn layers_rank_by_first_declaration() {
+        let sheet = parse(
+            r"
+            @layer reset, components;
+            @layer components { h1 { color: red; } }
+            @layer reset { h1 { color: blue; } }
+            @layer components.buttons { h1 { color: green; } }
+            @layer utilities { h1 { color: black; } }
+            h1 { color: white; }
+            ",
+        );
+        let order = LayerOrder::new([&sheet]);
+
+        let reset = order.rank(&layers("reset"));
+        let buttons = order.rank(&layers("components.buttons"));
+        let components = order.rank(&layers("components"));
+        let utilities = order.rank(&layers("utilities"));
+        assert!(reset < buttons);
+        assert!(buttons < components, "sublayers come before the layer itself");
+        assert!(components < utilities);
+        assert!(utilities < order.rank(&[]));
+        assert_eq!(order.rank(&[]), UNLAYERED);
+    }
+
+    #[test]
+    fn anonymous_layers_are_distinct() {
+        let sheet = parse("@layer { h1 { color: red; } } @layer { h2 { color: blue; } }");
+        assert_ne!(sheet.rules[0].layers, sheet.rules[1].layers);
+
+        let order = LayerOrder::new([&sheet]);
+        assert!(order.rank(&sheet.rules[0].layers) < order.rank(&sheet.rules[1].layers));
+    }
+
+    #[test]
+    fn later_sheets_add_layers_after_earlier_ones() {
+        let first = parse("@layer b { h1 { color: red; } }");
+        let second = parse("@layer a, b; @layer a { h1 { color: blue; } }");
+        let order = LayerOrder::new([&first, &second]);
+        assert!(order.rank(&layers("b")) < order.rank(&layers("a")));
+    }
+}
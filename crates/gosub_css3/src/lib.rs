@@ -17,16 +17,21 @@ use gosub_shared::{timing_start, timing_stop};
 pub mod ast;
 pub mod colors;
 mod functions;
+pub mod layer;
 pub mod matcher;
+pub mod media;
 // The as_* accessors panic by contract when called on the wrong node type;
 // callers are expected to check the matching is_* predicate first.
 #[allow(clippy::panic)]
 pub mod node;
+pub mod page;
 pub mod parser;
 pub mod stylesheet;
+pub mod supports;
 pub mod system;
 pub mod tokenizer;
 mod unicode;
+pub mod viewport;
 pub mod walker;
 
 /// Cap on recursive-descent depth, shared by every recursive cycle in the parser.
@@ -1,3 +1,4 @@
+use crate::layer::UNLAYERED;
 use crate::stylesheet::{CssValue, Specificity};
 use gosub_interface::css3::CssOrigin;
 use std::collections::hash_map::Entry;
@@ -119,16 +120,18 @@ pub struct FixListInfo {
     important: bool,
     location: String,
     specificity: Specificity,
+    layer: u32,
 }
 
 impl FixListInfo {
     #[must_use]
-    pub fn new(origin: CssOrigin, important: bool, location: String, specificity: Specificity) -> Self {
+    pub fn new(origin: CssOrigin, important: bool, location: String, specificity: Specificity, layer: u32) -> Self {
         Self {
             origin,
             important,
             location,
             specificity,
+            layer,
         }
     }
 }
@@ -369,6 +372,7 @@ impl FixList {
                 important: info.important,
                 specificity: info.specificity,
                 location: info.location.clone(),
+                layer: info.layer,
             }
         } else {
             DeclarationProperty {
@@ -377,6 +381,7 @@ impl FixList {
                 important: false,
                 specificity: Specificity::new(0, 0, 0),
                 location: String::new(),
+                layer: UNLAYERED,
             }
         }
     }
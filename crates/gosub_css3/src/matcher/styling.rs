@@ -8,9 +8,10 @@ use gosub_interface::config::HasDocument;
 use gosub_interface::css3;
 use gosub_interface::css3::{CssOrigin, CssPropertyMap};
 use gosub_interface::document::Document;
-use gosub_interface::node::NodeType;
+use gosub_interface::node::{NodeType, QuirksMode};
 use gosub_shared::node::NodeId;
 
+use crate::layer::UNLAYERED;
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::stylesheet::{Combinator, CssSelector, CssSelectorPart, CssValue, MatcherType, Specificity};
 use crate::system::Css3System;
@@ -29,6 +30,49 @@ pub(crate) fn match_selector<C: HasDocument>(
     selector: &CssSelector,
     pseudo: Option<&str>,
 ) -> (bool, Specificity) {
+    match_selector_in::<C>(document, node_id, selector, pseudo, MatchContext::DOCUMENT)
+}
+
+/// The shadow tree context the rules of a stylesheet are matched in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MatchContext {
+    /// Rules of the tree the element lives in (or of the shadow tree it hosts). When that is a
+    /// shadow tree, `host` is its shadow host: combinators climb from the shadow root to the host,
+    /// but the host itself only matches `:host`.
+    Tree { host: Option<NodeId> },
+    /// `::slotted()` rules of the shadow tree of `host`, matched against an element assigned to
+    /// `slot`.
+    Slotted { slot: NodeId, host: NodeId },
+}
+
+impl MatchContext {
+    /// Rules of the document tree
+    pub(crate) const DOCUMENT: Self = Self::Tree { host: None };
+}
+
+/// Same as `match_selector()`, for the rules of a stylesheet in the given shadow tree context.
+pub(crate) fn match_selector_in<C: HasDocument>(
+    document: &C::Document,
+    node_id: NodeId,
+    selector: &CssSelector,
+    pseudo: Option<&str>,
+    context: MatchContext,
+) -> (bool, Specificity) {
+    match matching_alternative_in::<C>(document, node_id, selector, pseudo, context) {
+        Some(part) => (true, Specificity::from(part)),
+        None => (false, Specificity::new(0, 0, 0)),
+    }
+}
+
+/// The first selector of the selector list that matches the node in the given shadow tree
+/// context, if any.
+pub(crate) fn matching_alternative_in<'s, C: HasDocument>(
+    document: &C::Document,
+    node_id: NodeId,
+    selector: &'s CssSelector,
+    pseudo: Option<&str>,
+    context: MatchContext,
+) -> Option<&'s [CssSelectorPart]> {
     for part in &selector.parts {
         // When matching a pseudo-element, the selector must explicitly target it.
         if let Some(target) = pseudo {
@@ -40,12 +84,44 @@ pub(crate) fn match_selector<C: HasDocument>(
             }
         }
 
-        if match_selector_parts::<C>(document, node_id, part, pseudo) {
-            return (true, Specificity::from(part.as_slice()));
+        let matched = match context {
+            MatchContext::Tree { host } => match_selector_parts::<C>(document, node_id, part, pseudo, host),
+            MatchContext::Slotted { slot, host } => {
+                pseudo.is_none() && match_slotted::<C>(document, node_id, part, slot, host)
+            }
+        };
+        if matched {
+            return Some(part);
         }
     }
 
-    (false, Specificity::new(0, 0, 0))
+    None
+}
+
+/// Matches `<compound>::slotted(<argument>)` for an element assigned to `slot`: the argument
+/// against the element itself, and everything before it against the slot.
+fn match_slotted<C: HasDocument>(
+    doc: &C::Document,
+    node_id: NodeId,
+    parts: &[CssSelectorPart],
+    slot: NodeId,
+    host: NodeId,
+) -> bool {
+    let Some((CssSelectorPart::Slotted(argument), rest)) = parts.split_last() else {
+        return false;
+    };
+    match_selector_parts::<C>(doc, node_id, argument, None, None)
+        && match_selector_parts::<C>(doc, slot, rest, None, Some(host))
+}
+
+/// Parent of `id` as seen by combinators. Inside the shadow tree of `host`, the host stands in
+/// for the shadow root so selectors like `:host(.dark) p` can reach it.
+fn selector_parent<C: HasDocument>(doc: &C::Document, id: NodeId, host: Option<NodeId>) -> Option<NodeId> {
+    let parent = doc.parent(id)?;
+    match host {
+        Some(host) if doc.shadow_host(parent) == Some(host) => Some(host),
+        _ => Some(parent),
+    }
 }
 
 /// Case-insensitive compare of a pseudo-element name against a target (`before`/`after`).
@@ -69,6 +145,7 @@ fn match_selector_parts<C: HasDocument>(
     node_id: NodeId,
     mut parts: &[CssSelectorPart],
     pseudo: Option<&str>,
+    host: Option<NodeId>,
 ) -> bool {
     let mut next_current_id: Option<NodeId> = Some(node_id);
 
@@ -81,7 +158,7 @@ fn match_selector_parts<C: HasDocument>(
             return false;
         }
 
-        if !match_selector_part::<C>(part, current_id, doc, &mut next_current_id, &mut parts, pseudo) {
+        if !match_selector_part::<C>(part, current_id, doc, &mut next_current_id, &mut parts, pseudo, host) {
             return false;
         }
     }
@@ -96,16 +173,38 @@ fn match_selector_part<C: HasDocument>(
     next_id: &mut Option<NodeId>,
     parts: &mut &[CssSelectorPart],
     pseudo: Option<&str>,
+    host: Option<NodeId>,
 ) -> bool {
+    // To the rules of its shadow tree the host is featureless: only `:host` matches it, and
+    // nothing outside the host can be reached
+    if host == Some(current_id) {
+        return match part {
+            CssSelectorPart::Universal => true,
+            CssSelectorPart::Host(None) => true,
+            CssSelectorPart::Host(Some(compound)) => match_selector_parts::<C>(doc, current_id, compound, None, None),
+            _ => false,
+        };
+    }
+
     match part {
         CssSelectorPart::Universal => true,
         CssSelectorPart::Type(name) => {
             doc.node_type(current_id) == NodeType::ElementNode && doc.tag_name(current_id).is_some_and(|t| t == name)
         }
+        // Quirks mode documents match class names and ids ASCII case-insensitively
+        CssSelectorPart::Class(name) if doc.quirks_mode() == QuirksMode::Quirks => {
+            doc.node_type(current_id) == NodeType::ElementNode
+                && doc
+                    .attribute(current_id, "class")
+                    .is_some_and(|v| v.split_ascii_whitespace().any(|c| c.eq_ignore_ascii_case(name)))
+        }
         CssSelectorPart::Class(name) => doc.has_class(current_id, name),
         CssSelectorPart::Id(name) => {
+            let quirks = doc.quirks_mode() == QuirksMode::Quirks;
             doc.node_type(current_id) == NodeType::ElementNode
-                && doc.attribute(current_id, "id").is_some_and(|v| v == name)
+                && doc
+                    .attribute(current_id, "id")
+                    .is_some_and(|v| v == name || (quirks && v.eq_ignore_ascii_case(name)))
         }
         CssSelectorPart::Attribute(attr) => {
             if doc.node_type(current_id) != NodeType::ElementNode {
@@ -121,7 +220,7 @@ fn match_selector_part<C: HasDocument>(
             let mut _got_buf = String::new();
 
             let (wanted_attr_value, got_attr_value): (&str, &str) = if attr.case_insensitive {
-                _wanted_buf = attr.name.cow_to_lowercase().to_string();
+                _wanted_buf = attr.value.cow_to_lowercase().to_string();
                 _got_buf = got_raw.cow_to_lowercase().to_string();
                 (&_wanted_buf, &_got_buf)
             } else {
@@ -131,7 +230,7 @@ fn match_selector_part<C: HasDocument>(
             match attr.matcher {
                 MatcherType::None => true,
                 MatcherType::Equals => wanted_attr_value == got_attr_value,
-                MatcherType::Includes => wanted_attr_value.split_whitespace().any(|s| s == got_attr_value),
+                MatcherType::Includes => got_attr_value.split_whitespace().any(|s| s == wanted_attr_value),
                 MatcherType::DashMatch => {
                     got_attr_value == wanted_attr_value || got_attr_value.starts_with(&format!("{wanted_attr_value}-"))
                 }
@@ -224,9 +323,9 @@ fn match_selector_part<C: HasDocument>(
             // and `:root { --custom: … }` custom properties would never be collected.
             "root" => {
                 doc.node_type(current_id) == NodeType::ElementNode
-                    && doc
-                        .parent(current_id)
-                        .is_none_or(|p| doc.node_type(p) != NodeType::ElementNode)
+                    && doc.parent(current_id).is_none_or(|p| {
+                        !matches!(doc.node_type(p), NodeType::ElementNode | NodeType::DocumentFragmentNode)
+                    })
             }
             "checked" => doc.attribute(current_id, "checked").is_some(),
             "disabled" => doc.attribute(current_id, "disabled").is_some(),
@@ -248,9 +347,12 @@ fn match_selector_part<C: HasDocument>(
         // pseudo-element (`pseudo == Some(name)`). It does not advance `next_id`: the remaining
         // compound continues to match against the originating element.
         CssSelectorPart::PseudoElement(name) => pseudo.is_some_and(|target| pseudo_eq(name, target)),
+        // `:host` only matches the host itself (handled above), and `::slotted()` is only matched
+        // through `MatchContext::Slotted`
+        CssSelectorPart::Host(_) | CssSelectorPart::Slotted(_) => false,
         CssSelectorPart::Combinator(combinator) => match combinator {
             Combinator::Descendant => {
-                let Some(mut parent_id) = doc.parent(current_id) else {
+                let Some(mut parent_id) = selector_parent::<C>(doc, current_id, host) else {
                     return false;
                 };
 
@@ -261,11 +363,14 @@ fn match_selector_part<C: HasDocument>(
                 loop {
                     *next_id = Some(parent_id);
 
-                    if match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo) {
+                    if match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo, host) {
                         return true;
                     }
 
-                    let Some(p) = doc.parent(parent_id) else {
+                    if host == Some(parent_id) {
+                        return false;
+                    }
+                    let Some(p) = selector_parent::<C>(doc, parent_id, host) else {
                         return false;
                     };
 
@@ -273,7 +378,7 @@ fn match_selector_part<C: HasDocument>(
                 }
             }
             Combinator::Child => {
-                let Some(parent_id) = doc.parent(current_id) else {
+                let Some(parent_id) = selector_parent::<C>(doc, current_id, host) else {
                     return false;
                 };
 
@@ -283,7 +388,7 @@ fn match_selector_part<C: HasDocument>(
 
                 *next_id = Some(parent_id);
 
-                match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo)
+                match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo, host)
             }
             Combinator::NextSibling => {
                 let Some(parent_id) = doc.parent(current_id) else {
@@ -310,7 +415,7 @@ fn match_selector_part<C: HasDocument>(
 
                 *next_id = Some(prev_id);
 
-                match_selector_part::<C>(last, prev_id, doc, next_id, parts, pseudo)
+                match_selector_part::<C>(last, prev_id, doc, next_id, parts, pseudo, host)
             }
             Combinator::SubsequentSibling => {
                 let Some(parent_id) = doc.parent(current_id) else {
@@ -328,7 +433,7 @@ fn match_selector_part<C: HasDocument>(
                         break;
                     }
 
-                    if match_selector_part::<C>(last, child_id, doc, next_id, parts, pseudo) {
+                    if match_selector_part::<C>(last, child_id, doc, next_id, parts, pseudo, host) {
                         return true;
                     }
                 }
@@ -370,35 +475,48 @@ pub struct DeclarationProperty {
     pub location: String,
     /// The specificity of the selector that declared this property
     pub specificity: Specificity,
+    /// Rank of the cascade layer the declaration is in (see [`LayerOrder`](crate::layer::LayerOrder)),
+    /// [`UNLAYERED`] outside any layer
+    pub layer: u32,
 }
 
 impl DeclarationProperty {
-    /// Priority of the declaration based on the origin and importance as defined in <https://developer.mozilla.org/en-US/docs/Web/CSS/Cascade>
-    fn priority(&self) -> u8 {
-        match self.origin {
-            CssOrigin::UserAgent => {
-                if self.important {
-                    7
-                } else {
-                    1
-                }
+    /// Priority of the declaration based on the origin, importance and cascade layer
+    fn priority(&self) -> (u8, u32) {
+        cascade_priority(self.origin, self.important, self.layer)
+    }
+}
+
+/// Cascade priority of a declaration from its origin, importance and the rank of its cascade
+/// layer, as defined in <https://developer.mozilla.org/en-US/docs/Web/CSS/Cascade>. Later layers
+/// win for normal declarations, earlier ones for `!important` declarations, so unlayered
+/// `!important` declarations lose to layered ones.
+pub(crate) fn cascade_priority(origin: CssOrigin, important: bool, layer: u32) -> (u8, u32) {
+    let layer = if important { UNLAYERED - layer } else { layer };
+    let origin = match origin {
+        CssOrigin::UserAgent => {
+            if important {
+                7
+            } else {
+                1
             }
-            CssOrigin::User => {
-                if self.important {
-                    6
-                } else {
-                    2
-                }
+        }
+        CssOrigin::User => {
+            if important {
+                6
+            } else {
+                2
             }
-            CssOrigin::Author => {
-                if self.important {
-                    5
-                } else {
-                    3
-                }
+        }
+        CssOrigin::Author => {
+            if important {
+                5
+            } else {
+                3
             }
         }
-    }
+    };
+    (origin, layer)
 }
 
 impl PartialEq<Self> for DeclarationProperty {
@@ -573,6 +691,7 @@ impl From<CssValue> for CssProperty {
             value,
             origin: CssOrigin::Author,
             specificity: Specificity::new(0, 0, 0),
+            layer: UNLAYERED,
         }];
 
         this.calculate_value();
@@ -589,6 +708,7 @@ impl From<CssValue> for DeclarationProperty {
             value,
             origin: CssOrigin::Author,
             specificity: Specificity::new(0, 0, 0),
+            layer: UNLAYERED,
         }
     }
 }
@@ -774,6 +894,7 @@ mod tests {
             important: false,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         });
 
         assert_eq!(
@@ -800,6 +921,7 @@ mod tests {
             important: false,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         });
 
         assert_eq!(prop.compute_value(), &CssValue::String("red".into()));
@@ -817,6 +939,7 @@ mod tests {
             important: false,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
         let b = DeclarationProperty {
             value: CssValue::String("blue".into()),
@@ -824,6 +947,7 @@ mod tests {
             important: false,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
         let c = DeclarationProperty {
             value: CssValue::String("green".into()),
@@ -831,6 +955,7 @@ mod tests {
             important: false,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
         let d = DeclarationProperty {
             value: CssValue::String("yellow".into()),
@@ -838,6 +963,7 @@ mod tests {
             important: true,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
         let e = DeclarationProperty {
             value: CssValue::String("orange".into()),
@@ -845,6 +971,7 @@ mod tests {
             important: true,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
         let f = DeclarationProperty {
             value: CssValue::String("purple".into()),
@@ -852,14 +979,15 @@ mod tests {
             important: true,
             location: String::new(),
             specificity: Specificity::new(1, 0, 0),
+            layer: UNLAYERED,
         };
 
-        assert_eq!(3, a.priority());
-        assert_eq!(1, b.priority());
-        assert_eq!(2, c.priority());
-        assert_eq!(5, d.priority());
-        assert_eq!(7, e.priority());
-        assert_eq!(6, f.priority());
+        assert_eq!((3, UNLAYERED), a.priority());
+        assert_eq!((1, UNLAYERED), b.priority());
+        assert_eq!((2, UNLAYERED), c.priority());
+        assert_eq!((5, 0), d.priority());
+        assert_eq!((7, 0), e.priority());
+        assert_eq!((6, 0), f.priority());
 
         assert!(a > b);
         assert!(b < c);
@@ -875,6 +1003,31 @@ mod tests {
         assert_eq!(d, d);
     }
 
+    #[test]
+    fn compare_layered() {
+        let declaration = |layer, important, specificity| DeclarationProperty {
+            value: CssValue::String("red".into()),
+            origin: CssOrigin::Author,
+            important,
+            location: String::new(),
+            specificity,
+            layer,
+        };
+
+        let base = declaration(0, false, Specificity::new(1, 0, 0));
+        let theme = declaration(1, false, Specificity::new(0, 0, 1));
+        let unlayered = declaration(UNLAYERED, false, Specificity::new(0, 0, 1));
+        assert!(base < theme, "later layers win regardless of specificity");
+        assert!(theme < unlayered);
+
+        let base = declaration(0, true, Specificity::new(0, 0, 1));
+        let theme = declaration(1, true, Specificity::new(1, 0, 0));
+        let unlayered = declaration(UNLAYERED, true, Specificity::new(1, 0, 0));
+        assert!(base > theme, "!important reverses the layer order");
+        assert!(theme > unlayered);
+        assert!(unlayered > declaration(0, false, Specificity::new(0, 0, 1)));
+    }
+
     #[test]
     fn is_inheritable() {
         let prop = CssProperty::new("border");
@@ -0,0 +1,507 @@
+//! `@media` support.
+//!
+//! Rules nested in `@media` blocks keep the query lists they were declared under (see
+//! [`CssRule::media`](crate::stylesheet::CssRule::media)) and are filtered at cascade time
+//! against the media type and [`MediaFeatures`] of the current style pass. Like the layout
+//! viewport, both are per thread: the render flow calls [`set_media_type`] and
+//! [`set_media_features`] before computing styles.
+
+use crate::node::{Node, NodeType};
+use cow_utils::CowUtils;
+use gosub_interface::css3::{ColorScheme, MediaFeatures, PointerAccuracy};
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+
+thread_local! {
+    /// Media type the cascade evaluates `@media` rules against on this thread.
+    static MEDIA_TYPE: Cell<MediaType> = const { Cell::new(MediaType::Screen) };
+    /// Media features the cascade evaluates `@media` conditions against on this thread.
+    static MEDIA_FEATURES: Cell<MediaFeatures> = Cell::new(MediaFeatures::default());
+}
+
+/// Set the media type used by subsequent style computations on this thread.
+///
+/// Printing switches to [`MediaType::Print`] for the duration of the print layout; everything
+/// else renders for [`MediaType::Screen`].
+pub fn set_media_type(media_type: MediaType) {
+    MEDIA_TYPE.with(|m| m.set(media_type));
+}
+
+/// The media type of the current style pass on this thread.
+#[must_use]
+pub fn media_type() -> MediaType {
+    MEDIA_TYPE.with(Cell::get)
+}
+
+/// Set the media features (viewport, device pixel ratio, user preferences, pointer) used by
+/// subsequent style computations on this thread.
+pub fn set_media_features(features: MediaFeatures) {
+    MEDIA_FEATURES.with(|m| m.set(features));
+}
+
+/// The media features of the current style pass on this thread.
+#[must_use]
+pub fn media_features() -> MediaFeatures {
+    MEDIA_FEATURES.with(Cell::get)
+}
+
+/// The media types a document can be rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MediaType {
+    #[default]
+    Screen,
+    Print,
+}
+
+impl MediaType {
+    /// Whether a media type named in a query (`all`, `screen`, `print`, ...) covers this one.
+    /// Unknown and deprecated media types (`tv`, `handheld`, ...) never match.
+    fn matches(self, name: &str) -> bool {
+        match name.cow_to_ascii_lowercase().as_ref() {
+            "all" => true,
+            "screen" => self == MediaType::Screen,
+            "print" => self == MediaType::Print,
+            _ => false,
+        }
+    }
+}
+
+/// A single media query, e.g. `not print` or `screen and (min-width: 600px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    /// The query was prefixed with `not`.
+    pub negated: bool,
+    /// Media type named by the query; `None` when the query only has a condition (same as `all`).
+    pub media_type: Option<String>,
+    /// Feature condition (`(min-width: 600px)` and friends), as parsed.
+    pub condition: Option<Node>,
+}
+
+impl MediaQuery {
+    /// Evaluates the query against `media_type` and `features`. Unknown media features and
+    /// malformed conditions evaluate to false.
+    #[must_use]
+    pub fn matches(&self, media_type: MediaType, features: &MediaFeatures) -> bool {
+        let type_matches = self.media_type.as_deref().is_none_or(|name| media_type.matches(name));
+        let condition_matches = self
+            .condition
+            .as_ref()
+            .is_none_or(|condition| evaluate_condition(condition, features));
+        (type_matches && condition_matches) != self.negated
+    }
+}
+
+impl Display for MediaQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.negated {
+            write!(f, "not ")?;
+        }
+        match (&self.media_type, &self.condition) {
+            (Some(media_type), Some(condition)) => write!(f, "{media_type} and {condition}"),
+            (Some(media_type), None) => write!(f, "{media_type}"),
+            (None, Some(condition)) => write!(f, "{condition}"),
+            (None, None) => write!(f, "all"),
+        }
+    }
+}
+
+/// The comma-separated query list of an `@media` rule. Matches when any query matches.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+impl MediaQueryList {
+    /// Converts a parsed `MediaQueryList` node. Returns `None` for any other node.
+    #[must_use]
+    pub fn from_node(node: &Node) -> Option<Self> {
+        let NodeType::MediaQueryList { media_queries } = &*node.node_type else {
+            return None;
+        };
+
+        let queries = media_queries
+            .iter()
+            .filter_map(|query| match &*query.node_type {
+                NodeType::MediaQuery {
+                    modifier,
+                    media_type,
+                    condition,
+                } => Some(MediaQuery {
+                    negated: modifier.eq_ignore_ascii_case("not"),
+                    media_type: (!media_type.is_empty()).then(|| media_type.clone()),
+                    condition: condition.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Some(Self { queries })
+    }
+
+    /// Evaluates the list against `media_type` and `features`. An empty list (`@media {}`)
+    /// matches everything.
+    #[must_use]
+    pub fn matches(&self, media_type: MediaType, features: &MediaFeatures) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|q| q.matches(media_type, features))
+    }
+}
+
+/// Serializes the list back to CSS, e.g. `screen and (min-width: 600px), print`.
+impl Display for MediaQueryList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, query) in self.queries.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{query}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates a parsed condition: a flat list of terms joined by `and` / `or`, each optionally
+/// preceded by `not`. Mixing `and` and `or` without parentheses is invalid CSS; it is read as
+/// `or` binding loosest.
+fn evaluate_condition(condition: &Node, features: &MediaFeatures) -> bool {
+    let NodeType::Condition { list } = &*condition.node_type else {
+        return false;
+    };
+    list.split(|term| keyword(term).is_some_and(|kw| kw.eq_ignore_ascii_case("or")))
+        .any(|terms| evaluate_conjunction(terms, features))
+}
+
+/// Evaluates `terms` joined by `and`.
+fn evaluate_conjunction(terms: &[Node], features: &MediaFeatures) -> bool {
+    let mut negated = false;
+    let mut evaluated = false;
+    for term in terms {
+        if let Some(kw) = keyword(term) {
+            match kw.cow_to_ascii_lowercase().as_ref() {
+                "and" => {}
+                "not" => negated = !negated,
+                _ => return false,
+            }
+            continue;
+        }
+        if evaluate_term(term, features) == negated {
+            return false;
+        }
+        negated = false;
+        evaluated = true;
+    }
+    evaluated
+}
+
+fn evaluate_term(term: &Node, features: &MediaFeatures) -> bool {
+    match &*term.node_type {
+        NodeType::Feature { name, value, .. } => evaluate_feature(name, value.as_ref(), features),
+        NodeType::Range {
+            left,
+            left_comparison,
+            middle,
+            right_comparison,
+            right,
+        } => {
+            let right = right_comparison.as_ref().zip(right.as_ref());
+            evaluate_range(left, left_comparison, middle, right, features)
+        }
+        NodeType::Condition { .. } => evaluate_condition(term, features),
+        _ => false,
+    }
+}
+
+/// Evaluates `(name)` or `(name: value)`, including the `min-` / `max-` prefixed forms.
+fn evaluate_feature(name: &str, value: Option<&Node>, features: &MediaFeatures) -> bool {
+    let name = name.cow_to_ascii_lowercase();
+    // `-webkit-min-device-pixel-ratio` and friends predate `resolution`.
+    let name = name.strip_prefix("-webkit-").unwrap_or(&name);
+    let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+        (">=", name)
+    } else if let Some(name) = name.strip_prefix("max-") {
+        ("<=", name)
+    } else {
+        ("=", name)
+    };
+
+    let Some(value) = value else {
+        // Boolean context: true unless the feature would be zero or `none`. Prefixed features
+        // are only valid with a value.
+        return comparison == "=" && evaluate_boolean(name, features);
+    };
+
+    match numeric_feature(name, features) {
+        Some(actual) => numeric_value(value).is_some_and(|expected| compare(actual, comparison, expected)),
+        None => comparison == "=" && keyword(value).is_some_and(|kw| evaluate_discrete(name, kw, features)),
+    }
+}
+
+/// Evaluates a range form: `(width >= 600px)`, `(600px <= width)` or
+/// `(400px <= width < 700px)`.
+fn evaluate_range(
+    left: &Node,
+    left_comparison: &Node,
+    middle: &Node,
+    right: Option<(&Node, &Node)>,
+    features: &MediaFeatures,
+) -> bool {
+    if let Some(name) = keyword(left) {
+        let Some(actual) = numeric_feature(&name.cow_to_ascii_lowercase(), features) else {
+            return false;
+        };
+        return right.is_none()
+            && operator(left_comparison)
+                .zip(numeric_value(middle))
+                .is_some_and(|(op, expected)| compare(actual, op, expected));
+    }
+
+    let Some(actual) = keyword(middle).and_then(|name| numeric_feature(&name.cow_to_ascii_lowercase(), features))
+    else {
+        return false;
+    };
+    let left_holds = operator(left_comparison)
+        .zip(numeric_value(left))
+        .is_some_and(|(op, bound)| compare(bound, op, actual));
+    let right_holds = right.is_none_or(|(comparison, right)| {
+        operator(comparison)
+            .zip(numeric_value(right))
+            .is_some_and(|(op, bound)| compare(actual, op, bound))
+    });
+    left_holds && right_holds
+}
+
+/// The value of a feature that compares as a number (lengths in px, resolutions in dppx), or
+/// `None` for discrete and unknown features. The device-* features report the viewport.
+fn numeric_feature(name: &str, features: &MediaFeatures) -> Option<f32> {
+    match name {
+        "width" | "device-width" => Some(features.width),
+        "height" | "device-height" => Some(features.height),
+        "aspect-ratio" | "device-aspect-ratio" => (features.height > 0.0).then_some(features.width / features.height),
+        "resolution" | "device-pixel-ratio" => Some(features.device_pixel_ratio),
+        // An 8-bit-per-channel color screen without a grid.
+        "color" => Some(8.0),
+        "monochrome" | "color-index" | "grid" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Whether a discrete feature has the keyword value `value`.
+fn evaluate_discrete(name: &str, value: &str, features: &MediaFeatures) -> bool {
+    let value = value.cow_to_ascii_lowercase();
+    match (name, value.as_ref()) {
+        ("orientation", "portrait") => features.height >= features.width,
+        ("orientation", "landscape") => features.width > features.height,
+        ("prefers-color-scheme", "light") => features.color_scheme == ColorScheme::Light,
+        ("prefers-color-scheme", "dark") => features.color_scheme == ColorScheme::Dark,
+        ("prefers-reduced-motion", "reduce") => features.reduced_motion,
+        ("prefers-reduced-motion", "no-preference") => !features.reduced_motion,
+        ("hover" | "any-hover", "hover") => features.hover,
+        ("hover" | "any-hover", "none") => !features.hover,
+        ("pointer" | "any-pointer", "none") => features.pointer == PointerAccuracy::None,
+        ("pointer" | "any-pointer", "coarse") => features.pointer == PointerAccuracy::Coarse,
+        ("pointer" | "any-pointer", "fine") => features.pointer == PointerAccuracy::Fine,
+        _ => false,
+    }
+}
+
+/// Evaluates a feature in boolean context, e.g. `(hover)` or `(color)`.
+fn evaluate_boolean(name: &str, features: &MediaFeatures) -> bool {
+    if let Some(value) = numeric_feature(name, features) {
+        return value != 0.0;
+    }
+    match name {
+        "orientation" | "prefers-color-scheme" => true,
+        "prefers-reduced-motion" => features.reduced_motion,
+        "hover" | "any-hover" => features.hover,
+        "pointer" | "any-pointer" => features.pointer != PointerAccuracy::None,
+        _ => false,
+    }
+}
+
+/// A number, a length (in px), a resolution (in dppx) or a ratio (`16/9`).
+fn numeric_value(node: &Node) -> Option<f32> {
+    match &*node.node_type {
+        NodeType::Number { value } => Some(*value),
+        NodeType::Dimension { value, unit } => {
+            let factor = match unit.cow_to_ascii_lowercase().as_ref() {
+                "px" | "dppx" | "x" => 1.0,
+                "em" | "rem" => 16.0,
+                "in" => 96.0,
+                "cm" => 96.0 / 2.54,
+                "mm" => 96.0 / 25.4,
+                "q" => 96.0 / 101.6,
+                "pt" => 96.0 / 72.0,
+                "pc" => 96.0 / 6.0,
+                "dpi" => 1.0 / 96.0,
+                "dpcm" => 2.54 / 96.0,
+                _ => return None,
+            };
+            Some(value * factor)
+        }
+        NodeType::Value { children } => match children.as_slice() {
+            [numerator, slash, denominator] if operator(slash) == Some("/") => {
+                let denominator = numeric_value(denominator)?;
+                (denominator != 0.0).then_some(numeric_value(numerator)? / denominator)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn keyword(node: &Node) -> Option<&str> {
+    match &*node.node_type {
+        NodeType::Ident { value } => Some(value),
+        _ => None,
+    }
+}
+
+fn operator(node: &Node) -> Option<&str> {
+    match &*node.node_type {
+        NodeType::Operator(op) => Some(op),
+        _ => None,
+    }
+}
+
+/// `a <op> b`. Equality allows for rounding, so `aspect-ratio: 16/9` matches a 1920×1080
+/// viewport.
+fn compare(a: f32, op: &str, b: f32) -> bool {
+    const EPSILON: f32 = 0.001;
+    match op {
+        "<" => a < b - EPSILON,
+        "<=" => a <= b + EPSILON,
+        ">" => a > b + EPSILON,
+        ">=" => a >= b - EPSILON,
+        "=" => (a - b).abs() <= EPSILON,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::CssOrigin;
+    use gosub_shared::config::ParserConfig;
+
+    fn media_of(css: &str) -> Vec<MediaQueryList> {
+        let sheet = Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap();
+        sheet.rules[0].media.clone()
+    }
+
+    fn matches_at(css: &str, features: &MediaFeatures) -> bool {
+        media_of(css)[0].matches(MediaType::Screen, features)
+    }
+
+    fn viewport(width: f32, height: f32) -> MediaFeatures {
+        MediaFeatures {
+            width,
+            height,
+            ..MediaFeatures::default()
+        }
+    }
+
+    #[test]
+    fn media_types_match() {
+        let features = MediaFeatures::default();
+        let media = media_of("@media print { h1 { color: red; } }");
+        assert!(media[0].matches(MediaType::Print, &features));
+        assert!(!media[0].matches(MediaType::Screen, &features));
+
+        let media = media_of("@media screen, print { h1 { color: red; } }");
+        assert!(media[0].matches(MediaType::Print, &features));
+        assert!(media[0].matches(MediaType::Screen, &features));
+
+        let media = media_of("@media not print { h1 { color: red; } }");
+        assert!(!media[0].matches(MediaType::Print, &features));
+        assert!(media[0].matches(MediaType::Screen, &features));
+    }
+
+    #[test]
+    fn viewport_features() {
+        let css = "@media screen and (min-width: 600px) and (max-width: 900px) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(800.0, 600.0)));
+        assert!(!matches_at(css, &viewport(1000.0, 600.0)));
+        assert!(!matches_at(css, &viewport(500.0, 600.0)));
+
+        let css = "@media print and (min-width: 100px) { h1 { color: red; } }";
+        assert!(!matches_at(css, &viewport(800.0, 600.0)));
+
+        let css = "@media (max-width: 40em) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(640.0, 600.0)));
+        assert!(!matches_at(css, &viewport(641.0, 600.0)));
+
+        let css = "@media (orientation: portrait) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(400.0, 800.0)));
+        assert!(!matches_at(css, &viewport(800.0, 400.0)));
+
+        let css = "@media (min-aspect-ratio: 16/9) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(1920.0, 1080.0)));
+        assert!(!matches_at(css, &viewport(1024.0, 768.0)));
+    }
+
+    #[test]
+    fn range_syntax() {
+        let css = "@media (width >= 600px) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(600.0, 600.0)));
+        assert!(!matches_at(css, &viewport(599.0, 600.0)));
+
+        let css = "@media (400px <= width < 700px) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(400.0, 600.0)));
+        assert!(!matches_at(css, &viewport(700.0, 600.0)));
+    }
+
+    #[test]
+    fn not_and_or() {
+        let css = "@media not all and (max-width: 600px) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(800.0, 600.0)));
+        assert!(!matches_at(css, &viewport(500.0, 600.0)));
+
+        let css = "@media (max-width: 400px) or (min-width: 1000px) { h1 { color: red; } }";
+        assert!(matches_at(css, &viewport(300.0, 600.0)));
+        assert!(matches_at(css, &viewport(1200.0, 600.0)));
+        assert!(!matches_at(css, &viewport(700.0, 600.0)));
+
+        let css = "@media (unknown-feature: 1) { h1 { color: red; } }";
+        assert!(!matches_at(css, &viewport(700.0, 600.0)));
+    }
+
+    #[test]
+    fn device_and_preference_features() {
+        let retina = MediaFeatures {
+            device_pixel_ratio: 2.0,
+            ..MediaFeatures::default()
+        };
+        let css = "@media (min-resolution: 2dppx) { h1 { color: red; } }";
+        assert!(matches_at(css, &retina));
+        assert!(!matches_at(css, &MediaFeatures::default()));
+        let css = "@media (-webkit-min-device-pixel-ratio: 1.5) { h1 { color: red; } }";
+        assert!(matches_at(css, &retina));
+        let css = "@media (min-resolution: 192dpi) { h1 { color: red; } }";
+        assert!(matches_at(css, &retina));
+
+        let dark = MediaFeatures {
+            color_scheme: ColorScheme::Dark,
+            reduced_motion: true,
+            ..MediaFeatures::default()
+        };
+        let css = "@media (prefers-color-scheme: dark) { h1 { color: red; } }";
+        assert!(matches_at(css, &dark));
+        assert!(!matches_at(css, &MediaFeatures::default()));
+        let css = "@media (prefers-reduced-motion: reduce) { h1 { color: red; } }";
+        assert!(matches_at(css, &dark));
+        assert!(!matches_at(css, &MediaFeatures::default()));
+
+        let touch = MediaFeatures {
+            hover: false,
+            pointer: PointerAccuracy::Coarse,
+            ..MediaFeatures::default()
+        };
+        let css = "@media (hover: hover) and (pointer: fine) { h1 { color: red; } }";
+        assert!(matches_at(css, &MediaFeatures::default()));
+        assert!(!matches_at(css, &touch));
+        let css = "@media (hover) { h1 { color: red; } }";
+        assert!(!matches_at(css, &touch));
+        let css = "@media (any-pointer: coarse) { h1 { color: red; } }";
+        assert!(matches_at(css, &touch));
+    }
+}
@@ -79,6 +79,8 @@ pub enum NodeType {
     },
     PseudoElementSelector {
         value: String,
+        /// Argument of a functional pseudo-element, like the selector of `::slotted(span)`
+        argument: Option<Node>,
     },
     PseudoClassSelector {
         value: Node,
@@ -359,7 +361,7 @@ impl Node {
     #[must_use]
     pub fn as_pseudo_element_selector(&self) -> Option<&String> {
         match &&*self.node_type {
-            &NodeType::PseudoElementSelector { value } => Some(value),
+            &NodeType::PseudoElementSelector { value, .. } => Some(value),
             _ => None,
         }
     }
@@ -459,7 +461,10 @@ impl Display for Node {
                 format!("[{name}{matcher}{value}{flags}]")
             }
             NodeType::PseudoClassSelector { value } => format!(":{value}"),
-            NodeType::PseudoElementSelector { value } => format!("::{value}"),
+            NodeType::PseudoElementSelector { value, argument } => match argument {
+                Some(argument) => format!("::{value}({argument})"),
+                None => format!("::{value}"),
+            },
             NodeType::Operator(value) => value.clone(),
             NodeType::ClassSelector { value } => format!(".{value}"),
             NodeType::TypeSelector { namespace, value } => {
@@ -476,6 +481,34 @@ impl Display for Node {
             NodeType::AnPlusB { a, b } => format!("{a}n+{b}"),
             NodeType::Calc { expr } => format!("calc({expr})"),
             NodeType::Raw { value } => value.clone(),
+            NodeType::Value { children } => children
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<String>(),
+            NodeType::Condition { list } => list
+                .iter()
+                .map(|term| match &*term.node_type {
+                    NodeType::Condition { .. } => format!("({term})"),
+                    _ => term.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+            NodeType::Feature { name, value, .. } => match value {
+                Some(value) => format!("({name}: {value})"),
+                None => format!("({name})"),
+            },
+            NodeType::Range {
+                left,
+                left_comparison,
+                middle,
+                right_comparison,
+                right,
+            } => match right_comparison.as_ref().zip(right.as_ref()) {
+                Some((right_comparison, right)) => {
+                    format!("({left} {left_comparison} {middle} {right_comparison} {right})")
+                }
+                None => format!("({left} {left_comparison} {middle})"),
+            },
 
             _ => {
                 String::new()
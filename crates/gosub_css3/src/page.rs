@@ -0,0 +1,256 @@
+//! `@page` descriptors (CSS Paged Media).
+//!
+//! [`convert_ast_to_stylesheet`](crate::ast::convert_ast_to_stylesheet) collects every `@page`
+//! rule into a [`PageRule`]; the helpers here resolve the `size` and `margin` descriptors to CSS
+//! pixels and merge the rules that apply to each page ([`page_descriptors`]).
+
+use crate::stylesheet::CssValue;
+use cow_utils::CowUtils;
+pub use gosub_interface::css3::PageRule;
+
+/// Page size used when no `@page` rule sets one: A4 portrait, in CSS pixels.
+pub const DEFAULT_PAGE_SIZE: (f32, f32) = (210.0 * 96.0 / 25.4, 297.0 * 96.0 / 25.4);
+
+/// Page margin used on every side when no `@page` rule sets one: 1cm, in CSS pixels.
+pub const DEFAULT_PAGE_MARGIN: f32 = 96.0 / 2.54;
+
+/// Named page sizes from CSS Paged Media, in millimeters (portrait).
+const PAGE_SIZES_MM: &[(&str, f32, f32)] = &[
+    ("a5", 148.0, 210.0),
+    ("a4", 210.0, 297.0),
+    ("a3", 297.0, 420.0),
+    ("b5", 176.0, 250.0),
+    ("b4", 250.0, 353.0),
+    ("jis-b5", 182.0, 257.0),
+    ("jis-b4", 257.0, 364.0),
+];
+
+/// Named page sizes from CSS Paged Media, in inches (portrait).
+const PAGE_SIZES_IN: &[(&str, f32, f32)] = &[("letter", 8.5, 11.0), ("legal", 8.5, 14.0), ("ledger", 11.0, 17.0)];
+
+/// Resolves the components of a `size` descriptor to `(width, height)` in CSS pixels.
+///
+/// Accepts one or two lengths, a named page size, and an optional `portrait`/`landscape`
+/// orientation (which swaps the named size or, on its own, the default size). `auto` and
+/// unparsable values yield `None` so the user agent default applies.
+#[must_use]
+pub fn page_size(values: &[CssValue], default: (f32, f32)) -> Option<(f32, f32)> {
+    let mut lengths = Vec::new();
+    let mut named = None;
+    let mut landscape = None;
+
+    for value in values {
+        match value {
+            CssValue::Unit(..) => lengths.push(value.unit_to_px()),
+            CssValue::String(keyword) => {
+                let keyword = keyword.cow_to_ascii_lowercase();
+                match keyword.as_ref() {
+                    "auto" => return None,
+                    "portrait" => landscape = Some(false),
+                    "landscape" => landscape = Some(true),
+                    name => named = Some(named_page_size(name)?),
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let (width, height) = match (lengths.as_slice(), named) {
+        ([], Some(size)) => size,
+        ([], None) => default,
+        ([side], None) => (*side, *side),
+        ([width, height], None) => (*width, *height),
+        _ => return None,
+    };
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+
+    // An orientation only applies to named or default sizes, never to explicit lengths.
+    Some(match landscape {
+        Some(landscape) if lengths.is_empty() && landscape == (width < height) => (height, width),
+        _ => (width, height),
+    })
+}
+
+/// Resolves a `margin` shorthand (one to four lengths) to top/right/bottom/left in CSS pixels.
+#[must_use]
+pub fn margin_edges(values: &[CssValue]) -> Option<[f32; 4]> {
+    let mut px = Vec::with_capacity(4);
+    for value in values {
+        px.push(length(value)?);
+    }
+    match px.as_slice() {
+        [all] => Some([*all; 4]),
+        [vertical, horizontal] => Some([*vertical, *horizontal, *vertical, *horizontal]),
+        [top, horizontal, bottom] => Some([*top, *horizontal, *bottom, *horizontal]),
+        [top, right, bottom, left] => Some([*top, *right, *bottom, *left]),
+        _ => None,
+    }
+}
+
+/// Resolves a single length to CSS pixels. Percentages and unitless numbers other than zero are
+/// not supported on page margins.
+#[must_use]
+pub fn length(value: &CssValue) -> Option<f32> {
+    match value {
+        CssValue::Zero => Some(0.0),
+        CssValue::Unit(..) => Some(value.unit_to_px().max(0.0)),
+        _ => None,
+    }
+}
+
+/// The descriptors that apply to the page at `index` (0-based), from `rules` in cascade order.
+///
+/// Later rules win over earlier ones, and more specific rules over less specific ones whatever
+/// their order: `:first` (and `:blank`) over `:left` and `:right`, those over rules without
+/// page selectors. Pages are numbered as in a left-to-right document, so the first page is a
+/// right page. Blank pages are never generated, so `:blank` rules never apply. The result has no
+/// pseudo-classes.
+#[must_use]
+pub fn page_descriptors(rules: &[PageRule], index: usize) -> PageRule {
+    let applies = |pseudo_class: &String| match pseudo_class.as_str() {
+        "first" => index == 0,
+        "left" => index % 2 == 1,
+        "right" => index.is_multiple_of(2),
+        _ => false,
+    };
+
+    let mut matching: Vec<&PageRule> = rules
+        .iter()
+        .filter(|rule| rule.pseudo_classes.iter().all(applies))
+        .collect();
+    // Stable, so source order decides between rules of equal specificity
+    matching.sort_by_key(|rule| {
+        let first = rule.pseudo_classes.iter().filter(|p| *p == "first").count();
+        (first, rule.pseudo_classes.len() - first)
+    });
+
+    let mut page = PageRule::default();
+    for rule in matching {
+        if rule.size.is_some() {
+            page.size = rule.size;
+        }
+        for (edge, value) in page.margin.iter_mut().zip(rule.margin) {
+            if value.is_some() {
+                *edge = value;
+            }
+        }
+    }
+    page
+}
+
+fn named_page_size(name: &str) -> Option<(f32, f32)> {
+    if let Some((_, w, h)) = PAGE_SIZES_MM.iter().find(|(n, ..)| *n == name) {
+        return Some((w * 96.0 / 25.4, h * 96.0 / 25.4));
+    }
+    PAGE_SIZES_IN
+        .iter()
+        .find(|(n, ..)| *n == name)
+        .map(|(_, w, h)| (w * 96.0, h * 96.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::{CssOrigin, CssStylesheet as _};
+    use gosub_shared::config::ParserConfig;
+
+    const LETTER: (f32, f32) = (816.0, 1056.0);
+
+    fn s(v: &str) -> CssValue {
+        CssValue::String(v.into())
+    }
+
+    #[test]
+    fn named_sizes_and_orientation() {
+        let (w, h) = page_size(&[s("A4")], LETTER).unwrap();
+        assert!((w - 793.7).abs() < 0.1 && (h - 1122.5).abs() < 0.1);
+
+        let (w, h) = page_size(&[s("a4"), s("landscape")], LETTER).unwrap();
+        assert!(w > h);
+
+        assert_eq!(page_size(&[s("landscape")], LETTER), Some((1056.0, 816.0)));
+        assert_eq!(page_size(&[s("auto")], LETTER), None);
+        assert_eq!(page_size(&[s("bogus")], LETTER), None);
+    }
+
+    #[test]
+    fn explicit_lengths() {
+        assert_eq!(
+            page_size(&[CssValue::Unit(5.0, "in".into())], LETTER),
+            Some((480.0, 480.0))
+        );
+        assert_eq!(
+            page_size(
+                &[CssValue::Unit(4.0, "in".into()), CssValue::Unit(6.0, "in".into())],
+                LETTER
+            ),
+            Some((384.0, 576.0))
+        );
+    }
+
+    #[test]
+    fn margin_shorthand_expands() {
+        let px = |v: f32| CssValue::Unit(v, "px".into());
+        assert_eq!(margin_edges(&[px(10.0)]), Some([10.0; 4]));
+        assert_eq!(margin_edges(&[px(1.0), px(2.0)]), Some([1.0, 2.0, 1.0, 2.0]));
+        assert_eq!(margin_edges(&[CssValue::Zero]), Some([0.0; 4]));
+        assert_eq!(margin_edges(&[CssValue::Percentage(10.0)]), None);
+    }
+
+    #[test]
+    fn page_rules_are_collected() {
+        let sheet = Css3::parse_str(
+            r"
+            @page { size: letter landscape; margin: 1in; }
+            @page :first { margin-top: 2in; }
+            h1 { color: red; }
+            ",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(sheet.rules.len(), 1);
+        let pages = sheet.page_rules();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].size, Some((1056.0, 816.0)));
+        assert_eq!(pages[0].margin, [Some(96.0); 4]);
+        assert!(pages[0].pseudo_classes.is_empty());
+        assert_eq!(pages[1].pseudo_classes, vec!["first".to_string()]);
+        assert_eq!(pages[1].margin, [Some(192.0), None, None, None]);
+    }
+
+    #[test]
+    fn page_selectors_pick_the_pages() {
+        let sheet = Css3::parse_str(
+            r"
+            @page :first { margin-top: 2in; }
+            @page { size: letter; margin: 1in; }
+            @page :left { margin-left: 0.5in; }
+            @page :right { margin-right: 0.5in; }
+            @page :blank { size: a4; }
+            ",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+        let rules = sheet.page_rules();
+
+        // The first page is a right page; `:first` wins over the later rule without selectors
+        let first = page_descriptors(&rules, 0);
+        assert_eq!(first.size, Some(LETTER));
+        assert_eq!(first.margin, [Some(192.0), Some(48.0), Some(96.0), Some(96.0)]);
+        assert!(first.pseudo_classes.is_empty());
+
+        let left = page_descriptors(&rules, 1);
+        assert_eq!(left.margin, [Some(96.0), Some(96.0), Some(96.0), Some(48.0)]);
+        let right = page_descriptors(&rules, 2);
+        assert_eq!(right.margin, [Some(96.0), Some(48.0), Some(96.0), Some(96.0)]);
+        assert_eq!(right.size, Some(LETTER));
+    }
+}
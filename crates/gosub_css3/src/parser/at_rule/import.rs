@@ -36,10 +36,11 @@ impl Css3<'_> {
         let t = self.tokenizer.lookahead_sc(0);
         match t.token_type {
             TokenType::Ident(value) if value.eq_ignore_ascii_case("layer") => {
+                self.consume_any()?;
                 children.push(Node::new(NodeType::Ident { value }, t.location));
             }
             TokenType::Function(name) if name.eq_ignore_ascii_case("layer") => {
-                children.push(self.parse_function()?);
+                children.push(self.parse_raw_import_function(name)?);
             }
             _ => {}
         }
@@ -49,13 +50,86 @@ impl Css3<'_> {
         let t = self.tokenizer.lookahead_sc(0);
         match t.token_type {
             TokenType::Function(name) if name.eq_ignore_ascii_case("supports") => {
-                children.push(self.parse_function()?);
+                children.push(self.parse_raw_import_function(name)?);
             }
             _ => {}
         }
 
         self.consume_whitespace_comments();
 
+        let t = self.tokenizer.lookahead_sc(0);
+        if !matches!(t.token_type, TokenType::Semicolon | TokenType::Eof) {
+            children.push(self.parse_media_query_list()?);
+        }
+
         Ok(Node::new(NodeType::ImportList { children }, loc))
     }
+
+    /// Parses `layer(...)` or `supports(...)` into a function node with a single raw argument.
+    /// Their contents (a dotted layer name, a supports condition or a bare declaration) are not
+    /// values, so they are kept as source text for the stylesheet builder to interpret.
+    fn parse_raw_import_function(&mut self, name: String) -> CssResult<Node> {
+        self.consume_whitespace_comments();
+        let loc = self.tokenizer.lookahead(0).location;
+        self.consume_function()?;
+
+        let start = self.tokenizer.lookahead(0).location.offset;
+        let mut depth = 0usize;
+        loop {
+            let t = self.tokenizer.consume();
+            match t.token_type {
+                TokenType::Function(_) | TokenType::LParen => depth += 1,
+                TokenType::RParen if depth > 0 => depth -= 1,
+                TokenType::RParen => {
+                    let value = self.tokenizer.slice(start, t.location.offset);
+                    let arguments = vec![Node::new(NodeType::Raw { value }, loc)];
+                    return Ok(Node::new(NodeType::Function { name, arguments }, loc));
+                }
+                TokenType::Eof => {
+                    return Err(CssError::with_location(
+                        format!("Unterminated {name}() in @import").as_str(),
+                        t.location,
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::walker::Walker;
+    use crate::{CssOrigin, ParserConfig};
+    use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+    fn parse(prelude: &str) -> String {
+        let mut stream = ByteStream::from_str(prelude, Encoding::UTF8);
+
+        let mut parser = crate::Css3::new(&mut stream, ParserConfig::default(), CssOrigin::User, "");
+        let node = parser.parse_at_rule_import_prelude().unwrap();
+
+        Walker::new(&node).walk_to_string()
+    }
+
+    #[test]
+    fn test_parse_at_rule_import_prelude() {
+        assert_eq!(parse("\"a.css\""), "[ImportList]\n  [String] a.css\n");
+        assert_eq!(
+            parse("url(a.css) layer"),
+            "[ImportList]\n  [Url] a.css\n  [Ident] layer\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_at_rule_import_conditions() {
+        let tree = parse("\"a.css\" layer(base.reset) supports(display: grid) screen and (min-width: 600px)");
+
+        assert!(tree.contains("[Function] layer\n    [Raw] base.reset\n"), "{tree}");
+        assert!(
+            tree.contains("[Function] supports\n    [Raw] display: grid\n"),
+            "{tree}"
+        );
+        assert!(tree.contains("[MediaQueryList (1)]"), "{tree}");
+    }
 }
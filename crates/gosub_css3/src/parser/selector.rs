@@ -200,16 +200,15 @@ impl Css3<'_> {
         self.consume(TokenType::Colon)?;
 
         let t = self.tokenizer.consume();
-        let value = match t.token_type {
-            TokenType::Ident(value) => value,
+        let (value, argument) = match t.token_type {
+            TokenType::Ident(value) => (value, None),
             TokenType::Function(name) => {
                 // Functional pseudo-element, e.g. `::part(foo)`, `::slotted(span)`,
-                // `::highlight(name)`. Parse and consume the arguments so the stream advances
-                // past the `)`; the selector model only retains the pseudo-element name.
+                // `::highlight(name)`.
                 let lower = name.cow_to_lowercase();
-                self.parse_pseudo_function(lower.as_ref())?;
+                let argument = self.parse_pseudo_function(lower.as_ref())?;
                 self.consume(TokenType::RParen)?;
-                name
+                (name, Some(argument))
             }
             _ => {
                 return Err(CssError::with_location(
@@ -219,7 +218,7 @@ impl Css3<'_> {
             }
         };
 
-        Ok(Node::new(NodeType::PseudoElementSelector { value }, loc))
+        Ok(Node::new(NodeType::PseudoElementSelector { value, argument }, loc))
     }
 
     fn parse_pseudo_selector(&mut self) -> CssResult<Node> {
@@ -1,7 +1,7 @@
 use core::fmt::Debug;
 use core::slice;
 use cow_utils::CowUtils;
-use gosub_interface::css3::CssOrigin;
+use gosub_interface::css3::{CssOrigin, ImportConditions, PageRule};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::errors::CssError;
 use gosub_shared::errors::CssResult;
@@ -10,6 +10,8 @@ use std::cmp::Ordering;
 use std::fmt::Display;
 
 use crate::colors::{oklab_to_srgb, oklch_to_srgb, RgbColor};
+use crate::media::{media_features, media_type, MediaQueryList, MediaType};
+use gosub_interface::css3::MediaFeatures;
 
 thread_local! {
     /// Viewport size (CSS px) used to resolve viewport-relative units (`vw`/`vh`/`vmin`/`vmax`)
@@ -22,6 +24,10 @@ thread_local! {
 /// computations on this thread. The render flow calls this before building and laying out the
 /// render tree so viewport units (including those inside `clamp()`) track the real window size
 /// instead of a fixed fallback. Non-positive dimensions are ignored.
+///
+/// When a `<meta name="viewport">` tag is honored (device emulation), this is the *layout*
+/// viewport resolved by [`ViewportMeta::resolve`](crate::viewport::ViewportMeta::resolve), not
+/// the device screen.
 pub fn set_layout_viewport(width: f32, height: f32) {
     if width > 0.0 && height > 0.0 {
         LAYOUT_VIEWPORT.with(|vp| vp.set((width, height)));
@@ -29,7 +35,8 @@ pub fn set_layout_viewport(width: f32, height: f32) {
 }
 
 /// The current viewport (CSS px) for resolving viewport-relative units on this thread.
-fn layout_viewport() -> (f32, f32) {
+#[must_use]
+pub fn layout_viewport() -> (f32, f32) {
     LAYOUT_VIEWPORT.with(Cell::get)
 }
 
@@ -134,6 +141,16 @@ pub struct FontFace {
     /// The raw `unicode-range` descriptor, if any (e.g. `"U+0000-00FF, U+0131"`). Used to
     /// pick the subset that covers the content; `None` means the face covers all code points.
     pub unicode_range: Option<String>,
+    /// Query lists of the `@media` blocks (and media-restricted `@import`s) the rule is in,
+    /// outermost first. The face is only available while all of them match.
+    pub media: Vec<MediaQueryList>,
+}
+
+/// An `@page` rule and the `@media` query lists it is in, outermost first.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CssPageRule {
+    pub rule: PageRule,
+    pub media: Vec<MediaQueryList>,
 }
 
 /// Defines a complete stylesheet with all its rules and the location where it was found
@@ -143,6 +160,13 @@ pub struct CssStylesheet {
     pub rules: Vec<CssRule>,
     /// `@font-face` rules found in this stylesheet (web fonts).
     pub font_faces: Vec<FontFace>,
+    /// `@page` rules found in this stylesheet.
+    pub page_rules: Vec<CssPageRule>,
+    /// Full (dotted) names of the cascade layers this stylesheet declares, in order of first
+    /// declaration, whether by an `@layer` statement, an `@layer` block or a layered `@import`.
+    pub layer_order: Vec<String>,
+    /// `@import` rules at the top of this stylesheet, in source order.
+    pub imports: Vec<CssImport>,
     /// Origin of the stylesheet (user agent, author, user)
     pub origin: CssOrigin,
     /// Url or file path where the stylesheet was found
@@ -163,9 +187,85 @@ impl gosub_interface::css3::CssStylesheet for CssStylesheet {
     fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
         self.font_faces
             .iter()
+            .filter(|f| media_applies(&f.media))
             .map(|f| (f.family.clone(), f.sources.clone(), f.unicode_range.clone()))
             .collect()
     }
+
+    fn page_rules(&self) -> Vec<PageRule> {
+        self.page_rules
+            .iter()
+            .filter(|page| media_applies(&page.media))
+            .map(|page| page.rule.clone())
+            .collect()
+    }
+
+    fn imports(&self) -> Vec<String> {
+        self.imports.iter().map(|import| import.url.clone()).collect()
+    }
+
+    fn import_conditions(&self, index: usize) -> ImportConditions {
+        self.imports
+            .get(index)
+            .map(|import| ImportConditions {
+                layer: import.layer.clone(),
+                media: import.media.as_ref().map(ToString::to_string),
+            })
+            .unwrap_or_default()
+    }
+
+    fn apply_import(&self, index: usize, imported: &mut Self) {
+        let Some(import) = self.imports.get(index) else {
+            return;
+        };
+
+        if let Some(media) = &import.media {
+            let restricted = imported
+                .rules
+                .iter_mut()
+                .map(|rule| &mut rule.media)
+                .chain(imported.font_faces.iter_mut().map(|face| &mut face.media))
+                .chain(imported.page_rules.iter_mut().map(|page| &mut page.media));
+            for list in restricted {
+                list.insert(0, media.clone());
+            }
+        }
+
+        if let Some(layer) = &import.layer {
+            for rule in &mut imported.rules {
+                rule.layers.insert(0, layer.clone());
+            }
+            for name in &mut imported.layer_order {
+                *name = format!("{layer}.{name}");
+            }
+        }
+        // The layers declared up to the import come before those it declares
+        let declared = &self.layer_order[..import.declared_layers.min(self.layer_order.len())];
+        imported.layer_order.splice(0..0, declared.iter().cloned());
+    }
+}
+
+/// Whether every query list in `media` matches the current media type and features
+fn media_applies(media: &[MediaQueryList]) -> bool {
+    let (media_type, features) = (media_type(), media_features());
+    media.iter().all(|list| list.matches(media_type, &features))
+}
+
+/// An `@import` rule. Only imports whose `supports()` condition holds are kept.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CssImport {
+    /// Url of the imported stylesheet, unresolved
+    pub url: String,
+    /// Cascade layer from `layer` (a generated name for the anonymous layer) or `layer(name)`.
+    /// The imported rules are put in this layer, outside any layer they declare themselves.
+    pub layer: Option<String>,
+    /// Media query list the imported rules are restricted to, if any
+    pub media: Option<MediaQueryList>,
+    /// Number of entries of [`CssStylesheet::layer_order`] declared up to and including this
+    /// import
+    pub declared_layers: usize,
+    /// Where the rule starts in the stylesheet
+    pub location: Location,
 }
 
 /// A CSS rule, which contains a list of selectors and a list of declarations
@@ -175,6 +275,15 @@ pub struct CssRule {
     pub selectors: Vec<CssSelector>,
     /// Actual declarations that will be applied if the selectors match
     pub declarations: Vec<CssDeclaration>,
+    /// Query lists of the `@media` blocks this rule is nested in, outermost first. Empty for
+    /// rules outside any `@media` block.
+    pub media: Vec<MediaQueryList>,
+    /// Names of the cascade layers the rule is in, from `@layer` blocks and layered `@import`s,
+    /// outermost first. An anonymous layer gets a generated name. The cascade ranks them with a
+    /// [`LayerOrder`](crate::layer::LayerOrder).
+    pub layers: Vec<String>,
+    /// Where the rule starts in the stylesheet
+    pub location: Location,
 }
 
 impl CssRule {
@@ -187,6 +296,27 @@ impl CssRule {
     pub fn declarations(&self) -> &Vec<CssDeclaration> {
         &self.declarations
     }
+
+    /// Whether every enclosing `@media` query list matches the current media type and features
+    /// (see [`set_media_type`](crate::media::set_media_type) and
+    /// [`set_media_features`](crate::media::set_media_features)).
+    #[must_use]
+    pub fn applies(&self) -> bool {
+        self.applies_in(media_type(), &media_features())
+    }
+
+    /// Whether every enclosing `@media` query list matches `media_type` and `features`.
+    #[must_use]
+    pub fn applies_in(&self, media_type: MediaType, features: &MediaFeatures) -> bool {
+        self.media.iter().all(|list| list.matches(media_type, features))
+    }
+
+    /// Whether any declaration uses a viewport-relative unit, so the rule's values change with
+    /// the viewport size.
+    #[must_use]
+    pub fn uses_viewport_units(&self) -> bool {
+        self.declarations.iter().any(|d| d.value.uses_viewport_units())
+    }
 }
 
 /// A CSS declaration, which contains a property, value and a flag for !important
@@ -208,6 +338,32 @@ pub struct CssSelector {
 }
 
 impl CssSelector {
+    /// Parses a standalone selector list (as passed to `querySelectorAll`), e.g. `ul > li.active, #main`.
+    ///
+    /// # Errors
+    /// Returns an error when `selector` is empty or is not a valid selector list.
+    pub fn parse(selector: &str) -> CssResult<Self> {
+        // A brace would let the input close the wrapping rule and smuggle in more rules.
+        if selector.trim().is_empty() || selector.contains(['{', '}']) {
+            return Err(CssError::new(format!("Invalid selector: {selector:?}").as_str()));
+        }
+
+        let sheet = crate::Css3::parse_str(
+            &format!("{selector} {{}}"),
+            gosub_shared::config::ParserConfig::default(),
+            CssOrigin::Author,
+            "",
+        )?;
+        match sheet.rules.as_slice() {
+            [rule] => rule
+                .selectors
+                .first()
+                .cloned()
+                .ok_or_else(|| CssError::new(format!("Invalid selector: {selector:?}").as_str())),
+            _ => Err(CssError::new(format!("Invalid selector: {selector:?}").as_str())),
+        }
+    }
+
     /// Generate specificity for this selector
     #[must_use]
     pub fn specificity(&self) -> Vec<Specificity> {
@@ -228,6 +384,10 @@ pub enum CssSelectorPart {
     Id(String),
     PseudoClass(String),
     PseudoElement(String),
+    /// `:host` or `:host(<compound-selector>)`: matches the shadow host from inside its shadow tree
+    Host(Option<Vec<CssSelectorPart>>),
+    /// `::slotted(<compound-selector>)`: matches the elements slotted into a shadow tree's slot
+    Slotted(Vec<CssSelectorPart>),
     Combinator(Combinator),
     Type(String),
 }
@@ -263,6 +423,33 @@ impl Display for Combinator {
     }
 }
 
+/// Serializes the selector list back to CSS text, e.g. `ul > li.active, #main`.
+impl Display for CssSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, parts) in self.parts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            for part in parts {
+                match part {
+                    CssSelectorPart::Combinator(Combinator::Descendant) => write!(f, " ")?,
+                    CssSelectorPart::Combinator(Combinator::Namespace) => write!(f, "|")?,
+                    CssSelectorPart::Combinator(combinator) => write!(f, " {combinator} ")?,
+                    CssSelectorPart::Attribute(attr) if attr.matcher == MatcherType::None => {
+                        write!(f, "[{}]", attr.name)?;
+                    }
+                    CssSelectorPart::Attribute(attr) => {
+                        let flag = if attr.case_insensitive { " i" } else { "" };
+                        write!(f, "[{}{}{:?}{flag}]", attr.name, attr.matcher, attr.value)?;
+                    }
+                    part => write!(f, "{part:?}")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Debug for CssSelectorPart {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -288,6 +475,15 @@ impl Debug for CssSelectorPart {
             CssSelectorPart::PseudoElement(name) => {
                 write!(f, "::{name}")
             }
+            CssSelectorPart::Host(None) => {
+                write!(f, ":host")
+            }
+            CssSelectorPart::Host(Some(compound)) => {
+                write!(f, ":host({compound:?})")
+            }
+            CssSelectorPart::Slotted(compound) => {
+                write!(f, "::slotted({compound:?})")
+            }
             CssSelectorPart::Combinator(combinator) => {
                 write!(f, "'{combinator}'")
             }
@@ -348,6 +544,12 @@ impl Specificity {
     pub fn new(a: u32, b: u32, c: u32) -> Self {
         Self(a, b, c)
     }
+
+    /// The id, class and type counts
+    #[must_use]
+    pub fn components(self) -> (u32, u32, u32) {
+        (self.0, self.1, self.2)
+    }
 }
 
 impl From<&[CssSelectorPart]> for Specificity {
@@ -366,6 +568,20 @@ impl From<&[CssSelectorPart]> for Specificity {
                 CssSelectorPart::Type(_) => {
                     element_count += 1;
                 }
+                // Both count as their pseudo-class / pseudo-element plus their argument
+                CssSelectorPart::Host(compound) => {
+                    let Specificity(ids, classes, elements) =
+                        compound.as_deref().map_or(Specificity::new(0, 0, 0), Specificity::from);
+                    id_count += ids;
+                    class_count += classes + 1;
+                    element_count += elements;
+                }
+                CssSelectorPart::Slotted(compound) => {
+                    let Specificity(ids, classes, elements) = Specificity::from(compound.as_slice());
+                    id_count += ids;
+                    class_count += classes;
+                    element_count += elements + 1;
+                }
                 _ => {}
             }
         }
@@ -459,6 +675,19 @@ impl Display for CssValue {
 }
 
 impl CssValue {
+    /// Whether the value (or any part of it, e.g. inside `clamp()`) has a viewport-relative unit.
+    #[must_use]
+    pub fn uses_viewport_units(&self) -> bool {
+        match self {
+            CssValue::Unit(_, unit) => matches!(
+                unit.cow_to_ascii_lowercase().as_ref(),
+                "vw" | "svw" | "lvw" | "dvw" | "vh" | "svh" | "lvh" | "dvh" | "vmin" | "vmax"
+            ),
+            CssValue::Function(_, values) | CssValue::List(values) => values.iter().any(CssValue::uses_viewport_units),
+            _ => false,
+        }
+    }
+
     #[must_use]
     pub fn to_color(&self) -> Option<RgbColor> {
         match self {
@@ -925,6 +1154,26 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn parse_standalone_selector() {
+        let selector = CssSelector::parse("ul > li.active, #main").unwrap();
+        assert_eq!(selector.parts.len(), 2);
+        assert_eq!(
+            selector.parts[0],
+            vec![
+                CssSelectorPart::Type("ul".into()),
+                CssSelectorPart::Combinator(Combinator::Child),
+                CssSelectorPart::Type("li".into()),
+                CssSelectorPart::Class("active".into()),
+            ]
+        );
+        assert_eq!(selector.parts[1], vec![CssSelectorPart::Id("main".into())]);
+
+        assert!(CssSelector::parse("").is_err());
+        assert!(CssSelector::parse("a {} b").is_err());
+        assert!(CssSelector::parse("@media print").is_err());
+    }
+
     #[test]
     fn test_css_rule() {
         let rule = CssRule {
@@ -936,6 +1185,9 @@ mod test {
                 value: CssValue::String("red".to_string()),
                 important: false,
             }],
+            media: vec![],
+            layers: vec![],
+            location: Location::default(),
         };
 
         assert_eq!(rule.selectors().len(), 1);
@@ -0,0 +1,223 @@
+//! `supports()` conditions.
+//!
+//! Conditions are evaluated statically against the property definitions: a declaration is
+//! supported when its property is known and its value matches the property's syntax, a
+//! `selector()` when the selector parses. Anything else (`<general-enclosed>`) is false. This
+//! backs the `supports()` condition of `@import`; `@supports` blocks are not applied yet.
+
+use crate::matcher::property_definitions::get_css_definitions;
+use crate::stylesheet::CssValue;
+use crate::tokenizer::{TokenType, Tokenizer};
+use crate::Css3;
+use cow_utils::CowUtils;
+use gosub_interface::css3::CssOrigin;
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
+use gosub_shared::config::ParserConfig;
+
+/// A significant token of a condition and the source range it covers.
+struct Part {
+    token_type: TokenType,
+    start: usize,
+    end: usize,
+}
+
+/// Whether `condition` holds. Besides a `<supports-condition>` this accepts a bare declaration,
+/// as `@import ... supports(display: grid)` does.
+#[must_use]
+pub fn supports(condition: &str) -> bool {
+    let parts = tokenize(condition);
+    if is_declaration(&parts) {
+        return declaration_supported(text(condition, &parts));
+    }
+    eval_condition(condition, &parts).unwrap_or(false)
+}
+
+/// Tokenizes `source`, dropping whitespace and comments. Each part ends where the next token
+/// starts, so [`text`] may carry trailing whitespace.
+fn tokenize(source: &str) -> Vec<Part> {
+    let mut stream = ByteStream::from_str(source, Encoding::UTF8);
+    let mut tokenizer = Tokenizer::new(&mut stream, Location::default());
+
+    let mut tokens = Vec::new();
+    loop {
+        let token = tokenizer.consume();
+        if token.token_type == TokenType::Eof {
+            break;
+        }
+        tokens.push(token);
+    }
+
+    let mut parts: Vec<Part> = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let end = tokens.get(index + 1).map_or(source.len(), |next| next.location.offset);
+        if !matches!(token.token_type, TokenType::Whitespace(_) | TokenType::Comment(_)) {
+            parts.push(Part {
+                token_type: token.token_type.clone(),
+                start: token.location.offset,
+                end,
+            });
+        }
+    }
+    parts
+}
+
+/// Source text covered by `parts`.
+fn text<'a>(source: &'a str, parts: &[Part]) -> &'a str {
+    match (parts.first(), parts.last()) {
+        (Some(first), Some(last)) => source.get(first.start..last.end).unwrap_or_default().trim(),
+        _ => "",
+    }
+}
+
+fn is_ident(part: Option<&Part>, keyword: &str) -> bool {
+    matches!(part.map(|p| &p.token_type), Some(TokenType::Ident(value)) if value.eq_ignore_ascii_case(keyword))
+}
+
+fn is_declaration(parts: &[Part]) -> bool {
+    matches!(parts.first().map(|p| &p.token_type), Some(TokenType::Ident(_)))
+        && matches!(parts.get(1).map(|p| &p.token_type), Some(TokenType::Colon))
+}
+
+/// Index of the `)` closing the parenthesized group or function that starts at `start`.
+fn group_end(parts: &[Part], start: usize) -> Option<usize> {
+    if !matches!(parts.get(start)?.token_type, TokenType::LParen | TokenType::Function(_)) {
+        return None;
+    }
+    let mut depth = 0usize;
+    for (index, part) in parts.iter().enumerate().skip(start) {
+        match part.token_type {
+            TokenType::LParen | TokenType::Function(_) => depth += 1,
+            TokenType::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `not <in-parens>`, or `<in-parens>` joined by only `and` or only `or`. `None` on a syntax error.
+fn eval_condition(source: &str, parts: &[Part]) -> Option<bool> {
+    if is_ident(parts.first(), "not") {
+        let end = group_end(parts, 1)?;
+        return (end + 1 == parts.len()).then(|| !eval_in_parens(source, &parts[1..=end]));
+    }
+
+    let mut operator: Option<&str> = None;
+    let mut result = None;
+    let mut index = 0;
+    loop {
+        let end = group_end(parts, index)?;
+        let value = eval_in_parens(source, &parts[index..=end]);
+        result = Some(match (result, operator) {
+            (None, _) => value,
+            (Some(acc), Some("or")) => acc || value,
+            (Some(acc), _) => acc && value,
+        });
+
+        index = end + 1;
+        if index == parts.len() {
+            return result;
+        }
+        let next = if is_ident(parts.get(index), "and") {
+            "and"
+        } else if is_ident(parts.get(index), "or") {
+            "or"
+        } else {
+            return None;
+        };
+        // Mixing `and` and `or` without parentheses is invalid.
+        if operator.is_some_and(|op| op != next) {
+            return None;
+        }
+        operator = Some(next);
+        index += 1;
+    }
+}
+
+/// A single parenthesized group or function, `parts` running up to its closing `)`.
+fn eval_in_parens(source: &str, parts: &[Part]) -> bool {
+    let inner = &parts[1..parts.len() - 1];
+    match &parts[0].token_type {
+        TokenType::Function(name) if name.eq_ignore_ascii_case("selector") => selector_supported(text(source, inner)),
+        TokenType::Function(_) => false,
+        _ if is_declaration(inner) => declaration_supported(text(source, inner)),
+        _ => eval_condition(source, inner).unwrap_or(false),
+    }
+}
+
+fn declaration_supported(declaration: &str) -> bool {
+    let Some((property, _)) = declaration.split_once(':') else {
+        return false;
+    };
+    if property.trim().starts_with("--") {
+        return true;
+    }
+    // The declaration parses as the body of a rule; braces would let it escape that rule.
+    if declaration.contains(['{', '}']) {
+        return false;
+    }
+
+    let Ok(sheet) = Css3::parse_str(
+        &format!("* {{{declaration}}}"),
+        ParserConfig::default(),
+        CssOrigin::Author,
+        "",
+    ) else {
+        return false;
+    };
+    let Some(declaration) = sheet.rules.first().and_then(|rule| rule.declarations().first()) else {
+        return false;
+    };
+    let Some(definition) = get_css_definitions().find_property(&declaration.property.cow_to_ascii_lowercase()) else {
+        return false;
+    };
+    match &declaration.value {
+        CssValue::List(values) => definition.matches(values),
+        value => definition.matches(std::slice::from_ref(value)),
+    }
+}
+
+fn selector_supported(selector: &str) -> bool {
+    if selector.is_empty() || selector.contains(['{', '}']) {
+        return false;
+    }
+    Css3::parse_str(
+        &format!("{selector} {{}}"),
+        ParserConfig::default(),
+        CssOrigin::Author,
+        "",
+    )
+    .is_ok_and(|sheet| !sheet.rules.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declarations() {
+        assert!(supports("display: grid"));
+        assert!(supports("(display: flex)"));
+        assert!(!supports("display: no-such-display"));
+        assert!(!supports("no-such-property: 1px"));
+        assert!(supports("--custom: anything"));
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(supports("(display: grid) and (color: red)"));
+        assert!(!supports("(display: grid) and (display: nope)"));
+        assert!(supports("(display: nope) or (display: block)"));
+        assert!(supports("not (display: nope)"));
+        assert!(supports(
+            "((display: grid) or (display: nope)) and (not (display: nope))"
+        ));
+        assert!(!supports("(display: grid) and (color: red) or (display: block)"));
+        assert!(!supports("foo(bar)"));
+        assert!(supports("selector(a > b)"));
+    }
+}
@@ -1,16 +1,26 @@
 use crate::functions::attr::resolve_attr;
 use crate::functions::math::resolve_math;
 use crate::functions::var::resolve_var;
+use crate::layer::{LayerOrder, UNLAYERED};
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::matcher::shorthands::{FixList, FixListInfo};
-use crate::matcher::styling::{match_selector, CssProperties, CssProperty, DeclarationProperty};
-use crate::stylesheet::{CssDeclaration, CssStylesheet, CssValue, Specificity};
+use crate::matcher::styling::{
+    cascade_priority, match_selector, match_selector_in, matching_alternative_in, CssProperties, CssProperty,
+    DeclarationProperty, MatchContext,
+};
+use crate::media::media_type;
+use crate::stylesheet::{CssDeclaration, CssRule, CssSelector, CssStylesheet, CssValue, Specificity};
+use crate::tokenizer::{TokenType, Tokenizer};
 use crate::{load_default_useragent_stylesheet, Css3};
 use cow_utils::CowUtils;
 use gosub_interface::config::HasDocument;
-use gosub_interface::css3::{CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints};
+use gosub_interface::css3::{
+    CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints, MatchedDeclaration, MatchedRule, MediaFeatures,
+    StyleDeclaration,
+};
 use gosub_interface::document::Document;
-use gosub_interface::node::NodeType;
+use gosub_interface::node::{NodeType, QuirksMode};
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
 use gosub_shared::config::ParserConfig;
 use gosub_shared::errors::CssResult;
 use gosub_shared::node::NodeId;
@@ -37,6 +47,54 @@ fn normalize_vendor_prefixes(value: CssValue) -> CssValue {
     }
 }
 
+/// Properties that accept a bare number as a pixel length in quirks mode (the unitless length
+/// quirk).
+const UNITLESS_LENGTH_PROPERTIES: [&str; 33] = [
+    "background-position",
+    "border-spacing",
+    "border-bottom-width",
+    "border-left-width",
+    "border-right-width",
+    "border-top-width",
+    "border-width",
+    "bottom",
+    "clip",
+    "font-size",
+    "height",
+    "left",
+    "letter-spacing",
+    "margin",
+    "margin-bottom",
+    "margin-left",
+    "margin-right",
+    "margin-top",
+    "max-height",
+    "max-width",
+    "min-height",
+    "min-width",
+    "padding",
+    "padding-bottom",
+    "padding-left",
+    "padding-right",
+    "padding-top",
+    "right",
+    "text-indent",
+    "top",
+    "vertical-align",
+    "width",
+    "word-spacing",
+];
+
+/// Turns the bare numbers of a quirky length value into pixels. Numbers inside functions are
+/// left alone.
+fn apply_unitless_length_quirk(value: CssValue) -> CssValue {
+    match value {
+        CssValue::Number(n) => CssValue::Unit(n, "px".to_string()),
+        CssValue::List(values) => CssValue::List(values.into_iter().map(apply_unitless_length_quirk).collect()),
+        other => other,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Css3System;
 
@@ -77,6 +135,24 @@ impl CssSystem for Css3System {
         Some(map)
     }
 
+    fn query_selector_all<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        root: NodeId,
+        selector: &str,
+    ) -> CssResult<Vec<NodeId>> {
+        let selector = CssSelector::parse(selector)?;
+        Ok(select_descendants::<C>(doc, root, &selector, false))
+    }
+
+    fn query_selector<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        root: NodeId,
+        selector: &str,
+    ) -> CssResult<Option<NodeId>> {
+        let selector = CssSelector::parse(selector)?;
+        Ok(select_descendants::<C>(doc, root, &selector, true).first().copied())
+    }
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet {
         load_default_useragent_stylesheet()
     }
@@ -84,6 +160,110 @@ impl CssSystem for Css3System {
     fn hover_fingerprints(sheets: &[Self::Stylesheet]) -> HoverFingerprints {
         hover_fingerprints_impl(sheets)
     }
+
+    fn matched_rules<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        id: NodeId,
+        sheets: &[Self::Stylesheet],
+    ) -> Vec<MatchedRule> {
+        matched_rules_impl::<C>(doc, id, sheets)
+    }
+
+    fn parse_style_attribute(style: &str) -> Vec<StyleDeclaration> {
+        parse_style_attribute_impl(style)
+    }
+
+    fn media_restyle_roots<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        sheets: &[Self::Stylesheet],
+        old: &MediaFeatures,
+        new: &MediaFeatures,
+    ) -> Option<Vec<NodeId>> {
+        Some(media_restyle_roots_impl::<C>(doc, sheets, old, new))
+    }
+}
+
+/// The elements below `root` matching `selector`, in document order. With `first_only` the walk
+/// stops at the first match.
+fn select_descendants<C: HasDocument<CssSystem = Css3System>>(
+    doc: &C::Document,
+    root: NodeId,
+    selector: &CssSelector,
+    first_only: bool,
+) -> Vec<NodeId> {
+    let mut found = Vec::new();
+    // Pre-order walk; children are pushed in reverse so they pop in document order.
+    let mut stack: Vec<NodeId> = doc.children(root).iter().rev().copied().collect();
+    while let Some(id) = stack.pop() {
+        if matches!(doc.node_type(id), NodeType::ElementNode) && match_selector::<C>(doc, id, selector, None).0 {
+            found.push(id);
+            if first_only {
+                break;
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    found
+}
+
+/// The elements whose styles change when the media features go from `old` to `new`, outermost
+/// first. An element is affected when a rule that matches it (or its `::before` / `::after`) is:
+///
+/// - nested in `@media` queries that evaluate differently under `old` and `new`, or
+/// - using viewport units while the viewport size changed. The same goes for a `style`
+///   attribute.
+///
+/// The subtree of an affected element restyles as a whole, so it is not searched further.
+fn media_restyle_roots_impl<C: HasDocument<CssSystem = Css3System>>(
+    doc: &C::Document,
+    sheets: &[CssStylesheet],
+    old: &MediaFeatures,
+    new: &MediaFeatures,
+) -> Vec<NodeId> {
+    let media_type = media_type();
+    let resized = old.width != new.width || old.height != new.height;
+    let affected = |rule: &CssRule| {
+        rule.applies_in(media_type, old) != rule.applies_in(media_type, new) || (resized && rule.uses_viewport_units())
+    };
+
+    let mut roots = Vec::new();
+    let mut stack = vec![doc.root()];
+    while let Some(id) = stack.pop() {
+        if matches!(doc.node_type(id), NodeType::ElementNode) {
+            let matches_affected_rule = scoped_sheets::<C>(doc, id, sheets).into_iter().any(|(sheet, context)| {
+                sheet.rules.iter().filter(|rule| affected(rule)).any(|rule| {
+                    rule.selectors().iter().any(|selector| {
+                        [None, Some("before"), Some("after")]
+                            .into_iter()
+                            .any(|pseudo| match_selector_in::<C>(doc, id, selector, pseudo, context).0)
+                    })
+                })
+            });
+            if matches_affected_rule || (resized && inline_style_uses_viewport_units::<C>(doc, id)) {
+                roots.push(id);
+                continue;
+            }
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+        stack.extend(doc.shadow_root(id));
+    }
+    roots
+}
+
+/// Whether the `style` attribute of `id` sets a value with viewport units.
+fn inline_style_uses_viewport_units<C: HasDocument<CssSystem = Css3System>>(doc: &C::Document, id: NodeId) -> bool {
+    doc.attribute(id, "style")
+        .filter(|style| !style.contains(['{', '}']))
+        .and_then(|style| {
+            Css3System::parse_str(
+                &format!("* {{{style}}}"),
+                ParserConfig::default(),
+                CssOrigin::Author,
+                "",
+            )
+            .ok()
+        })
+        .is_some_and(|sheet| sheet.rules.iter().any(CssRule::uses_viewport_units))
 }
 
 /// Shared style-collection core for both real elements (`pseudo == None`) and pseudo-elements
@@ -109,15 +289,19 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     let custom_props = collect_custom_props::<C>(doc, id, sheets);
 
     let mut fix_list = FixList::new();
+    let quirks = doc.quirks_mode() == QuirksMode::Quirks;
 
-    for sheet in sheets {
-        for rule in &sheet.rules {
+    let scoped = scoped_sheets::<C>(doc, id, sheets);
+    let layer_order = LayerOrder::new(scoped.iter().map(|(sheet, _)| *sheet));
+    for (sheet, context) in scoped {
+        for rule in sheet.rules.iter().filter(|rule| rule.applies()) {
             for selector in rule.selectors() {
-                let (matched, specificity) = match_selector::<C>(doc, id, selector, pseudo);
+                let (matched, specificity) = match_selector_in::<C>(doc, id, selector, pseudo, context);
 
                 if !matched {
                     continue;
                 }
+                let layer = layer_order.rank(&rule.layers);
 
                 // Selector matched, so we add all declared values to the map
                 for declaration in rule.declarations() {
@@ -130,6 +314,16 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                     // Normalize vendor-prefixed values (-webkit-X → X) so they match
                     // against the standard keyword definitions.
                     let value = normalize_vendor_prefixes(value);
+                    // Legacy pages write `width: 100` and mean pixels; only honoured for author
+                    // styles in quirks mode.
+                    let value = if quirks
+                        && sheet.origin == CssOrigin::Author
+                        && UNITLESS_LENGTH_PROPERTIES.contains(&declaration.property.as_str())
+                    {
+                        apply_unitless_length_quirk(value)
+                    } else {
+                        value
+                    };
 
                     // `content` carries arbitrary tokens (strings, `attr()`, counters,
                     // quotes) that the property-syntax matcher cannot validate - notably the
@@ -140,6 +334,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                             &mut css_map_entry,
                             sheet,
                             specificity,
+                            layer,
                             &CssDeclaration {
                                 property: "content".to_string(),
                                 value,
@@ -168,6 +363,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                                 declaration.important,
                                 sheet.url.clone(),
                                 specificity,
+                                layer,
                             ));
 
                             // Each CSS declaration starts with a fresh TRBL multiplier
@@ -196,6 +392,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                                             &mut css_map_entry,
                                             sheet,
                                             specificity,
+                                            layer,
                                             &CssDeclaration {
                                                 property: "background-image".to_string(),
                                                 value: image_value,
@@ -209,6 +406,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                                             &mut css_map_entry,
                                             sheet,
                                             specificity,
+                                            layer,
                                             &CssDeclaration {
                                                 property: "background-color".to_string(),
                                                 value: color_value,
@@ -242,6 +440,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                                 &mut css_map_entry,
                                 sheet,
                                 specificity,
+                                layer,
                                 &CssDeclaration {
                                     property: declaration.property.clone(),
                                     value,
@@ -270,6 +469,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                                 &mut css_map_entry,
                                 sheet,
                                 specificity,
+                                layer,
                                 &CssDeclaration {
                                     property: declaration.property.clone(),
                                     value,
@@ -345,6 +545,200 @@ fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
     fp
 }
 
+/// Cascade precedence of a declaration: origin, importance and cascade layer, then the `style`
+/// attribute over selectors, then specificity, then source order.
+type Precedence = ((u8, u32), bool, Specificity, usize);
+
+/// A matched rule with its declarations, specificity, cascade layer rank and source order.
+type Matched<'a> = (MatchedRule, &'a [CssDeclaration], Specificity, u32, usize);
+
+/// Collects the rules matching `id` (plus its `style` attribute) for inspection, and marks the
+/// declarations that lose on every longhand they set.
+fn matched_rules_impl<C: HasDocument<CssSystem = Css3System>>(
+    doc: &C::Document,
+    id: NodeId,
+    sheets: &[CssStylesheet],
+) -> Vec<MatchedRule> {
+    // The `style` attribute parses as the body of a rule; braces would let it escape that rule.
+    let inline_sheet = doc
+        .attribute(id, "style")
+        .filter(|style| !style.contains(['{', '}']))
+        .and_then(|style| {
+            Css3System::parse_str(
+                &format!("* {{{style}}}"),
+                ParserConfig::default(),
+                CssOrigin::Author,
+                "",
+            )
+            .ok()
+        });
+
+    let mut matched: Vec<Matched<'_>> = Vec::new();
+    let mut order = 0;
+    let scoped = scoped_sheets::<C>(doc, id, sheets);
+    let layer_order = LayerOrder::new(scoped.iter().map(|(sheet, _)| *sheet));
+    for (sheet, context) in scoped {
+        for rule in sheet.rules.iter().filter(|rule| rule.applies()) {
+            order += 1;
+            let layer = layer_order.rank(&rule.layers);
+            for selector in rule.selectors() {
+                let Some(part) = matching_alternative_in::<C>(doc, id, selector, None, context) else {
+                    continue;
+                };
+                let specificity = Specificity::from(part);
+                let info = MatchedRule {
+                    // The selector of the list that matched, not the whole list.
+                    selector: CssSelector {
+                        parts: vec![part.to_vec()],
+                    }
+                    .to_string(),
+                    sheet_url: sheet.url.clone(),
+                    location: rule.location,
+                    specificity: specificity.components(),
+                    origin: sheet.origin,
+                    inline: false,
+                    declarations: Vec::new(),
+                };
+                matched.push((info, rule.declarations().as_slice(), specificity, layer, order));
+            }
+        }
+    }
+
+    if let Some(rule) = inline_sheet.as_ref().and_then(|sheet| sheet.rules.first()) {
+        let info = MatchedRule {
+            selector: String::new(),
+            sheet_url: String::new(),
+            location: Location::default(),
+            specificity: (0, 0, 0),
+            origin: CssOrigin::Author,
+            inline: true,
+            declarations: Vec::new(),
+        };
+        matched.push((
+            info,
+            rule.declarations().as_slice(),
+            Specificity::new(0, 0, 0),
+            UNLAYERED,
+            order + 1,
+        ));
+    }
+
+    // The winning declaration per longhand, so shorthands only lose when all their parts do.
+    let definitions = get_css_definitions();
+    let longhands = |property: &str| -> Vec<String> {
+        definitions
+            .find_property(property)
+            .map(|definition| definition.expanded_properties())
+            .filter(|expanded| expanded.len() > 1)
+            .unwrap_or_else(|| vec![property.to_string()])
+    };
+    let precedence = |(info, _, specificity, layer, order): &Matched<'_>, important: bool| -> Precedence {
+        (
+            cascade_priority(info.origin, important, *layer),
+            info.inline,
+            *specificity,
+            *order,
+        )
+    };
+
+    let mut winners: HashMap<String, (Precedence, usize)> = HashMap::new();
+    for entry in &matched {
+        for (index, declaration) in entry.1.iter().enumerate() {
+            let key = precedence(entry, declaration.important);
+            for longhand in longhands(&declaration.property) {
+                let winner = winners.entry(longhand).or_insert((key, index));
+                // Later declarations win ties, both across rules and within one.
+                if (key, index) >= *winner {
+                    *winner = (key, index);
+                }
+            }
+        }
+    }
+
+    let mut rules: Vec<(Precedence, MatchedRule)> = matched
+        .iter()
+        .map(|entry| {
+            let mut info = entry.0.clone();
+            info.declarations = entry
+                .1
+                .iter()
+                .enumerate()
+                .map(|(index, declaration)| {
+                    let key = precedence(entry, declaration.important);
+                    let overridden = longhands(&declaration.property)
+                        .iter()
+                        .all(|longhand| winners.get(longhand) != Some(&(key, index)));
+                    MatchedDeclaration {
+                        property: declaration.property.clone(),
+                        value: declaration.value.to_string(),
+                        important: declaration.important,
+                        overridden,
+                    }
+                })
+                .collect();
+            (precedence(entry, false), info)
+        })
+        .collect();
+
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.0));
+    rules.into_iter().map(|(_, info)| info).collect()
+}
+
+/// Splits a `style` attribute into its declarations at the top-level semicolons, so that each
+/// keeps its source text, and has the parser validate them one by one.
+fn parse_style_attribute_impl(style: &str) -> Vec<StyleDeclaration> {
+    let mut stream = ByteStream::from_str(style, Encoding::UTF8);
+    let mut tokenizer = Tokenizer::new(&mut stream, Location::default());
+
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    loop {
+        let token = tokenizer.consume();
+        match token.token_type {
+            TokenType::Function(_) | TokenType::LParen | TokenType::LBracket | TokenType::LCurly => depth += 1,
+            TokenType::RParen | TokenType::RBracket | TokenType::RCurly => depth = depth.saturating_sub(1),
+            TokenType::Semicolon if depth > 0 => {}
+            TokenType::Semicolon | TokenType::Eof => {
+                let end = token.location.offset;
+                declarations.extend(style.get(start..end).and_then(parse_style_declaration));
+                if token.token_type == TokenType::Eof {
+                    break;
+                }
+                start = end + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations
+}
+
+/// Parses a single `property: value` declaration of a `style` attribute
+fn parse_style_declaration(text: &str) -> Option<StyleDeclaration> {
+    // The declaration parses as the body of a rule; braces would let it escape that rule.
+    if text.contains(['{', '}']) {
+        return None;
+    }
+    let sheet = Css3System::parse_str(&format!("* {{{text}}}"), ParserConfig::default(), CssOrigin::Author, "").ok()?;
+    let declaration = sheet.rules.first()?.declarations().first()?;
+    let property = declaration.property.cow_to_ascii_lowercase();
+    get_css_definitions().find_property(&property)?;
+
+    let (_, value) = text.split_once(':')?;
+    let value = value.trim();
+    let value = match value.rsplit_once('!') {
+        Some((value, important)) if declaration.important && important.trim().eq_ignore_ascii_case("important") => {
+            value.trim_end()
+        }
+        _ => value,
+    };
+    Some(StyleDeclaration {
+        property: property.into_owned(),
+        value: value.to_string(),
+        important: declaration.important,
+    })
+}
+
 #[must_use]
 pub fn prop_is_inherit(name: &str) -> bool {
     get_css_definitions()
@@ -356,6 +750,7 @@ pub fn add_property_to_map(
     css_map_entry: &mut CssProperties,
     sheet: &crate::stylesheet::CssStylesheet,
     specificity: Specificity,
+    layer: u32,
     declaration: &CssDeclaration,
 ) {
     let property_name = declaration.property.clone();
@@ -367,6 +762,7 @@ pub fn add_property_to_map(
         important: declaration.important,
         location: sheet.url.clone(),
         specificity,
+        layer,
     };
 
     css_map_entry
@@ -387,7 +783,54 @@ pub fn node_is_unrenderable<C: HasDocument>(doc: &C::Document, id: NodeId) -> bo
     }
 }
 
-/// Collects all custom property (`--*`) values visible to `id`, walking ancestors
+/// The stylesheets whose rules can apply to `id`, in cascade order, each with the shadow tree
+/// context its selectors are matched in:
+///
+/// - `::slotted()` rules of the shadow tree `id` is assigned into,
+/// - the rules of the shadow tree `id` hosts (which only reach it through `:host`),
+/// - the rules of the tree `id` lives in. Author styles of the document do not cross into a
+///   shadow tree; user agent and user styles apply everywhere.
+///
+/// Rules of an outer tree come later, so they win over the inner tree on equal specificity.
+fn scoped_sheets<'a, C: HasDocument<CssSystem = Css3System>>(
+    doc: &'a C::Document,
+    id: NodeId,
+    sheets: &'a [CssStylesheet],
+) -> Vec<(&'a CssStylesheet, MatchContext)> {
+    let mut scoped = Vec::new();
+
+    if let Some(slot) = doc.assigned_slot(id) {
+        let root = doc.tree_root(slot);
+        if let Some(host) = doc.shadow_host(root) {
+            let context = MatchContext::Slotted { slot, host };
+            scoped.extend(doc.shadow_stylesheets(root).iter().map(|sheet| (sheet, context)));
+        }
+    }
+
+    if let Some(root) = doc.shadow_root(id) {
+        let context = MatchContext::Tree { host: Some(id) };
+        scoped.extend(doc.shadow_stylesheets(root).iter().map(|sheet| (sheet, context)));
+    }
+
+    let root = doc.tree_root(id);
+    match doc.shadow_host(root) {
+        Some(host) => {
+            scoped.extend(
+                sheets
+                    .iter()
+                    .filter(|sheet| sheet.origin != CssOrigin::Author)
+                    .map(|sheet| (sheet, MatchContext::DOCUMENT)),
+            );
+            let context = MatchContext::Tree { host: Some(host) };
+            scoped.extend(doc.shadow_stylesheets(root).iter().map(|sheet| (sheet, context)));
+        }
+        None => scoped.extend(sheets.iter().map(|sheet| (sheet, MatchContext::DOCUMENT))),
+    }
+
+    scoped
+}
+
+/// Collects all custom property (`--*`) values visible to `id`, walking its flat tree ancestors
 /// root-first so that each element's own declarations override inherited ones.
 fn collect_custom_props<C: HasDocument<CssSystem = Css3System>>(
     doc: &C::Document,
@@ -396,7 +839,7 @@ fn collect_custom_props<C: HasDocument<CssSystem = Css3System>>(
 ) -> HashMap<String, CssValue> {
     let mut chain = vec![id];
     let mut cur = id;
-    while let Some(parent) = doc.parent(cur) {
+    while let Some(parent) = doc.flat_tree_parent(cur) {
         chain.push(parent);
         cur = parent;
     }
@@ -404,10 +847,10 @@ fn collect_custom_props<C: HasDocument<CssSystem = Css3System>>(
 
     let mut custom_props: HashMap<String, CssValue> = HashMap::new();
     for node_id in chain {
-        for sheet in sheets {
-            for rule in &sheet.rules {
+        for (sheet, context) in scoped_sheets::<C>(doc, node_id, sheets) {
+            for rule in sheet.rules.iter().filter(|rule| rule.applies()) {
                 for selector in rule.selectors() {
-                    let (matched, _) = match_selector::<C>(doc, node_id, selector, None);
+                    let (matched, _) = match_selector_in::<C>(doc, node_id, selector, None, context);
                     if !matched {
                         continue;
                     }
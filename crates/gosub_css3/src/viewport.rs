@@ -0,0 +1,177 @@
+//! `<meta name="viewport">` handling.
+//!
+//! Mobile browsers lay a page out against a *layout viewport* that can be wider than the screen
+//! and then scale it down to fit the *visual viewport* (the device screen). The page controls both
+//! through the viewport meta tag, e.g. `width=device-width, initial-scale=1`. This module parses
+//! the tag's `content` attribute and resolves it against a device size, following the translation
+//! described in CSS Device Adaptation.
+//!
+//! The resolved layout viewport is what the render flow hands to
+//! [`set_layout_viewport`](crate::stylesheet::set_layout_viewport) and the layouter.
+
+use cow_utils::CowUtils;
+
+/// Layout width used by mobile browsers for pages without a viewport meta tag.
+pub const DEFAULT_LAYOUT_WIDTH: f32 = 980.0;
+
+/// Lower bound for any zoom factor in the viewport meta tag.
+const MIN_SCALE: f32 = 0.1;
+/// Upper bound for any zoom factor in the viewport meta tag.
+const MAX_SCALE: f32 = 10.0;
+/// Upper bound for an explicit `width`.
+const MAX_WIDTH: f32 = 10000.0;
+
+/// The `width` descriptor of the viewport meta tag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportWidth {
+    /// `width=device-width`: the device width in CSS pixels.
+    DeviceWidth,
+    /// An explicit width in CSS pixels.
+    Px(f32),
+}
+
+/// Parsed `content` of a `<meta name="viewport">` tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ViewportMeta {
+    pub width: Option<ViewportWidth>,
+    pub initial_scale: Option<f32>,
+    pub minimum_scale: Option<f32>,
+    pub maximum_scale: Option<f32>,
+}
+
+/// A layout viewport resolved against a device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolvedViewport {
+    /// Layout viewport width in CSS pixels.
+    pub width: f32,
+    /// Layout viewport height in CSS pixels.
+    pub height: f32,
+    /// Scale from the layout viewport to the visual viewport (device CSS pixels).
+    pub scale: f32,
+}
+
+impl ViewportMeta {
+    /// Parses the `content` attribute of a viewport meta tag.
+    ///
+    /// Properties are `key=value` pairs separated by commas (semicolons are accepted as well, as
+    /// browsers do). Unknown keys and unparsable values are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut meta = Self::default();
+
+        for pair in content.split([',', ';']) {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().cow_to_ascii_lowercase();
+            let value = value.trim().cow_to_ascii_lowercase();
+
+            match key.as_ref() {
+                "width" => {
+                    meta.width = match value.as_ref() {
+                        "device-width" => Some(ViewportWidth::DeviceWidth),
+                        _ => parse_number(&value).map(|w| ViewportWidth::Px(w.clamp(1.0, MAX_WIDTH))),
+                    }
+                }
+                "initial-scale" => meta.initial_scale = parse_scale(&value),
+                "minimum-scale" => meta.minimum_scale = parse_scale(&value),
+                "maximum-scale" => meta.maximum_scale = parse_scale(&value),
+                _ => {}
+            }
+        }
+
+        meta
+    }
+
+    /// Resolves the layout viewport for a device of `device_width` × `device_height` CSS pixels.
+    ///
+    /// Without a `width`, the layout width follows from `initial-scale`, or falls back to
+    /// [`DEFAULT_LAYOUT_WIDTH`]. Without an `initial-scale`, the page is scaled to fit the layout
+    /// width into the device width. The layout viewport is never narrower than what the visual
+    /// viewport shows at the chosen scale.
+    pub fn resolve(&self, device_width: f32, device_height: f32) -> ResolvedViewport {
+        let min = self.minimum_scale.unwrap_or(MIN_SCALE);
+        let max = self.maximum_scale.unwrap_or(MAX_SCALE).max(min);
+        let initial_scale = self.initial_scale.map(|s| s.clamp(min, max));
+
+        let explicit_width = self.width.map(|w| match w {
+            ViewportWidth::DeviceWidth => device_width,
+            ViewportWidth::Px(px) => px,
+        });
+
+        let (width, scale) = match (explicit_width, initial_scale) {
+            (Some(width), Some(scale)) => (width.max(device_width / scale), scale),
+            (Some(width), None) => (width, (device_width / width).clamp(min, max)),
+            (None, Some(scale)) => (device_width / scale, scale),
+            (None, None) => (
+                DEFAULT_LAYOUT_WIDTH,
+                (device_width / DEFAULT_LAYOUT_WIDTH).clamp(min, max),
+            ),
+        };
+
+        ResolvedViewport {
+            width,
+            height: device_height / scale,
+            scale,
+        }
+    }
+}
+
+/// Parses the leading number of a descriptor value (`"1.0"`, `"2px"` → 2.0).
+fn parse_number(value: &str) -> Option<f32> {
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    value[..end].parse::<f32>().ok().filter(|v| v.is_finite() && *v > 0.0)
+}
+
+fn parse_scale(value: &str) -> Option<f32> {
+    parse_number(value).map(|s| s.clamp(MIN_SCALE, MAX_SCALE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_content() {
+        let meta = ViewportMeta::parse("width=device-width, initial-scale=1.0, maximum-scale=2");
+        assert_eq!(meta.width, Some(ViewportWidth::DeviceWidth));
+        assert_eq!(meta.initial_scale, Some(1.0));
+        assert_eq!(meta.maximum_scale, Some(2.0));
+        assert_eq!(meta.minimum_scale, None);
+
+        let meta = ViewportMeta::parse("WIDTH = 600; user-scalable=no; bogus");
+        assert_eq!(meta.width, Some(ViewportWidth::Px(600.0)));
+        assert_eq!(meta.initial_scale, None);
+    }
+
+    #[test]
+    fn resolves_device_width() {
+        let vp = ViewportMeta::parse("width=device-width, initial-scale=1").resolve(390.0, 844.0);
+        assert_eq!(
+            vp,
+            ResolvedViewport {
+                width: 390.0,
+                height: 844.0,
+                scale: 1.0
+            }
+        );
+
+        let vp = ViewportMeta::parse("initial-scale=2").resolve(400.0, 800.0);
+        assert_eq!((vp.width, vp.height, vp.scale), (200.0, 400.0, 2.0));
+    }
+
+    #[test]
+    fn missing_meta_lays_out_desktop_width_and_scales_to_fit() {
+        let vp = ViewportMeta::default().resolve(490.0, 1000.0);
+        assert_eq!(vp.width, DEFAULT_LAYOUT_WIDTH);
+        assert_eq!(vp.scale, 0.5);
+        assert_eq!(vp.height, 2000.0);
+    }
+
+    #[test]
+    fn explicit_width_is_scaled_to_fit() {
+        let vp = ViewportMeta::parse("width=800").resolve(400.0, 600.0);
+        assert_eq!((vp.width, vp.scale), (800.0, 0.5));
+    }
+}
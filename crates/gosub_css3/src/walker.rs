@@ -123,8 +123,11 @@ fn inner_walk(node: &Node, depth: usize, f: &mut dyn Write) -> Result<(), std::i
                 inner_walk(child, depth + 1, f)?;
             }
         }
-        NodeType::PseudoElementSelector { value } => {
+        NodeType::PseudoElementSelector { value, argument } => {
             writeln!(f, "{prefix}[PseudoElementSelector] {value}")?;
+            if let Some(argument) = argument {
+                inner_walk(argument, depth + 1, f)?;
+            }
         }
         NodeType::PseudoClassSelector { value } => {
             writeln!(f, "{prefix}[PseudoClassSelector]")?;
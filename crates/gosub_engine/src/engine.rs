@@ -2,6 +2,7 @@
 //!
 //! Most users should start with [`GosubEngine`].
 
+mod accessibility;
 mod context;
 #[allow(clippy::module_inception)]
 mod engine;
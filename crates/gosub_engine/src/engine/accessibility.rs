@@ -0,0 +1,767 @@
+//! Accessibility tree for screen readers, delivered as AccessKit tree updates.
+//!
+//! The tree is derived from the DOM and the cached layout: roles come from HTML semantics or an
+//! ARIA `role`, names follow a simplified accessible-name computation (`aria-labelledby`,
+//! `aria-label`, `alt`, `<label for>`, then text content), and states come from attributes
+//! (`checked`, `disabled`, `aria-expanded`, ...) and the tab's focus. Bounds are border boxes in
+//! document CSS pixels; the document node carries the zoom and scroll transform, so scrolling only
+//! changes one node. Hosts with a device pixel ratio other than 1 scale the root themselves.
+//!
+//! [`AccessibilityTracker`] remembers what the host has been sent so each update carries only the
+//! nodes that changed.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use accesskit::{
+    Action, Affine, Node, NodeId as AxNodeId, Rect as AxRect, Role, Toggled, Tree, TreeId, TreeUpdate, Vec2,
+};
+use cow_utils::CowUtils;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+
+/// Elements that never render and are left out of the tree with their subtree.
+const NON_RENDERED: &[&str] = &[
+    "head", "script", "style", "template", "meta", "link", "title", "noscript", "base",
+];
+
+/// Elements that do not get a node of their own; their children are attached to the parent.
+const TRANSPARENT: &[&str] = &["html", "body"];
+
+/// Everything the tree is built from.
+pub(crate) struct AccessibilitySource<'a, C: RenderConfiguration> {
+    pub doc: &'a EngineDocument<C>,
+    /// Border box per DOM node, empty when the document has not been laid out yet.
+    pub boxes: &'a HashMap<NodeId, Rect>,
+    pub focused: Option<NodeId>,
+    pub page_size: (f64, f64),
+    pub zoom: f64,
+    pub scroll: (f64, f64),
+}
+
+/// A computed tree: its nodes, root and focused node.
+pub(crate) struct AccessibilityTree {
+    pub nodes: Vec<(AxNodeId, Node)>,
+    pub root: AxNodeId,
+    pub focus: AxNodeId,
+}
+
+/// Builds the accessibility tree of a document.
+pub(crate) fn build_tree<C: RenderConfiguration>(source: &AccessibilitySource<'_, C>) -> AccessibilityTree {
+    let doc = source.doc;
+    let root_id = doc.root();
+    let mut builder = Builder {
+        source,
+        nodes: Vec::new(),
+        focus: None,
+    };
+
+    let mut children = Vec::new();
+    for &child in doc.children(root_id) {
+        builder.visit(child, &mut children);
+    }
+
+    let mut root = Node::new(Role::Document);
+    let (width, height) = source.page_size;
+    root.set_bounds(AxRect::new(0.0, 0.0, width, height));
+    let (scroll_x, scroll_y) = source.scroll;
+    root.set_transform(Affine::translate(Vec2::new(-scroll_x, -scroll_y)) * Affine::scale(source.zoom));
+    if let Some(title) = crate::html::document_title(doc) {
+        root.set_label(title);
+    }
+    root.set_children(children);
+
+    let root_ax = ax_id(root_id);
+    let focus = builder.focus.unwrap_or(root_ax);
+    let mut nodes = builder.nodes;
+    nodes.push((root_ax, root));
+    AccessibilityTree {
+        nodes,
+        root: root_ax,
+        focus,
+    }
+}
+
+struct Builder<'s, 'a, C: RenderConfiguration> {
+    source: &'s AccessibilitySource<'a, C>,
+    nodes: Vec<(AxNodeId, Node)>,
+    focus: Option<AxNodeId>,
+}
+
+impl<C: RenderConfiguration> Builder<'_, '_, C> {
+    /// Adds the nodes for `id` and its subtree, pushing the ids of the top-most ones onto `out`.
+    /// Returns `true` when anything was added.
+    fn visit(&mut self, id: NodeId, out: &mut Vec<AxNodeId>) -> bool {
+        let doc = self.source.doc;
+        match doc.node_type(id) {
+            NodeType::TextNode => self.visit_text(id, out),
+            NodeType::ElementNode => self.visit_element(id, out),
+            _ => false,
+        }
+    }
+
+    fn visit_text(&mut self, id: NodeId, out: &mut Vec<AxNodeId>) -> bool {
+        let doc = self.source.doc;
+        let text = collapse_whitespace(doc.text_value(id).unwrap_or(""));
+        if text.is_empty() {
+            return false;
+        }
+        // Text is only exposed inside a rendered element (see `visit_element`).
+        if !self.source.boxes.is_empty() && !doc.parent(id).is_some_and(|p| self.source.boxes.contains_key(&p)) {
+            return false;
+        }
+
+        let mut node = Node::new(Role::Label);
+        node.set_value(text);
+        if let Some(rect) = self.source.boxes.get(&id) {
+            node.set_bounds(ax_rect(rect));
+        }
+        let ax = ax_id(id);
+        self.nodes.push((ax, node));
+        out.push(ax);
+        true
+    }
+
+    fn visit_element(&mut self, id: NodeId, out: &mut Vec<AxNodeId>) -> bool {
+        let doc = self.source.doc;
+        let tag = doc.tag_name(id).unwrap_or("").cow_to_ascii_lowercase();
+        if NON_RENDERED.contains(&tag.as_ref())
+            || doc.attribute(id, "hidden").is_some()
+            || attr_is(doc, id, "aria-hidden", "true")
+        {
+            return false;
+        }
+
+        let Some(role) = role_for(doc, id, &tag) else {
+            return false;
+        };
+
+        let mut children = Vec::new();
+        for &child in doc.children(id) {
+            self.visit(child, &mut children);
+        }
+
+        if TRANSPARENT.contains(&tag.as_ref()) {
+            let added = !children.is_empty();
+            out.append(&mut children);
+            return added;
+        }
+
+        let rect = self.source.boxes.get(&id);
+        // Laid out, but no box: `display: none` (nothing below survives either) or
+        // `display: contents` (the children carry the content).
+        if !self.source.boxes.is_empty() && rect.is_none() {
+            let added = !children.is_empty();
+            out.append(&mut children);
+            return added;
+        }
+
+        let mut node = Node::new(role);
+        if let Some(rect) = rect {
+            node.set_bounds(ax_rect(rect));
+        }
+        if let Some(name) = accessible_name(doc, id, &tag, role) {
+            node.set_label(name);
+        }
+        apply_states(&mut node, doc, id, &tag, role);
+        if role == Role::Link {
+            if let Some(href) = doc.attribute(id, "href") {
+                node.set_url(href);
+            }
+        }
+        if is_focusable(doc, id, &tag) {
+            node.add_action(Action::Focus);
+        }
+        if matches!(
+            role,
+            Role::Button | Role::Link | Role::CheckBox | Role::RadioButton | Role::Switch | Role::MenuItem | Role::Tab
+        ) {
+            node.add_action(Action::Click);
+        }
+        node.set_children(children);
+
+        let ax = ax_id(id);
+        if self.source.focused == Some(id) {
+            self.focus = Some(ax);
+        }
+        self.nodes.push((ax, node));
+        out.push(ax);
+        true
+    }
+}
+
+/// Keeps the tree last sent to the host, to turn new trees into minimal updates.
+#[derive(Default)]
+pub(crate) struct AccessibilityTracker {
+    sent: HashMap<AxNodeId, Node>,
+    root: Option<AxNodeId>,
+    focus: Option<AxNodeId>,
+}
+
+impl AccessibilityTracker {
+    /// Returns an update carrying the whole tree, for a host that has nothing yet.
+    pub(crate) fn full(&mut self, tree: AccessibilityTree) -> TreeUpdate {
+        self.sent.clear();
+        self.root = None;
+        self.focus = None;
+        self.diff(tree).unwrap_or_else(|| TreeUpdate {
+            nodes: Vec::new(),
+            tree: None,
+            tree_id: TreeId::ROOT,
+            focus: AxNodeId(0),
+        })
+    }
+
+    /// Returns the nodes that are new or changed since the last update, or `None` when nothing
+    /// changed. Removed nodes need no mention: dropping them from their parent's children is
+    /// enough for AccessKit.
+    pub(crate) fn diff(&mut self, tree: AccessibilityTree) -> Option<TreeUpdate> {
+        let mut changed = Vec::new();
+        let mut current = HashMap::with_capacity(tree.nodes.len());
+        for (id, node) in tree.nodes {
+            if self.sent.get(&id) != Some(&node) {
+                changed.push((id, node.clone()));
+            }
+            current.insert(id, node);
+        }
+
+        let new_root = self.root != Some(tree.root);
+        if changed.is_empty() && !new_root && self.focus == Some(tree.focus) {
+            return None;
+        }
+
+        self.sent = current;
+        self.root = Some(tree.root);
+        self.focus = Some(tree.focus);
+        Some(TreeUpdate {
+            nodes: changed,
+            tree: new_root.then(|| Tree::new(tree.root)),
+            tree_id: TreeId::ROOT,
+            focus: tree.focus,
+        })
+    }
+}
+
+/// Role of an element: its first known ARIA role, else the implicit role of the tag. `None` for
+/// elements that are not exposed at all.
+fn role_for<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, tag: &str) -> Option<Role> {
+    if let Some(roles) = doc.attribute(id, "role") {
+        if let Some(role) = roles.split_ascii_whitespace().find_map(aria_role) {
+            return Some(role);
+        }
+    }
+
+    let input_type = || {
+        doc.attribute(id, "type")
+            .unwrap_or("text")
+            .trim()
+            .cow_to_ascii_lowercase()
+            .into_owned()
+    };
+    Some(match tag {
+        "a" | "area" if doc.attribute(id, "href").is_some() => Role::Link,
+        "button" | "summary" => Role::Button,
+        "input" => match input_type().as_str() {
+            "hidden" => return None,
+            "checkbox" => Role::CheckBox,
+            "radio" => Role::RadioButton,
+            "button" | "submit" | "reset" | "image" => Role::Button,
+            "range" => Role::Slider,
+            "number" => Role::SpinButton,
+            _ if doc.attribute(id, "list").is_some() => Role::ComboBox,
+            _ => Role::TextInput,
+        },
+        "textarea" => Role::MultilineTextInput,
+        "select" => {
+            let size = doc.attribute(id, "size").and_then(|s| s.trim().parse::<u32>().ok());
+            if doc.attribute(id, "multiple").is_some() || size.is_some_and(|s| s > 1) {
+                Role::ListBox
+            } else {
+                Role::ComboBox
+            }
+        }
+        "option" => Role::ListBoxOption,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Role::Heading,
+        "img" => Role::Image,
+        "ul" | "ol" | "menu" => Role::List,
+        "li" => Role::ListItem,
+        "table" => Role::Table,
+        "tr" => Role::Row,
+        "td" => Role::Cell,
+        "th" if attr_is(doc, id, "scope", "row") => Role::RowHeader,
+        "th" => Role::ColumnHeader,
+        "nav" => Role::Navigation,
+        "main" => Role::Main,
+        "header" => Role::Banner,
+        "footer" => Role::ContentInfo,
+        "aside" => Role::Complementary,
+        "form" => Role::Form,
+        "section" if doc.attribute(id, "aria-label").is_some() || doc.attribute(id, "aria-labelledby").is_some() => {
+            Role::Region
+        }
+        "article" => Role::Article,
+        "p" => Role::Paragraph,
+        "label" => Role::Label,
+        "dialog" => Role::Dialog,
+        "progress" => Role::ProgressIndicator,
+        "fieldset" | "details" | "figure" => Role::Group,
+        _ => Role::GenericContainer,
+    })
+}
+
+/// Maps an ARIA role token. `presentation`/`none` keep the element as a plain container so its
+/// children stay exposed.
+fn aria_role(token: &str) -> Option<Role> {
+    Some(match token.cow_to_ascii_lowercase().as_ref() {
+        "alert" => Role::Alert,
+        "article" => Role::Article,
+        "banner" => Role::Banner,
+        "button" => Role::Button,
+        "cell" | "gridcell" => Role::Cell,
+        "checkbox" => Role::CheckBox,
+        "columnheader" => Role::ColumnHeader,
+        "combobox" => Role::ComboBox,
+        "complementary" => Role::Complementary,
+        "contentinfo" => Role::ContentInfo,
+        "dialog" | "alertdialog" => Role::Dialog,
+        "document" => Role::Document,
+        "form" => Role::Form,
+        "grid" => Role::Grid,
+        "group" => Role::Group,
+        "heading" => Role::Heading,
+        "img" | "image" => Role::Image,
+        "link" => Role::Link,
+        "list" => Role::List,
+        "listbox" => Role::ListBox,
+        "listitem" => Role::ListItem,
+        "main" => Role::Main,
+        "menu" => Role::Menu,
+        "menubar" => Role::MenuBar,
+        "menuitem" => Role::MenuItem,
+        "navigation" => Role::Navigation,
+        "none" | "presentation" | "generic" => Role::GenericContainer,
+        "option" => Role::ListBoxOption,
+        "paragraph" => Role::Paragraph,
+        "progressbar" => Role::ProgressIndicator,
+        "radio" => Role::RadioButton,
+        "region" => Role::Region,
+        "row" => Role::Row,
+        "rowheader" => Role::RowHeader,
+        "search" => Role::Search,
+        "slider" => Role::Slider,
+        "spinbutton" => Role::SpinButton,
+        "switch" => Role::Switch,
+        "tab" => Role::Tab,
+        "table" => Role::Table,
+        "tablist" => Role::TabList,
+        "tabpanel" => Role::TabPanel,
+        "textbox" => Role::TextInput,
+        "tree" => Role::Tree,
+        "treeitem" => Role::TreeItem,
+        _ => return None,
+    })
+}
+
+/// Simplified accessible name computation (accname 1.2, without recursion into embedded controls).
+fn accessible_name<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    id: NodeId,
+    tag: &str,
+    role: Role,
+) -> Option<String> {
+    if let Some(ids) = doc.attribute(id, "aria-labelledby") {
+        let name = ids
+            .split_ascii_whitespace()
+            .filter_map(|label_id| doc.node_by_named_id(label_id))
+            .map(|label| text_content(doc, label))
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    if let Some(label) = non_empty(doc.attribute(id, "aria-label")) {
+        return Some(label);
+    }
+
+    match tag {
+        "img" | "area" => {
+            if let Some(alt) = non_empty(doc.attribute(id, "alt")) {
+                return Some(alt);
+            }
+        }
+        "input" => {
+            let kind = doc
+                .attribute(id, "type")
+                .unwrap_or("text")
+                .trim()
+                .cow_to_ascii_lowercase();
+            match kind.as_ref() {
+                "button" | "submit" | "reset" => {
+                    let fallback = match kind.as_ref() {
+                        "submit" => Some("Submit"),
+                        "reset" => Some("Reset"),
+                        _ => None,
+                    };
+                    return non_empty(doc.attribute(id, "value")).or(fallback.map(str::to_string));
+                }
+                "image" => return non_empty(doc.attribute(id, "alt")),
+                _ => {}
+            }
+        }
+        "fieldset" => return child_text(doc, id, "legend"),
+        "figure" => return child_text(doc, id, "figcaption"),
+        "table" => return child_text(doc, id, "caption"),
+        _ => {}
+    }
+
+    if matches!(tag, "input" | "select" | "textarea" | "meter" | "progress") {
+        if let Some(label) = label_for(doc, id) {
+            return Some(label);
+        }
+    }
+
+    if names_from_content(role) {
+        let text = text_content(doc, id);
+        if !text.is_empty() {
+            return Some(text);
+        }
+    }
+    non_empty(doc.attribute(id, "title"))
+}
+
+/// Roles whose name comes from their content when nothing else names them.
+fn names_from_content(role: Role) -> bool {
+    matches!(
+        role,
+        Role::Button
+            | Role::Cell
+            | Role::CheckBox
+            | Role::ColumnHeader
+            | Role::Heading
+            | Role::Link
+            | Role::ListBoxOption
+            | Role::MenuItem
+            | Role::RadioButton
+            | Role::Row
+            | Role::RowHeader
+            | Role::Switch
+            | Role::Tab
+            | Role::TreeItem
+    )
+}
+
+/// Text of the `<label>` that labels a form control: `<label for=id>`, else an enclosing label.
+fn label_for<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<String> {
+    if let Some(control_id) = doc.attribute(id, "id") {
+        let label = find_element(doc, doc.root(), &|node| {
+            doc.tag_name(node).is_some_and(|t| t.eq_ignore_ascii_case("label"))
+                && doc.attribute(node, "for") == Some(control_id)
+        });
+        if let Some(text) = label.map(|label| text_content(doc, label)).filter(|t| !t.is_empty()) {
+            return Some(text);
+        }
+    }
+
+    let mut ancestor = doc.parent(id);
+    while let Some(node) = ancestor {
+        if doc.tag_name(node).is_some_and(|t| t.eq_ignore_ascii_case("label")) {
+            let text = text_content(doc, node);
+            return (!text.is_empty()).then_some(text);
+        }
+        ancestor = doc.parent(node);
+    }
+    None
+}
+
+fn apply_states<C: RenderConfiguration>(node: &mut Node, doc: &EngineDocument<C>, id: NodeId, tag: &str, role: Role) {
+    if matches!(role, Role::Heading) {
+        let level = tag
+            .strip_prefix('h')
+            .and_then(|n| n.parse::<usize>().ok())
+            .or_else(|| doc.attribute(id, "aria-level").and_then(|l| l.trim().parse().ok()));
+        if let Some(level) = level {
+            node.set_level(level);
+        }
+    }
+
+    let toggled = match doc.attribute(id, "aria-checked").map(str::trim) {
+        Some("true") => Some(Toggled::True),
+        Some("false") => Some(Toggled::False),
+        Some("mixed") => Some(Toggled::Mixed),
+        _ if matches!(role, Role::CheckBox | Role::RadioButton) && tag == "input" => {
+            Some(if doc.attribute(id, "checked").is_some() {
+                Toggled::True
+            } else {
+                Toggled::False
+            })
+        }
+        _ => None,
+    };
+    if let Some(toggled) = toggled {
+        node.set_toggled(toggled);
+    }
+
+    let disabled_attr = matches!(
+        tag,
+        "button" | "input" | "select" | "textarea" | "option" | "optgroup" | "fieldset"
+    ) && doc.attribute(id, "disabled").is_some();
+    if disabled_attr || attr_is(doc, id, "aria-disabled", "true") {
+        node.set_disabled();
+    }
+
+    match doc.attribute(id, "aria-expanded").map(str::trim) {
+        Some("true") => node.set_expanded(true),
+        Some("false") => node.set_expanded(false),
+        _ if tag == "details" => node.set_expanded(doc.attribute(id, "open").is_some()),
+        _ => {}
+    }
+}
+
+/// Whether an element can take focus: interactive HTML elements and anything with a `tabindex`.
+pub(crate) fn is_focusable<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, tag: &str) -> bool {
+    if doc.attribute(id, "tabindex").is_some() {
+        return true;
+    }
+    match tag {
+        "a" | "area" => doc.attribute(id, "href").is_some(),
+        "button" | "select" | "textarea" | "summary" => doc.attribute(id, "disabled").is_none(),
+        "input" => {
+            doc.attribute(id, "disabled").is_none()
+                && !doc
+                    .attribute(id, "type")
+                    .is_some_and(|t| t.trim().eq_ignore_ascii_case("hidden"))
+        }
+        _ => false,
+    }
+}
+
+/// Whitespace-collapsed text of an element's subtree.
+fn text_content<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> String {
+    fn collect<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, out: &mut String) {
+        for &child in doc.children(id) {
+            match doc.node_type(child) {
+                NodeType::TextNode => {
+                    out.push_str(doc.text_value(child).unwrap_or(""));
+                    out.push(' ');
+                }
+                NodeType::ElementNode => {
+                    let skip = doc
+                        .tag_name(child)
+                        .is_some_and(|t| NON_RENDERED.iter().any(|n| t.eq_ignore_ascii_case(n)));
+                    if !skip {
+                        collect(doc, child, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(doc, id, &mut text);
+    collapse_whitespace(&text)
+}
+
+fn child_text<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, tag: &str) -> Option<String> {
+    doc.children(id)
+        .iter()
+        .find(|&&child| doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case(tag)))
+        .map(|&child| text_content(doc, child))
+        .filter(|text| !text.is_empty())
+}
+
+fn find_element<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    id: NodeId,
+    pred: &dyn Fn(NodeId) -> bool,
+) -> Option<NodeId> {
+    for &child in doc.children(id) {
+        if doc.node_type(child) != NodeType::ElementNode {
+            continue;
+        }
+        if pred(child) {
+            return Some(child);
+        }
+        if let Some(found) = find_element(doc, child, pred) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn attr_is<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId, name: &str, value: &str) -> bool {
+    doc.attribute(id, name)
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case(value))
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(collapse_whitespace).filter(|v| !v.is_empty())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn ax_id(id: NodeId) -> AxNodeId {
+    AxNodeId(u64::from(id))
+}
+
+fn ax_rect(rect: &Rect) -> AxRect {
+    AxRect::new(rect.x, rect.y, rect.x + rect.width, rect.y + rect.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_html5::document::builder::DocumentBuilderImpl;
+    use gosub_html5::parser::Html5Parser;
+    use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+    fn parse(html: &str) -> EngineDocument<DefaultRenderConfig> {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(None);
+        let _ = Html5Parser::<DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+        doc
+    }
+
+    fn tree(doc: &EngineDocument<DefaultRenderConfig>, focused: Option<NodeId>) -> AccessibilityTree {
+        build_tree(&AccessibilitySource {
+            doc,
+            boxes: &HashMap::new(),
+            focused,
+            page_size: (800.0, 600.0),
+            zoom: 1.0,
+            scroll: (0.0, 0.0),
+        })
+    }
+
+    fn node_by_role(tree: &AccessibilityTree, role: Role) -> &Node {
+        tree.nodes
+            .iter()
+            .map(|(_, node)| node)
+            .find(|node| node.role() == role)
+            .unwrap_or_else(|| panic!("no {role:?} node"))
+    }
+
+    #[test]
+    fn roles_and_names_follow_html_and_aria() {
+        let doc = parse(
+            r#"<html><head><title>Shop</title><script>var x;</script></head><body>
+                <h2>Cart <em>items</em></h2>
+                <a href="/checkout">Check out</a>
+                <img src="a.png" alt="Logo">
+                <label for="qty">Quantity</label><input id="qty" type="number">
+                <label>Gift wrap <input type="checkbox" checked></label>
+                <div role="button" aria-label="Close" aria-expanded="false"></div>
+                <button disabled>Pay</button>
+                <input type="hidden" name="token">
+                <p hidden>secret</p>
+            </body></html>"#,
+        );
+        let tree = tree(&doc, None);
+
+        let root = tree
+            .nodes
+            .iter()
+            .find(|(id, _)| *id == tree.root)
+            .map(|(_, n)| n)
+            .unwrap();
+        assert_eq!(root.role(), Role::Document);
+        assert_eq!(root.label(), Some("Shop"));
+
+        let heading = node_by_role(&tree, Role::Heading);
+        assert_eq!(heading.label(), Some("Cart items"));
+        assert_eq!(heading.level(), Some(2));
+
+        let link = node_by_role(&tree, Role::Link);
+        assert_eq!(link.label(), Some("Check out"));
+        assert_eq!(link.url(), Some("/checkout"));
+
+        assert_eq!(node_by_role(&tree, Role::Image).label(), Some("Logo"));
+        assert_eq!(node_by_role(&tree, Role::SpinButton).label(), Some("Quantity"));
+
+        let checkbox = node_by_role(&tree, Role::CheckBox);
+        assert_eq!(checkbox.label(), Some("Gift wrap"));
+        assert_eq!(checkbox.toggled(), Some(Toggled::True));
+
+        let buttons: Vec<&Node> = tree
+            .nodes
+            .iter()
+            .map(|(_, n)| n)
+            .filter(|n| n.role() == Role::Button)
+            .collect();
+        assert_eq!(buttons.len(), 2);
+        assert_eq!(buttons[0].label(), Some("Close"));
+        assert_eq!(buttons[0].is_expanded(), Some(false));
+        assert_eq!(buttons[1].label(), Some("Pay"));
+        assert!(buttons[1].is_disabled());
+
+        let texts: Vec<&str> = tree.nodes.iter().filter_map(|(_, n)| n.value()).collect();
+        assert!(!texts.contains(&"secret"));
+        assert!(!texts.contains(&"var x;"));
+        assert!(!tree.nodes.iter().any(|(_, n)| n.role() == Role::TextInput));
+    }
+
+    #[test]
+    fn tracker_sends_only_changes() {
+        let doc = parse(r#"<body><button id="a">A</button><button id="b">B</button></body>"#);
+        let b = doc.node_by_named_id("b").unwrap();
+        let mut tracker = AccessibilityTracker::default();
+
+        let full = tracker.full(tree(&doc, None));
+        assert!(full.tree.is_some());
+        assert_eq!(full.focus, AxNodeId(u64::from(doc.root())));
+        let total = full.nodes.len();
+        assert!(total >= 5);
+
+        assert!(tracker.diff(tree(&doc, None)).is_none());
+
+        let update = tracker.diff(tree(&doc, Some(b))).unwrap();
+        assert!(update.tree.is_none());
+        assert!(update.nodes.is_empty());
+        assert_eq!(update.focus, AxNodeId(u64::from(b)));
+
+        let scrolled = build_tree(&AccessibilitySource {
+            doc: &doc,
+            boxes: &HashMap::new(),
+            focused: Some(b),
+            page_size: (800.0, 600.0),
+            zoom: 1.0,
+            scroll: (0.0, 120.0),
+        });
+        let update = tracker.diff(scrolled).unwrap();
+        assert_eq!(update.nodes.len(), 1);
+        assert_eq!(update.nodes[0].0, AxNodeId(u64::from(doc.root())));
+    }
+
+    #[test]
+    fn unboxed_elements_are_dropped_once_laid_out() {
+        let doc = parse(r#"<body><p id="shown">Shown</p><p id="gone">Gone</p></body>"#);
+        let shown = doc.node_by_named_id("shown").unwrap();
+        let boxes = HashMap::from([(shown, Rect::new(0.0, 10.0, 100.0, 20.0))]);
+        let tree = build_tree(&AccessibilitySource {
+            doc: &doc,
+            boxes: &boxes,
+            focused: None,
+            page_size: (800.0, 600.0),
+            zoom: 1.0,
+            scroll: (0.0, 0.0),
+        });
+
+        let paragraphs: Vec<&Node> = tree
+            .nodes
+            .iter()
+            .map(|(_, n)| n)
+            .filter(|n| n.role() == Role::Paragraph)
+            .collect();
+        assert_eq!(paragraphs.len(), 1);
+        assert_eq!(paragraphs[0].bounds(), Some(AxRect::new(0.0, 10.0, 100.0, 30.0)));
+        let texts: Vec<&str> = tree.nodes.iter().filter_map(|(_, n)| n.value()).collect();
+        assert_eq!(texts, vec!["Shown"]);
+    }
+}
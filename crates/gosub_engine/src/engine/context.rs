@@ -8,9 +8,14 @@
 //! context via `set_document`, after which the context rebuilds whichever render
 //! representation the active backend consumes.
 
+use crate::engine::accessibility::{self, AccessibilitySource, AccessibilityTree};
+use crate::engine::events::{ElementRect, HitTestElement, NodeInspection};
 use crate::engine::storage::{StorageArea, StorageHandles};
 use crate::html::EngineDocument;
+use crate::tab::DeviceEmulation;
+use cow_utils::CowUtils;
 use gosub_config::{Config, HasConfig};
+use gosub_css3::viewport::ViewportMeta;
 use gosub_render_pipeline::rasterizer::{
     collect_placed_gpu_tiles, cpu_cached_tiles, rasterize_parallel, rasterize_sequential, BakedTile, RasterStrategy,
     Rasterable, TilePixelCache,
@@ -19,13 +24,20 @@ use gosub_render_pipeline::render::{Color, DisplayItem, RenderContext, RenderLis
 use std::sync::Arc;
 
 use crate::html::RenderConfiguration;
-use gosub_interface::css3::{CssSystem, HoverFingerprints};
+use gosub_interface::css3::{ColorScheme, CssSystem, HoverFingerprints, MediaFeatures, PointerAccuracy};
 use gosub_interface::document::Document as _;
+use gosub_interface::node::{NodeType, QuirksMode};
+use gosub_render_pipeline::common::browser_state::DebugOverlays;
+use gosub_render_pipeline::common::document::pipeline_doc::PipelineDocument;
+use gosub_render_pipeline::common::geo::Rect as PipelineRect;
 use gosub_render_pipeline::common::texture::TilePixels;
 use gosub_render_pipeline::layering::layer::LayerList;
 use gosub_render_pipeline::layouter::LayoutElementId;
+use gosub_render_pipeline::painter::commands::PaintCommand;
+use gosub_render_pipeline::painter::debug_overlay::OverlayTile;
 use gosub_render_pipeline::painter::{PaintScene, Painter};
-use gosub_render_pipeline::render::backend::{CachedTile, ExternalHandle};
+use gosub_render_pipeline::print::{self, PageSetup, PageSetups, PrintDocument};
+use gosub_render_pipeline::render::backend::{anchored_tile_pos, CachedTile, ExternalHandle};
 use gosub_shared::node::NodeId;
 use std::any::Any;
 
@@ -48,6 +60,19 @@ fn hover_matches<C: RenderConfiguration>(fp: &HoverFingerprints, doc: &EngineDoc
             return true;
         }
     }
+    // Quirks mode selectors match class names and ids case-insensitively.
+    if doc.quirks_mode() == QuirksMode::Quirks {
+        let classes = doc.attribute(node_id, "class").unwrap_or_default();
+        if classes
+            .split_ascii_whitespace()
+            .any(|c| fp.classes.iter().any(|cls| cls.eq_ignore_ascii_case(c)))
+        {
+            return true;
+        }
+        return doc
+            .attribute(node_id, "id")
+            .is_some_and(|id_attr| fp.ids.iter().any(|id| id.eq_ignore_ascii_case(id_attr)));
+    }
     for cls in &fp.classes {
         if doc.has_class(node_id, cls) {
             return true;
@@ -74,8 +99,13 @@ struct PipelineCache {
     /// Passed to the next render so unchanged tiles skip rasterization.
     /// Value is (physical_width, physical_height, pixel_data).
     tile_pixel_cache: TilePixelCache,
+    /// Tile bounds for the debug overlay, with the tiles this build repainted.
+    overlay_tiles: Vec<OverlayTile>,
 }
 
+/// Layer id of the baked tiles carrying the debug overlay. Real layer ids count up from 0.
+const DEBUG_OVERLAY_LAYER: u64 = u64::MAX;
+
 /// BrowsingContext dedicated to a specific tab
 ///
 /// A BrowsingContext is a single instance of the engine that deals with a specific tab. Each tab
@@ -93,8 +123,19 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     render_dirty: bool,
     /// Viewport size (width/height only - scroll offset lives in scroll_x/y)
     viewport: Viewport,
+    /// Page zoom factor (1.0 = 100%). The page is laid out against `viewport / zoom` and its
+    /// tiles are rasterized `zoom` times larger, so content grows while the viewport stays put.
+    zoom: f64,
+    /// Emulated device, if any. Pins the visual viewport to the device screen and makes the
+    /// layout honor the document's `<meta name="viewport">`.
+    device_emulation: Option<DeviceEmulation>,
+    /// Parsed `<meta name="viewport">` of the current document (default when absent).
+    viewport_meta: ViewportMeta,
     /// Epoch of the scene, used to determine if the scene has changed
     scene_epoch: u64,
+    /// Styles of the last build, reused by the next one when a viewport change only restyles
+    /// part of the page (see [`Self::set_viewport`]). Any other invalidation drops them.
+    retained_styles: Option<Arc<dyn PipelineDocument>>,
 
     /// DOM dirty flag, used to determine if the DOM has changed
     dom_dirty: bool,
@@ -132,6 +173,13 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     hover_chain_sensitive: bool,
     /// The href of the link currently under the pointer, if any.
     pub hover_link_url: Option<String>,
+    /// The element that has keyboard focus, if any. Set by clicking a focusable element.
+    focused: Option<NodeId>,
+    /// Debug overlays painted over the page.
+    debug_overlays: DebugOverlays,
+    /// Set when the debug overlay must be redrawn without a content rebuild: the overlays were
+    /// toggled, or the previous frame showed a repaint flash that must now fade.
+    overlay_dirty: bool,
 
     /// The active backend's per-tile rasterizer and how to drive it. Built once by the tab
     /// worker from the engine's `RenderBackend` (replacing the former per-backend cfg cascade).
@@ -157,7 +205,11 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             render_list: RenderList::new(),
             render_dirty: false,
             viewport: Viewport::default(),
+            zoom: 1.0,
+            device_emulation: None,
+            viewport_meta: ViewportMeta::default(),
             scene_epoch: 0,
+            retained_styles: None,
             dom_dirty: false,
             style_dirty: false,
             layout_dirty: false,
@@ -168,6 +220,9 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             scene_cache: None,
             hover_dirty: false,
             hover_leaf: None,
+            focused: None,
+            debug_overlays: DebugOverlays::default(),
+            overlay_dirty: false,
             hover_old_lei: None,
             hover_dirty_nodes: Vec::new(),
             hover_layout_element: None,
@@ -206,6 +261,9 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
 
     /// Sets the parsed DOM document for the given tab.
     pub fn set_document(&mut self, doc: Arc<EngineDocument<C>>) {
+        self.viewport_meta = crate::html::document_viewport_meta(&doc)
+            .map(|content| ViewportMeta::parse(&content))
+            .unwrap_or_default();
         self.document = Some(doc);
         self.dom_dirty = true;
         self.style_dirty = true;
@@ -215,25 +273,222 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_cache = None;
         self.hover_dirty = false;
         self.hover_leaf = None;
+        self.focused = None;
         self.hover_layout_element = None;
         self.hover_fingerprints = None;
         self.hover_chain_sensitive = false;
     }
 
+    /// Edits the document with `mutate` and restyles only what the edit touched. The mutation
+    /// journal is recorded while `mutate` runs; when the last build is current, its styles are
+    /// carried over and only the subtrees the journal invalidates re-cascade. The page is laid
+    /// out again either way.
+    ///
+    /// Fails when no document is loaded, or when it is shared with another owner and so cannot
+    /// be edited in place.
+    pub fn mutate_document(&mut self, mutate: impl FnOnce(&mut EngineDocument<C>)) -> anyhow::Result<()> {
+        use gosub_interface::mutation::Invalidation;
+        use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+
+        if self.document.is_none() {
+            anyhow::bail!("no document loaded");
+        }
+        let up_to_date = !self.render_dirty && !self.hover_dirty;
+        let styles = self.active_layer_list().filter(|_| up_to_date).and_then(|layer_list| {
+            let styles = layer_list.layout_tree.render_tree.doc.as_any();
+            styles
+                .downcast_ref::<GosubDocumentAdapter<C>>()
+                .map(|adapter| adapter.take_styles())
+        });
+
+        // The caches hold on to the document; let go of them so that it can be edited in place
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+        self.dom_dirty = true;
+        self.style_dirty = true;
+        self.layout_dirty = true;
+        self.invalidate_render();
+
+        let Some(doc) = self.document.as_mut().and_then(Arc::get_mut) else {
+            anyhow::bail!("the document is shared and cannot be edited");
+        };
+        doc.record_mutations(true);
+        mutate(doc);
+        let records = doc.take_mutations();
+        doc.record_mutations(false);
+        let invalidation = Invalidation::from_records::<C, _>(&*doc, &records);
+
+        if let (Some(styles), Some(doc)) = (styles, &self.document) {
+            self.retained_styles = Some(Arc::new(GosubDocumentAdapter::<C>::with_styles(
+                Arc::clone(doc),
+                styles,
+                &invalidation.restyle,
+            )));
+        }
+        Ok(())
+    }
+
     /// Update the viewport SIZE. Only triggers a full re-layout when width or height changes.
     /// Scroll offset is managed separately via `set_scroll`.
+    ///
+    /// When the last build is current, its styles are kept and only the elements whose rules
+    /// depend on the viewport (`@media` queries that flip, viewport units) re-cascade.
     pub fn set_viewport(&mut self, vp: Viewport) {
         if self.viewport.width == vp.width && self.viewport.height == vp.height {
             return;
         }
+        let old_features = self.media_features();
+        let up_to_date = !self.render_dirty && !self.hover_dirty;
         self.viewport.width = vp.width;
         self.viewport.height = vp.height;
         self.layout_dirty = true;
         self.invalidate_render();
+        if up_to_date {
+            self.retained_styles = self.restyle_for_media_change(&old_features);
+        }
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+    }
+
+    /// Set the page zoom factor. Triggers a full re-layout against the narrower (or wider)
+    /// effective viewport and a re-raster at the new scale. The scroll offset is rescaled so the
+    /// same part of the page stays at the top-left corner.
+    ///
+    /// Returns `false` when the factor is unchanged or not a positive finite number.
+    pub fn set_zoom(&mut self, factor: f64) -> bool {
+        if !factor.is_finite() || factor <= 0.0 || (self.zoom - factor).abs() < f64::EPSILON {
+            return false;
+        }
+        let ratio = factor / self.zoom;
+        self.zoom = factor;
+        self.scroll_x *= ratio;
+        self.scroll_y *= ratio;
+        self.layout_dirty = true;
+        self.invalidate_render();
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+        true
+    }
+
+    /// Switch the debug overlays. Returns `false` when they are unchanged.
+    pub fn set_debug_overlays(&mut self, overlays: DebugOverlays) -> bool {
+        if self.debug_overlays == overlays {
+            return false;
+        }
+        self.debug_overlays = overlays;
+        self.overlay_dirty = true;
+        true
+    }
+
+    /// The debug overlays currently switched on.
+    pub fn debug_overlays(&self) -> DebugOverlays {
+        self.debug_overlays
+    }
+
+    /// True while the debug overlay waits to be redrawn, e.g. to fade a repaint flash. The
+    /// worker keeps ticking until this clears.
+    pub fn debug_overlay_dirty(&self) -> bool {
+        self.overlay_dirty
+    }
+
+    /// Current page zoom factor (1.0 = 100%).
+    #[inline]
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Emulate a device (or stop emulating with `None`). Triggers a full re-layout and re-raster.
+    /// The caller is expected to size the viewport to the device screen.
+    pub fn set_device_emulation(&mut self, device: Option<DeviceEmulation>) {
+        if self.device_emulation == device {
+            return;
+        }
+        self.device_emulation = device;
+        self.layout_dirty = true;
+        self.invalidate_render();
         self.pipeline_cache = None;
         self.scene_cache = None;
     }
 
+    /// The emulated device, if any.
+    pub fn device_emulation(&self) -> Option<&DeviceEmulation> {
+        self.device_emulation.as_ref()
+    }
+
+    /// Layout viewport size and its scale to the visual viewport, before page zoom. Only an
+    /// emulated device honors `<meta name="viewport">`; desktop rendering lays out at the viewport.
+    fn resolved_viewport(&self) -> (f64, f64, f64) {
+        let (w, h) = (self.viewport.width as f64, self.viewport.height as f64);
+        if self.device_emulation.is_none() || w <= 0.0 || h <= 0.0 {
+            return (w, h, 1.0);
+        }
+        let resolved = self.viewport_meta.resolve(w as f32, h as f32);
+        (resolved.width as f64, resolved.height as f64, resolved.scale as f64)
+    }
+
+    /// Scale from layout pixels to the visual viewport: the viewport-meta scale times page zoom.
+    fn raster_scale(&self) -> f64 {
+        self.resolved_viewport().2 * self.zoom
+    }
+
+    /// The viewport the page is laid out against: the layout viewport divided by the zoom factor.
+    fn layout_viewport(&self) -> Viewport {
+        let (w, h, _) = self.resolved_viewport();
+        let scale = |v: f64| {
+            if v <= 0.0 {
+                0
+            } else {
+                ((v / self.zoom).round() as u32).max(1)
+            }
+        };
+        Viewport::new(0, 0, scale(w), scale(h))
+    }
+
+    /// The media features `@media` queries are evaluated against: the layout viewport, the
+    /// device pixel ratio at the current zoom, the user's preferences and the pointing device
+    /// (a touch screen when emulating a touch device).
+    fn media_features(&self) -> MediaFeatures {
+        let viewport = self.layout_viewport();
+        let dpr = self.device_pixel_ratio(self.config_store.get_uint("renderer.device_pixel_ratio") as u32);
+        let touch = self.device_emulation.as_ref().is_some_and(|d| d.touch);
+        let color_scheme = match self.config_store.get_string("renderer.prefers_color_scheme").as_str() {
+            "dark" => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        };
+        MediaFeatures {
+            width: viewport.width as f32,
+            height: viewport.height as f32,
+            device_pixel_ratio: (dpr as f64 * self.raster_scale()) as f32,
+            color_scheme,
+            reduced_motion: self.config_store.get_bool("renderer.prefers_reduced_motion"),
+            hover: !touch,
+            pointer: if touch {
+                PointerAccuracy::Coarse
+            } else {
+                PointerAccuracy::Fine
+            },
+        }
+    }
+
+    /// The styles of the last build with the subtrees affected by going from the `old` media
+    /// features to the current ones invalidated. `None` when nothing was built yet or the CSS
+    /// system cannot tell which styles changed; the next build then restyles everything.
+    fn restyle_for_media_change(&self, old: &MediaFeatures) -> Option<Arc<dyn PipelineDocument>> {
+        let doc = self.document.as_ref()?;
+        let styles = Arc::clone(&self.active_layer_list()?.layout_tree.render_tree.doc);
+        let roots =
+            <C::CssSystem as CssSystem>::media_restyle_roots::<C>(doc, doc.stylesheets(), old, &self.media_features())?;
+        styles.invalidate_style_for_subtrees(&roots);
+        Some(styles)
+    }
+
+    /// Device pixel ratio to rasterize and composite at: the emulated device's, else `backend_dpr`.
+    fn device_pixel_ratio(&self, backend_dpr: u32) -> u32 {
+        self.device_emulation
+            .as_ref()
+            .map_or(backend_dpr, |d| d.device_pixel_ratio)
+    }
+
     /// Update the scroll offset without triggering a full re-layout.
     /// The next composite will shift tiles by (x, y).
     pub fn set_scroll(&mut self, x: f64, y: f64) {
@@ -269,6 +524,7 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
 
     pub fn invalidate_render(&mut self) {
         self.render_dirty = true;
+        self.retained_styles = None;
     }
 
     /// Poll whether a background media fetch (e.g. an image download started during layout) has
@@ -297,7 +553,11 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                 .unwrap_or_default();
             self.pipeline_cache = Some(pipeline_build_cache(
                 doc.clone(),
-                &self.viewport,
+                self.retained_styles.take(),
+                &self.layout_viewport(),
+                self.media_features(),
+                self.raster_scale(),
+                self.device_emulation.as_ref().map(|d| d.device_pixel_ratio),
                 self.rasterizer.as_deref(),
                 self.raster_strategy,
                 prev_tile_cache,
@@ -322,9 +582,10 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// - **Paint-only repaint** (`hover_dirty`): reuses the cached layout tree and repaints
     ///   only the affected tiles, skipping stages 1–2.
     pub fn rebuild_pipeline_cache_if_needed(&mut self) {
-        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty {
+        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty && !self.overlay_dirty {
             return;
         }
+        let repainted = self.render_dirty || self.hover_dirty;
         if self.render_dirty {
             self.rebuild_full_pipeline();
         } else if self.hover_dirty {
@@ -344,7 +605,9 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                     self.hover_old_lei,
                     self.hover_layout_element,
                     &self.hover_dirty_nodes,
-                    &self.viewport,
+                    &self.layout_viewport(),
+                    self.raster_scale(),
+                    self.device_emulation.as_ref().map(|d| d.device_pixel_ratio),
                     self.rasterizer.as_deref(),
                     self.raster_strategy,
                     prev_tile_cache,
@@ -356,7 +619,11 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                 if let Some(doc) = &self.document {
                     self.pipeline_cache = Some(pipeline_build_cache(
                         doc.clone(),
-                        &self.viewport,
+                        None,
+                        &self.layout_viewport(),
+                        self.media_features(),
+                        self.raster_scale(),
+                        self.device_emulation.as_ref().map(|d| d.device_pixel_ratio),
                         self.rasterizer.as_deref(),
                         self.raster_strategy,
                         std::collections::HashMap::new(),
@@ -367,6 +634,9 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             }
             self.hover_dirty = false;
         }
+        if repainted || self.overlay_dirty {
+            self.refresh_debug_overlay();
+        }
         self.scroll_dirty = false;
         self.scene_epoch = self.scene_epoch.wrapping_add(1);
     }
@@ -379,12 +649,15 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// - **Scroll composite** (`scroll_dirty`): re-composites visible tiles from the cache with
     ///   the new scroll offset. No layout or rasterization work.
     pub fn rebuild_render_list_if_needed(&mut self) {
-        if !self.render_dirty && !self.scroll_dirty {
+        if !self.render_dirty && !self.scroll_dirty && !self.overlay_dirty {
             return;
         }
 
         if self.render_dirty {
             self.rebuild_full_pipeline();
+            self.refresh_debug_overlay();
+        } else if self.overlay_dirty {
+            self.refresh_debug_overlay();
         }
 
         let mut rl = RenderList::default();
@@ -414,23 +687,34 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// don't rebuild anything (the backend re-renders with a new translate); they just advance the
     /// scene epoch so the worker emits a frame.
     pub fn rebuild_scene_cache_if_needed(&mut self) {
-        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty {
+        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty && !self.overlay_dirty {
             return;
         }
         // Both content changes and hover-style changes rebuild the command list. Hover could reuse
         // the cached layout (it only changes paint), but a GPU re-paint is cheap and avoids the
         // tile path's hover-repaint bookkeeping; revisit if hover proves hot.
-        if self.render_dirty || self.hover_dirty {
+        if self.render_dirty || self.hover_dirty || self.overlay_dirty {
             if let Some(doc) = &self.document {
-                self.scene_cache = Some(pipeline_build_scene(
+                let mut scene_cache = pipeline_build_scene(
                     doc.clone(),
-                    &self.viewport,
+                    self.retained_styles.take(),
+                    &self.layout_viewport(),
+                    self.media_features(),
                     self.rasterizer.as_deref(),
                     self.media_store.clone(),
-                ));
+                );
+                // The scene has no tiles, so only the box-model and layer overlays apply.
+                if self.debug_overlays.any() {
+                    let font_system = self.rasterizer.as_deref().and_then(|r| r.font_system());
+                    let painter = Painter::new(Arc::clone(&scene_cache.layer_list), font_system);
+                    let overlay = painter.paint_debug_overlay(self.debug_overlays, self.hover_layout_element, &[]);
+                    scene_cache.scene.commands.extend(overlay);
+                }
+                self.scene_cache = Some(scene_cache);
             }
             self.render_dirty = false;
             self.hover_dirty = false;
+            self.overlay_dirty = false;
             self.dom_dirty = false;
             self.style_dirty = false;
             self.layout_dirty = false;
@@ -439,6 +723,45 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_epoch = self.scene_epoch.wrapping_add(1);
     }
 
+    /// Replace the debug overlay tiles in the pipeline cache with freshly rasterized ones.
+    ///
+    /// The overlay is painted over the whole page on the regular tile grid and rasterized by the
+    /// active backend like any other layer, so every compositor shows it. A repaint flash is only
+    /// shown for one frame: afterwards the overlay is marked dirty to be redrawn without it.
+    fn refresh_debug_overlay(&mut self) {
+        self.overlay_dirty = false;
+        let zoom = self.raster_scale();
+        let device_pixel_ratio = self.device_emulation.as_ref().map(|d| d.device_pixel_ratio);
+        let tile_size = self.config_store.get_uint("renderer.tile.size") as f64 / zoom;
+        let Some(cache) = self.pipeline_cache.as_mut() else {
+            return;
+        };
+        // Nothing to draw and nothing left to remove: keep the tile cache as it is.
+        if !self.debug_overlays.any() && !cache.tiles.iter().any(|t| t.layer_id == DEBUG_OVERLAY_LAYER) {
+            return;
+        }
+
+        cache.tiles.retain(|t| t.layer_id != DEBUG_OVERLAY_LAYER);
+        if let (true, Some(rasterizer)) = (self.debug_overlays.any(), self.rasterizer.as_deref()) {
+            let painter = Painter::new(Arc::clone(&cache.layer_list), rasterizer.font_system());
+            let commands =
+                painter.paint_debug_overlay(self.debug_overlays, self.hover_layout_element, &cache.overlay_tiles);
+            cache.tiles.extend(rasterize_overlay(
+                rasterizer,
+                commands,
+                tile_size,
+                zoom,
+                device_pixel_ratio,
+                &self.media_store,
+            ));
+        }
+        if self.debug_overlays.repaint_flash && cache.overlay_tiles.iter().any(|t| t.repainted) {
+            cache.overlay_tiles.iter_mut().for_each(|t| t.repainted = false);
+            self.overlay_dirty = true;
+        }
+        cache.cached_tiles = Arc::new(cpu_cached_tiles(&cache.tiles));
+    }
+
     /// The active layer list for hit-testing - from the GPU scene cache or the CPU pipeline cache,
     /// whichever this tab's backend populates.
     fn active_layer_list(&self) -> Option<&Arc<LayerList>> {
@@ -448,12 +771,13 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             .or_else(|| self.pipeline_cache.as_ref().map(|c| &c.layer_list))
     }
 
-    /// The active full-page height, from whichever cache this tab populates.
+    /// The active full-page height in zoomed CSS pixels, from whichever cache this tab populates.
     fn active_page_height(&self) -> Option<f64> {
         self.scene_cache
             .as_ref()
             .map(|c| c.scene.page_height)
             .or_else(|| self.pipeline_cache.as_ref().map(|c| c.page_height))
+            .map(|h| h * self.raster_scale())
     }
 
     /// If only the scroll offset changed (no content/layout change), returns a zero-copy
@@ -462,17 +786,17 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     ///
     /// Calling this consumes the scroll-dirty flag and advances the scene epoch.
     pub fn take_scroll_handle(&mut self, dpr: u32) -> Option<ExternalHandle> {
-        if !self.scroll_dirty || self.render_dirty || self.hover_dirty {
+        if !self.scroll_dirty || self.render_dirty || self.hover_dirty || self.overlay_dirty {
             return None;
         }
         let cache = self.pipeline_cache.as_ref()?;
         let handle = ExternalHandle::TileCache {
             viewport_width: self.viewport.width,
             viewport_height: self.viewport.height,
-            dpr,
+            dpr: self.device_pixel_ratio(dpr),
             scroll_x: self.scroll_x as f32,
             scroll_y: self.scroll_y as f32,
-            page_height: cache.page_height as f32,
+            page_height: (cache.page_height * self.raster_scale()) as f32,
             tiles: Arc::clone(&cache.cached_tiles),
         };
         self.scroll_dirty = false;
@@ -488,14 +812,228 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         Some(ExternalHandle::TileCache {
             viewport_width: self.viewport.width,
             viewport_height: self.viewport.height,
-            dpr,
+            dpr: self.device_pixel_ratio(dpr),
             scroll_x: self.scroll_x as f32,
             scroll_y: self.scroll_y as f32,
-            page_height: cache.page_height as f32,
+            page_height: (cache.page_height * self.raster_scale()) as f32,
             tiles: Arc::clone(&cache.cached_tiles),
         })
     }
 
+    /// Border boxes of the elements matching `selector`, in document order.
+    ///
+    /// Boxes come from the last built layout, so they are `None` until the page has rendered.
+    /// An element split over several layout boxes (generated `::before`/`::after` content)
+    /// reports their union.
+    pub fn element_rects(&self, selector: &str) -> anyhow::Result<Vec<ElementRect>> {
+        let Some(doc) = &self.document else {
+            anyhow::bail!("no document loaded");
+        };
+        let nodes = <C::CssSystem as CssSystem>::query_selector_all::<C>(doc, doc.root(), selector)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+
+        let wanted: std::collections::HashSet<NodeId> = nodes.iter().copied().collect();
+        let boxes = self.border_boxes(|node_id| wanted.contains(&node_id));
+
+        Ok(nodes
+            .into_iter()
+            .map(|node_id| {
+                let boxes = boxes.get(&node_id);
+                ElementRect {
+                    node_id,
+                    border_box: boxes.map(|b| b.page),
+                    viewport_box: boxes.map(|b| b.viewport),
+                }
+            })
+            .collect())
+    }
+
+    /// The elements under viewport point `(vp_x, vp_y)`, innermost first, up to the root element.
+    ///
+    /// Unlike [`update_hover`](Self::update_hover) this leaves the hover state alone. Empty when
+    /// nothing has been laid out or the point misses the page.
+    pub fn hit_test(&self, vp_x: f64, vp_y: f64) -> Vec<HitTestElement> {
+        let (Some(doc), (Some(leaf), _)) = (&self.document, self.element_at(vp_x, vp_y)) else {
+            return Vec::new();
+        };
+
+        let mut chain = Vec::new();
+        let mut node = Some(leaf);
+        while let Some(id) = node {
+            if doc.node_type(id) == NodeType::ElementNode {
+                chain.push(id);
+            }
+            node = doc.parent(id);
+        }
+
+        let wanted: std::collections::HashSet<NodeId> = chain.iter().copied().collect();
+        let boxes = self.border_boxes(|node_id| wanted.contains(&node_id));
+        let attr = |id: NodeId, name: &str| doc.attribute(id, name).map(str::to_string);
+        chain
+            .into_iter()
+            .map(|node_id| {
+                let boxes = boxes.get(&node_id);
+                HitTestElement {
+                    node_id,
+                    tag: doc.tag_name(node_id).unwrap_or_default().to_string(),
+                    id: attr(node_id, "id"),
+                    classes: doc
+                        .attribute(node_id, "class")
+                        .map(|c| c.split_ascii_whitespace().map(str::to_string).collect())
+                        .unwrap_or_default(),
+                    href: attr(node_id, "href"),
+                    src: attr(node_id, "src"),
+                    border_box: boxes.map(|b| b.page),
+                    viewport_box: boxes.map(|b| b.viewport),
+                }
+            })
+            .collect()
+    }
+
+    /// Computed style and matching rules of element `node_id`.
+    ///
+    /// Computed values come from the styles the last layout used, so they reflect hover state;
+    /// before the first layout they are computed from the document directly.
+    pub fn inspect_node(&self, node_id: NodeId) -> anyhow::Result<NodeInspection> {
+        use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+        use gosub_render_pipeline::common::document::style::StyleProperty;
+
+        let Some(doc) = &self.document else {
+            anyhow::bail!("no document loaded");
+        };
+        if doc.node_type(node_id) != NodeType::ElementNode {
+            anyhow::bail!("node {} is not an element", u64::from(node_id));
+        }
+
+        let styles: Arc<dyn PipelineDocument> = match self.active_layer_list() {
+            Some(layer_list) => Arc::clone(&layer_list.layout_tree.render_tree.doc),
+            None => Arc::new(GosubDocumentAdapter::<C>::new(Arc::clone(doc))),
+        };
+        let computed = StyleProperty::all()
+            .map(|prop| {
+                let value = styles.get_style(node_id, &prop).to_css_string();
+                (prop.css_name().to_string(), value)
+            })
+            .collect();
+        let rules = <C::CssSystem as CssSystem>::matched_rules::<C>(doc, node_id, doc.stylesheets());
+
+        Ok(NodeInspection { computed, rules })
+    }
+
+    /// The DOM node and layout element under viewport point `(vp_x, vp_y)`.
+    fn element_at(&self, vp_x: f64, vp_y: f64) -> (Option<NodeId>, Option<LayoutElementId>) {
+        // The layer list is in layout (unzoomed) pixels; map the pointer and scroll into it.
+        let scale = self.raster_scale();
+        let (vp_x, vp_y) = (vp_x / scale, vp_y / scale);
+        let (scroll_x, scroll_y) = (self.scroll_x / scale, self.scroll_y / scale);
+
+        self.active_layer_list().map_or((None, None), |layer_list| {
+            let _t = gosub_shared::timing_guard!("hover.hit_test");
+            // find_element_at handles scroll per-layer (fixed layers ignore it).
+            let Some(lei) = layer_list.find_element_at(vp_x, vp_y, scroll_x, scroll_y) else {
+                return (None, None);
+            };
+            let dom_node_id = layer_list.layout_tree.get_node_by_id(lei).map(|el| el.dom_node_id);
+            (dom_node_id, Some(lei))
+        })
+    }
+
+    /// Border boxes per DOM node from the last built layout, for the nodes `wanted` accepts.
+    /// Nodes split over several layout boxes get their union.
+    fn border_boxes(&self, wanted: impl Fn(NodeId) -> bool) -> std::collections::HashMap<NodeId, NodeBoxes> {
+        let mut boxes: std::collections::HashMap<NodeId, NodeBoxes> = std::collections::HashMap::new();
+        let Some(layer_list) = self.active_layer_list() else {
+            return boxes;
+        };
+
+        let scale = self.raster_scale();
+        let (scroll_x, scroll_y) = (self.scroll_x / scale, self.scroll_y / scale);
+        let pinned = layer_list.pinned_element_anchors();
+        for element in layer_list.layout_tree.arena.values() {
+            if !wanted(element.dom_node_id) {
+                continue;
+            }
+            let page = element.box_model.border_box;
+            let anchor = pinned.get(&element.id).copied().unwrap_or_default();
+            let (x, y) = anchored_tile_pos(page.x, page.y, scroll_x, scroll_y, anchor);
+            let viewport = PipelineRect::new(x * scale, y * scale, page.width * scale, page.height * scale);
+            boxes
+                .entry(element.dom_node_id)
+                .and_modify(|b| {
+                    b.page = union_rect(&b.page, &page);
+                    b.viewport = union_rect(&b.viewport, &viewport);
+                })
+                .or_insert(NodeBoxes { page, viewport });
+        }
+        boxes
+    }
+
+    /// The element with keyboard focus, if any.
+    pub fn focused(&self) -> Option<NodeId> {
+        self.focused
+    }
+
+    /// Moves focus to the nearest focusable ancestor of the element under the pointer, or clears
+    /// it when there is none (clicking empty space blurs). Returns `true` when focus changed.
+    pub fn focus_at_hover(&mut self) -> bool {
+        let focused = match (&self.document, self.hover_leaf) {
+            (Some(doc), Some(leaf)) => {
+                let mut node = Some(leaf);
+                while let Some(id) = node {
+                    let focusable = doc
+                        .tag_name(id)
+                        .is_some_and(|tag| accessibility::is_focusable(doc, id, &tag.cow_to_ascii_lowercase()));
+                    if focusable {
+                        break;
+                    }
+                    node = doc.parent(id);
+                }
+                node
+            }
+            _ => None,
+        };
+        let changed = focused != self.focused;
+        self.focused = focused;
+        changed
+    }
+
+    /// The accessibility tree of the current document, or `None` when nothing is loaded.
+    pub(crate) fn accessibility_tree(&self) -> Option<AccessibilityTree> {
+        let doc = self.document.as_ref()?;
+        let boxes = self
+            .border_boxes(|_| true)
+            .into_iter()
+            .map(|(node_id, boxes)| (node_id, boxes.page))
+            .collect();
+        let width = self.layout_viewport().width as f64;
+        let height = self.active_page_height().unwrap_or(0.0) / self.raster_scale();
+        Some(accessibility::build_tree(&AccessibilitySource {
+            doc,
+            boxes: &boxes,
+            focused: self.focused,
+            page_size: (width, height),
+            zoom: self.raster_scale(),
+            scroll: (self.scroll_x, self.scroll_y),
+        }))
+    }
+
+    /// Lay the document out for print and paginate it.
+    ///
+    /// The page boxes come from the document's `@page` rules, including `:first`, `:left` and
+    /// `:right` ones, falling back to A4 with 1cm margins. Layout runs at the first page's content
+    /// width with `@media print` rules applied. Returns `None` when no document is loaded.
+    pub fn print_document(&self) -> Option<PrintDocument> {
+        let doc = self.document.clone()?;
+        let setups = page_setups::<C>(&doc);
+        Some(pipeline_print(
+            doc,
+            setups,
+            self.media_features(),
+            self.rasterizer.as_deref(),
+            Arc::clone(&self.media_store),
+        ))
+    }
+
     /// Returns the full page height from whichever cache is active (0 if not yet rendered).
     pub fn page_height(&self) -> f64 {
         self.active_page_height().unwrap_or(0.0)
@@ -518,23 +1056,14 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// Hit-test at viewport coordinates `(vp_x, vp_y)` and update hover state.
     ///
     /// Returns `(visual_dirty, url_changed, link_url)`:
-    /// - `visual_dirty`: a node with a `:hover` CSS rule entered or left the hover chain → needs repaint.
+    /// - `visual_dirty`: a node with a `:hover` CSS rule entered or left the hover chain, or the
+    ///   box-model debug overlay moved → needs repaint.
     /// - `url_changed`: the link URL under the cursor changed → caller should emit a `HoverUrl` event.
     /// - `link_url`: the href of the nearest `<a>` ancestor, if any.
     pub fn update_hover(&mut self, vp_x: f64, vp_y: f64) -> (bool, bool, Option<String>) {
         let _t_total = gosub_shared::timing_guard!("hover.total");
 
-        let (scroll_x, scroll_y) = (self.scroll_x, self.scroll_y);
-
-        let (new_leaf, new_lei) = self.active_layer_list().map_or((None, None), |layer_list| {
-            let _t = gosub_shared::timing_guard!("hover.hit_test");
-            // find_element_at handles scroll per-layer (fixed layers ignore it).
-            let Some(lei) = layer_list.find_element_at(vp_x, vp_y, scroll_x, scroll_y) else {
-                return (None, None);
-            };
-            let dom_node_id = layer_list.layout_tree.get_node_by_id(lei).map(|el| el.dom_node_id);
-            (dom_node_id, Some(lei))
-        });
+        let (new_leaf, new_lei) = self.element_at(vp_x, vp_y);
 
         // Common case: same element - skip the ancestor walk entirely.
         if new_leaf == self.hover_leaf {
@@ -619,8 +1148,12 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             // Use the cheap hover-dirty path which skips render-tree + layout.
             self.hover_dirty = true;
         }
+        // The box-model overlay follows the hovered element even when no :hover rule applies.
+        if self.debug_overlays.box_model && self.hover_layout_element != self.hover_old_lei {
+            self.overlay_dirty = true;
+        }
 
-        (visual_dirty, url_changed, link_url)
+        (visual_dirty || self.overlay_dirty, url_changed, link_url)
     }
 
     /// Returns the render list
@@ -664,6 +1197,9 @@ impl<C: RenderConfiguration> RenderContext for BrowsingContext<C> {
     fn scroll_offset(&self) -> (f64, f64) {
         (self.scroll_x, self.scroll_y)
     }
+    fn page_zoom(&self) -> f64 {
+        self.raster_scale()
+    }
 }
 
 /// GPU-scene build: stages 1–3 (render tree → layout → layering) plus a paint pass over every
@@ -671,24 +1207,31 @@ impl<C: RenderConfiguration> RenderContext for BrowsingContext<C> {
 /// rasterization, and compositing - the backend renders the commands into a GPU texture.
 fn pipeline_build_scene<C: RenderConfiguration>(
     doc: Arc<EngineDocument<C>>,
+    styles: Option<Arc<dyn PipelineDocument>>,
     viewport: &Viewport,
+    media_features: MediaFeatures,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
 ) -> SceneCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
     use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
-    use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
+    use gosub_render_pipeline::common::geo::Dimension as PipelineDimension;
     use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
     use gosub_render_pipeline::layouter::CanLayout;
     use gosub_render_pipeline::rendertree_builder::RenderTree;
 
-    // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) against the
-    // real viewport. Must precede parse(), which computes styles for display:none filtering.
+    // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) and `@media`
+    // queries against the real viewport. Must precede parse(), which computes styles for
+    // display:none filtering.
     gosub_css3::stylesheet::set_layout_viewport(viewport.width as f32, viewport.height as f32);
+    gosub_css3::media::set_media_features(media_features);
 
-    // Stage 1: render tree
-    let adapter = GosubDocumentAdapter::<C>::new(doc);
-    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    // Stage 1: render tree, on the retained styles when a viewport change kept them
+    let styles: Arc<dyn PipelineDocument> = match styles {
+        Some(styles) => styles,
+        None => Arc::new(GosubDocumentAdapter::<C>::new(doc)),
+    };
+    let mut render_tree = RenderTree::new(styles);
     if let Err(e) = render_tree.parse() {
         log::error!("Failed to build render tree: {e}");
     }
@@ -739,14 +1282,128 @@ fn pipeline_build_scene<C: RenderConfiguration>(
     }
 }
 
+/// Where a DOM node's border box is, both on the page and on screen.
+#[derive(Clone, Copy)]
+struct NodeBoxes {
+    /// Border box in document CSS pixels (unzoomed, scroll-independent)
+    page: PipelineRect,
+    /// Border box in viewport pixels, after zoom and scrolling
+    viewport: PipelineRect,
+}
+
+/// Smallest rectangle covering both `a` and `b`.
+fn union_rect(a: &PipelineRect, b: &PipelineRect) -> PipelineRect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.width).max(b.x + b.width);
+    let bottom = (a.y + a.height).max(b.y + b.height);
+    PipelineRect::new(x, y, right - x, bottom - y)
+}
+
+/// Resolves the page boxes of the first, left and right pages from the document's `@page` rules.
+fn page_setups<C: RenderConfiguration>(doc: &EngineDocument<C>) -> PageSetups {
+    use gosub_css3::page::{page_descriptors, DEFAULT_PAGE_MARGIN, DEFAULT_PAGE_SIZE};
+    use gosub_interface::css3::CssStylesheet as _;
+
+    let rules: Vec<_> = doc.stylesheets().iter().flat_map(|sheet| sheet.page_rules()).collect();
+    let setup = |index| {
+        let page = page_descriptors(&rules, index);
+        let size = page.size.unwrap_or(DEFAULT_PAGE_SIZE);
+        PageSetup {
+            width: f64::from(size.0),
+            height: f64::from(size.1),
+            margin: page.margin.map(|edge| f64::from(edge.unwrap_or(DEFAULT_PAGE_MARGIN))),
+        }
+    };
+
+    PageSetups {
+        first: setup(0),
+        left: setup(1),
+        right: setup(2),
+    }
+}
+
+/// Runs pipeline stages 1–3 for print media at the page's content width, then paginates.
+fn pipeline_print<C: RenderConfiguration>(
+    doc: Arc<EngineDocument<C>>,
+    setups: PageSetups,
+    media_features: MediaFeatures,
+    rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
+    media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
+) -> PrintDocument {
+    use gosub_css3::media::{set_media_features, set_media_type, MediaType};
+    use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
+    use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+    use gosub_render_pipeline::common::geo::Dimension as PipelineDimension;
+    use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+    use gosub_render_pipeline::layouter::CanLayout;
+    use gosub_render_pipeline::rendertree_builder::RenderTree;
+
+    let (width, height) = (setups.first.content_width(), setups.first.content_height());
+    let screen_viewport = gosub_css3::stylesheet::layout_viewport();
+    set_media_type(MediaType::Print);
+    set_media_features(MediaFeatures {
+        width: width as f32,
+        height: height as f32,
+        ..media_features
+    });
+    gosub_css3::stylesheet::set_layout_viewport(width as f32, height as f32);
+
+    // A fresh adapter: its style caches are per instance, so the screen styles of the live
+    // scene are not reused here.
+    let adapter = GosubDocumentAdapter::<C>::new(doc);
+    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    if let Err(e) = render_tree.parse() {
+        log::error!("Failed to build render tree for print: {e}");
+    }
+
+    let mut layouter = match rasterizer.and_then(|r| r.font_system()) {
+        Some(font_system) => TaffyLayouter::with_font_system(font_system),
+        None => TaffyLayouter::new(),
+    };
+    layouter.set_media_store(Arc::clone(&media_store));
+    let layout_tree = layouter.layout(render_tree, Some(PipelineDimension::new(width, height)), 1.0);
+    let page_height = layout_tree.root_dimension.height;
+    let layer_list = Arc::new(LayerList::new(layout_tree));
+
+    let layer_count = layer_list.layer_ids.read().len();
+    let state = BrowserState {
+        visible_layer_list: vec![true; layer_count],
+        wireframed: WireframeState::None,
+        debug_hover: false,
+        current_hovered_element: None,
+        show_tilegrid: false,
+        debug_table_cells: false,
+        viewport: PipelineRect::new(0.0, 0.0, width, page_height.max(1.0)),
+        tile_list: None,
+        dpi_scale_factor: 1.0,
+    };
+    let painter = Painter::new(Arc::clone(&layer_list), rasterizer.and_then(|r| r.font_system()));
+    let document = print::paginate(&layer_list, &painter, &state, &setups, media_store);
+
+    // Styles computed for the screen after this must not see print rules or the page size.
+    set_media_type(MediaType::Screen);
+    set_media_features(media_features);
+    gosub_css3::stylesheet::set_layout_viewport(screen_viewport.0, screen_viewport.1);
+    document
+}
+
 /// Runs pipeline stages 1–6 for the **entire page** (all tiles, not just the viewport slice)
 /// and returns a `PipelineCache` of rasterized tiles ready for repeated compositing.
 ///
 /// Splitting the full pipeline from compositing lets scroll re-use the cached tiles without
 /// re-running layout or rasterization.
+///
+/// `viewport` is the layout viewport (already divided by `zoom`); tiles are rasterized at `zoom`
+/// and, when set, at `device_pixel_ratio` instead of the backend's ratio.
+#[allow(clippy::too_many_arguments)]
 fn pipeline_build_cache<C: RenderConfiguration>(
     doc: Arc<EngineDocument<C>>,
+    styles: Option<Arc<dyn PipelineDocument>>,
     viewport: &Viewport,
+    media_features: MediaFeatures,
+    zoom: f64,
+    device_pixel_ratio: Option<u32>,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     strategy: RasterStrategy,
     prev_tile_cache: TilePixelCache,
@@ -755,7 +1412,7 @@ fn pipeline_build_cache<C: RenderConfiguration>(
 ) -> PipelineCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
     use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
-    use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
+    use gosub_render_pipeline::common::geo::Dimension as PipelineDimension;
     use gosub_render_pipeline::layering::layer::LayerList;
     use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
     use gosub_render_pipeline::layouter::CanLayout;
@@ -766,14 +1423,19 @@ fn pipeline_build_cache<C: RenderConfiguration>(
 
     let ts_total = timing_start!("pipeline.total");
 
-    // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) against the
-    // real viewport. Must precede parse(), which computes styles for display:none filtering.
+    // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) and `@media`
+    // queries against the real viewport. Must precede parse(), which computes styles for
+    // display:none filtering.
     gosub_css3::stylesheet::set_layout_viewport(viewport.width as f32, viewport.height as f32);
+    gosub_css3::media::set_media_features(media_features);
 
-    // Stage 1: render tree
+    // Stage 1: render tree, on the retained styles when a viewport change kept them
     let ts1 = timing_start!("pipeline.render_tree");
-    let adapter = GosubDocumentAdapter::<C>::new(doc);
-    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    let styles: Arc<dyn PipelineDocument> = match styles {
+        Some(styles) => styles,
+        None => Arc::new(GosubDocumentAdapter::<C>::new(doc)),
+    };
+    let mut render_tree = RenderTree::new(styles);
     if let Err(e) = render_tree.parse() {
         // The layouter tolerates a tree without a root; the frame degrades to empty.
         log::error!("Failed to build render tree: {e}");
@@ -809,7 +1471,11 @@ fn pipeline_build_cache<C: RenderConfiguration>(
 
     // Stage 4: tiling
     let ts4 = timing_start!("pipeline.tiling");
-    let mut tile_list = TileList::new(layer_list, PipelineDimension::new(tile_size, tile_size));
+    // Tiles are sized in layout pixels so that, once scaled by the zoom, each still rasterizes
+    // to the configured physical tile size.
+    let mut tile_list = TileList::new(layer_list, PipelineDimension::new(tile_size / zoom, tile_size / zoom));
+    tile_list.raster_scale = zoom;
+    tile_list.device_pixel_ratio = device_pixel_ratio;
     let saved_layer_list = Arc::clone(&tile_list.layer_list);
     tile_list.generate();
     timing_stop!(ts4);
@@ -850,6 +1516,7 @@ fn pipeline_build_cache<C: RenderConfiguration>(
         }
     }
     timing_stop!(ts5);
+    let overlay_tiles = OverlayTile::collect(&tile_list);
 
     // Stage 6: rasterize tiles using the active backend's rasterizer + strategy (chosen at
     // runtime by the engine's RenderBackend; no per-backend cfg here). Vello stays
@@ -882,6 +1549,7 @@ fn pipeline_build_cache<C: RenderConfiguration>(
         cached_tiles,
         layer_list: saved_layer_list,
         tile_pixel_cache: new_tile_cache,
+        overlay_tiles,
     }
 }
 
@@ -898,6 +1566,8 @@ fn pipeline_hover_repaint(
     new_hover_lei: Option<LayoutElementId>,
     hover_dirty_nodes: &[NodeId],
     viewport: &gosub_render_pipeline::render::Viewport,
+    zoom: f64,
+    device_pixel_ratio: Option<u32>,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     strategy: RasterStrategy,
     prev_tile_cache: TilePixelCache,
@@ -905,14 +1575,19 @@ fn pipeline_hover_repaint(
     tile_size: f64,
 ) -> PipelineCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
-    use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
+    use gosub_render_pipeline::common::geo::Dimension as PipelineDimension;
     use gosub_render_pipeline::painter::Painter;
     use gosub_render_pipeline::tiler::{TileList, TileState};
     use gosub_shared::{timing_start, timing_stop};
 
     // Stage 4: tiling — reuse existing LayerList, no layout work.
     let ts4 = timing_start!("pipeline.hover.tiling");
-    let mut tile_list = TileList::from_arc(Arc::clone(&layer_list), PipelineDimension::new(tile_size, tile_size));
+    let mut tile_list = TileList::from_arc(
+        Arc::clone(&layer_list),
+        PipelineDimension::new(tile_size / zoom, tile_size / zoom),
+    );
+    tile_list.raster_scale = zoom;
+    tile_list.device_pixel_ratio = device_pixel_ratio;
     tile_list.generate();
     let total_tiles = tile_list.arena.len();
     timing_stop!(ts4);
@@ -977,7 +1652,11 @@ fn pipeline_hover_repaint(
             }
 
             tile.state = TileState::Ready;
-            let key = (tile_rect.x.to_bits(), tile_rect.y.to_bits(), tile.layer_id.as_u64());
+            let key = (
+                (tile_rect.x * tile.scale).to_bits(),
+                (tile_rect.y * tile.scale).to_bits(),
+                tile.layer_id.as_u64(),
+            );
             if let Some(baked) = prev_by_pos.remove(&key) {
                 clean_baked.push(baked);
             }
@@ -988,12 +1667,15 @@ fn pipeline_hover_repaint(
         // unordered and would scramble overlapping-layer compositing.
         let all_tiles = order_baked_tiles_by_layer(&tile_list, &layer_ids, full_page_rect, prev_by_pos);
         let cached_tiles = Arc::new(cpu_cached_tiles(&all_tiles));
+        let mut overlay_tiles = OverlayTile::collect(&tile_list);
+        overlay_tiles.iter_mut().for_each(|t| t.repainted = false);
         return PipelineCache {
             tiles: all_tiles,
             page_height,
             cached_tiles,
             layer_list,
             tile_pixel_cache: prev_tile_cache,
+            overlay_tiles,
         };
     }
 
@@ -1027,6 +1709,7 @@ fn pipeline_hover_repaint(
         }
     }
     timing_stop!(ts5);
+    let overlay_tiles = OverlayTile::collect(&tile_list);
 
     // Stage 6 (hover): rasterize the dirty tiles with the active backend's rasterizer + strategy.
     let (baked_tiles, new_tile_cache) = match (strategy, rasterizer) {
@@ -1064,9 +1747,141 @@ fn pipeline_hover_repaint(
         cached_tiles,
         layer_list,
         tile_pixel_cache: new_tile_cache,
+        overlay_tiles,
     }
 }
 
+/// Rasterize debug overlay commands into [`DEBUG_OVERLAY_LAYER`] tiles on the page's tile grid.
+///
+/// `commands` come from [`Painter::paint_debug_overlay`]; each `PushLayer` group becomes tiles
+/// with that group's anchor. Only grid cells that some command touches are rasterized.
+fn rasterize_overlay(
+    rasterizer: &(dyn Rasterable + Send + Sync),
+    commands: Vec<PaintCommand>,
+    tile_size: f64,
+    zoom: f64,
+    device_pixel_ratio: Option<u32>,
+    media_store: &gosub_render_pipeline::common::media::MediaStore,
+) -> Vec<BakedTile> {
+    use gosub_render_pipeline::common::geo::Coordinate;
+    use gosub_render_pipeline::common::texture_store::TextureStore;
+    use gosub_render_pipeline::layering::layer::LayerId;
+    use gosub_render_pipeline::render::backend::TileAnchor;
+    use gosub_render_pipeline::tiler::{Tile, TileId, TileState, TiledLayoutElement};
+
+    fn command_rect(command: &PaintCommand) -> Option<PipelineRect> {
+        match command {
+            PaintCommand::Rectangle(r) => Some(r.rect()),
+            PaintCommand::Text(t) => Some(t.rect),
+            PaintCommand::Svg(s) => Some(s.rect.rect()),
+            PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => None,
+        }
+    }
+
+    let mut groups: Vec<(TileAnchor, Vec<PaintCommand>)> = vec![(TileAnchor::Scroll, Vec::new())];
+    for command in commands {
+        match command {
+            PaintCommand::PushLayer { anchor, .. } => groups.push((anchor, Vec::new())),
+            PaintCommand::PopLayer => groups.push((TileAnchor::Scroll, Vec::new())),
+            command => {
+                if let Some((_, group)) = groups.last_mut() {
+                    group.push(command);
+                }
+            }
+        }
+    }
+
+    let tile_size = tile_size.max(1.0);
+    let mut texture_store = TextureStore::new();
+    let mut baked = Vec::new();
+    let mut next_id = 0;
+    for (anchor, group) in groups {
+        // Outlines straddle their rect's edge, so grow each bound by a pixel when binning.
+        let bounds: Vec<PipelineRect> = group
+            .iter()
+            .map(|c| {
+                command_rect(c)
+                    .map(|r| PipelineRect::new(r.x - 1.0, r.y - 1.0, r.width + 2.0, r.height + 2.0))
+                    .unwrap_or(PipelineRect::ZERO)
+            })
+            .collect();
+        let Some(extent) = bounds
+            .iter()
+            .filter(|r| r.width > 0.0 && r.height > 0.0)
+            .copied()
+            .reduce(|a, b| union_rect(&a, &b))
+        else {
+            continue;
+        };
+
+        let (col0, row0) = (
+            (extent.x / tile_size).floor().max(0.0),
+            (extent.y / tile_size).floor().max(0.0),
+        );
+        let col1 = ((extent.x + extent.width) / tile_size).ceil();
+        let row1 = ((extent.y + extent.height) / tile_size).ceil();
+        let mut row = row0;
+        while row < row1 {
+            let mut col = col0;
+            while col < col1 {
+                let rect = PipelineRect::new(col * tile_size, row * tile_size, tile_size, tile_size);
+                col += 1.0;
+                let paint_commands: Vec<PaintCommand> = group
+                    .iter()
+                    .zip(&bounds)
+                    .filter(|(_, b)| {
+                        b.x < rect.x + rect.width
+                            && b.x + b.width > rect.x
+                            && b.y < rect.y + rect.height
+                            && b.y + b.height > rect.y
+                    })
+                    .map(|(c, _)| c.clone())
+                    .collect();
+                if paint_commands.is_empty() {
+                    continue;
+                }
+
+                next_id += 1;
+                let tile = Tile {
+                    id: TileId::new(next_id),
+                    layer_id: LayerId::new(DEBUG_OVERLAY_LAYER),
+                    elements: vec![TiledLayoutElement {
+                        id: LayoutElementId::new(0),
+                        rect: PipelineRect::new(0.0, 0.0, rect.width, rect.height),
+                        position: Coordinate::new(0.0, 0.0),
+                        paint_commands,
+                    }],
+                    texture_id: None,
+                    state: TileState::Dirty,
+                    rect,
+                    bgcolor: None,
+                    scale: zoom,
+                    device_pixel_ratio,
+                };
+                let Some(tex) = rasterizer
+                    .rasterize(&tile, &mut texture_store, media_store)
+                    .and_then(|id| texture_store.get(id))
+                else {
+                    continue;
+                };
+                baked.push(BakedTile {
+                    page_x: rect.x * zoom,
+                    page_y: rect.y * zoom,
+                    layer_id: DEBUG_OVERLAY_LAYER,
+                    width: tex.width as u32,
+                    height: tex.height as u32,
+                    pixels: tex.pixels.clone(),
+                    format: tex.format,
+                    opacity: 1.0,
+                    anchor,
+                });
+            }
+            row += 1.0;
+        }
+    }
+    baked
+}
+
 /// Re-emit baked tiles in strict back-to-front layer order (the same order a full render
 /// produces them). The compositor blits tiles in list order with source-over, so overlapping
 /// layers (e.g. the base layer and a `position: sticky`/`fixed` header sharing a page position)
@@ -1085,7 +1900,11 @@ fn order_baked_tiles_by_layer(
             let Some(tile) = tile_list.arena.get(&tile_id) else {
                 continue;
             };
-            let key = (tile.rect.x.to_bits(), tile.rect.y.to_bits(), tile.layer_id.as_u64());
+            let key = (
+                (tile.rect.x * tile.scale).to_bits(),
+                (tile.rect.y * tile.scale).to_bits(),
+                tile.layer_id.as_u64(),
+            );
             if let Some(t) = by_key.remove(&key) {
                 ordered.push(t);
             }
@@ -1133,7 +1952,10 @@ fn pipeline_composite(cache: &PipelineCache, scroll_x: f64, scroll_y: f64, vp_w:
 
 #[cfg(test)]
 mod tests {
-    use super::parse_clear_color;
+    use super::{parse_clear_color, BrowsingContext};
+    use crate::tab::DeviceEmulation;
+    use gosub_css3::viewport::ViewportMeta;
+    use gosub_render_pipeline::render::Viewport;
 
     #[test]
     fn parse_clear_color_handles_rgb_rgba_and_garbage() {
@@ -1153,4 +1975,247 @@ mod tests {
         let c = parse_clear_color("not-a-color");
         assert_eq!((c.r, c.g, c.b, c.a), (1.0, 1.0, 1.0, 1.0));
     }
+
+    #[test]
+    fn zoom_narrows_layout_viewport_and_rescales_scroll() {
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        ctx.set_viewport(Viewport::new(0, 0, 1200, 800));
+        ctx.set_scroll(0.0, 100.0);
+
+        assert!(ctx.set_zoom(2.0));
+        assert_eq!(ctx.zoom(), 2.0);
+        let vp = ctx.layout_viewport();
+        assert_eq!((vp.width, vp.height), (600, 400));
+        assert_eq!(ctx.scroll_xy(), (0.0, 200.0));
+
+        // Same factor and invalid factors are no-ops.
+        assert!(!ctx.set_zoom(2.0));
+        assert!(!ctx.set_zoom(0.0));
+        assert!(!ctx.set_zoom(f64::NAN));
+        assert_eq!(ctx.zoom(), 2.0);
+    }
+
+    #[test]
+    fn device_emulation_lays_out_at_meta_viewport_width() {
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        ctx.set_device_emulation(Some(DeviceEmulation::new(490, 1000, 3)));
+        ctx.set_viewport(Viewport::new(0, 0, 490, 1000));
+
+        // No <meta name="viewport">: desktop layout width, scaled down to fit the device.
+        let vp = ctx.layout_viewport();
+        assert_eq!((vp.width, vp.height), (980, 2000));
+        assert_eq!(ctx.raster_scale(), 0.5);
+        assert_eq!(ctx.device_pixel_ratio(1), 3);
+
+        ctx.viewport_meta = ViewportMeta::parse("width=device-width, initial-scale=1");
+        let vp = ctx.layout_viewport();
+        assert_eq!((vp.width, vp.height), (490, 1000));
+        assert_eq!(ctx.raster_scale(), 1.0);
+
+        // Desktop rendering ignores the meta tag.
+        ctx.set_device_emulation(None);
+        ctx.set_viewport(Viewport::new(0, 0, 1280, 800));
+        ctx.viewport_meta = ViewportMeta::parse("width=320");
+        let vp = ctx.layout_viewport();
+        assert_eq!((vp.width, vp.height), (1280, 800));
+        assert_eq!(ctx.device_pixel_ratio(2), 2);
+    }
+
+    #[test]
+    fn inspect_node_reports_computed_values_and_cascade() {
+        use gosub_html5::document::builder::DocumentBuilderImpl;
+        use gosub_html5::parser::Html5Parser;
+        use gosub_interface::document::Document as _;
+        use gosub_shared::byte_stream::{ByteStream, Encoding};
+        use std::sync::Arc;
+
+        let html = r#"<html><head><style>
+            p { color: red; margin: 4px; }
+            #intro { color: blue; }
+            .note, p.lead { margin-top: 10px !important; }
+        </style></head><body><p id="intro" class="lead" style="color: green">Hi</p></body></html>"#;
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<crate::html::DefaultRenderConfig>(None);
+        let _ = Html5Parser::<crate::html::DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+        let p = doc.node_by_named_id("intro").unwrap();
+
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        ctx.set_document(Arc::new(doc));
+        let inspection = ctx.inspect_node(p).unwrap();
+
+        let computed = |name: &str| {
+            inspection
+                .computed
+                .iter()
+                .find(|(prop, _)| prop == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(computed("color"), Some("rgb(0, 128, 0)"));
+        assert_eq!(computed("margin-top"), Some("10px"));
+
+        let selectors: Vec<&str> = inspection.rules.iter().map(|r| r.selector.as_str()).collect();
+        assert_eq!(selectors, vec!["", "#intro", "p.lead", "p"]);
+
+        let inline = &inspection.rules[0];
+        assert!(inline.inline);
+        assert!(!inline.declarations[0].overridden);
+
+        let lead = &inspection.rules[2];
+        assert_eq!(lead.specificity, (0, 1, 1));
+        assert!(lead.declarations[0].important);
+        assert!(!lead.declarations[0].overridden);
+
+        let id_rule = &inspection.rules[1];
+        assert!(id_rule.declarations[0].overridden);
+
+        // `margin` still sets three sides, so only `color` lost.
+        let p_rule = &inspection.rules[3];
+        let overridden: Vec<(&str, bool)> = p_rule
+            .declarations
+            .iter()
+            .map(|d| (d.property.as_str(), d.overridden))
+            .collect();
+        assert_eq!(overridden, vec![("color", true), ("margin", false)]);
+
+        assert!(ctx.inspect_node(ctx.document.as_ref().unwrap().root()).is_err());
+    }
+
+    #[test]
+    fn element_rects_and_hit_test_report_border_boxes() {
+        use gosub_html5::document::builder::DocumentBuilderImpl;
+        use gosub_html5::parser::Html5Parser;
+        use gosub_shared::byte_stream::{ByteStream, Encoding};
+        use std::sync::Arc;
+
+        let html = r#"<html><body style="margin: 0">
+            <div id="box" class="a b" style="width: 200px; height: 100px"></div>
+            <p class="hidden" style="display: none">gone</p>
+        </body></html>"#;
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<crate::html::DefaultRenderConfig>(None);
+        let _ = Html5Parser::<crate::html::DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        assert!(ctx.element_rects("div").is_err(), "no document yet");
+        ctx.set_document(Arc::new(doc));
+        ctx.set_viewport(Viewport::new(0, 0, 800, 600));
+        ctx.rebuild_scene_cache_if_needed();
+
+        let rects = ctx.element_rects("#box").unwrap();
+        assert_eq!(rects.len(), 1);
+        let page = rects[0].border_box.unwrap();
+        assert_eq!((page.width, page.height), (200.0, 100.0));
+        // Unzoomed and unscrolled, the viewport box is the page box.
+        let viewport = rects[0].viewport_box.unwrap();
+        assert_eq!((viewport.x, viewport.y), (page.x, page.y));
+
+        let hidden = ctx.element_rects(".hidden").unwrap();
+        assert_eq!(hidden.len(), 1);
+        assert!(hidden[0].border_box.is_none());
+        assert!(ctx.element_rects("[").is_err());
+
+        let chain = ctx.hit_test(viewport.x + 150.0, viewport.y + 80.0);
+        assert_eq!(chain[0].node_id, rects[0].node_id);
+        assert_eq!(chain[0].tag, "div");
+        assert_eq!(chain[0].id.as_deref(), Some("box"));
+        assert_eq!(chain[0].classes, ["a", "b"]);
+        assert_eq!(chain[0].border_box.map(|b| (b.width, b.height)), Some((200.0, 100.0)));
+        let tags: Vec<&str> = chain.iter().map(|e| e.tag.as_str()).collect();
+        assert_eq!(tags, ["div", "body", "html"]);
+
+        assert!(ctx.hit_test(-10.0, -10.0).is_empty());
+    }
+
+    #[test]
+    fn mutations_restyle_the_touched_subtrees() {
+        use gosub_html5::document::builder::DocumentBuilderImpl;
+        use gosub_html5::parser::Html5Parser;
+        use gosub_interface::document::Document as _;
+        use gosub_shared::byte_stream::{ByteStream, Encoding};
+        use std::sync::Arc;
+
+        let html = r#"<html><head><style>.wide { width: 300px; }</style></head>
+            <body style="margin: 0"><div id="box" style="width: 100px; height: 10px"></div></body></html>"#;
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<crate::html::DefaultRenderConfig>(None);
+        let _ = Html5Parser::<crate::html::DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+        let id = doc.node_by_named_id("box").unwrap();
+
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        assert!(ctx.mutate_document(|_| {}).is_err(), "no document yet");
+        ctx.set_document(Arc::new(doc));
+        ctx.set_viewport(Viewport::new(0, 0, 800, 600));
+        ctx.rebuild_scene_cache_if_needed();
+        let width = |ctx: &BrowsingContext| ctx.element_rects("#box").unwrap()[0].border_box.unwrap().width;
+        assert_eq!(width(&ctx), 100.0);
+
+        ctx.mutate_document(|doc| {
+            doc.set_attribute(id, "class", "wide");
+            doc.set_attribute(id, "style", "height: 10px");
+        })
+        .unwrap();
+        assert!(ctx.retained_styles.is_some(), "the styles of the last build are kept");
+        ctx.rebuild_scene_cache_if_needed();
+        assert_eq!(width(&ctx), 300.0);
+
+        // Another owner of the document keeps it from being edited in place
+        let shared = Arc::clone(ctx.document.as_ref().unwrap());
+        assert!(ctx.mutate_document(|doc| doc.set_attribute(id, "class", "")).is_err());
+        assert_eq!(
+            shared.attributes(id).unwrap().get("class").map(String::as_str),
+            Some("wide")
+        );
+    }
+
+    #[test]
+    fn debug_overlays_are_appended_to_the_scene() {
+        use gosub_html5::document::builder::DocumentBuilderImpl;
+        use gosub_html5::parser::Html5Parser;
+        use gosub_render_pipeline::common::browser_state::DebugOverlays;
+        use gosub_render_pipeline::painter::commands::PaintCommand;
+        use gosub_shared::byte_stream::{ByteStream, Encoding};
+        use std::sync::Arc;
+
+        let html = r#"<html><body><div style="position: fixed; top: 0">bar</div><p>Hi</p></body></html>"#;
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<crate::html::DefaultRenderConfig>(None);
+        let _ = Html5Parser::<crate::html::DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+
+        let mut ctx: BrowsingContext = BrowsingContext::new(crate::engine::settings_store::default_config());
+        ctx.set_document(Arc::new(doc));
+        ctx.set_viewport(Viewport::new(0, 0, 800, 600));
+        ctx.rebuild_scene_cache_if_needed();
+        let plain = ctx.scene_cache.as_ref().unwrap().scene.commands.len();
+
+        assert!(ctx.set_debug_overlays(DebugOverlays {
+            layers: true,
+            ..Default::default()
+        }));
+        assert!(!ctx.set_debug_overlays(ctx.debug_overlays()));
+        ctx.rebuild_scene_cache_if_needed();
+        assert!(!ctx.debug_overlay_dirty());
+
+        let commands = &ctx.scene_cache.as_ref().unwrap().scene.commands;
+        let labels: Vec<&str> = commands[plain..]
+            .iter()
+            .filter_map(|c| match c {
+                PaintCommand::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(labels.contains(&"layer 0 z=0"));
+        assert_eq!(labels.len(), 2);
+        // The fixed layer's outline is grouped so it stays pinned while scrolling.
+        assert!(commands[plain..]
+            .iter()
+            .any(|c| matches!(c, PaintCommand::PushLayer { anchor, .. } if *anchor != Default::default())));
+    }
 }
@@ -51,6 +51,7 @@
 use crate::cookies::DefaultCookieJar;
 use crate::engine::cookies::store::CookieStore;
 use crate::engine::cookies::CookieJar;
+use crate::engine::cookies::PersistentCookieJar;
 use crate::zone::ZoneId;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use serde::{Deserialize, Serialize};
@@ -112,6 +113,39 @@ impl CookieJarHandle {
     pub fn write(&self) -> RwLockWriteGuard<'_, Box<dyn CookieJar + Send + Sync>> {
         self.0.write()
     }
+
+    /// Returns a copy of the jar's cookies, looking through a [`PersistentCookieJar`] wrapper.
+    ///
+    /// Returns `None` when the jar is not backed by a [`DefaultCookieJar`].
+    pub fn snapshot(&self) -> Option<DefaultCookieJar> {
+        let jar = self.read();
+        if let Some(persist) = jar.as_any().downcast_ref::<PersistentCookieJar>() {
+            return persist.inner.snapshot();
+        }
+        jar.as_any().downcast_ref::<DefaultCookieJar>().cloned()
+    }
+
+    /// Replaces the jar's cookies with those in `snapshot`, keeping the jar's own
+    /// third-party policy. A [`PersistentCookieJar`] writes the result back to its store.
+    ///
+    /// Returns `false` when the jar is not backed by a [`DefaultCookieJar`].
+    pub fn restore(&self, snapshot: &DefaultCookieJar) -> bool {
+        let mut jar = self.write();
+        if let Some(persist) = jar.as_any_mut().downcast_mut::<PersistentCookieJar>() {
+            let restored = persist.inner.restore(snapshot);
+            if restored {
+                persist.persist();
+            }
+            return restored;
+        }
+        match jar.as_any_mut().downcast_mut::<DefaultCookieJar>() {
+            Some(default) => {
+                default.entries = snapshot.entries.clone();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl Deref for CookieJarHandle {
@@ -79,6 +79,14 @@ pub enum EngineError {
     /// A cookie/storage backing store failed to initialize.
     #[error("Cookie store error: {0}")]
     CookieStore(#[source] anyhow::Error),
+
+    /// A zone profile could not be exported or imported.
+    #[error("Profile error: {0}")]
+    Profile(#[source] anyhow::Error),
+
+    /// A tab recording could not be saved or loaded.
+    #[error("Recording error: {0}")]
+    Recording(#[source] anyhow::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -18,12 +18,18 @@ use crate::net::req_ref_tracker::RequestReference;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResult, FetchResultMeta, Initiator, Priority, ResourceKind};
 use crate::net::DecisionToken;
 use crate::storage::event::StorageScope;
+use crate::tab::recording::Recording;
 use crate::tab::TabId;
 use crate::zone::ZoneId;
 use crate::EngineError;
 use bitflags::bitflags;
+use gosub_interface::css3::MatchedRule;
+use gosub_render_pipeline::common::browser_state::DebugOverlays;
+use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::render::backend::ExternalHandle;
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
 use std::time::Duration;
@@ -31,7 +37,7 @@ use tokio::sync::oneshot;
 use url::Url;
 
 /// Represents a mouse button that can be pressed or released
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MouseButton {
     /// Left mouse button pressed (or depressed)
     Left,
@@ -135,6 +141,32 @@ pub enum TabCommand {
     SuspendDrawing,
     /// Set viewport
     SetViewport { x: i32, y: i32, width: u32, height: u32 },
+    /// Set the page zoom factor (1.0 = 100%). Clamped to 0.25..=5.0; answered with
+    /// [`EngineEvent::ZoomChanged`].
+    SetZoom { factor: f32 },
+    /// Look up the elements matching a CSS selector list and report their boxes. Answered with
+    /// [`EngineEvent::ElementRects`].
+    GetElementRects { selector: String },
+    /// Report the elements under a viewport point, innermost first. Answered with
+    /// [`EngineEvent::HitTestResult`].
+    HitTest { x: f32, y: f32 },
+    /// Report an element's computed style and the rules that produced it. Answered with
+    /// [`EngineEvent::NodeInspected`].
+    InspectNode { node_id: NodeId },
+    /// Lay the page out for print and render it to PDF. Answered with [`EngineEvent::PdfPrinted`]
+    /// or [`EngineEvent::PrintFailed`].
+    PrintToPdf,
+    /// Start recording the input commands this tab receives, replacing any recording in progress.
+    StartRecording,
+    /// Stop recording. Answered with [`EngineEvent::RecordingFinished`].
+    StopRecording,
+    /// Turn the accessibility tree on or off. While on, the tab sends
+    /// [`EngineEvent::AccessibilityUpdate`]s: the full tree when enabled, then the changes after
+    /// each render, scroll or focus change.
+    SetAccessibility { enabled: bool },
+    /// Choose which debug overlays (box model, tiles, layers, repaint flash) are painted over
+    /// the page.
+    SetDebugOverlays { overlays: DebugOverlays },
 
     // ****************************************
     // ** Tab properties
@@ -358,6 +390,49 @@ pub enum ResourceEvent {
     },
 }
 
+/// An element matched by [`TabCommand::GetElementRects`].
+#[derive(Debug, Clone)]
+pub struct ElementRect {
+    /// DOM node of the element
+    pub node_id: NodeId,
+    /// Border box in document CSS pixels (unzoomed, scroll-independent). `None` when the element
+    /// generates no box, e.g. `display: none` or not laid out yet.
+    pub border_box: Option<Rect>,
+    /// The same box in viewport pixels (zoomed, scrolled; `fixed` and `sticky` content placed
+    /// where it is shown), the space mouse commands use.
+    pub viewport_box: Option<Rect>,
+}
+
+/// An element found by [`TabCommand::HitTest`].
+#[derive(Debug, Clone)]
+pub struct HitTestElement {
+    /// DOM node of the element
+    pub node_id: NodeId,
+    /// Tag name, as in the document
+    pub tag: String,
+    /// The `id` attribute
+    pub id: Option<String>,
+    /// The `class` attribute, split into class names
+    pub classes: Vec<String>,
+    /// The `href` attribute, unresolved
+    pub href: Option<String>,
+    /// The `src` attribute, unresolved
+    pub src: Option<String>,
+    /// Border box in document CSS pixels, as in [`ElementRect::border_box`]
+    pub border_box: Option<Rect>,
+    /// Border box in viewport pixels, as in [`ElementRect::viewport_box`]
+    pub viewport_box: Option<Rect>,
+}
+
+/// An element's style as reported for [`TabCommand::InspectNode`].
+#[derive(Debug, Clone)]
+pub struct NodeInspection {
+    /// Computed value of every style property the renderer uses, as `(css name, value)`
+    pub computed: Vec<(String, String)>,
+    /// The rules matching the element, highest precedence first
+    pub rules: Vec<MatchedRule>,
+}
+
 /// Reasons for cancelling a load request
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum CancelReason {
@@ -457,6 +532,55 @@ pub enum EngineEvent {
         tab_id: TabId,
         viewport: Viewport,
     },
+    /// Page zoom of the tab has been set (in response to `TabCommand::SetZoom`)
+    ZoomChanged {
+        tab_id: TabId,
+        factor: f32,
+    },
+    /// Boxes of the elements matching a selector (in response to `TabCommand::GetElementRects`),
+    /// or the reason the selector could not be evaluated
+    ElementRects {
+        tab_id: TabId,
+        selector: String,
+        result: Result<Vec<ElementRect>, String>,
+    },
+    /// The elements under a point (in response to `TabCommand::HitTest`), innermost first up to
+    /// the root element; empty when the point hits nothing
+    HitTestResult {
+        tab_id: TabId,
+        x: f32,
+        y: f32,
+        elements: Vec<HitTestElement>,
+    },
+    /// An element's style (in response to `TabCommand::InspectNode`), or the reason it could not
+    /// be inspected
+    NodeInspected {
+        tab_id: TabId,
+        node_id: NodeId,
+        result: Result<NodeInspection, String>,
+    },
+    /// The page has been printed (in response to `TabCommand::PrintToPdf`)
+    PdfPrinted {
+        tab_id: TabId,
+        pdf: Vec<u8>,
+    },
+    /// Printing failed: no document is loaded or the backend has no PDF output
+    PrintFailed {
+        tab_id: TabId,
+        message: String,
+    },
+    /// Recording stopped (in response to `TabCommand::StopRecording`); `None` when no recording
+    /// was in progress
+    RecordingFinished {
+        tab_id: TabId,
+        recording: Option<Recording>,
+    },
+    /// Accessibility tree changes, ready to hand to an AccessKit adapter (only sent while
+    /// enabled with `TabCommand::SetAccessibility`)
+    AccessibilityUpdate {
+        tab_id: TabId,
+        update: accesskit::TreeUpdate,
+    },
 
     // ****************************************
     // ** Navigation
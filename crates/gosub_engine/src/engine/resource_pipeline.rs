@@ -30,12 +30,19 @@ pub struct ResourcePipelines<C: RenderConfiguration> {
 }
 
 impl<C: RenderConfiguration> ResourcePipelines<C> {
-    pub fn new(zone_id: ZoneId, io_tx: IoChannel, accept_language: Option<String>, max_document_bytes: usize) -> Self {
+    pub fn new(
+        zone_id: ZoneId,
+        io_tx: IoChannel,
+        accept_language: Option<String>,
+        user_agent: Option<String>,
+        max_document_bytes: usize,
+    ) -> Self {
         Self {
             html: Box::new(HtmlPipelineImpl::new(
                 zone_id,
                 io_tx,
                 accept_language,
+                user_agent,
                 max_document_bytes,
             )),
             css: Box::new(CssPipelineImpl {}),
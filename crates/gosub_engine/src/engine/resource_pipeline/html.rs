@@ -1,8 +1,11 @@
 use crate::engine::types::{IoChannel, PeekBuf, RequestId};
-use crate::html::{parse_main_document_stream, EngineDocument, RenderConfiguration, ResourceHint};
+use crate::html::{
+    load_stylesheet_imports, parse_main_document_stream, DocumentFormat, EngineDocument, RenderConfiguration,
+    ResourceHint,
+};
 use crate::net::req_ref_tracker::REF_REGISTRY;
-use crate::net::types::{FetchHandle, FetchRequest, FetchResultMeta, Initiator};
-use crate::net::{submit_to_io, SharedBody};
+use crate::net::types::{FetchHandle, FetchRequest, FetchResult, FetchResultMeta, Initiator, Priority, ResourceKind};
+use crate::net::{stream_to_bytes, submit_to_io, SharedBody};
 use crate::util::spawn_named;
 use crate::zone::ZoneId;
 use anyhow::anyhow;
@@ -16,6 +19,7 @@ use std::sync::Arc;
 use tokio::io::AsyncRead;
 use tokio::task::JoinHandle;
 use tokio_util::io::StreamReader;
+use url::Url;
 
 #[async_trait]
 pub trait HtmlPipeline<C: RenderConfiguration> {
@@ -24,6 +28,7 @@ pub trait HtmlPipeline<C: RenderConfiguration> {
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
+        format: DocumentFormat,
         peek_buf: PeekBuf,
         body: Arc<SharedBody>,
     ) -> anyhow::Result<EngineDocument<C>>;
@@ -33,6 +38,7 @@ pub trait HtmlPipeline<C: RenderConfiguration> {
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
+        format: DocumentFormat,
         body: &[u8],
     ) -> anyhow::Result<EngineDocument<C>>;
 }
@@ -42,16 +48,25 @@ pub struct HtmlPipelineImpl {
     zone_id: ZoneId,
     /// `Accept-Language` header value sent with discovered subresource requests.
     accept_language: Option<String>,
+    /// `User-Agent` header value sent with discovered subresource requests.
+    user_agent: Option<String>,
     /// Max document size in bytes (`net.document.max_bytes`); larger documents are truncated.
     max_document_bytes: usize,
 }
 
 impl HtmlPipelineImpl {
-    pub fn new(zone_id: ZoneId, io_tx: IoChannel, accept_language: Option<String>, max_document_bytes: usize) -> Self {
+    pub fn new(
+        zone_id: ZoneId,
+        io_tx: IoChannel,
+        accept_language: Option<String>,
+        user_agent: Option<String>,
+        max_document_bytes: usize,
+    ) -> Self {
         Self {
             io_tx,
             zone_id,
             accept_language,
+            user_agent,
             max_document_bytes,
         }
     }
@@ -61,6 +76,7 @@ impl HtmlPipelineImpl {
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
+        format: DocumentFormat,
         reader: R,
     ) -> anyhow::Result<EngineDocument<C>>
     where
@@ -69,6 +85,7 @@ impl HtmlPipelineImpl {
     {
         let cfg = crate::html::HtmlParseConfig {
             max_bytes: self.max_document_bytes,
+            format,
         };
 
         let io_tx = self.io_tx.clone();
@@ -88,6 +105,11 @@ impl HtmlPipelineImpl {
                 sub_headers.insert(http::header::ACCEPT_LANGUAGE, val);
             }
         }
+        if let Some(ua) = &self.user_agent {
+            if let Ok(val) = ua.parse() {
+                sub_headers.insert(http::header::USER_AGENT, val);
+            }
+        }
 
         let mut on_discover = |hint: ResourceHint| {
             let sub_req_id = RequestId::new();
@@ -132,7 +154,7 @@ impl HtmlPipelineImpl {
         let was_cancelled = handle.cancel.is_cancelled();
 
         let _doc_timer = timing_guard!("html.document", meta.final_url.as_str());
-        let res = parse_main_document_stream(
+        let mut res = parse_main_document_stream(
             meta.final_url, // This is the base URL
             reader,
             handle.cancel.clone(),
@@ -141,6 +163,48 @@ impl HtmlPipelineImpl {
         )
         .await;
 
+        // `@import`ed stylesheets are requested by the stylesheets themselves, through the same
+        // IO path as the parser's subresources.
+        let fetch_import = |url: Url| {
+            let sub_req_id = RequestId::new();
+            REF_REGISTRY.register_request(sub_req_id, ResourceKind::Stylesheet, Initiator::CSS);
+            let sub_req = FetchRequest::builder(Method::GET, url)
+                .with_req_id(sub_req_id)
+                .with_reference(parent_ref)
+                .with_priority(Priority::High)
+                .with_initiator(Initiator::CSS.to_net())
+                .with_kind(ResourceKind::Stylesheet.to_net())
+                .with_headers(sub_headers.clone())
+                .with_streaming(true)
+                .with_auto_decode(true)
+                .build();
+
+            let io_tx = io_tx.clone();
+            let parent_cancel = parent_cancel.clone();
+            async move {
+                let rx = match submit_to_io(zone_id, sub_req, io_tx, Some(parent_cancel)).await {
+                    Ok((_, rx)) => rx,
+                    Err(e) => {
+                        log::warn!("Failed to submit @import request: {:?}", e);
+                        return None;
+                    }
+                };
+                let body = match rx.await.ok()? {
+                    FetchResult::Stream { meta, peek_buf, shared } if (200..300).contains(&meta.status) => {
+                        stream_to_bytes(peek_buf, shared).await.ok()?
+                    }
+                    FetchResult::Buffered { meta, body } if (200..300).contains(&meta.status) => body,
+                    _ => return None,
+                };
+                Some(String::from_utf8_lossy(&body).into_owned())
+            }
+        };
+        if let Ok(doc) = &mut res {
+            if !handle.cancel.is_cancelled() {
+                load_stylesheet_imports(doc, fetch_import).await;
+            }
+        }
+
         // Cancel the parent token so that all child fetch tokens (which are children of
         // parent_cancel via child_token()) are also cancelled. This works regardless of
         // whether the spawned submission tasks have run yet, since the cancellation
@@ -170,11 +234,13 @@ impl<C: RenderConfiguration> HtmlPipeline<C> for HtmlPipelineImpl {
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
+        format: DocumentFormat,
         peek_buf: PeekBuf,
         shared: Arc<SharedBody>,
     ) -> anyhow::Result<EngineDocument<C>> {
         let reader = SharedBody::combined_reader(peek_buf, shared);
-        self.parse_with_reader::<C, _>(request, handle, meta, reader).await
+        self.parse_with_reader::<C, _>(request, handle, meta, format, reader)
+            .await
     }
 
     async fn parse_bytes(
@@ -182,12 +248,14 @@ impl<C: RenderConfiguration> HtmlPipeline<C> for HtmlPipelineImpl {
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
+        format: DocumentFormat,
         body: &[u8],
     ) -> anyhow::Result<EngineDocument<C>> {
         // parsing bytes is just creating a stream of those bytes and passing it to the stream reader
         let stream = stream::iter(vec![Ok::<Bytes, std::io::Error>(Bytes::copy_from_slice(body))]);
         let reader = StreamReader::new(stream);
-        self.parse_with_reader::<C, _>(request, handle, meta, reader).await
+        self.parse_with_reader::<C, _>(request, handle, meta, format, reader)
+            .await
     }
 }
 
@@ -202,7 +270,6 @@ mod tests {
     use std::time::Duration;
     use tokio::sync::mpsc;
     use tokio::time::sleep;
-    use url::Url;
 
     // Minimal HTML that triggers 3 resource discoveries: link/script/img + a title.
     const HTML_WITH_RESOURCES: &str = r#"
@@ -286,16 +353,23 @@ mod tests {
         // Arrange
         let (io_tx, seen_children) = start_dummy_io();
         let zone_id = ZoneId::new();
-        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, 10 * 1024 * 1024);
+        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, None, 10 * 1024 * 1024);
 
         let (req, handle) = test_request("https://example.com/path/index.html");
         let meta = test_meta("https://example.com/path/index.html");
         let body = HTML_WITH_RESOURCES.as_bytes();
 
         // Act
-        let doc = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(&mut pipeline, req, handle, meta, body)
-            .await
-            .expect("parse_bytes should succeed");
+        let doc = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(
+            &mut pipeline,
+            req,
+            handle,
+            meta,
+            DocumentFormat::Html,
+            body,
+        )
+        .await
+        .expect("parse_bytes should succeed");
 
         // Allow spawned tasks to submit to IO and be recorded
         sleep(Duration::from_millis(10)).await;
@@ -313,16 +387,23 @@ mod tests {
         // Arrange
         let (io_tx, seen_children) = start_dummy_io();
         let zone_id = ZoneId::new();
-        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, 10 * 1024 * 1024);
+        let mut pipeline = HtmlPipelineImpl::new(zone_id, io_tx, None, None, 10 * 1024 * 1024);
 
         let (req, handle) = test_request("https://example.com/");
         let meta = test_meta("https://example.com/");
         let body = HTML_WITH_RESOURCES.as_bytes();
 
         // Act
-        let _ = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(&mut pipeline, req, handle, meta, body)
-            .await
-            .expect("parse ok");
+        let _ = HtmlPipeline::<DefaultRenderConfig>::parse_bytes(
+            &mut pipeline,
+            req,
+            handle,
+            meta,
+            DocumentFormat::Html,
+            body,
+        )
+        .await
+        .expect("parse ok");
 
         // Give the pipeline a tick to run the post-parse cancellation
         sleep(Duration::from_millis(10)).await;
@@ -34,6 +34,9 @@ pub trait StorageArea: Send + Sync {
 pub trait LocalStore: Send + Sync {
     /// Retrieves a storage area for the given zone, partition, and origin.
     fn area(&self, zone: ZoneId, part: &PartitionKey, origin: &url::Origin) -> Result<Arc<dyn StorageArea>>;
+
+    /// Lists the (partition, origin) pairs that hold at least one item for the given zone.
+    fn areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>>;
 }
 
 /// Store for sessionStorage-like areas (isolated per (zone, tab, partition, origin)).
@@ -30,6 +30,15 @@ impl LocalStore for InMemoryLocalStore {
             .or_insert_with(|| Arc::new(InMemoryLocalArea::default()) as Arc<dyn StorageArea>)
             .clone())
     }
+
+    fn areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        let guard = self.areas.lock();
+        Ok(guard
+            .iter()
+            .filter(|((z, _, _), area)| *z == zone && !area.is_empty())
+            .map(|((_, part, origin), _)| (part.clone(), origin.clone()))
+            .collect())
+    }
 }
 
 #[derive(Default)]
@@ -139,4 +148,28 @@ mod tests {
         let b_same_part_origin = store.area(zone_b, &part_a, &orig_a).unwrap();
         assert!(b_same_part_origin.get_item("k").is_none());
     }
+
+    #[test]
+    fn areas_lists_only_non_empty_areas_of_zone() {
+        let store = InMemoryLocalStore::new();
+        let zone_a = ZoneId::new();
+        let zone_b = ZoneId::new();
+        let part = PartitionKey::TopLevel(o("https://a.test"));
+
+        store
+            .area(zone_a, &part, &o("https://a.test"))
+            .unwrap()
+            .set_item("k", "v")
+            .unwrap();
+        // touched but empty
+        store.area(zone_a, &part, &o("https://b.test")).unwrap();
+        store
+            .area(zone_b, &part, &o("https://c.test"))
+            .unwrap()
+            .set_item("k", "v")
+            .unwrap();
+
+        let areas = store.areas(zone_a).unwrap();
+        assert_eq!(areas, vec![(part, o("https://a.test"))]);
+    }
 }
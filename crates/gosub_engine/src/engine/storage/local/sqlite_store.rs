@@ -50,14 +50,29 @@ impl LocalStore for SqliteLocalStore {
         Ok(Arc::new(SqliteLocalArea {
             pool: self.pool.clone(),
             zone,
-            partition: match part {
-                PartitionKey::None => "".to_string(),
-                PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
-                PartitionKey::Custom(s) => s.to_string(),
-            },
+            partition: part.to_storage_key(),
             origin: origin.ascii_serialization(),
         }))
     }
+
+    fn areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT partition, origin FROM local_storage WHERE zone=?1")?;
+        let rows = stmt.query_map(params![zone.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            let (partition, origin) = row?;
+            // Opaque origins serialize as "null" and cannot be addressed again; skip them.
+            let Ok(url) = url::Url::parse(&origin) else {
+                continue;
+            };
+            out.push((PartitionKey::from_storage_key(&partition), url.origin()));
+        }
+        Ok(out)
+    }
 }
 
 struct SqliteLocalArea {
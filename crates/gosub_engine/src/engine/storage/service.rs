@@ -66,6 +66,11 @@ impl StorageService {
         Ok(self.wrap_notifying(inner, zone, None, part.clone(), origin.clone(), StorageScope::Local))
     }
 
+    /// Lists the (partition, origin) pairs with local storage data for `zone`.
+    pub fn local_areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        self.local.areas(zone)
+    }
+
     pub fn session_for(
         &self,
         zone: ZoneId,
@@ -1,4 +1,5 @@
 use crate::zone::ZoneId;
+use serde::{Deserialize, Serialize};
 use url::{Origin, Url};
 
 /// Partitioning key (future-proof for state partitioning).
@@ -32,10 +33,30 @@ impl PartitionKey {
         let url_str = format!("https://zone-{zone_id}.local");
         Self::from_str(&url_str)
     }
+
+    /// Encodes the key as a flat string, as used by persistent stores and profile archives.
+    pub fn to_storage_key(&self) -> String {
+        match self {
+            PartitionKey::None => "".to_string(),
+            PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
+            PartitionKey::Custom(s) => s.to_string(),
+        }
+    }
+
+    /// Decodes a key produced by [`PartitionKey::to_storage_key`].
+    pub fn from_storage_key(s: &str) -> Self {
+        if s.is_empty() {
+            return PartitionKey::None;
+        }
+        match s.strip_prefix("top:").and_then(|o| Url::parse(o).ok()) {
+            Some(url) => PartitionKey::TopLevel(url.origin()),
+            None => PartitionKey::Custom(s.to_string()),
+        }
+    }
 }
 
 /// Partitioning policy for determining how to compute the partition key.
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub enum PartitionPolicy {
     /// No partitioning, uses a global state.
     None,
@@ -127,4 +148,15 @@ mod tests {
         assert!(set.contains(&none));
         assert_eq!(set.len(), 3); // a/b, c, none
     }
+
+    #[test]
+    fn storage_key_round_trip() {
+        for pk in [
+            PartitionKey::None,
+            PartitionKey::TopLevel(o("https://example.com:8443")),
+            PartitionKey::Custom("work-profile".into()),
+        ] {
+            assert_eq!(PartitionKey::from_storage_key(&pk.to_storage_key()), pk);
+        }
+    }
 }
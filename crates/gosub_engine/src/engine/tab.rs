@@ -1,5 +1,6 @@
 mod handle;
 mod options;
+pub mod recording;
 mod scroll;
 pub mod services;
 mod sink;
@@ -11,6 +12,7 @@ mod worker;
 pub use handle::TabHandle;
 pub use tab::*;
 
+pub use options::DeviceEmulation;
 pub use options::TabCookieJar;
 pub use options::TabDefaults;
 pub use options::TabOverrides;
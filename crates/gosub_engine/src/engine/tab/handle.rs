@@ -3,7 +3,9 @@ use crate::events::TabCommand;
 use crate::tab::sink::TabSink;
 use crate::tab::TabId;
 use crate::EngineError;
+use gosub_render_pipeline::common::browser_state::DebugOverlays;
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
 use std::sync::Arc;
 
 /// A handle to a running [`Tab`](crate::tab).
@@ -80,6 +82,118 @@ impl TabHandle {
         .await
     }
 
+    /// Set the page zoom factor of the tab (1.0 = 100%).
+    ///
+    /// The page is re-laid out against a viewport narrowed by `factor` and rasterized at the
+    /// matching scale. The applied (clamped) factor is reported via `EngineEvent::ZoomChanged`.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.set_zoom(1.25).await?;
+    /// ```
+    pub async fn set_zoom(&self, factor: f32) -> Result<(), EngineError> {
+        self.send(TabCommand::SetZoom { factor }).await
+    }
+
+    /// Look up the elements matching a CSS selector list.
+    ///
+    /// Their border boxes arrive via `EngineEvent::ElementRects`, in document order.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.get_element_rects("main article > h1").await?;
+    /// ```
+    pub async fn get_element_rects(&self, selector: impl Into<String>) -> Result<(), EngineError> {
+        self.send(TabCommand::GetElementRects {
+            selector: selector.into(),
+        })
+        .await
+    }
+
+    /// Find the elements under viewport point `(x, y)`, in the coordinates of `TabCommand::MouseMove`.
+    ///
+    /// The chain arrives via `EngineEvent::HitTestResult`, innermost element first. Hover state is
+    /// not affected.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.hit_test(120.0, 48.0).await?;
+    /// ```
+    pub async fn hit_test(&self, x: f32, y: f32) -> Result<(), EngineError> {
+        self.send(TabCommand::HitTest { x, y }).await
+    }
+
+    /// Inspect the style of an element, e.g. one found with `hit_test`.
+    ///
+    /// Its computed values and matching rules arrive via `EngineEvent::NodeInspected`.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.inspect_node(node_id).await?;
+    /// ```
+    pub async fn inspect_node(&self, node_id: NodeId) -> Result<(), EngineError> {
+        self.send(TabCommand::InspectNode { node_id }).await
+    }
+
+    /// Print the current page to PDF.
+    ///
+    /// The page is laid out with `@media print` rules and paginated per its `@page` rules. The
+    /// file arrives via `EngineEvent::PdfPrinted`, or `EngineEvent::PrintFailed` when the render
+    /// backend has no PDF output.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.print_to_pdf().await?;
+    /// ```
+    pub async fn print_to_pdf(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::PrintToPdf).await
+    }
+
+    /// Start recording the navigation, viewport, zoom, mouse, scroll and key commands this tab
+    /// receives, from any handle. See [`recording`](crate::tab::recording).
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.start_recording().await?;
+    /// ```
+    pub async fn start_recording(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::StartRecording).await
+    }
+
+    /// Stop recording. The recording arrives via `EngineEvent::RecordingFinished`.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.stop_recording().await?;
+    /// ```
+    pub async fn stop_recording(&self) -> Result<(), EngineError> {
+        self.send(TabCommand::StopRecording).await
+    }
+
+    /// Turn accessibility tree updates on or off. While on, the tab sends
+    /// `EngineEvent::AccessibilityUpdate`s, which AccessKit adapters (winit, GTK, egui) consume
+    /// directly.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.set_accessibility(true).await?;
+    /// ```
+    pub async fn set_accessibility(&self, enabled: bool) -> Result<(), EngineError> {
+        self.send(TabCommand::SetAccessibility { enabled }).await
+    }
+
+    /// Paint debug overlays over the page: the hovered element's box model, tile and layer
+    /// bounds, and a flash over repainted tiles. They are drawn with ordinary paint commands,
+    /// so they show up on every render backend.
+    ///
+    /// # Example
+    /// ```no_run,ignore
+    /// tab_handle.set_debug_overlays(DebugOverlays { tiles: true, ..Default::default() }).await?;
+    /// ```
+    pub async fn set_debug_overlays(&self, overlays: DebugOverlays) -> Result<(), EngineError> {
+        self.send(TabCommand::SetDebugOverlays { overlays }).await
+    }
+
     /// Navigate the tab to a new URL.
     ///
     /// This triggers a load in the tab’s context. The URL can be any supported scheme
@@ -69,6 +69,56 @@ pub struct TabOverrides {
     /// Per-tab `Accept-Language` header override. `None` = inherit the zone's
     /// [`ZoneConfig::accept_languages`](crate::zone::ZoneConfig::accept_languages).
     pub accept_language: Option<String>,
+
+    // --- Content ---
+    /// Emulate a mobile device (screen size, DPR, user agent, touch). `None` = desktop rendering.
+    pub device_emulation: Option<DeviceEmulation>,
+}
+
+/// Device-emulation profile for a tab.
+///
+/// An emulated tab renders to a visual viewport of `width` × `height` CSS pixels regardless of
+/// the host's viewport, and honors the page's `<meta name="viewport">`: the page is laid out at
+/// the resolved layout-viewport width and scaled to fit the device screen, like a mobile browser.
+///
+/// # Example
+/// ```no_run
+/// use gosub_engine::tab::{DeviceEmulation, TabOverrides};
+///
+/// let overrides = TabOverrides {
+///     device_emulation: Some(DeviceEmulation {
+///         user_agent: Some("Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X)".into()),
+///         ..DeviceEmulation::new(390, 844, 3)
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceEmulation {
+    /// Device screen width in CSS pixels.
+    pub width: u32,
+    /// Device screen height in CSS pixels.
+    pub height: u32,
+    /// Device pixel ratio the tab rasterizes at, instead of the backend's.
+    pub device_pixel_ratio: u32,
+    /// `User-Agent` sent with this tab's requests. `None` = inherit.
+    pub user_agent: Option<String>,
+    /// Touch device: pointer moves don't hover, presses hit-test at the tap position.
+    pub touch: bool,
+}
+
+impl DeviceEmulation {
+    /// A touch device of `width` × `height` CSS pixels at the given DPR, keeping the inherited
+    /// user agent.
+    pub fn new(width: u32, height: u32, device_pixel_ratio: u32) -> Self {
+        Self {
+            width: width.max(1),
+            height: height.max(1),
+            device_pixel_ratio: device_pixel_ratio.max(1),
+            user_agent: None,
+            touch: true,
+        }
+    }
 }
 
 /// Policy for selecting a tab's cookie jar.
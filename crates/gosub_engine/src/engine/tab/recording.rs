@@ -0,0 +1,563 @@
+//! Recording and replay of the commands a tab receives.
+//!
+//! While recording, the tab worker logs every input-like [`TabCommand`] it handles (navigation,
+//! viewport and zoom changes, mouse, scroll and keys) with the time it arrived, together with the
+//! state the tab was in when recording started. The resulting [`Recording`] is plain JSON, so it
+//! can be attached to a bug report and fed back into a fresh tab with [`replay`] - step by step,
+//! with the original gaps or as fast as possible, optionally grabbing the frame after each step.
+//!
+//! Recording is started and stopped with [`TabHandle::start_recording`] and
+//! [`TabHandle::stop_recording`]; the latter is answered with `EngineEvent::RecordingFinished`.
+
+use crate::events::{EngineEvent, Modifiers, MouseButton, NavigationEvent, TabCommand};
+use crate::tab::TabHandle;
+use crate::EngineError;
+use anyhow::anyhow;
+use gosub_render_pipeline::render::backend::ExternalHandle;
+use gosub_render_pipeline::render::Viewport;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Version of the on-disk recording layout. Bumped on incompatible changes.
+const RECORDING_FORMAT_VERSION: u32 = 1;
+
+/// State of the tab when recording started, restored before the first step is replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingStart {
+    /// Committed URL of the tab, if a page was loaded.
+    pub url: Option<String>,
+    /// Viewport size in CSS pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Page zoom factor.
+    pub zoom: f32,
+    /// Scroll offset in CSS pixels.
+    pub scroll_x: i32,
+    pub scroll_y: i32,
+}
+
+/// A recorded command. Mirrors the recordable subset of [`TabCommand`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedCommand {
+    Navigate {
+        url: String,
+    },
+    Reload {
+        ignore_cache: bool,
+    },
+    CancelNavigation,
+    SetViewport {
+        width: u32,
+        height: u32,
+    },
+    SetZoom {
+        factor: f32,
+    },
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseDown {
+        x: f32,
+        y: f32,
+        button: MouseButton,
+    },
+    MouseUp {
+        x: f32,
+        y: f32,
+        button: MouseButton,
+    },
+    MouseScroll {
+        delta_x: f32,
+        delta_y: f32,
+    },
+    KeyDown {
+        key: String,
+        code: String,
+        /// [`Modifiers`] bits.
+        modifiers: u8,
+    },
+    KeyUp {
+        key: String,
+        code: String,
+        modifiers: u8,
+    },
+    TextInput {
+        text: String,
+    },
+    CharInput {
+        ch: char,
+    },
+}
+
+impl RecordedCommand {
+    /// Returns the recordable form of `cmd`, or `None` for commands that are not user input
+    /// (drawing control, queries, storage, ...).
+    pub fn from_command(cmd: &TabCommand) -> Option<Self> {
+        Some(match cmd {
+            TabCommand::Navigate { url } => Self::Navigate { url: url.clone() },
+            TabCommand::Reload { ignore_cache } => Self::Reload {
+                ignore_cache: *ignore_cache,
+            },
+            TabCommand::CancelNavigation => Self::CancelNavigation,
+            TabCommand::SetViewport { width, height, .. } => Self::SetViewport {
+                width: *width,
+                height: *height,
+            },
+            TabCommand::SetZoom { factor } => Self::SetZoom { factor: *factor },
+            TabCommand::MouseMove { x, y } => Self::MouseMove { x: *x, y: *y },
+            TabCommand::MouseDown { x, y, button } => Self::MouseDown {
+                x: *x,
+                y: *y,
+                button: button.clone(),
+            },
+            TabCommand::MouseUp { x, y, button } => Self::MouseUp {
+                x: *x,
+                y: *y,
+                button: button.clone(),
+            },
+            TabCommand::MouseScroll { delta_x, delta_y } => Self::MouseScroll {
+                delta_x: *delta_x,
+                delta_y: *delta_y,
+            },
+            TabCommand::KeyDown { key, code, modifiers } => Self::KeyDown {
+                key: key.clone(),
+                code: code.clone(),
+                modifiers: modifiers.bits(),
+            },
+            TabCommand::KeyUp { key, code, modifiers } => Self::KeyUp {
+                key: key.clone(),
+                code: code.clone(),
+                modifiers: modifiers.bits(),
+            },
+            TabCommand::TextInput { text } => Self::TextInput { text: text.clone() },
+            TabCommand::CharInput { ch } => Self::CharInput { ch: *ch },
+            _ => return None,
+        })
+    }
+
+    /// Converts back into the command to send to a tab.
+    pub fn to_command(&self) -> TabCommand {
+        match self {
+            Self::Navigate { url } => TabCommand::Navigate { url: url.clone() },
+            Self::Reload { ignore_cache } => TabCommand::Reload {
+                ignore_cache: *ignore_cache,
+            },
+            Self::CancelNavigation => TabCommand::CancelNavigation,
+            Self::SetViewport { width, height } => TabCommand::SetViewport {
+                x: 0,
+                y: 0,
+                width: *width,
+                height: *height,
+            },
+            Self::SetZoom { factor } => TabCommand::SetZoom { factor: *factor },
+            Self::MouseMove { x, y } => TabCommand::MouseMove { x: *x, y: *y },
+            Self::MouseDown { x, y, button } => TabCommand::MouseDown {
+                x: *x,
+                y: *y,
+                button: button.clone(),
+            },
+            Self::MouseUp { x, y, button } => TabCommand::MouseUp {
+                x: *x,
+                y: *y,
+                button: button.clone(),
+            },
+            Self::MouseScroll { delta_x, delta_y } => TabCommand::MouseScroll {
+                delta_x: *delta_x,
+                delta_y: *delta_y,
+            },
+            Self::KeyDown { key, code, modifiers } => TabCommand::KeyDown {
+                key: key.clone(),
+                code: code.clone(),
+                modifiers: Modifiers::from_bits_truncate(*modifiers),
+            },
+            Self::KeyUp { key, code, modifiers } => TabCommand::KeyUp {
+                key: key.clone(),
+                code: code.clone(),
+                modifiers: Modifiers::from_bits_truncate(*modifiers),
+            },
+            Self::TextInput { text } => TabCommand::TextInput { text: text.clone() },
+            Self::CharInput { ch } => TabCommand::CharInput { ch: *ch },
+        }
+    }
+
+    /// True for commands that start loading a document.
+    fn loads_document(&self) -> bool {
+        matches!(self, Self::Navigate { .. } | Self::Reload { .. })
+    }
+}
+
+/// A command and when it arrived, relative to the start of the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedStep {
+    /// Milliseconds since recording started.
+    pub at_ms: u64,
+    pub command: RecordedCommand,
+}
+
+/// A recorded command stream, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Layout version, see [`RECORDING_FORMAT_VERSION`].
+    version: u32,
+    pub start: RecordingStart,
+    pub steps: Vec<RecordedStep>,
+}
+
+impl Recording {
+    pub fn new(start: RecordingStart, steps: Vec<RecordedStep>) -> Self {
+        Self {
+            version: RECORDING_FORMAT_VERSION,
+            start,
+            steps,
+        }
+    }
+
+    /// Writes the recording to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        let contents = serde_json::to_vec_pretty(self).map_err(|e| EngineError::Recording(e.into()))?;
+        fs::write(path, contents).map_err(|e| EngineError::Recording(e.into()))
+    }
+
+    /// Reads and validates a recording written by [`Recording::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EngineError> {
+        let contents = fs::read(path).map_err(|e| EngineError::Recording(e.into()))?;
+        let recording: Self = serde_json::from_slice(&contents).map_err(|e| EngineError::Recording(e.into()))?;
+        if recording.version != RECORDING_FORMAT_VERSION {
+            return Err(EngineError::Recording(anyhow!(
+                "unsupported recording version {} (expected {RECORDING_FORMAT_VERSION})",
+                recording.version
+            )));
+        }
+        Ok(recording)
+    }
+}
+
+/// Collects the steps of an active recording inside the tab worker.
+pub(crate) struct TabRecorder {
+    started: Instant,
+    start: RecordingStart,
+    steps: Vec<RecordedStep>,
+}
+
+impl TabRecorder {
+    pub(crate) fn new(start: RecordingStart) -> Self {
+        Self {
+            started: Instant::now(),
+            start,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Logs `cmd` if it is recordable.
+    pub(crate) fn record(&mut self, cmd: &TabCommand) {
+        if let Some(command) = RecordedCommand::from_command(cmd) {
+            self.steps.push(RecordedStep {
+                at_ms: self.started.elapsed().as_millis() as u64,
+                command,
+            });
+        }
+    }
+
+    pub(crate) fn finish(self) -> Recording {
+        Recording::new(self.start, self.steps)
+    }
+}
+
+/// How [`replay`] paces the steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayTiming {
+    /// Keep the recorded gap between consecutive steps.
+    #[default]
+    Recorded,
+    /// Send each step as soon as the previous one is done.
+    AsFastAsPossible,
+}
+
+/// Options for [`replay`].
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    pub timing: ReplayTiming,
+    /// Wait for a frame after each step and hand it to the step callback. The tab must be
+    /// drawing (`TabCommand::ResumeDrawing`).
+    pub snapshots: bool,
+    /// How long to wait for a frame after a step before giving up on its snapshot.
+    pub snapshot_timeout: Duration,
+    /// How long to wait for a navigation or reload to finish before moving on.
+    pub load_timeout: Duration,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            timing: ReplayTiming::Recorded,
+            snapshots: false,
+            snapshot_timeout: Duration::from_millis(500),
+            load_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A replayed step, passed to the [`replay`] callback.
+pub struct ReplayStep<'a> {
+    pub index: usize,
+    pub step: &'a RecordedStep,
+    /// Frame drawn after the step, when [`ReplayOptions::snapshots`] is set and one arrived in time.
+    pub snapshot: Option<ExternalHandle>,
+}
+
+/// Replays `recording` into `tab`, which should be a fresh tab.
+///
+/// The starting state (viewport, zoom, page and scroll offset) is restored first. Steps that load
+/// a document wait for the load to finish, in both timing modes, so later input reaches the same
+/// page it did while recording. `events` must be subscribed to the engine the tab belongs to.
+///
+/// # Example
+/// ```no_run,ignore
+/// let recording = Recording::load("bug-1234.json")?;
+/// let mut events = engine.subscribe_events();
+/// replay(&tab, &recording, &mut events, &ReplayOptions::default(), |step| {
+///     println!("step {}: {:?}", step.index, step.step.command);
+/// })
+/// .await?;
+/// ```
+pub async fn replay(
+    tab: &TabHandle,
+    recording: &Recording,
+    events: &mut broadcast::Receiver<EngineEvent>,
+    options: &ReplayOptions,
+    mut on_step: impl FnMut(ReplayStep<'_>),
+) -> Result<(), EngineError> {
+    let start = &recording.start;
+    tab.set_viewport(Viewport::new(0, 0, start.width, start.height)).await?;
+    if start.zoom != 1.0 {
+        tab.set_zoom(start.zoom).await?;
+    }
+    if let Some(url) = &start.url {
+        tab.navigate(url.clone()).await?;
+        wait_for_load(tab, events, options.load_timeout).await;
+    }
+    if start.scroll_x != 0 || start.scroll_y != 0 {
+        tab.send(TabCommand::MouseScroll {
+            delta_x: start.scroll_x as f32,
+            delta_y: start.scroll_y as f32,
+        })
+        .await?;
+    }
+
+    let mut last_sent: Option<(Instant, u64)> = None;
+    for (index, step) in recording.steps.iter().enumerate() {
+        if let (ReplayTiming::Recorded, Some((sent, at_ms))) = (options.timing, last_sent) {
+            let gap = Duration::from_millis(step.at_ms.saturating_sub(at_ms));
+            tokio::time::sleep_until((sent + gap).into()).await;
+        }
+
+        // Drop frames and loads from before this step so they are not mistaken for its own.
+        *events = events.resubscribe();
+        tab.send(step.command.to_command()).await?;
+        last_sent = Some((Instant::now(), step.at_ms));
+
+        if step.command.loads_document() {
+            wait_for_load(tab, events, options.load_timeout).await;
+        }
+        let snapshot = if options.snapshots {
+            wait_for_frame(tab, events, options.snapshot_timeout).await
+        } else {
+            None
+        };
+        on_step(ReplayStep { index, step, snapshot });
+    }
+    Ok(())
+}
+
+/// Waits until the tab finishes (or fails) loading its document, or `timeout` passes.
+async fn wait_for_load(tab: &TabHandle, events: &mut broadcast::Receiver<EngineEvent>, timeout: Duration) {
+    let done = next_event(events, timeout, |event| match event {
+        EngineEvent::Navigation {
+            tab_id,
+            event:
+                NavigationEvent::Finished { .. }
+                | NavigationEvent::Failed { .. }
+                | NavigationEvent::FailedUrl { .. }
+                | NavigationEvent::Cancelled { .. },
+        } if *tab_id == tab.tab_id => Some(()),
+        _ => None,
+    })
+    .await;
+    if done.is_none() {
+        log::warn!("Replay: tab {} did not finish loading within {timeout:?}", tab.tab_id);
+    }
+}
+
+async fn wait_for_frame(
+    tab: &TabHandle,
+    events: &mut broadcast::Receiver<EngineEvent>,
+    timeout: Duration,
+) -> Option<ExternalHandle> {
+    next_event(events, timeout, |event| match event {
+        EngineEvent::Redraw { tab_id, handle } if *tab_id == tab.tab_id => Some(handle.clone()),
+        _ => None,
+    })
+    .await
+}
+
+/// Returns the first event `pick` accepts, or `None` when `timeout` passes or the channel closes.
+async fn next_event<T>(
+    events: &mut broadcast::Receiver<EngineEvent>,
+    timeout: Duration,
+    mut pick: impl FnMut(&EngineEvent) -> Option<T>,
+) -> Option<T> {
+    let wait = async {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Some(found) = pick(&event) {
+                        return Some(found);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    };
+    tokio::time::timeout(timeout, wait).await.ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tab::TabSink;
+    use gosub_shared::tab_id::TabId;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn start() -> RecordingStart {
+        RecordingStart {
+            url: None,
+            width: 800,
+            height: 600,
+            zoom: 1.0,
+            scroll_x: 0,
+            scroll_y: 0,
+        }
+    }
+
+    #[test]
+    fn recorder_keeps_only_input() {
+        let mut recorder = TabRecorder::new(start());
+        recorder.record(&TabCommand::MouseMove { x: 1.0, y: 2.0 });
+        recorder.record(&TabCommand::PrintToPdf);
+        recorder.record(&TabCommand::ResumeDrawing { fps: 30 });
+        recorder.record(&TabCommand::KeyDown {
+            key: "A".into(),
+            code: "KeyA".into(),
+            modifiers: Modifiers::SHIFT,
+        });
+
+        let recording = recorder.finish();
+        assert_eq!(recording.steps.len(), 2);
+        assert_eq!(
+            recording.steps[1].command.to_command(),
+            TabCommand::KeyDown {
+                key: "A".into(),
+                code: "KeyA".into(),
+                modifiers: Modifiers::SHIFT,
+            }
+        );
+    }
+
+    #[test]
+    fn recordings_round_trip_through_files() {
+        let recording = Recording::new(
+            RecordingStart {
+                url: Some("https://example.com/".into()),
+                scroll_y: 120,
+                ..start()
+            },
+            vec![
+                RecordedStep {
+                    at_ms: 0,
+                    command: RecordedCommand::MouseDown {
+                        x: 10.0,
+                        y: 20.0,
+                        button: MouseButton::Left,
+                    },
+                },
+                RecordedStep {
+                    at_ms: 16,
+                    command: RecordedCommand::MouseScroll {
+                        delta_x: 0.0,
+                        delta_y: 40.0,
+                    },
+                },
+            ],
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.json");
+        recording.save(&path).unwrap();
+        assert_eq!(Recording::load(&path).unwrap(), recording);
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(json.contains("\"type\": \"mouse_down\""));
+        fs::write(&path, json.replace("\"version\": 1", "\"version\": 99")).unwrap();
+        assert!(Recording::load(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_sends_start_state_then_steps() {
+        let (cmd_tx, mut cmd_rx) = mpsc::channel(16);
+        let tab = TabHandle {
+            tab_id: TabId::new(),
+            cmd_tx,
+            sink: Arc::new(TabSink::new()),
+        };
+        let (_event_tx, mut events) = broadcast::channel(16);
+        let recording = Recording::new(
+            RecordingStart { zoom: 2.0, ..start() },
+            vec![
+                RecordedStep {
+                    at_ms: 5,
+                    command: RecordedCommand::MouseMove { x: 3.0, y: 4.0 },
+                },
+                RecordedStep {
+                    at_ms: 10,
+                    command: RecordedCommand::CharInput { ch: 'x' },
+                },
+            ],
+        );
+
+        let mut seen = Vec::new();
+        let options = ReplayOptions {
+            timing: ReplayTiming::AsFastAsPossible,
+            ..Default::default()
+        };
+        replay(&tab, &recording, &mut events, &options, |step| seen.push(step.index))
+            .await
+            .unwrap();
+        assert_eq!(seen, vec![0, 1]);
+
+        let mut sent = Vec::new();
+        while let Ok(cmd) = cmd_rx.try_recv() {
+            sent.push(cmd);
+        }
+        assert_eq!(
+            sent,
+            vec![
+                TabCommand::SetViewport {
+                    x: 0,
+                    y: 0,
+                    width: 800,
+                    height: 600
+                },
+                TabCommand::SetZoom { factor: 2.0 },
+                TabCommand::MouseMove { x: 3.0, y: 4.0 },
+                TabCommand::CharInput { ch: 'x' },
+            ]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::cookies::{CookieJarHandle, DefaultCookieJar};
 use crate::storage::{InMemoryLocalStore, InMemorySessionStore, PartitionKey, PartitionPolicy, StorageService};
-use crate::tab::options::{TabCookieJar, TabOverrides, TabStorageScope};
+use crate::tab::options::{DeviceEmulation, TabCookieJar, TabOverrides, TabStorageScope};
 use crate::zone::{ZoneConfig, ZoneId, ZoneServices};
 use std::sync::Arc;
 
@@ -13,6 +13,10 @@ pub struct EffectiveTabServices {
     pub cookie_jar: CookieJarHandle,
     /// `Accept-Language` header value for this tab's requests, if configured.
     pub accept_language: Option<String>,
+    /// `User-Agent` header value for this tab's requests, if configured.
+    pub user_agent: Option<String>,
+    /// Device-emulation profile, if the tab emulates a device.
+    pub device_emulation: Option<DeviceEmulation>,
 }
 
 /// Resolve the effective services for a tab based on the zone services/config and tab overrides.
@@ -61,11 +65,19 @@ pub fn resolve_tab_services(
         .clone()
         .or_else(|| zone_config.accept_languages.clone());
 
+    let device_emulation = ov.device_emulation.clone();
+    let user_agent = device_emulation
+        .as_ref()
+        .and_then(|d| d.user_agent.clone())
+        .or_else(|| zone_config.user_agent.clone());
+
     EffectiveTabServices {
         partition_key,
         partition_policy,
         storage,
         cookie_jar,
         accept_language,
+        user_agent,
+        device_emulation,
     }
 }
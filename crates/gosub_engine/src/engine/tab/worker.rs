@@ -1,4 +1,5 @@
 use crate::cookies::SameSiteContext;
+use crate::engine::accessibility::AccessibilityTracker;
 use crate::engine::errors::NavigationError;
 use crate::engine::events::{EngineEvent, NavigationEvent};
 use crate::engine::resource_pipeline::ResourcePipelines;
@@ -11,6 +12,7 @@ use crate::net::types::{FetchRequest, FetchResult, Initiator, NetError, Priority
 use crate::net::{route_response_for, submit_to_io, RequestDestination, RoutedOutcome};
 use crate::storage::types::compute_partition_key;
 use crate::storage::StorageHandles;
+use crate::tab::recording::{RecordingStart, TabRecorder};
 use crate::tab::scroll::{default_text_scroll, ScrollState};
 use crate::tab::services::EffectiveTabServices;
 use crate::tab::state::{TabRuntime, TabState};
@@ -21,6 +23,7 @@ use anyhow::{anyhow, Context};
 use gosub_render_pipeline::rasterizer::RasterStrategy;
 use gosub_render_pipeline::render::backend::{CompositorSink, ErasedSurface, PresentMode, RenderBackend, SurfaceSize};
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
 use http::{HeaderMap, Method};
 use std::sync::Arc;
 use tokio::select;
@@ -37,6 +40,11 @@ fn about_blank() -> Url {
     Url::parse("about:blank").unwrap()
 }
 
+/// Smallest page zoom accepted by `TabCommand::SetZoom`.
+const MIN_ZOOM: f32 = 0.25;
+/// Largest page zoom accepted by `TabCommand::SetZoom`.
+const MAX_ZOOM: f32 = 5.0;
+
 #[derive(Debug)]
 pub enum NavigationResult<C: RenderConfiguration> {
     Ok {
@@ -123,6 +131,12 @@ pub struct TabWorker<C: RenderConfiguration> {
     load: Option<NavJoin<C>>,
     /// Current active navigation (if any)
     active_nav: Option<ActiveNav>,
+    /// Recording in progress (if any), see `TabCommand::StartRecording`
+    recorder: Option<TabRecorder>,
+    /// What the host has been sent of the accessibility tree, while enabled
+    accessibility: Option<AccessibilityTracker>,
+    /// Scene epoch, scroll offset and focus the accessibility tree was last built for
+    accessibility_key: Option<(u64, (i64, i64), Option<NodeId>)>,
 }
 
 /// Whether a CSS `unicode-range` descriptor (e.g. `"U+0000-00FF, U+0131"`) includes the
@@ -290,7 +304,8 @@ impl<C: RenderConfiguration> TabWorker<C> {
         cmd_rx: mpsc::Receiver<TabCommand>,
     ) -> Self {
         let config_store = zone_context.config_store.clone();
-        let context = BrowsingContext::new(config_store.clone());
+        let mut context = BrowsingContext::new(config_store.clone());
+        context.set_device_emulation(services.device_emulation.clone());
         let runtime = TabRuntime::with_fps(config_store.get_uint("renderer.tab.default_fps") as u32);
 
         Self {
@@ -319,6 +334,9 @@ impl<C: RenderConfiguration> TabWorker<C> {
             runtime,
             load: None,
             active_nav: None,
+            recorder: None,
+            accessibility: None,
+            accessibility_key: None,
         }
     }
 
@@ -364,6 +382,7 @@ impl<C: RenderConfiguration> TabWorker<C> {
                         self.state = TabState::Failed(format!("Tab {:?} tick error: {}", self.tab_id, e));
                         self.runtime.dirty = true;
                     }
+                    self.publish_accessibility(false);
                 }
 
                 // In-flight load completion - uses a persistent receiver so it is not
@@ -397,6 +416,7 @@ impl<C: RenderConfiguration> TabWorker<C> {
                             self.runtime.dirty = true;
                         }
                     }
+                    self.publish_accessibility(false);
                 }
             }
         }
@@ -521,6 +541,10 @@ impl<C: RenderConfiguration> TabWorker<C> {
     }
 
     fn handle_tab_command(&mut self, cmd: TabCommand) -> ControlFlow {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&cmd);
+        }
+
         match cmd {
             TabCommand::CloseTab => ControlFlow::Break,
             TabCommand::SetTitle { title } => {
@@ -551,6 +575,112 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
+            TabCommand::SetZoom { factor } => {
+                // A non-finite factor leaves the zoom untouched; the event still reports it.
+                let factor = if factor.is_finite() {
+                    factor.clamp(MIN_ZOOM, MAX_ZOOM)
+                } else {
+                    self.context.zoom() as f32
+                };
+                if self.context.set_zoom(factor as f64) {
+                    // The context rescaled its scroll offset to keep the same content in view;
+                    // adopt it so the worker's scroll state doesn't snap it back.
+                    let (x, y) = self.context.scroll_xy();
+                    self.scroll_x = x.round() as i32;
+                    self.scroll_y = y.round() as i32;
+                    self.scroll.reset(x, y);
+                    self.scroll_anim_last = None;
+                    self.runtime.dirty = true;
+                }
+                self.send_event(EngineEvent::ZoomChanged {
+                    tab_id: self.tab_id,
+                    factor,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::GetElementRects { selector } => {
+                let result = self.context.element_rects(&selector).map_err(|e| e.to_string());
+                self.send_event(EngineEvent::ElementRects {
+                    tab_id: self.tab_id,
+                    selector,
+                    result,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::HitTest { x, y } => {
+                let elements = self.context.hit_test(x as f64, y as f64);
+                self.send_event(EngineEvent::HitTestResult {
+                    tab_id: self.tab_id,
+                    x,
+                    y,
+                    elements,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::InspectNode { node_id } => {
+                let result = self.context.inspect_node(node_id).map_err(|e| e.to_string());
+                self.send_event(EngineEvent::NodeInspected {
+                    tab_id: self.tab_id,
+                    node_id,
+                    result,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::PrintToPdf => {
+                let result = match self.context.print_document() {
+                    Some(document) => self
+                        .zone_context
+                        .render_backend
+                        .print_to_pdf(&document as &dyn std::any::Any),
+                    None => Err(anyhow!("no document loaded")),
+                };
+                let event = match result {
+                    Ok(pdf) => EngineEvent::PdfPrinted {
+                        tab_id: self.tab_id,
+                        pdf,
+                    },
+                    Err(e) => EngineEvent::PrintFailed {
+                        tab_id: self.tab_id,
+                        message: e.to_string(),
+                    },
+                };
+                self.send_event(event);
+                ControlFlow::Continue
+            }
+            TabCommand::StartRecording => {
+                self.recorder = Some(TabRecorder::new(RecordingStart {
+                    url: self.current_url.as_ref().map(|url| url.to_string()),
+                    width: self.desired_viewport.width,
+                    height: self.desired_viewport.height,
+                    zoom: self.context.zoom() as f32,
+                    scroll_x: self.scroll_x,
+                    scroll_y: self.scroll_y,
+                }));
+                ControlFlow::Continue
+            }
+            TabCommand::StopRecording => {
+                self.send_event(EngineEvent::RecordingFinished {
+                    tab_id: self.tab_id,
+                    recording: self.recorder.take().map(TabRecorder::finish),
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::SetAccessibility { enabled } => {
+                self.accessibility_key = None;
+                if enabled {
+                    self.accessibility = Some(AccessibilityTracker::default());
+                    self.publish_accessibility(true);
+                } else {
+                    self.accessibility = None;
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::SetDebugOverlays { overlays } => {
+                if self.context.set_debug_overlays(overlays) {
+                    self.runtime.dirty = true;
+                }
+                ControlFlow::Continue
+            }
             TabCommand::MouseScroll { delta_x, delta_y } => {
                 // When page height is known, clamp to the real maximum so worker and context
                 // stay in sync. When the page hasn't rendered yet, allow free scrolling (the
@@ -600,6 +730,10 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 }
                 ControlFlow::Continue
             }
+            TabCommand::MouseMove { .. } if self.is_touch() => {
+                // Touch devices have no hovering pointer: no :hover, no link preview.
+                ControlFlow::Continue
+            }
             TabCommand::MouseMove { x, y } => {
                 // Process the hit-test immediately so hover doesn't wait for the next tick.
                 let (visual_dirty, url_changed, link_url) = self.context.update_hover(x as f64, y as f64);
@@ -615,8 +749,13 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 }
                 ControlFlow::Continue
             }
-            TabCommand::MouseDown { button, .. } => {
+            TabCommand::MouseDown { x, y, button } => {
+                if self.is_touch() {
+                    // A tap has no preceding pointer move; hit-test at the tap position.
+                    self.context.update_hover(x as f64, y as f64);
+                }
                 if matches!(button, crate::events::MouseButton::Left) {
+                    self.context.focus_at_hover();
                     if let Some(href) = self.context.hover_link_url.clone() {
                         let resolved = self
                             .current_url
@@ -689,6 +828,42 @@ impl<C: RenderConfiguration> TabWorker<C> {
         }
     }
 
+    /// Send the accessibility tree changes since the last update, when enabled and the scene,
+    /// scroll offset or focus moved on. `full` sends the whole tree instead.
+    fn publish_accessibility(&mut self, full: bool) {
+        if self.accessibility.is_none() {
+            return;
+        }
+        let (scroll_x, scroll_y) = self.context.scroll_xy();
+        let key = (
+            self.context.scene_epoch(),
+            (scroll_x.round() as i64, scroll_y.round() as i64),
+            self.context.focused(),
+        );
+        if !full && self.accessibility_key == Some(key) {
+            return;
+        }
+        self.accessibility_key = Some(key);
+
+        let Some(tree) = self.context.accessibility_tree() else {
+            return;
+        };
+        let Some(tracker) = self.accessibility.as_mut() else {
+            return;
+        };
+        let update = if full {
+            Some(tracker.full(tree))
+        } else {
+            tracker.diff(tree)
+        };
+        if let Some(update) = update {
+            self.send_event(EngineEvent::AccessibilityUpdate {
+                tab_id: self.tab_id,
+                update,
+            });
+        }
+    }
+
     /// Navigate to a new URL, cancelling any in-flight navigation.
     fn navigate_to(&mut self, url: impl Into<String>, _ignore_cache: bool) {
         self.scroll_x = 0;
@@ -761,6 +936,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 fetch_headers.insert(http::header::ACCEPT_LANGUAGE, val);
             }
         }
+        if let Some(ua) = &self.services.user_agent {
+            if let Ok(val) = ua.parse() {
+                fetch_headers.insert(http::header::USER_AGENT, val);
+            }
+        }
 
         let req_id = RequestId::new();
         REF_REGISTRY.register_request(req_id, ResourceKind::Document, Initiator::Navigation);
@@ -786,6 +966,7 @@ impl<C: RenderConfiguration> TabWorker<C> {
         let event_tx = self.zone_context.event_tx.clone();
         let cookie_jar = self.services.cookie_jar.clone();
         let accept_language = self.services.accept_language.clone();
+        let user_agent = self.services.user_agent.clone();
         let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
 
         let span = tracing::info_span!(
@@ -851,8 +1032,13 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 allow_download_without_user_activation: false,
             };
 
-            let mut hooks =
-                ResourcePipelines::<C>::new(zone_id, io_tx.clone(), accept_language.clone(), max_document_bytes);
+            let mut hooks = ResourcePipelines::<C>::new(
+                zone_id,
+                io_tx.clone(),
+                accept_language.clone(),
+                user_agent.clone(),
+                max_document_bytes,
+            );
 
             let outcome = route_response_for(
                 RequestDestination::Document,
@@ -958,6 +1144,10 @@ impl<C: RenderConfiguration> TabWorker<C> {
         if self.context.poll_media_completed() {
             self.runtime.dirty = true;
         }
+        // A repaint flash lasts one frame; keep ticking until the overlay is redrawn without it.
+        if self.context.debug_overlay_dirty() {
+            self.runtime.dirty = true;
+        }
 
         // Skip rendering when nothing has changed to avoid burning CPU at the tick rate.
         if !self.runtime.dirty {
@@ -1173,6 +1363,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
 
     /// Set a new viewport and schedule a re-render by transitioning to [`TabState::PendingRendering`].
     pub fn set_viewport(&mut self, vp: Viewport) {
+        // An emulated device pins the visual viewport to its screen, whatever the host's size.
+        let vp = match &self.services.device_emulation {
+            Some(device) => Viewport::new(0, 0, device.width, device.height),
+            None => vp,
+        };
         // Already at the viewport we want, then we can skip
         if vp == self.desired_viewport {
             return;
@@ -1182,6 +1377,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
         self.runtime.dirty = true;
     }
 
+    /// True when the tab emulates a touch device.
+    fn is_touch(&self) -> bool {
+        self.services.device_emulation.as_ref().is_some_and(|d| d.touch)
+    }
+
     /// Bind local+session storage handles into the underlying browsing context.
     /// Call this after creating the tab or when the zone’s storage changes.
     pub fn bind_storage(&mut self, storage: StorageHandles) {
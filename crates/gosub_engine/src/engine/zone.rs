@@ -13,10 +13,13 @@
 //! - [`ZoneServices`] - collection of shared services bound to a zone
 //! - [`ZoneContext`] - context for zone operations
 //! - [`ZoneSink`] - a sink for zone events
+//! - profile archives - [`Zone::export_profile`] / [`Zone::import_profile`] bundle a
+//!   zone's config, cookies and localStorage into one file
 //!
 //! Internally, the [`Zone`] type manages the full state and lifecycle.
 
 mod config;
+mod profile;
 #[allow(clippy::module_inception)]
 mod zone;
 
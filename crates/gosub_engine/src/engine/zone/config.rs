@@ -59,9 +59,11 @@
 //! or `max_tabs == 0`).
 
 use crate::storage::PartitionPolicy;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ZoneConfig {
     /// Maximum number of tabs allowed in this zone.
     pub max_tabs: usize,
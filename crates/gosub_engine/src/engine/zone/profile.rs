@@ -0,0 +1,247 @@
+//! Zone profile archives.
+//!
+//! A profile bundles the state that makes up a zone's identity - its [`ZoneConfig`],
+//! every cookie in its jar and every localStorage area - into one JSON document.
+//! This lets a profile move between machines (or CI runners) without copying the
+//! individual cookie and storage backends and hoping their schemas match.
+//!
+//! Profiles are written with [`Zone::export_profile`](crate::zone::Zone::export_profile)
+//! and read back with [`Zone::import_profile`](crate::zone::Zone::import_profile).
+//! Data is keyed by partition and origin, never by [`ZoneId`], so a profile can be
+//! imported into any zone.
+
+use crate::cookies::{Cookie, DefaultCookieJar};
+use crate::storage::{PartitionKey, StorageService};
+use crate::zone::{ZoneConfig, ZoneId};
+use crate::EngineError;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Version of the on-disk profile layout. Bumped on incompatible changes.
+const PROFILE_FORMAT_VERSION: u32 = 1;
+
+/// On-disk representation of a zone profile.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ZoneProfile {
+    /// Layout version, see [`PROFILE_FORMAT_VERSION`].
+    version: u32,
+    /// Zone configuration at the time of export.
+    pub(crate) config: ZoneConfig,
+    /// Cookies bucketed by origin, as held by [`DefaultCookieJar::entries`].
+    cookies: BTreeMap<String, Vec<Cookie>>,
+    /// Every non-empty localStorage area of the zone.
+    local_storage: Vec<LocalStorageArea>,
+}
+
+/// A single localStorage area, addressed by partition and origin.
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalStorageArea {
+    /// Partition key, encoded with [`PartitionKey::to_storage_key`].
+    partition: String,
+    /// ASCII serialization of the origin.
+    origin: String,
+    /// Key/value pairs stored in the area.
+    items: BTreeMap<String, String>,
+}
+
+impl ZoneProfile {
+    /// Captures the profile of `zone_id` from its config, cookie snapshot and storage service.
+    pub(crate) fn capture(
+        zone_id: ZoneId,
+        config: &ZoneConfig,
+        cookies: &DefaultCookieJar,
+        storage: &StorageService,
+    ) -> Result<Self, EngineError> {
+        let mut local_storage = Vec::new();
+        for (partition, origin) in storage.local_areas(zone_id).map_err(EngineError::Profile)? {
+            let area = storage
+                .local_for(zone_id, &partition, &origin)
+                .map_err(EngineError::Profile)?;
+            let items = area
+                .keys()
+                .into_iter()
+                .filter_map(|key| area.get_item(&key).map(|value| (key, value)))
+                .collect();
+
+            local_storage.push(LocalStorageArea {
+                partition: partition.to_storage_key(),
+                origin: origin.ascii_serialization(),
+                items,
+            });
+        }
+        local_storage.sort_by(|a, b| (&a.partition, &a.origin).cmp(&(&b.partition, &b.origin)));
+
+        Ok(Self {
+            version: PROFILE_FORMAT_VERSION,
+            config: config.clone(),
+            cookies: cookies.entries.clone().into_iter().collect(),
+            local_storage,
+        })
+    }
+
+    /// Returns the profile's cookies as a jar snapshot.
+    pub(crate) fn cookie_jar(&self) -> DefaultCookieJar {
+        let mut jar = DefaultCookieJar::new();
+        jar.entries = self.cookies.clone().into_iter().collect();
+        jar
+    }
+
+    /// Returns `true` when the profile carries at least one cookie.
+    pub(crate) fn has_cookies(&self) -> bool {
+        self.cookies.values().any(|cookies| !cookies.is_empty())
+    }
+
+    /// Replaces the localStorage of `zone_id` with the areas in this profile.
+    ///
+    /// Existing areas of the zone are cleared first, so the result mirrors the exported zone.
+    pub(crate) fn restore_local_storage(&self, zone_id: ZoneId, storage: &StorageService) -> Result<(), EngineError> {
+        for (partition, origin) in storage.local_areas(zone_id).map_err(EngineError::Profile)? {
+            storage
+                .local_for(zone_id, &partition, &origin)
+                .and_then(|area| area.clear())
+                .map_err(EngineError::Profile)?;
+        }
+
+        for entry in &self.local_storage {
+            let Ok(url) = url::Url::parse(&entry.origin) else {
+                log::warn!("Skipping localStorage area with invalid origin {:?}", entry.origin);
+                continue;
+            };
+            let partition = PartitionKey::from_storage_key(&entry.partition);
+            let area = storage
+                .local_for(zone_id, &partition, &url.origin())
+                .map_err(EngineError::Profile)?;
+            for (key, value) in &entry.items {
+                area.set_item(key, value).map_err(EngineError::Profile)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the profile to `path` (via a temp file that is renamed over the target).
+    pub(crate) fn write(&self, path: &Path) -> Result<(), EngineError> {
+        let contents = serde_json::to_vec_pretty(self).map_err(|e| EngineError::Profile(e.into()))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).map_err(|e| EngineError::Profile(e.into()))?;
+        fs::rename(&tmp, path).map_err(|e| EngineError::Profile(e.into()))
+    }
+
+    /// Reads and validates a profile from `path`.
+    pub(crate) fn read(path: &Path) -> Result<Self, EngineError> {
+        let contents = fs::read(path).map_err(|e| EngineError::Profile(e.into()))?;
+        let profile: Self = serde_json::from_slice(&contents).map_err(|e| EngineError::Profile(e.into()))?;
+        if profile.version != PROFILE_FORMAT_VERSION {
+            return Err(EngineError::Profile(anyhow!(
+                "unsupported profile version {} (expected {PROFILE_FORMAT_VERSION})",
+                profile.version
+            )));
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieJar;
+    use crate::storage::{InMemoryLocalStore, InMemorySessionStore};
+    use std::sync::Arc;
+
+    fn storage() -> StorageService {
+        StorageService::new(
+            Arc::new(InMemoryLocalStore::new()),
+            Arc::new(InMemorySessionStore::new()),
+        )
+    }
+
+    fn origin(s: &str) -> url::Origin {
+        url::Url::parse(s).unwrap().origin()
+    }
+
+    #[test]
+    fn profile_round_trips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+
+        // Source zone: one cookie and two localStorage areas.
+        let src_zone = ZoneId::new();
+        let src_storage = storage();
+        let part = PartitionKey::TopLevel(origin("https://example.com"));
+        src_storage
+            .local_for(src_zone, &part, &origin("https://example.com"))
+            .unwrap()
+            .set_item("theme", "dark")
+            .unwrap();
+        src_storage
+            .local_for(src_zone, &PartitionKey::None, &origin("https://cdn.example.com"))
+            .unwrap()
+            .set_item("cache", "1")
+            .unwrap();
+
+        let mut jar = DefaultCookieJar::new();
+        let mut headers = http::HeaderMap::new();
+        headers.append(http::header::SET_COOKIE, "sid=abc123; Path=/".parse().unwrap());
+        jar.store_response_cookies(&url::Url::parse("https://example.com/").unwrap(), &headers, None);
+
+        let config = ZoneConfig::builder()
+            .max_tabs(3)
+            .user_agent("Gosub/test")
+            .build()
+            .unwrap();
+        ZoneProfile::capture(src_zone, &config, &jar, &src_storage)
+            .unwrap()
+            .write(&path)
+            .unwrap();
+
+        // Destination zone: different id, with stale data that the import replaces.
+        let dst_zone = ZoneId::new();
+        let dst_storage = storage();
+        dst_storage
+            .local_for(dst_zone, &part, &origin("https://stale.example"))
+            .unwrap()
+            .set_item("old", "x")
+            .unwrap();
+
+        let profile = ZoneProfile::read(&path).unwrap();
+        profile.restore_local_storage(dst_zone, &dst_storage).unwrap();
+
+        assert_eq!(profile.config.max_tabs, 3);
+        assert_eq!(profile.config.user_agent.as_deref(), Some("Gosub/test"));
+        assert!(profile.has_cookies());
+        assert_eq!(
+            profile
+                .cookie_jar()
+                .get_all_cookies()
+                .into_iter()
+                .map(|(_, c)| c)
+                .collect::<Vec<_>>(),
+            vec!["sid=abc123".to_string()]
+        );
+
+        let area = dst_storage
+            .local_for(dst_zone, &part, &origin("https://example.com"))
+            .unwrap();
+        assert_eq!(area.get_item("theme").as_deref(), Some("dark"));
+        let area = dst_storage
+            .local_for(dst_zone, &PartitionKey::None, &origin("https://cdn.example.com"))
+            .unwrap();
+        assert_eq!(area.get_item("cache").as_deref(), Some("1"));
+        let stale = dst_storage
+            .local_for(dst_zone, &part, &origin("https://stale.example"))
+            .unwrap();
+        assert!(stale.is_empty(), "import should replace existing localStorage");
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        fs::write(&path, r#"{"version":99,"config":{},"cookies":{},"local_storage":[]}"#).unwrap();
+
+        assert!(matches!(ZoneProfile::read(&path), Err(EngineError::Profile(_))));
+    }
+}
@@ -5,6 +5,7 @@ use crate::engine::events::EngineEvent;
 use crate::engine::storage::{StorageService, Subscription};
 use crate::engine::tab::TabId;
 use crate::engine::types::{EventChannel, IoChannel, TabChannel};
+use crate::engine::zone::profile::ZoneProfile;
 use crate::events::TabCommand;
 use crate::html::RenderConfiguration;
 use crate::net::req_ref_tracker::RequestReferenceMap;
@@ -21,6 +22,7 @@ use rand::{RngExt, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Debug, Display};
+use std::path::Path;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -426,6 +428,53 @@ impl<C: RenderConfiguration> Zone<C> {
     pub fn list_tabs(&self) -> Vec<TabId> {
         self.tabs.keys().cloned().collect()
     }
+
+    /// Writes this zone's config, cookies and localStorage areas to a single profile
+    /// archive at `path`.
+    pub fn export_profile(&self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        let cookies = self.cookie_jar().and_then(|jar| jar.snapshot()).unwrap_or_default();
+        ZoneProfile::capture(self.id, &self.config, &cookies, &self.context.services.storage)?.write(path.as_ref())
+    }
+
+    /// Loads a profile archive written by [`Zone::export_profile`] into this zone.
+    ///
+    /// The zone's cookies and localStorage are replaced by those in the profile, and its
+    /// config is replaced by the profile's config (which applies to tabs created afterwards).
+    pub fn import_profile(&mut self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        let profile = ZoneProfile::read(path.as_ref())?;
+        let config = ZoneConfig::builder()
+            .with(|c| *c = profile.config.clone())
+            .build()
+            .map_err(|e| EngineError::InvalidConfiguration(e.to_string()))?;
+
+        match self.cookie_jar() {
+            Some(jar) => {
+                if !jar.restore(&profile.cookie_jar()) {
+                    log::warn!(
+                        "Zone {}: cookie jar does not support restoring; cookies not imported",
+                        self.id
+                    );
+                }
+            }
+            None if profile.has_cookies() => {
+                log::warn!("Zone {} has no cookie jar or store; cookies not imported", self.id);
+            }
+            None => {}
+        }
+
+        profile.restore_local_storage(self.id, &self.context.services.storage)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Returns the zone-wide cookie jar, resolved the same way tabs inherit it.
+    fn cookie_jar(&self) -> Option<CookieJarHandle> {
+        let services = &self.context.services;
+        services
+            .cookie_jar
+            .clone()
+            .or_else(|| services.cookie_store.as_ref().and_then(|store| store.jar_for(self.id)))
+    }
 }
 
 impl<C: RenderConfiguration> Drop for Zone<C> {
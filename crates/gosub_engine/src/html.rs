@@ -2,10 +2,14 @@
 //!
 //! This module provides functionality to parse HTML documents, extract resource hints,
 //! and handle various HTML configurations.
+mod imports;
 mod parser;
+mod preload;
 
+pub use imports::load_stylesheet_imports;
 pub use parser::parse_main_document_stream;
-pub use parser::{DocumentError, HtmlParseConfig, ResourceHint};
+pub use parser::{DocumentError, DocumentFormat, HtmlParseConfig, ResourceHint};
+pub use preload::PreloadScanner;
 
 use gosub_css3::system::Css3System;
 use gosub_fontmanager::ParleyFontSystem;
@@ -132,3 +136,31 @@ fn find_title<C: RenderConfiguration>(doc: &EngineDocument<C>, node_id: NodeId)
     }
     None
 }
+
+/// Return the `content` attribute of the first `<meta name="viewport">` element in the document.
+pub fn document_viewport_meta<C: RenderConfiguration>(doc: &EngineDocument<C>) -> Option<String> {
+    find_viewport_meta(doc, doc.root())
+}
+
+fn find_viewport_meta<C: RenderConfiguration>(doc: &EngineDocument<C>, node_id: NodeId) -> Option<String> {
+    for &child in doc.children(node_id) {
+        if doc.node_type(child) != NodeType::ElementNode {
+            continue;
+        }
+
+        let is_viewport_meta = doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case("meta"))
+            && doc
+                .attribute(child, "name")
+                .is_some_and(|name| name.trim().eq_ignore_ascii_case("viewport"));
+        if is_viewport_meta {
+            if let Some(content) = doc.attribute(child, "content") {
+                return Some(content.to_string());
+            }
+        }
+
+        if let Some(found) = find_viewport_meta(doc, child) {
+            return Some(found);
+        }
+    }
+    None
+}
@@ -0,0 +1,465 @@
+//! `@import` loading.
+//!
+//! Once the document is parsed, the `@import` rules of its stylesheets are fetched and the
+//! imported sheets are spliced into the document's stylesheet list right before the sheet that
+//! imports them, depth first, which is their place in the cascade. Every import resolves against
+//! the URL of the sheet it appears in and is parsed with that sheet's origin; its media query and
+//! cascade layer are applied to everything it pulls in. The imports of a sheet are fetched all at
+//! once. Importing a URL that is already on the import chain is a cycle, importing one that was
+//! loaded before under the same conditions a duplicate; both are skipped.
+//!
+//! Only the document's own stylesheets are covered: `@import`s in the `<style>` elements of
+//! shadow trees are not loaded.
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use futures::future::join_all;
+use gosub_interface::css3::{CssOrigin, CssStylesheet, CssSystem, ImportConditions};
+use gosub_interface::document::Document as _;
+use gosub_shared::config::ParserConfig;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use url::Url;
+
+/// An `@import` of a sheet: its unresolved URL and its [conditions](CssStylesheet::import_conditions)
+type Import = (String, ImportConditions);
+
+/// A stylesheet whose imports are being loaded.
+struct Frame<S> {
+    /// The importing sheet, `None` for a sheet that stays in the document
+    sheet: Option<S>,
+    /// URL the imports resolve against
+    url: Url,
+    origin: CssOrigin,
+    /// Conditions of the imports that led to this sheet, outermost first, leaving out the
+    /// imports without any
+    conditions: Vec<ImportConditions>,
+    imports: Vec<Import>,
+    /// Next entry of `imports` to load
+    next: usize,
+    /// Which import of the parent frame this sheet was loaded for
+    parent_import: usize,
+    /// Sheets loaded so far in cascade order, each with the import of this frame it came from
+    loaded: Vec<(usize, S)>,
+}
+
+/// What has been loaded so far, shared by all stylesheets of the document
+struct Loaded {
+    /// Imports done, by URL and the conditions along their import chain
+    seen: HashSet<(Url, Vec<ImportConditions>)>,
+    /// Fetched bodies by URL, `None` for the ones that could not be loaded
+    bodies: HashMap<Url, Option<String>>,
+}
+
+/// Fetches the `@import`ed stylesheets of `doc` and inserts them into its stylesheet list.
+///
+/// `fetch` returns the body of the stylesheet at the given URL, or `None` when it could not be
+/// loaded; such imports are skipped.
+pub async fn load_stylesheet_imports<C, F, Fut>(doc: &mut EngineDocument<C>, mut fetch: F)
+where
+    C: RenderConfiguration,
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let base_url = doc.url();
+    let mut state = Loaded {
+        seen: HashSet::new(),
+        bodies: HashMap::new(),
+    };
+
+    let mut index = 0;
+    while index < doc.stylesheets().len() {
+        let sheet = &doc.stylesheets()[index];
+        let imports = imports_of(sheet);
+        let origin = sheet.origin();
+        let url = Url::parse(sheet.url()).ok().or_else(|| base_url.clone());
+        let Some(url) = url.filter(|_| !imports.is_empty()) else {
+            index += 1;
+            continue;
+        };
+
+        let loaded = load_imports::<C, _, _>(url, origin, imports, &mut state, &mut fetch).await;
+
+        // Restrict what each import pulled in to that import's conditions, then splice it in.
+        let sheet = &doc.stylesheets()[index];
+        let mut sheets = Vec::with_capacity(loaded.len());
+        for (import, mut imported) in loaded {
+            sheet.apply_import(import, &mut imported);
+            sheets.push(imported);
+        }
+        for imported in sheets {
+            doc.insert_stylesheet(index, imported);
+            index += 1;
+        }
+        index += 1;
+    }
+}
+
+fn imports_of<S: CssStylesheet>(sheet: &S) -> Vec<Import> {
+    sheet
+        .imports()
+        .into_iter()
+        .enumerate()
+        .map(|(index, url)| (url, sheet.import_conditions(index)))
+        .collect()
+}
+
+/// `conditions` with those of one more import along the chain
+fn with_conditions(conditions: &[ImportConditions], import_conditions: &ImportConditions) -> Vec<ImportConditions> {
+    let mut conditions = conditions.to_vec();
+    if !import_conditions.is_empty() {
+        conditions.push(import_conditions.clone());
+    }
+    conditions
+}
+
+/// Resolves an import against the URL of the sheet it is in
+fn resolve(base: &Url, import: &str) -> Option<Url> {
+    let mut url = base.join(import).ok()?;
+    url.set_fragment(None);
+    Some(url)
+}
+
+/// Fetches the `imports` of the sheet at `url`, all at the same time. Imports that were fetched
+/// before, already done under the same conditions or that are on the import chain of the sheet
+/// (`chain` plus `url` itself) are left out.
+async fn prefetch<F, Fut>(
+    url: &Url,
+    conditions: &[ImportConditions],
+    imports: &[Import],
+    chain: &[&Url],
+    state: &mut Loaded,
+    fetch: &mut F,
+) where
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let mut urls = Vec::new();
+    for (import, import_conditions) in imports {
+        let Some(import) = resolve(url, import) else {
+            continue;
+        };
+        let key = with_conditions(conditions, import_conditions);
+        if import == *url
+            || chain.contains(&&import)
+            || state.bodies.contains_key(&import)
+            || state.seen.contains(&(import.clone(), key))
+            || urls.contains(&import)
+        {
+            continue;
+        }
+        urls.push(import);
+    }
+
+    let bodies = join_all(urls.iter().map(|url| fetch(url.clone()))).await;
+    state.bodies.extend(urls.into_iter().zip(bodies));
+}
+
+/// Loads `imports` of the sheet at `url` and, recursively, their own imports. Returns the loaded
+/// sheets in cascade order, each with the entry of `imports` it came from.
+async fn load_imports<C, F, Fut>(
+    url: Url,
+    origin: CssOrigin,
+    imports: Vec<Import>,
+    state: &mut Loaded,
+    fetch: &mut F,
+) -> Vec<(usize, <C::CssSystem as CssSystem>::Stylesheet)>
+where
+    C: RenderConfiguration,
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    prefetch(&url, &[], &imports, &[], state, fetch).await;
+    let mut stack = vec![Frame {
+        sheet: None,
+        url,
+        origin,
+        conditions: Vec::new(),
+        imports,
+        next: 0,
+        parent_import: 0,
+        loaded: Vec::new(),
+    }];
+
+    loop {
+        let Some(frame) = stack.last_mut() else {
+            return Vec::new();
+        };
+
+        if let Some((import, import_conditions)) = frame.imports.get(frame.next) {
+            let parent_import = frame.next;
+            frame.next += 1;
+            let origin = frame.origin;
+            let conditions = with_conditions(&frame.conditions, import_conditions);
+
+            let Some(url) = resolve(&frame.url, import) else {
+                log::warn!("Invalid @import url {import:?} in {}", frame.url);
+                continue;
+            };
+            if stack.iter().any(|f| f.url == url) {
+                log::warn!("Skipping @import of {url}: import cycle");
+                continue;
+            }
+            if !state.seen.insert((url.clone(), conditions.clone())) {
+                log::debug!("Skipping @import of {url}: already imported");
+                continue;
+            }
+
+            let Some(css) = state.bodies.get(&url).cloned().flatten() else {
+                log::warn!("Could not load imported stylesheet from {url}");
+                continue;
+            };
+            let config = ParserConfig {
+                source: Some(url.to_string()),
+                ignore_errors: true,
+                ..Default::default()
+            };
+            match <C::CssSystem as CssSystem>::parse_str(&css, config, origin, url.as_str()) {
+                Ok(sheet) => {
+                    let imports = imports_of(&sheet);
+                    let chain: Vec<&Url> = stack.iter().map(|f| &f.url).collect();
+                    prefetch(&url, &conditions, &imports, &chain, state, fetch).await;
+                    stack.push(Frame {
+                        sheet: Some(sheet),
+                        url,
+                        origin,
+                        conditions,
+                        imports,
+                        next: 0,
+                        parent_import,
+                        loaded: Vec::new(),
+                    });
+                }
+                Err(err) => log::warn!("Error while parsing imported stylesheet {url}: {err}"),
+            }
+            continue;
+        }
+
+        // All imports of this sheet are loaded: hand them and the sheet itself to the importer.
+        let Some(frame) = stack.pop() else {
+            return Vec::new();
+        };
+        let Some(parent) = stack.last_mut() else {
+            return frame.loaded;
+        };
+        let mut sheets: Vec<_> = frame.loaded.into_iter().map(|(_, sheet)| sheet).collect();
+        sheets.extend(frame.sheet);
+        for mut sheet in sheets {
+            if let Some(importer) = &parent.sheet {
+                importer.apply_import(frame.parent_import, &mut sheet);
+            }
+            parent.loaded.push((frame.parent_import, sheet));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_css3::stylesheet::CssStylesheet;
+    use gosub_css3::Css3;
+    use gosub_html5::document::builder::DocumentBuilderImpl;
+    use std::cell::RefCell;
+
+    fn parse(css: &str, url: &str) -> CssStylesheet {
+        Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, url).unwrap()
+    }
+
+    /// Loads the imports of `sheets` from `files` and returns the resulting stylesheet URLs and
+    /// the URLs that were fetched.
+    async fn load(sheets: &[(&str, &str)], files: &[(&str, &str)]) -> (EngineDocument, Vec<String>) {
+        let base = Url::parse("https://example.com/index.html").unwrap();
+        let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(Some(base));
+        for (url, css) in sheets {
+            doc.add_stylesheet(parse(css, url));
+        }
+
+        let files: HashMap<&str, &str> = files.iter().copied().collect();
+        let mut fetched = Vec::new();
+        load_stylesheet_imports(&mut doc, |url: Url| {
+            fetched.push(url.to_string());
+            let body = files.get(url.as_str()).map(|css| (*css).to_string());
+            async move { body }
+        })
+        .await;
+
+        (doc, fetched)
+    }
+
+    fn urls(doc: &EngineDocument) -> Vec<&str> {
+        doc.stylesheets().iter().map(|s| s.url.as_str()).collect()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn imports_are_spliced_before_their_importer() {
+        let (doc, _) = load(
+            &[
+                (
+                    "https://example.com/css/main.css",
+                    "@import 'a.css'; @import url(/b.css); p { color: red; }",
+                ),
+                (
+                    "https://example.com/index.html#inline",
+                    "@import 'css/c.css'; h1 { color: blue; }",
+                ),
+            ],
+            &[
+                (
+                    "https://example.com/css/a.css",
+                    "@import 'nested.css'; a { color: red; }",
+                ),
+                ("https://example.com/css/nested.css", "i { color: red; }"),
+                ("https://example.com/b.css", "b { color: red; }"),
+                ("https://example.com/css/c.css", "em { color: red; }"),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            urls(&doc),
+            [
+                "https://example.com/css/nested.css",
+                "https://example.com/css/a.css",
+                "https://example.com/b.css",
+                "https://example.com/css/main.css",
+                "https://example.com/css/c.css",
+                "https://example.com/index.html#inline",
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn import_media_applies_to_nested_imports() {
+        let (doc, _) = load(
+            &[("https://example.com/main.css", "@import 'print.css' print;")],
+            &[
+                (
+                    "https://example.com/print.css",
+                    "@import 'wide.css' (min-width: 600px); p { color: red; }",
+                ),
+                ("https://example.com/wide.css", "@media screen { a { color: red; } }"),
+            ],
+        )
+        .await;
+
+        let sheets = doc.stylesheets();
+        assert_eq!(sheets[0].url, "https://example.com/wide.css");
+        // Outermost first: the print import, the (min-width) import, then the sheet's own @media.
+        assert_eq!(sheets[0].rules[0].media.len(), 3);
+        assert_eq!(sheets[0].rules[0].media[0], sheets[2].imports[0].media.clone().unwrap());
+        assert_eq!(sheets[1].url, "https://example.com/print.css");
+        assert_eq!(sheets[1].rules[0].media.len(), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn cycles_and_duplicates_are_skipped() {
+        let (doc, fetched) = load(
+            &[(
+                "https://example.com/a.css",
+                "@import 'b.css'; @import 'c.css'; @import 'b.css';",
+            )],
+            &[
+                ("https://example.com/b.css", "@import 'a.css'; @import 'c.css';"),
+                ("https://example.com/c.css", "@import 'b.css'; c { color: red; }"),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            urls(&doc),
+            [
+                "https://example.com/c.css",
+                "https://example.com/b.css",
+                "https://example.com/a.css",
+            ]
+        );
+        assert_eq!(fetched, ["https://example.com/b.css", "https://example.com/c.css"]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn same_url_under_other_conditions_is_imported_again() {
+        let (doc, fetched) = load(
+            &[
+                ("https://example.com/a.css", "@import 'x.css' screen;"),
+                ("https://example.com/b.css", "@import 'x.css' print;"),
+                ("https://example.com/c.css", "@import 'x.css' print;"),
+            ],
+            &[("https://example.com/x.css", "p { color: red; }")],
+        )
+        .await;
+
+        assert_eq!(
+            urls(&doc),
+            [
+                "https://example.com/x.css",
+                "https://example.com/a.css",
+                "https://example.com/x.css",
+                "https://example.com/b.css",
+                "https://example.com/c.css",
+            ]
+        );
+        assert_eq!(fetched, ["https://example.com/x.css"]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn import_layer_applies_to_imported_rules() {
+        let (doc, _) = load(
+            &[("https://example.com/main.css", "@import 'base.css' layer(base);")],
+            &[(
+                "https://example.com/base.css",
+                "@layer inner { a { color: red; } } b { color: red; }",
+            )],
+        )
+        .await;
+
+        let base = &doc.stylesheets()[0];
+        assert_eq!(base.rules[0].layers, ["base", "inner"]);
+        assert_eq!(base.rules[1].layers, ["base"]);
+        assert_eq!(base.layer_order, ["base", "base.inner"]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn sibling_imports_are_fetched_together() {
+        let base = Url::parse("https://example.com/index.html").unwrap();
+        let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(Some(base));
+        doc.add_stylesheet(parse(
+            "@import 'a.css'; @import 'b.css';",
+            "https://example.com/main.css",
+        ));
+
+        let log = RefCell::new(Vec::new());
+        let events = &log;
+        load_stylesheet_imports(&mut doc, move |url: Url| {
+            events.borrow_mut().push(format!("start {url}"));
+            async move {
+                tokio::task::yield_now().await;
+                events.borrow_mut().push(format!("end {url}"));
+                Some(String::new())
+            }
+        })
+        .await;
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "start https://example.com/a.css",
+                "start https://example.com/b.css",
+                "end https://example.com/a.css",
+                "end https://example.com/b.css",
+            ]
+        );
+        assert_eq!(doc.stylesheets().len(), 3);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn failed_imports_are_skipped() {
+        let (doc, fetched) = load(
+            &[("https://example.com/a.css", "@import 'missing.css'; @import 'ok.css';")],
+            &[("https://example.com/ok.css", "p { color: red; }")],
+        )
+        .await;
+
+        assert_eq!(urls(&doc), ["https://example.com/ok.css", "https://example.com/a.css"]);
+        assert_eq!(fetched.len(), 2);
+    }
+}
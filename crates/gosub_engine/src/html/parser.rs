@@ -1,16 +1,15 @@
 use std::io;
 
+use crate::html::preload::PreloadScanner;
 use crate::html::{EngineDocument, RenderConfiguration};
 use crate::net::types::{Priority, ResourceKind};
 use crate::net::RequestDestination;
-use cow_utils::CowUtils;
 use gosub_html5::document::builder::DocumentBuilderImpl;
 use gosub_html5::parser::Html5Parser;
+use gosub_html5::xml::XmlParser;
 use gosub_interface::css3::CssSystem;
 use gosub_interface::document::Document as _;
 use gosub_shared::byte_stream::{ByteStream, Encoding};
-use once_cell::sync::Lazy;
-use regex::Regex;
 use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio_util::sync::CancellationToken;
 use url::Url;
@@ -54,12 +53,24 @@ pub enum DocumentError {
     Cancelled,
 }
 
+/// Which parser a main document goes through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// `text/html`
+    #[default]
+    Html,
+    /// XHTML, SVG and other XML documents
+    Xml,
+}
+
 /// Configuration for parsing a main document (see [`parse_main_document_stream`]).
 #[derive(Debug, Clone)]
 pub struct HtmlParseConfig {
     /// Max bytes to buffer from the stream; a larger document is truncated (with a warning).
     /// The engine reads this from the `net.document.max_bytes` setting.
     pub max_bytes: usize,
+    /// Parser to use for the document
+    pub format: DocumentFormat,
 }
 
 impl Default for HtmlParseConfig {
@@ -67,6 +78,7 @@ impl Default for HtmlParseConfig {
         // Matches the `net.document.max_bytes` schema default.
         Self {
             max_bytes: 10 * 1024 * 1024,
+            format: DocumentFormat::Html,
         }
     }
 }
@@ -77,7 +89,7 @@ impl Default for HtmlParseConfig {
 /// - `base_url`: used to resolve relative URLs and as the document URL.
 /// - `reader`: the response body stream (after the UA has chosen Render).
 /// - `cancel`: cancellation token (tab/nav cancellation).
-/// - `cfg`: buffer limit config.
+/// - `cfg`: buffer limit and document format. XML documents are not preload-scanned.
 /// - `on_discover`: callback invoked for each sub-resource hint found.
 pub async fn parse_main_document_stream<C, R, F>(
     base_url: Url,
@@ -91,9 +103,12 @@ where
     R: AsyncRead + Unpin + Send + 'static,
     F: FnMut(ResourceHint) + Send,
 {
-    // Buffer the full stream (up to cfg.max_bytes); bail on cancellation.
+    // Buffer the full stream (up to cfg.max_bytes); bail on cancellation. Every chunk also goes
+    // through the preload scanner, so that subresource fetches are submitted while the document
+    // is still downloading instead of after it has been parsed.
     let mut buf = Vec::with_capacity(32 * 1024);
     let mut tmp = [0u8; 16 * 1024];
+    let mut scanner = (cfg.format == DocumentFormat::Html).then(|| PreloadScanner::new(base_url.clone()));
 
     loop {
         if cancel.is_cancelled() {
@@ -108,6 +123,9 @@ where
         let remaining = cfg.max_bytes.saturating_sub(buf.len()).min(n);
         if remaining > 0 {
             buf.extend_from_slice(&tmp[..remaining]);
+            for hint in scanner.iter_mut().flat_map(|scanner| scanner.feed(&tmp[..remaining])) {
+                on_discover(hint);
+            }
         }
         // If we hit the cap, we still drain the stream to EOF quickly
         // to avoid keeping the connection open unnecessarily.
@@ -128,12 +146,7 @@ where
         }
     }
 
-    // Use lossy UTF-8 only for the fast resource-discovery regex scan.
-    let html_lossy = String::from_utf8_lossy(&buf);
-
-    // Fire sub-resource callbacks using the fast regex-based scanner so that
-    // image/CSS/script fetches are submitted before the full parse completes.
-    for hint in discover_resources(&html_lossy, &base_url) {
+    for hint in scanner.iter_mut().flat_map(PreloadScanner::finish) {
         on_discover(hint);
     }
 
@@ -149,128 +162,21 @@ where
     let mut stream = ByteStream::new(encoding, None);
     stream.read_from_bytes(&buf)?;
     let mut doc = DocumentBuilderImpl::new_document::<C>(Some(base_url));
-    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+    match cfg.format {
+        DocumentFormat::Html => {
+            let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+        }
+        DocumentFormat::Xml => {
+            // Well-formedness errors are rendered into the document itself
+            let _ = XmlParser::<C>::parse_document(&mut stream, &mut doc, None);
+        }
+    }
     let ua = <C::CssSystem as CssSystem>::load_default_useragent_stylesheet();
     doc.add_stylesheet(ua);
 
     Ok(doc)
 }
 
-// ======== Forgiving resource discovery (regex-based) ========
-fn unquote(s: &str) -> &str {
-    let b = s.as_bytes();
-    if b.len() >= 2 && ((b[0] == b'"' && b[b.len() - 1] == b'"') || (b[0] == b'\'' && b[b.len() - 1] == b'\'')) {
-        &s[1..s.len() - 1]
-    } else {
-        s
-    }
-}
-
-/// Compile a literal regex pattern.
-fn re(pattern: &str) -> Regex {
-    #[allow(clippy::unwrap_used)] // PANIC-SAFE: all callers pass literal patterns, exercised by tests
-    Regex::new(pattern).unwrap()
-}
-
-static RE_LINK_STYLESHEET: Lazy<Regex> = Lazy::new(|| {
-    // allow "..." or '...' or unquoted; capture into the *same* group `href`
-    re(
-        r#"(?is)<\s*link\b[^>]*\brel\s*=\s*(?:"stylesheet"|'stylesheet')[^>]*\bhref\s*=\s*(?P<href>"[^"]*"|'[^']*'|[^\s>]+)[^>]*>"#,
-    )
-});
-
-static RE_SCRIPT_SRC: Lazy<Regex> =
-    Lazy::new(|| re(r#"(?is)<\s*script\b[^>]*\bsrc\s*=\s*(?P<src>"[^"]*"|'[^']*'|[^\s>]+)[^>]*>"#));
-
-static RE_ASYNC_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"\basync\b"#));
-
-static RE_DEFER_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"\bdefer\b"#));
-
-static RE_IMG_SRC: Lazy<Regex> =
-    Lazy::new(|| re(r#"(?is)<\s*img\b[^>]*\bsrc\s*=\s*(?P<src>"[^"]*"|'[^']*'|[^\s>]+)[^>]*>"#));
-
-fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
-    let mut out = Vec::new();
-
-    // Stylesheets
-    for cap in RE_LINK_STYLESHEET.captures_iter(html) {
-        let Some(m) = cap.name("href") else {
-            continue;
-        };
-        let Ok(u) = resolve(base, unquote(m.as_str())) else {
-            continue;
-        };
-        out.push(ResourceHint {
-            url: u,
-            dest: RequestDestination::Document,
-            referrer: None,
-            cross_origin: false,
-            integrity: None,
-            kind: ResourceKind::Stylesheet,
-            rel: Some("stylesheet".to_string()),
-            from_attr: "href",
-            priority: Priority::High,
-        });
-    }
-
-    // Scripts
-    for cap in RE_SCRIPT_SRC.captures_iter(html) {
-        let tag = cap.get(0).map_or("", |m| m.as_str());
-        let tag_lower = tag.cow_to_ascii_lowercase();
-        // A script is blocking unless it has async or defer attributes
-        let blocking = !RE_ASYNC_ATTR.is_match(tag_lower.as_ref()) && !RE_DEFER_ATTR.is_match(tag_lower.as_ref());
-        let Some(m) = cap.name("src") else {
-            continue;
-        };
-        let Ok(u) = resolve(base, unquote(m.as_str())) else {
-            continue;
-        };
-        out.push(ResourceHint {
-            url: u,
-            kind: ResourceKind::Script { blocking },
-            rel: None,
-            from_attr: "src",
-            dest: RequestDestination::Script,
-            referrer: None,
-            cross_origin: false,
-            integrity: None,
-            priority: Priority::Normal,
-        });
-    }
-
-    // Images
-    for cap in RE_IMG_SRC.captures_iter(html) {
-        let Some(m) = cap.name("src") else {
-            continue;
-        };
-        let Ok(u) = resolve(base, unquote(m.as_str())) else {
-            continue;
-        };
-        out.push(ResourceHint {
-            url: u,
-            kind: ResourceKind::Image,
-            rel: None,
-            from_attr: "src",
-            dest: RequestDestination::Image,
-            referrer: None,
-            cross_origin: false,
-            integrity: None,
-            priority: Priority::Low,
-        });
-    }
-
-    out
-}
-
-fn resolve(base: &Url, candidate: &str) -> Result<Url, url::ParseError> {
-    // Tolerate whitespace, no-op fragments, etc.
-    let trimmed = candidate.trim();
-    if trimmed.is_empty() {
-        return Err(url::ParseError::EmptyHost);
-    }
-    base.join(trimmed)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,6 +232,31 @@ mod tests {
             .any(|h| h.kind == ResourceKind::Image && h.url.as_str() == "https://example.com/path/images/logo.png"));
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn parses_xml_documents() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><image href="not-scanned.png"/></svg>"#;
+        let cfg = HtmlParseConfig {
+            format: DocumentFormat::Xml,
+            ..Default::default()
+        };
+        let mut hints = Vec::new();
+
+        let doc = parse_main_document_stream::<DefaultRenderConfig, _, _>(
+            Url::parse("https://e.test/image.svg").unwrap(),
+            reader_from_str(svg),
+            CancellationToken::new(),
+            cfg,
+            |h| hints.push(h),
+        )
+        .await
+        .unwrap();
+
+        assert!(hints.is_empty());
+        let root = doc.children(doc.root())[0];
+        assert_eq!(doc.tag_name(root), Some("svg"));
+        assert_eq!(doc.namespace(root), Some("http://www.w3.org/2000/svg"));
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn honors_cancellation() {
         let base = Url::parse("https://e.test/").unwrap();
@@ -0,0 +1,526 @@
+//! Speculative preload scanner.
+//!
+//! Runs the HTML tokenizer (without tree construction) over the input as it arrives, so that
+//! subresources are requested while the document is still downloading or while the main parser is
+//! blocked. Since there is no tree builder, the scanner switches the tokenizer into the raw text
+//! states itself, the same way the tree builder would for `<script>`, `<style>`, `<title>` and
+//! friends.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::html::ResourceHint;
+use crate::net::types::{Priority, ResourceKind};
+use crate::net::RequestDestination;
+use cow_utils::CowUtils;
+use gosub_html5::parser::errors::{ErrorLogger, ErrorMode};
+use gosub_html5::tokenizer::state::State;
+use gosub_html5::tokenizer::token::Token;
+use gosub_html5::tokenizer::{Options, ParserData, Tokenizer};
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location, Stream};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use url::Url;
+
+/// Matches `@import url(...)`, `@import "..."` and `@import '...'` in inline stylesheets.
+static RE_CSS_IMPORT: Lazy<Regex> = Lazy::new(|| {
+    #[allow(clippy::unwrap_used)] // PANIC-SAFE: literal pattern, exercised by tests
+    Regex::new(r#"(?i)@import\s+(?:url\(\s*(?:"(?P<q1>[^"]*)"|'(?P<q2>[^']*)'|(?P<bare>[^)\s]*))\s*\)|"(?P<s1>[^"]*)"|'(?P<s2>[^']*)')"#)
+        .unwrap()
+});
+
+/// Scans buffered HTML ahead of the parser and reports the subresources it finds.
+///
+/// Feed it the document bytes as they come in; every call returns the hints found in the data
+/// that could be tokenized so far. Text is taken as far as it has arrived, but a tag or other
+/// markup that is cut off at the end of the input is scanned again once the rest of it has
+/// arrived. Only that unscanned input is kept. Each URL is reported only once.
+pub struct PreloadScanner {
+    /// Input not scanned yet
+    pending: Vec<u8>,
+    /// True once the whole document has been fed
+    closed: bool,
+    /// Tokenizer state at the point where the previous scan stopped
+    state: State,
+    last_start_tag: String,
+    discovery: Discovery,
+}
+
+/// Turns the tokens of the scanner into resource hints
+struct Discovery {
+    /// Base URL for relative references. Updated by the first `<base href>`.
+    base: Url,
+    base_seen: bool,
+    /// True while inside a `<style>` element, whose text is scanned for `@import`
+    in_style: bool,
+    /// Text of the current `<style>` element that may hold the start of an `@import` whose end
+    /// has not arrived yet
+    style_tail: String,
+    seen: HashSet<Url>,
+}
+
+impl PreloadScanner {
+    /// Creates a scanner for a document served from `base`. The input is assumed to be UTF-8
+    /// (or ASCII compatible), which covers the URLs we are after.
+    pub fn new(base: Url) -> Self {
+        Self {
+            pending: Vec::new(),
+            closed: false,
+            state: State::Data,
+            last_start_tag: String::new(),
+            discovery: Discovery {
+                base,
+                base_seen: false,
+                in_style: false,
+                style_tail: String::new(),
+                seen: HashSet::new(),
+            },
+        }
+    }
+
+    /// Adds a chunk of the document and returns the hints found so far
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<ResourceHint> {
+        self.pending.extend_from_slice(bytes);
+        self.scan()
+    }
+
+    /// Marks the end of the document and returns the hints found in the remaining input
+    pub fn finish(&mut self) -> Vec<ResourceHint> {
+        self.closed = true;
+        self.scan()
+    }
+
+    fn scan(&mut self) -> Vec<ResourceHint> {
+        let mut hints = Vec::new();
+
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.append_bytes(&self.pending);
+        if self.closed {
+            stream.close();
+        }
+
+        // Tokenizer errors are of no interest to the scanner; the main parser reports them
+        let error_logger = Rc::new(RefCell::new(ErrorLogger::with_mode(ErrorMode::Ignore)));
+        let options = Options {
+            initial_state: self.state,
+            last_start_tag: std::mem::take(&mut self.last_start_tag),
+        };
+        let mut tokenizer = Tokenizer::new(&mut stream, Some(options), error_logger, Location::default());
+
+        let mut scanned;
+        loop {
+            let mark = tokenizer.stream.mark();
+            scanned = scanned_bytes(tokenizer.stream, &self.pending);
+            let state = tokenizer.state;
+            let last_start_tag = tokenizer.last_start_token.clone();
+
+            // A text run is emitted together with the tag that ends it, so take the whole batch.
+            // After that the tokenizer is at a token boundary.
+            let mut batch = Vec::new();
+            let mut failed = false;
+            loop {
+                match tokenizer.next_token(ParserData::default()) {
+                    Ok(token) => batch.push(token),
+                    Err(_) => failed = true,
+                }
+                if failed || tokenizer.token_queue.is_empty() {
+                    break;
+                }
+            }
+
+            let at_eof = batch.iter().any(|token| matches!(token, Token::Eof { .. }));
+            if failed || (!tokenizer.stream.closed() && (at_eof || tokenizer.stream.exhausted())) {
+                // The batch ran into the end of the data received so far and may be cut off.
+                // Rewind, and scan it again when more data has arrived. The text before the
+                // point where it may be cut off is done with, though: skip it, so that a long
+                // unterminated text run is not scanned again and again.
+                tokenizer.stream.reset_to_mark(mark);
+                tokenizer.state = state;
+                tokenizer.last_start_token = last_start_tag;
+                if !failed {
+                    let end = self.pending.len() - incomplete_utf8_len(&self.pending);
+                    let text = &self.pending[scanned..end];
+                    let len = text_run_len(state, text);
+                    if state == State::RAWTEXT {
+                        self.discovery
+                            .process_text(&String::from_utf8_lossy(&text[..len]), &mut hints);
+                    }
+                    scanned += len;
+                }
+                break;
+            }
+
+            for token in &batch {
+                if let Some(state) = self.discovery.process_token(token, &mut hints) {
+                    tokenizer.state = state;
+                }
+            }
+
+            if at_eof {
+                scanned = self.pending.len();
+                break;
+            }
+        }
+
+        self.state = tokenizer.state;
+        self.last_start_tag = std::mem::take(&mut tokenizer.last_start_token);
+        self.pending.drain(..scanned);
+        hints
+    }
+}
+
+impl Discovery {
+    /// Handles a single token, and returns the tokenizer state to switch to (if any)
+    fn process_token(&mut self, token: &Token, hints: &mut Vec<ResourceHint>) -> Option<State> {
+        match token {
+            Token::StartTag { name, attributes, .. } => self.process_start_tag(name, attributes, hints),
+            Token::EndTag { name, .. } if name == "style" => {
+                self.in_style = false;
+                self.style_tail.clear();
+                None
+            }
+            Token::Text { text, .. } => {
+                self.process_text(text, hints);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Handles a run of text. The text of a `<style>` element is scanned for `@import`s. It may
+    /// arrive in pieces, so an `@import` may be split over them; the part that can still turn
+    /// into one is kept for the next piece.
+    fn process_text(&mut self, text: &str, hints: &mut Vec<ResourceHint>) {
+        if !self.in_style {
+            return;
+        }
+
+        let mut css = std::mem::take(&mut self.style_tail);
+        css.push_str(text);
+
+        let mut scanned = 0;
+        for cap in RE_CSS_IMPORT.captures_iter(&css) {
+            let href = ["q1", "q2", "bare", "s1", "s2"]
+                .iter()
+                .find_map(|group| cap.name(group))
+                .map_or("", |m| m.as_str());
+            self.push_hint(hints, href, ResourceKind::Stylesheet, None, "@import");
+            scanned = cap.get(0).map_or(scanned, |m| m.end());
+        }
+
+        // Keep the last `@` after the imports found, if what follows it can still become one
+        let rest = &css[scanned..];
+        if let Some(at) = rest.rfind('@') {
+            let keyword = rest[at + 1..].get(..6).unwrap_or(&rest[at + 1..]);
+            if "import"
+                .get(..keyword.len())
+                .is_some_and(|k| k.eq_ignore_ascii_case(keyword))
+            {
+                self.style_tail = rest[at..].to_string();
+            }
+        }
+    }
+
+    fn process_start_tag(
+        &mut self,
+        name: &str,
+        attributes: &HashMap<String, String>,
+        hints: &mut Vec<ResourceHint>,
+    ) -> Option<State> {
+        let attr = |name: &str| attributes.get(name).map(String::as_str);
+
+        match name {
+            "base" => {
+                // Only the first <base href> counts
+                if let Some(href) = attr("href") {
+                    if !self.base_seen {
+                        self.base_seen = true;
+                        if let Ok(base) = self.base.join(href.trim()) {
+                            self.base = base;
+                        }
+                    }
+                }
+                None
+            }
+            "link" => {
+                let rel = attr("rel").unwrap_or_default().cow_to_ascii_lowercase();
+                let href = attr("href").unwrap_or_default();
+                for rel in rel.split_ascii_whitespace() {
+                    let kind = match rel {
+                        "stylesheet" => Some(ResourceKind::Stylesheet),
+                        "preload" => preload_kind(attr("as").unwrap_or_default()),
+                        _ => None,
+                    };
+                    if let Some(kind) = kind {
+                        self.push_hint(hints, href, kind, Some(rel.to_string()), "href");
+                    }
+                }
+                None
+            }
+            "script" => {
+                if let Some(src) = attr("src") {
+                    let module = attr("type").is_some_and(|t| t.trim().eq_ignore_ascii_case("module"));
+                    let blocking = !module && attr("async").is_none() && attr("defer").is_none();
+                    self.push_hint(hints, src, ResourceKind::Script { blocking }, None, "src");
+                }
+                Some(State::ScriptData)
+            }
+            "img" => {
+                if let Some(candidate) = attr("srcset").and_then(srcset_candidate) {
+                    self.push_hint(hints, candidate, ResourceKind::Image, None, "srcset");
+                } else if let Some(src) = attr("src") {
+                    self.push_hint(hints, src, ResourceKind::Image, None, "src");
+                }
+                None
+            }
+            "style" => {
+                self.in_style = true;
+                Some(State::RAWTEXT)
+            }
+            // Scripting is assumed to be enabled, so <noscript> content is raw text
+            "xmp" | "iframe" | "noembed" | "noframes" | "noscript" => Some(State::RAWTEXT),
+            "title" | "textarea" => Some(State::RCDATA),
+            "plaintext" => Some(State::PLAINTEXT),
+            _ => None,
+        }
+    }
+
+    fn push_hint(
+        &mut self,
+        hints: &mut Vec<ResourceHint>,
+        href: &str,
+        kind: ResourceKind,
+        rel: Option<String>,
+        from_attr: &'static str,
+    ) {
+        let Ok(url) = resolve(&self.base, href) else {
+            return;
+        };
+        if !self.seen.insert(url.clone()) {
+            return;
+        }
+
+        let (dest, priority) = match kind {
+            ResourceKind::Stylesheet => (RequestDestination::Style, Priority::High),
+            ResourceKind::Script { blocking: true } => (RequestDestination::Script, Priority::High),
+            ResourceKind::Script { blocking: false } => (RequestDestination::Script, Priority::Normal),
+            ResourceKind::Font => (RequestDestination::Font, Priority::High),
+            ResourceKind::Image => (RequestDestination::Image, Priority::Low),
+            _ => (RequestDestination::Other, Priority::Low),
+        };
+
+        hints.push(ResourceHint {
+            url,
+            dest,
+            kind,
+            rel,
+            from_attr,
+            referrer: None,
+            cross_origin: false,
+            integrity: None,
+            priority,
+        });
+    }
+}
+
+/// Resource kind for `<link rel=preload as=...>`. Preloads without a (known) `as` are not fetched.
+fn preload_kind(as_attr: &str) -> Option<ResourceKind> {
+    match as_attr.trim().cow_to_ascii_lowercase().as_ref() {
+        "style" => Some(ResourceKind::Stylesheet),
+        "script" => Some(ResourceKind::Script { blocking: false }),
+        "image" => Some(ResourceKind::Image),
+        "font" => Some(ResourceKind::Font),
+        "audio" | "video" | "track" => Some(ResourceKind::Media),
+        "fetch" => Some(ResourceKind::Fetch),
+        _ => None,
+    }
+}
+
+/// Picks the `srcset` candidate for a 1x display: an explicit `1x` (or descriptor-less) candidate
+/// when there is one, otherwise the first candidate.
+fn srcset_candidate(srcset: &str) -> Option<&str> {
+    let candidates: Vec<(&str, Option<&str>)> = srcset
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_ascii_whitespace();
+            parts.next().map(|url| (url, parts.next()))
+        })
+        .collect();
+
+    candidates
+        .iter()
+        .find(|(_, descriptor)| descriptor.is_none_or(|d| d == "1x"))
+        .or(candidates.first())
+        .map(|(url, _)| *url)
+}
+
+/// Length of the start of `text`, read in tokenizer `state`, that stays text whatever comes after
+/// it: everything before the last `<`, where a tag may start. In script data a `<!--` changes how
+/// the text after it is read, so a run holding one is only taken once it is complete.
+fn text_run_len(state: State, text: &[u8]) -> usize {
+    match state {
+        State::PLAINTEXT => text.len(),
+        State::Data | State::RCDATA | State::RAWTEXT => text.iter().rposition(|&b| b == b'<').unwrap_or(text.len()),
+        State::ScriptData if !text.windows(4).any(|w| w == b"<!--") => {
+            text.iter().rposition(|&b| b == b'<').unwrap_or(text.len())
+        }
+        _ => 0,
+    }
+}
+
+/// Number of bytes of `pending`, the input of `stream`, before the position of `stream`
+fn scanned_bytes(stream: &ByteStream, pending: &[u8]) -> usize {
+    // A character cut off at the end of the input is held back by the stream, which then reports
+    // the end of the input as its position
+    if stream.exhausted() {
+        pending.len() - incomplete_utf8_len(pending)
+    } else {
+        stream.tell_bytes()
+    }
+}
+
+/// Length of the UTF-8 sequence cut off at the end of `bytes`, if any
+fn incomplete_utf8_len(bytes: &[u8]) -> usize {
+    // A sequence is at most four bytes long, so a cut-off one starts in the last three
+    (1..=bytes.len().min(3))
+        .find(|&len| {
+            std::str::from_utf8(&bytes[bytes.len() - len..])
+                .is_err_and(|e| e.valid_up_to() == 0 && e.error_len().is_none())
+        })
+        .unwrap_or(0)
+}
+
+fn resolve(base: &Url, candidate: &str) -> Result<Url, url::ParseError> {
+    // Tolerate whitespace, no-op fragments, etc.
+    let trimmed = candidate.trim();
+    if trimmed.is_empty() {
+        return Err(url::ParseError::EmptyHost);
+    }
+    base.join(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/dir/index.html").unwrap()
+    }
+
+    fn urls(hints: &[ResourceHint]) -> Vec<&str> {
+        hints.iter().map(|h| h.url.as_str()).collect()
+    }
+
+    #[test]
+    fn finds_subresources() {
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = scanner.feed(
+            br#"<!doctype html><head>
+            <link rel="preload" href="font.woff2" as="font">
+            <link rel="Stylesheet" href=/main.css>
+            <link rel="icon" href="favicon.ico">
+            <script src="app.js" defer></script>
+            <script src="sync.js"></script>
+            <style>@import url("imported.css"); @import 'other.css';</style>
+            </head><body><img src="a.png"><img srcset="b-2x.png 2x, b.png 1x" src="b-fallback.png">"#,
+        );
+        hints.extend(scanner.finish());
+
+        assert_eq!(
+            urls(&hints),
+            [
+                "https://example.com/dir/font.woff2",
+                "https://example.com/main.css",
+                "https://example.com/dir/app.js",
+                "https://example.com/dir/sync.js",
+                "https://example.com/dir/imported.css",
+                "https://example.com/dir/other.css",
+                "https://example.com/dir/a.png",
+                "https://example.com/dir/b.png",
+            ]
+        );
+        assert_eq!(hints[0].kind, ResourceKind::Font);
+        assert_eq!(hints[2].kind, ResourceKind::Script { blocking: false });
+        assert_eq!(hints[3].kind, ResourceKind::Script { blocking: true });
+        assert_eq!(hints[4].from_attr, "@import");
+    }
+
+    #[test]
+    fn raw_text_is_not_scanned_for_tags() {
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = scanner.feed(
+            b"<script>document.write('<img src=no1.png>')</script><title><img src=no2.png></title>\
+              <textarea><img src=no3.png></textarea><!-- <img src=no4.png> --><img src=yes.png>",
+        );
+        hints.extend(scanner.finish());
+
+        assert_eq!(urls(&hints), ["https://example.com/dir/yes.png"]);
+    }
+
+    #[test]
+    fn tokens_split_over_chunks() {
+        let html = "<base href=\"/assets/\"><script src=\"x.js\"></script><style>@import \"y.css\";</style><img src=\"z.png\">";
+
+        // Feed one byte at a time: every tag is cut off at some point
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = Vec::new();
+        for byte in html.as_bytes() {
+            hints.extend(scanner.feed(std::slice::from_ref(byte)));
+        }
+        hints.extend(scanner.finish());
+
+        assert_eq!(
+            urls(&hints),
+            [
+                "https://example.com/assets/x.js",
+                "https://example.com/assets/y.css",
+                "https://example.com/assets/z.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_text_is_not_kept() {
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = scanner.feed(b"<script>");
+        for _ in 0..1000 {
+            hints.extend(scanner.feed(&[b'x'; 100]));
+            assert!(scanner.pending.is_empty(), "{} bytes kept", scanner.pending.len());
+        }
+        hints.extend(scanner.feed(b"if (a <"));
+        assert_eq!(scanner.pending, b"<");
+        hints.extend(scanner.feed(b" b) {}</script><img src=a.png>"));
+        hints.extend(scanner.finish());
+
+        assert_eq!(urls(&hints), ["https://example.com/dir/a.png"]);
+    }
+
+    #[test]
+    fn escaped_script_text_split_over_chunks() {
+        let html = "<script><!-- <script>x</script> <img src=no.png> --></script><img src=yes.png>\
+                    <p>caf\u{e9}</p><style>p { color: red } @import 'c.css';</style>";
+
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = Vec::new();
+        for byte in html.as_bytes() {
+            hints.extend(scanner.feed(std::slice::from_ref(byte)));
+        }
+        hints.extend(scanner.finish());
+
+        assert_eq!(
+            urls(&hints),
+            ["https://example.com/dir/yes.png", "https://example.com/dir/c.css"]
+        );
+    }
+
+    #[test]
+    fn urls_are_reported_once() {
+        let mut scanner = PreloadScanner::new(base());
+        let mut hints = scanner.feed(b"<img src=a.png><img src=./a.png>");
+        hints.extend(scanner.feed(b"<link rel=preload as=image href=a.png>"));
+        hints.extend(scanner.finish());
+
+        assert_eq!(urls(&hints), ["https://example.com/dir/a.png"]);
+    }
+}
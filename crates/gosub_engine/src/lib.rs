@@ -187,10 +187,15 @@ pub use crate::engine::config::EngineConfig;
 pub use crate::engine::cookies::SameSiteContext;
 pub use crate::engine::cookies::ThirdPartyCookiePolicy;
 
+/// The AccessKit version `EngineEvent::AccessibilityUpdate` carries, for hosts wiring up an adapter.
+pub use accesskit;
+
 /// Public `events` namespace with the enums/structs:
 pub mod events {
-    pub use crate::engine::events::{EngineCommand, EngineEvent, IoCommand, MouseButton, TabCommand};
-    pub use crate::engine::events::{NavigationEvent, ResourceEvent};
+    pub use crate::engine::events::{ElementRect, HitTestElement, NavigationEvent, NodeInspection, ResourceEvent};
+    pub use crate::engine::events::{EngineCommand, EngineEvent, IoCommand, Modifiers, MouseButton, TabCommand};
+    pub use gosub_interface::css3::{MatchedDeclaration, MatchedRule};
+    pub use gosub_render_pipeline::common::browser_state::DebugOverlays;
 }
 
 /// Configuration options for the Gosub engine.
@@ -58,7 +58,7 @@ pub fn decide_handling(
     {
         // Common mislabels: text/plain or application/octet-stream that actually contain HTML.
         if let Some(sniff) = sniffed_class {
-            if matches!(sniff, ResponseClass::Html | ResponseClass::Xml) {
+            if matches!(sniff, ResponseClass::Html | ResponseClass::Xml | ResponseClass::Svg) {
                 effective_class = Some(sniff);
             }
         }
@@ -97,9 +97,12 @@ pub fn decide_handling(
     let class = effective_class.unwrap_or(ResponseClass::Binary);
 
     let decision = match class {
-        ResponseClass::Html | ResponseClass::XHtml | ResponseClass::Xml => {
-            HandlingDecision::Render(RenderTarget::HtmlParser)
-        }
+        ResponseClass::Html => HandlingDecision::Render(RenderTarget::HtmlParser),
+        ResponseClass::XHtml | ResponseClass::Xml => HandlingDecision::Render(RenderTarget::XmlParser),
+        ResponseClass::Svg => match dest {
+            RequestDestination::Document => HandlingDecision::Render(RenderTarget::XmlParser),
+            _ => HandlingDecision::Render(RenderTarget::ImageDecoder),
+        },
         ResponseClass::Image => HandlingDecision::Render(RenderTarget::ImageDecoder),
         ResponseClass::Js => HandlingDecision::Render(RenderTarget::JsEngine),
         ResponseClass::Css => HandlingDecision::Render(RenderTarget::CssParser),
@@ -112,11 +115,16 @@ pub fn decide_handling(
         }
         ResponseClass::Json | ResponseClass::Text | ResponseClass::Binary => match dest {
             RequestDestination::Document
-                if policy.enable_sniffing_navigation_upgrade
-                    && matches!(sniffed_class, Some(ResponseClass::Html | ResponseClass::Xml)) =>
+                if policy.enable_sniffing_navigation_upgrade && sniffed_class == Some(ResponseClass::Html) =>
             {
                 HandlingDecision::Render(RenderTarget::HtmlParser)
             }
+            RequestDestination::Document
+                if policy.enable_sniffing_navigation_upgrade
+                    && matches!(sniffed_class, Some(ResponseClass::Xml | ResponseClass::Svg)) =>
+            {
+                HandlingDecision::Render(RenderTarget::XmlParser)
+            }
             _ => HandlingDecision::Download {
                 path: std::path::PathBuf::new(),
             },
@@ -171,7 +179,10 @@ fn mime_is_pdf(m: &mime::Mime) -> bool {
 /// Map a MIME type to a coarse `ResponseClass`.
 fn class_from_mime(m: &mime::Mime) -> Option<ResponseClass> {
     use ResponseClass::*;
-    if m.type_() == mime::TEXT && m.subtype() == mime::HTML {
+    if m.subtype() == mime::XML || m.suffix() == Some(mime::XML) {
+        // text/xml, application/xhtml+xml, image/svg+xml, application/atom+xml, ...
+        Some(ResponseClass::from_mime(m))
+    } else if m.type_() == mime::TEXT && m.subtype() == mime::HTML {
         Some(Html)
     } else if m.type_() == mime::TEXT && m.subtype() == mime::CSS {
         Some(Css)
@@ -10,6 +10,8 @@ pub enum ResponseClass {
     Html,
     XHtml,
     Xml,
+    /// `image/svg+xml`: an image when embedded, an XML document when navigated to
+    Svg,
     Text,
     Css,
     Js,
@@ -35,6 +37,9 @@ impl ResponseClass {
         if top == "application" && sub == "xhtml" && suffix == Some("xml") {
             return ResponseClass::XHtml;
         }
+        if top == "image" && sub == "svg" && suffix == Some("xml") {
+            return ResponseClass::Svg;
+        }
 
         match (top, sub) {
             ("text", "html") => ResponseClass::Html,
@@ -57,6 +62,9 @@ impl ResponseClass {
             ("application", "pdf") => ResponseClass::Pdf,
             ("application", "octet-stream") => ResponseClass::Binary,
 
+            // Other XML vocabularies (application/rss+xml, application/atom+xml, ...)
+            _ if suffix == Some("xml") => ResponseClass::Xml,
+
             _ => ResponseClass::Unknown,
         }
     }
@@ -74,6 +82,9 @@ pub fn sniff_class(peek_buf: PeekBuf) -> ResponseClass {
         if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
             return ResponseClass::Html;
         }
+        if lower.starts_with("<svg") {
+            return ResponseClass::Svg;
+        }
         if lower.starts_with("<?xml") || lower.starts_with("<rss") || lower.starts_with("<feed") {
             return ResponseClass::Xml;
         }
@@ -106,6 +117,9 @@ mod tests {
         let cases = vec![
             ("text/html", ResponseClass::Html),
             ("application/xhtml+xml", ResponseClass::XHtml),
+            ("application/xml", ResponseClass::Xml),
+            ("application/atom+xml", ResponseClass::Xml),
+            ("image/svg+xml", ResponseClass::Svg),
             ("text/plain", ResponseClass::Text),
             ("text/css", ResponseClass::Css),
             ("application/javascript", ResponseClass::Js),
@@ -70,8 +70,10 @@ impl std::fmt::Display for BlockReason {
 // Where to send the stream if we let the engine render it.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RenderTarget {
-    /// Send to the HTML parser (or XHTML parser).
+    /// Send to the HTML parser.
     HtmlParser,
+    /// Send to the XML parser (XHTML, SVG and other XML documents).
+    XmlParser,
     /// Send to the CSS parser.
     CssParser,
     /// Send to the JavaScript engine.
@@ -4,7 +4,7 @@ use crate::engine::resource_pipeline::js::DummyJsDocument;
 use crate::engine::resource_pipeline::ResourcePipelines;
 use crate::engine::types::PeekBuf;
 use crate::engine::UaPolicy;
-use crate::html::{EngineDocument, RenderConfiguration};
+use crate::html::{DocumentFormat, EngineDocument, RenderConfiguration};
 use crate::net::decision::types::BlockReason;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResult};
 use crate::net::{decide_handling, stream_to_bytes, HandlingDecision, RenderTarget, RequestDestination, SharedBody};
@@ -80,13 +80,24 @@ pub async fn route_response_for<C: RenderConfiguration>(
 
     match (dest, outcome.decision, body_content) {
         (RequestDestination::Document, HandlingDecision::Render(target), body_content) => match target {
-            RenderTarget::HtmlParser => {
+            RenderTarget::HtmlParser | RenderTarget::XmlParser => {
+                let format = if target == RenderTarget::XmlParser {
+                    DocumentFormat::Xml
+                } else {
+                    DocumentFormat::Html
+                };
                 let doc = match body_content {
                     BodyContent::Stream { shared } => {
-                        hooks.html.parse_stream(request, handle, meta, peek_buf, shared).await?
+                        hooks
+                            .html
+                            .parse_stream(request, handle, meta, format, peek_buf, shared)
+                            .await?
                     }
                     BodyContent::Buffered { body } => {
-                        hooks.html.parse_bytes(request, handle, meta, body.as_ref()).await?
+                        hooks
+                            .html
+                            .parse_bytes(request, handle, meta, format, body.as_ref())
+                            .await?
                     }
                 };
                 Ok(RoutedOutcome::MainDocument(Arc::new(doc)))
@@ -1,5 +1,6 @@
 pub mod builder;
 pub mod document_impl;
 pub mod fragment;
+pub mod markup;
 pub mod query;
 pub mod task_queue;
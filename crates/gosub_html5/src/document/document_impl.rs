@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 use gosub_interface::css3::CssSystem;
 use gosub_interface::document::{Document, DocumentType};
+use gosub_interface::mutation::MutationRecord;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
@@ -15,7 +16,7 @@ use crate::node::data::element::{ClassListImpl, ElementData};
 use crate::node::node_impl::{NodeDataTypeInternal, NodeImpl};
 use crate::node::visitor::Visitor;
 use gosub_interface::config::HasDocument;
-use gosub_interface::node::{NodeType, QuirksMode};
+use gosub_interface::node::{NodeType, QuirksMode, ShadowRootMode};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::node::NodeId;
 
@@ -31,7 +32,11 @@ pub struct DocumentImpl<C: HasDocument> {
     pub doctype: DocumentType,
     pub quirks_mode: QuirksMode,
     pub stylesheets: Vec<<C::CssSystem as CssSystem>::Stylesheet>,
+    /// Stylesheets scoped to a shadow root, keyed by the shadow root's node id
+    shadow_stylesheets: HashMap<NodeId, Vec<<C::CssSystem as CssSystem>::Stylesheet>>,
     hovered_nodes: parking_lot::RwLock<std::collections::HashSet<NodeId>>,
+    /// Changes recorded since the journal was last drained; `None` while not recording
+    journal: Option<Vec<MutationRecord>>,
 }
 
 impl<C: HasDocument> PartialEq for DocumentImpl<C> {
@@ -42,6 +47,7 @@ impl<C: HasDocument> PartialEq for DocumentImpl<C> {
             && self.doctype == other.doctype
             && self.quirks_mode == other.quirks_mode
             && self.stylesheets == other.stylesheets
+            && self.shadow_stylesheets == other.shadow_stylesheets
     }
 }
 
@@ -57,7 +63,9 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             doctype: document_type,
             quirks_mode: QuirksMode::NoQuirks,
             stylesheets: Vec::new(),
+            shadow_stylesheets: HashMap::new(),
             hovered_nodes: parking_lot::RwLock::new(std::collections::HashSet::new()),
+            journal: None,
         };
         let root = NodeImpl::new_document(Location::default(), QuirksMode::NoQuirks);
         doc.arena.register_node(root);
@@ -191,6 +199,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
     }
 
     fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        let old_value = self.attribute(id, name).map(str::to_owned);
         let is_element = if let Some(node) = self.arena.node_ref_mut(id) {
             if let NodeDataTypeInternal::Element(ref mut e) = node.data {
                 e.add_attribute(name, value);
@@ -208,23 +217,44 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
                 self.named_ids_by_node.entry(id).or_default().push(value.to_string());
             }
         }
+        if is_element {
+            self.record_mutation(|_| MutationRecord::Attributes {
+                target: id,
+                name: name.to_string(),
+                old_value,
+            });
+        }
     }
 
     fn remove_attribute(&mut self, id: NodeId, name: &str) {
+        let Some(old_value) = self.attribute(id, name).map(str::to_owned) else {
+            return;
+        };
         let Some(node) = self.arena.node_ref_mut(id) else {
             return;
         };
         if let NodeDataTypeInternal::Element(ref mut e) = node.data {
             e.remove_attribute(name);
         }
+        self.record_mutation(|_| MutationRecord::Attributes {
+            target: id,
+            name: name.to_string(),
+            old_value: Some(old_value),
+        });
     }
 
     fn add_class(&mut self, id: NodeId, class: &str) {
+        let old_value = self.attribute(id, "class").map(str::to_owned);
         let Some(node) = self.arena.node_ref_mut(id) else {
             return;
         };
         if let NodeDataTypeInternal::Element(ref mut e) = node.data {
             e.add_class(class);
+            self.record_mutation(|_| MutationRecord::Attributes {
+                target: id,
+                name: "class".to_string(),
+                old_value,
+            });
         }
     }
 
@@ -254,6 +284,43 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         }
     }
 
+    // ── shadow DOM ─────────────────────────────────────────────────────────
+
+    fn attach_shadow_root(&mut self, host: NodeId, mode: ShadowRootMode) -> Option<NodeId> {
+        match self.arena.node_ref(host)?.data {
+            NodeDataTypeInternal::Element(ref e) if e.shadow_root.is_none() => {}
+            _ => return None,
+        }
+        let root = self.register_node(NodeImpl::new_shadow_root(Location::default(), host, mode));
+        if let Some(node) = self.arena.node_ref_mut(host) {
+            if let NodeDataTypeInternal::Element(ref mut e) = node.data {
+                e.shadow_root = Some(root);
+            }
+        }
+        Some(root)
+    }
+
+    fn shadow_root(&self, host: NodeId) -> Option<NodeId> {
+        match self.arena.node_ref(host)?.data {
+            NodeDataTypeInternal::Element(ref e) => e.shadow_root,
+            _ => None,
+        }
+    }
+
+    fn shadow_host(&self, root: NodeId) -> Option<NodeId> {
+        match self.arena.node_ref(root)?.data {
+            NodeDataTypeInternal::ShadowRoot(ref r) => Some(r.host),
+            _ => None,
+        }
+    }
+
+    fn shadow_root_mode(&self, root: NodeId) -> Option<ShadowRootMode> {
+        match self.arena.node_ref(root)?.data {
+            NodeDataTypeInternal::ShadowRoot(ref r) => Some(r.mode),
+            _ => None,
+        }
+    }
+
     // ── text / comment / doctype ───────────────────────────────────────────
 
     fn text_value(&self, id: NodeId) -> Option<&str> {
@@ -268,18 +335,23 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             return;
         };
         if let NodeDataTypeInternal::Text(ref mut t) = node.data {
-            t.value = value.to_owned();
+            let old_value = std::mem::replace(&mut t.value, value.to_owned());
+            self.record_mutation(|_| MutationRecord::CharacterData { target: id, old_value });
         }
     }
 
     fn append_text_value(&mut self, id: NodeId, value: &str) -> bool {
+        let journaling = self.journal.is_some();
         let Some(node) = self.arena.node_ref_mut(id) else {
             return false;
         };
         if let NodeDataTypeInternal::Text(ref mut t) = node.data {
+            // Only copy the old text when someone will read it
+            let old_value = if journaling { t.value.clone() } else { String::new() };
             // In-place append: the backing String grows geometrically, so merging N adjacent
             // text runs into this node stays amortized O(total length) instead of O(N^2).
             t.value.push_str(value);
+            self.record_mutation(|_| MutationRecord::CharacterData { target: id, old_value });
             true
         } else {
             false
@@ -358,6 +430,32 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         self.stylesheets.push(sheet);
     }
 
+    fn insert_stylesheet(&mut self, index: usize, sheet: <C::CssSystem as CssSystem>::Stylesheet) {
+        self.stylesheets.insert(index, sheet);
+    }
+
+    fn shadow_stylesheets(&self, root: NodeId) -> &[<C::CssSystem as CssSystem>::Stylesheet] {
+        self.shadow_stylesheets.get(&root).map_or(&[], Vec::as_slice)
+    }
+
+    fn add_shadow_stylesheet(&mut self, root: NodeId, sheet: <C::CssSystem as CssSystem>::Stylesheet) {
+        self.shadow_stylesheets.entry(root).or_default().push(sheet);
+    }
+
+    // ── mutation journal ───────────────────────────────────────────────────
+
+    fn record_mutations(&mut self, enabled: bool) {
+        match (enabled, &self.journal) {
+            (true, None) => self.journal = Some(Vec::new()),
+            (false, _) => self.journal = None,
+            (true, Some(_)) => {}
+        }
+    }
+
+    fn take_mutations(&mut self) -> Vec<MutationRecord> {
+        self.journal.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     // ── serialisation ──────────────────────────────────────────────────────
 
     fn write(&self) -> String {
@@ -392,6 +490,47 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
         }
     }
 
+    /// Appends a record to the mutation journal when recording. The record is only built then.
+    fn record_mutation(&mut self, record: impl FnOnce(&Self) -> MutationRecord) {
+        if self.journal.is_none() {
+            return;
+        }
+        let record = record(self);
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push(record);
+        }
+    }
+
+    /// The siblings on either side of `node` among the children of `parent`
+    fn siblings_around(&self, parent: NodeId, node: NodeId) -> (Option<NodeId>, Option<NodeId>) {
+        let children = self.children(parent);
+        let Some(index) = children.iter().position(|&child| child == node) else {
+            return (None, None);
+        };
+        let previous = index.checked_sub(1).and_then(|i| children.get(i)).copied();
+        (previous, children.get(index + 1).copied())
+    }
+
+    /// Journals `node` being added to or removed from the children of `parent`. Called while
+    /// `node` is still among the children.
+    fn record_child_list(&mut self, parent: NodeId, node: NodeId, added: bool) {
+        self.record_mutation(|doc| {
+            let (previous_sibling, next_sibling) = doc.siblings_around(parent, node);
+            let (added, removed) = if added {
+                (vec![node], Vec::new())
+            } else {
+                (Vec::new(), vec![node])
+            };
+            MutationRecord::ChildList {
+                target: parent,
+                added,
+                removed,
+                previous_sibling,
+                next_sibling,
+            }
+        });
+    }
+
     fn on_document_node_mutation(&mut self, node: &NodeImpl) {
         self.on_document_node_mutation_update_named_id(node);
     }
@@ -507,6 +646,7 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
         };
         node.parent = Some(parent_id);
         self.on_document_node_mutation_by_id(node_id);
+        self.record_child_list(parent_id, node_id, true);
     }
 
     pub fn detach_node(&mut self, node_id: NodeId) {
@@ -514,6 +654,7 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
             return;
         };
         if let Some(parent_id) = parent {
+            self.record_child_list(parent_id, node_id, false);
             if let Some(parent_node) = self.arena.node_ref_mut(parent_id) {
                 parent_node.remove(node_id);
                 self.on_document_node_mutation_by_id(parent_id);
@@ -563,6 +704,7 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
             return;
         };
         if let Some(parent_id) = parent {
+            self.record_child_list(parent_id, node_id, false);
             if let Some(parent_node) = self.arena.node_ref_mut(parent_id) {
                 parent_node.remove(node_id);
                 self.on_document_node_mutation_by_id(parent_id);
@@ -678,6 +820,13 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
                 }
                 let _ = writeln!(f, ">");
             }
+            NodeDataTypeInternal::ShadowRoot(root) => {
+                let mode = match root.mode {
+                    ShadowRootMode::Open => "open",
+                    ShadowRootMode::Closed => "closed",
+                };
+                let _ = writeln!(f, "{buffer}#shadow-root ({mode})");
+            }
         }
 
         if prefix.len() > 40 {
@@ -692,8 +841,11 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
             buffer.push_str("│  ");
         }
 
-        let len = node.children.len();
-        for (i, child_id) in node.children.iter().enumerate() {
+        // A shadow root is printed as the first child of its host, like in devtools
+        let shadow_root = node.get_element_data().and_then(|e| e.shadow_root);
+        let children: Vec<NodeId> = shadow_root.into_iter().chain(node.children.iter().copied()).collect();
+        let len = children.len();
+        for (i, child_id) in children.iter().enumerate() {
             let Some(child_node) = self.node_by_id(*child_id) else {
                 continue;
             };
@@ -771,3 +923,112 @@ fn internal_visit<C: HasDocument<Document = DocumentImpl<C>>>(
     }
     visitor.document_leave(node);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::HTML_NAMESPACE;
+    use crate::testing::fixture::{parse, Config};
+    use gosub_interface::mutation::Invalidation;
+
+    #[test]
+    fn mutation_journal_records_changes() {
+        let mut doc = parse("<div id=list><p id=first>one</p><p id=last>two</p></div>");
+        let list = doc.node_by_named_id("list").unwrap();
+        let first = doc.node_by_named_id("first").unwrap();
+        let last = doc.node_by_named_id("last").unwrap();
+        let text = doc.children(first)[0];
+
+        // Nothing is recorded until asked for, so parsing leaves the journal empty
+        doc.set_attribute(list, "class", "a");
+        assert!(doc.take_mutations().is_empty());
+
+        doc.record_mutations(true);
+        doc.set_attribute(list, "class", "b");
+        doc.remove_attribute(list, "missing");
+        doc.set_text_value(text, "uno");
+        let item = doc.create_element("p", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        doc.attach(item, list, Some(1));
+        doc.detach(last);
+
+        assert_eq!(
+            doc.take_mutations(),
+            [
+                MutationRecord::Attributes {
+                    target: list,
+                    name: "class".into(),
+                    old_value: Some("a".into()),
+                },
+                MutationRecord::CharacterData {
+                    target: text,
+                    old_value: "one".into(),
+                },
+                MutationRecord::ChildList {
+                    target: list,
+                    added: vec![item],
+                    removed: vec![],
+                    previous_sibling: Some(first),
+                    next_sibling: Some(last),
+                },
+                MutationRecord::ChildList {
+                    target: list,
+                    added: vec![],
+                    removed: vec![last],
+                    previous_sibling: Some(item),
+                    next_sibling: None,
+                },
+            ]
+        );
+        assert!(doc.take_mutations().is_empty(), "taking drains the journal");
+
+        doc.record_mutations(false);
+        doc.set_attribute(list, "class", "c");
+        assert!(doc.take_mutations().is_empty());
+    }
+
+    #[test]
+    fn invalidation_folds_nested_subtrees() {
+        let mut doc = parse("<div id=outer><p id=inner>text</p></div><span id=other>x</span>");
+        let outer = doc.node_by_named_id("outer").unwrap();
+        let inner = doc.node_by_named_id("inner").unwrap();
+        let other = doc.node_by_named_id("other").unwrap();
+        let inner_text = doc.children(inner)[0];
+        let other_text = doc.children(other)[0];
+
+        doc.record_mutations(true);
+        doc.set_attribute(inner, "title", "t");
+        doc.set_attribute(outer, "class", "c");
+        doc.set_text_value(inner_text, "changed");
+        doc.set_text_value(other_text, "y");
+        let detached = doc.create_element("b", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        doc.set_attribute(detached, "class", "ignored");
+
+        let records = doc.take_mutations();
+        let invalidation = Invalidation::from_records::<Config, _>(&doc, &records);
+        // `other` follows `outer`, so sibling combinators may match it differently now
+        assert_eq!(invalidation.restyle, [outer, other]);
+        assert_eq!(invalidation.relayout, [outer, other]);
+    }
+
+    #[test]
+    fn invalidation_covers_following_siblings() {
+        let mut doc = parse("<ul id=list><li id=a></li><li id=b></li><li id=c></li></ul><p id=after></p>");
+        let list = doc.node_by_named_id("list").unwrap();
+        let b = doc.node_by_named_id("b").unwrap();
+        let c = doc.node_by_named_id("c").unwrap();
+        let after = doc.node_by_named_id("after").unwrap();
+
+        doc.record_mutations(true);
+        doc.set_attribute(b, "class", "x");
+        let records = doc.take_mutations();
+        let invalidation = Invalidation::from_records::<Config, _>(&doc, &records);
+        assert_eq!(invalidation.restyle, [b, c]);
+
+        // A child list change can flip `:empty` on the list, which `ul:empty + p` would see
+        let item = doc.create_element("li", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        doc.attach(item, list, None);
+        let records = doc.take_mutations();
+        let invalidation = Invalidation::from_records::<Config, _>(&doc, &records);
+        assert_eq!(invalidation.restyle, [list, after]);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::errors::Error;
+use crate::node::HTML_NAMESPACE;
+use crate::parser::errors::ErrorMode;
+use crate::parser::{Html5Parser, Html5ParserOptions};
+use gosub_interface::config::HasDocument;
+use gosub_interface::document::{Document, DocumentType};
+use gosub_interface::node::NodeType;
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
+use gosub_shared::node::NodeId;
+use gosub_shared::types::Result;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Where `insert_adjacent_html()` puts the parsed nodes, relative to the target node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjacentPosition {
+    /// Before the node itself
+    BeforeBegin,
+    /// Before the first child of the node
+    AfterBegin,
+    /// After the last child of the node
+    BeforeEnd,
+    /// After the node itself
+    AfterEnd,
+}
+
+impl FromStr for AdjacentPosition {
+    type Err = Error;
+
+    /// Parses the position names of `insertAdjacentHTML()`, ASCII case-insensitively
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        [
+            ("beforebegin", Self::BeforeBegin),
+            ("afterbegin", Self::AfterBegin),
+            ("beforeend", Self::BeforeEnd),
+            ("afterend", Self::AfterEnd),
+        ]
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        .map(|(_, position)| position)
+        .ok_or_else(|| Error::Markup(format!("invalid adjacent position '{s}'")))
+    }
+}
+
+/// `innerHTML`/`outerHTML`-style markup APIs: parse an HTML fragment in the context of a node
+/// and splice the resulting nodes into the document.
+pub struct DocumentMarkup<C: HasDocument> {
+    _phantom: std::marker::PhantomData<C>,
+}
+
+impl<C: HasDocument> DocumentMarkup<C> {
+    /// Replaces the children of `node` with the nodes parsed from `markup`, like setting
+    /// `innerHTML`. On a shadow root the markup is parsed in the context of its host. Returns
+    /// the inserted nodes; the old children are detached, not deleted.
+    pub fn set_inner_html(doc: &mut C::Document, node: NodeId, markup: &str) -> Result<Vec<NodeId>> {
+        let context = match doc.node_type(node) {
+            NodeType::ElementNode => Some(node),
+            NodeType::DocumentFragmentNode => doc.shadow_host(node),
+            _ => None,
+        };
+        let nodes = Self::parse_fragment(doc, context, markup)?;
+
+        for child in doc.children(node).to_vec() {
+            doc.detach(child);
+        }
+        for &id in &nodes {
+            doc.attach(id, node, None);
+        }
+        Ok(nodes)
+    }
+
+    /// Replaces `node` itself with the nodes parsed from `markup`, like setting `outerHTML`.
+    /// Fails when `node` has no parent or is a child of the document. Returns the inserted nodes;
+    /// `node` is detached, not deleted.
+    pub fn set_outer_html(doc: &mut C::Document, node: NodeId, markup: &str) -> Result<Vec<NodeId>> {
+        let (parent, index) = Self::position_in_parent(doc, node)?;
+        let context = (doc.node_type(parent) == NodeType::ElementNode).then_some(parent);
+        let nodes = Self::parse_fragment(doc, context, markup)?;
+
+        for (offset, &id) in nodes.iter().enumerate() {
+            doc.attach(id, parent, Some(index + offset));
+        }
+        doc.detach(node);
+        Ok(nodes)
+    }
+
+    /// Inserts the nodes parsed from `markup` at `position` relative to `node`, like
+    /// `insertAdjacentHTML()`. Positions outside `node` fail when it has no parent or is a child
+    /// of the document. Returns the inserted nodes.
+    pub fn insert_adjacent_html(
+        doc: &mut C::Document,
+        node: NodeId,
+        position: AdjacentPosition,
+        markup: &str,
+    ) -> Result<Vec<NodeId>> {
+        let (context, parent, index) = match position {
+            AdjacentPosition::BeforeBegin | AdjacentPosition::AfterEnd => {
+                let (parent, index) = Self::position_in_parent(doc, node)?;
+                let index = if position == AdjacentPosition::AfterEnd {
+                    index + 1
+                } else {
+                    index
+                };
+                (parent, parent, index)
+            }
+            AdjacentPosition::AfterBegin => (node, node, 0),
+            AdjacentPosition::BeforeEnd => (node, node, doc.children(node).len()),
+        };
+
+        // An `<html>` context would parse the markup as a whole document body; use `<body>`
+        let context = (doc.node_type(context) == NodeType::ElementNode
+            && !(doc.tag_name(context) == Some("html") && doc.namespace(context) == Some(HTML_NAMESPACE)))
+        .then_some(context);
+        let nodes = Self::parse_fragment(doc, context, markup)?;
+
+        for (offset, &id) in nodes.iter().enumerate() {
+            doc.attach(id, parent, Some(index + offset));
+        }
+        Ok(nodes)
+    }
+
+    /// Parses `markup` as the children of the `context` element (a `<body>` when `None`) and
+    /// returns the resulting nodes, created in `doc` but not attached to it.
+    ///
+    /// The fragment is parsed into a scratch document with the quirks mode of `doc` and then
+    /// copied over, `<template>` contents included. Declarative shadow roots are not attached and
+    /// scripts never run, as with `innerHTML`.
+    pub fn parse_fragment(doc: &mut C::Document, context: Option<NodeId>, markup: &str) -> Result<Vec<NodeId>> {
+        let mut scratch = C::Document::new(DocumentType::HTML, doc.url());
+        scratch.set_quirks_mode(doc.quirks_mode());
+
+        let context_id = match context {
+            Some(context) => scratch.create_element(
+                doc.tag_name(context).unwrap_or("body"),
+                doc.namespace(context),
+                doc.attributes(context).cloned().unwrap_or_default(),
+                Location::default(),
+            ),
+            None => scratch.create_element("body", Some(HTML_NAMESPACE), HashMap::new(), Location::default()),
+        };
+
+        let options = Html5ParserOptions {
+            error_mode: ErrorMode::Ignore,
+            allow_declarative_shadow_roots: false,
+            ..Default::default()
+        };
+        let mut stream = ByteStream::from_str(markup, Encoding::UTF8);
+        Html5Parser::<C>::parse_fragment(
+            &mut stream,
+            &mut scratch,
+            context_id,
+            Some(options),
+            Location::default(),
+        )?;
+
+        // The fragment parser puts the parsed nodes under an `<html>` element of the scratch root
+        let Some(&html_id) = scratch.children(scratch.root()).first() else {
+            return Ok(Vec::new());
+        };
+        Ok(scratch
+            .children(html_id)
+            .iter()
+            .filter_map(|&child| Self::import_node(doc, &scratch, child))
+            .collect())
+    }
+
+    /// Copies `id` and its subtree from `source` into `doc`
+    fn import_node(doc: &mut C::Document, source: &C::Document, id: NodeId) -> Option<NodeId> {
+        let new_id = match source.node_type(id) {
+            NodeType::ElementNode => doc.create_element(
+                source.tag_name(id)?,
+                source.namespace(id),
+                source.attributes(id).cloned().unwrap_or_default(),
+                Location::default(),
+            ),
+            NodeType::TextNode => doc.create_text(source.text_value(id)?, Location::default()),
+            NodeType::CommentNode => doc.create_comment(source.comment_value(id)?, Location::default()),
+            // Fragment parsing never produces other node types
+            _ => return None,
+        };
+
+        for &child in source.children(id) {
+            if let Some(child_id) = Self::import_node(doc, source, child) {
+                doc.attach(child_id, new_id, None);
+            }
+        }
+        if source.template_contents(id).is_some() {
+            doc.set_template_contents(new_id, new_id);
+        }
+        Some(new_id)
+    }
+
+    /// The parent of `node` and its index among the parent's children. Fails for detached nodes
+    /// and children of the document, which cannot take markup siblings.
+    fn position_in_parent(doc: &C::Document, node: NodeId) -> Result<(NodeId, usize)> {
+        let parent = doc
+            .parent(node)
+            .ok_or_else(|| Error::Markup("node has no parent".to_owned()))?;
+        if doc.node_type(parent) == NodeType::DocumentNode {
+            return Err(Error::Markup("cannot insert markup next to a child of the document".to_owned()).into());
+        }
+        let index = doc
+            .children(parent)
+            .iter()
+            .position(|&child| child == node)
+            .ok_or_else(|| Error::Markup("node not found among its parent's children".to_owned()))?;
+        Ok((parent, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::serializer::HtmlSerializer;
+    use crate::testing::fixture::{parse, Config};
+
+    type Markup = DocumentMarkup<Config>;
+
+    fn inner(doc: &DocumentImpl<Config>, id: NodeId) -> String {
+        HtmlSerializer::default().serialize_children::<Config>(doc, id)
+    }
+
+    #[test]
+    fn set_inner_html_parses_in_context() {
+        let mut doc = parse("<table><tbody id=rows><tr><td>old</td></tr></tbody></table>");
+        let rows = doc.node_by_named_id("rows").unwrap();
+
+        // Table rows only parse as rows in a table context
+        let nodes = Markup::set_inner_html(&mut doc, rows, "<tr><td>a</td></tr><tr><td>b</td></tr>").unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.iter().all(|&id| doc.tag_name(id) == Some("tr")));
+        assert_eq!(doc.children(rows), nodes.as_slice());
+        assert_eq!(inner(&doc, rows), "<tr><td>a</td></tr><tr><td>b</td></tr>");
+    }
+
+    #[test]
+    fn set_inner_html_keeps_template_contents() {
+        let mut doc = parse("<div id=target></div>");
+        let target = doc.node_by_named_id("target").unwrap();
+
+        let nodes = Markup::set_inner_html(&mut doc, target, "<template><p>inert</p></template>").unwrap();
+        let template = nodes[0];
+        assert_eq!(doc.template_contents(template), Some(template));
+        assert_eq!(doc.tag_name(doc.children(template)[0]), Some("p"));
+    }
+
+    #[test]
+    fn set_outer_html_replaces_the_node() {
+        let mut doc = parse("<div id=parent><p id=a>a</p><p id=b>b</p><p id=c>c</p></div>");
+        let parent = doc.node_by_named_id("parent").unwrap();
+        let b = doc.node_by_named_id("b").unwrap();
+
+        Markup::set_outer_html(&mut doc, b, "<span>x</span>text").unwrap();
+        assert_eq!(
+            inner(&doc, parent),
+            "<p id=\"a\">a</p><span>x</span>text<p id=\"c\">c</p>"
+        );
+        assert_eq!(doc.parent(b), None);
+
+        let html = doc.children(doc.root())[0];
+        assert!(Markup::set_outer_html(&mut doc, html, "<p></p>").is_err());
+    }
+
+    #[test]
+    fn insert_adjacent_html_positions() {
+        let mut doc = parse("<div id=parent><p id=target>t</p></div>");
+        let parent = doc.node_by_named_id("parent").unwrap();
+        let target = doc.node_by_named_id("target").unwrap();
+
+        for (position, markup) in [
+            ("beforeBegin", "<i>1</i>"),
+            ("afterbegin", "<i>2</i>"),
+            ("beforeend", "<i>3</i>"),
+            ("AFTEREND", "<i>4</i>"),
+        ] {
+            let position = position.parse::<AdjacentPosition>().unwrap();
+            Markup::insert_adjacent_html(&mut doc, target, position, markup).unwrap();
+        }
+        assert_eq!(
+            inner(&doc, parent),
+            "<i>1</i><p id=\"target\"><i>2</i>t<i>3</i></p><i>4</i>"
+        );
+        assert!("middle".parse::<AdjacentPosition>().is_err());
+    }
+}
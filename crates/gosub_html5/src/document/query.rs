@@ -2,8 +2,10 @@ use crate::document::document_impl::TreeIterator;
 use crate::errors::Error;
 use crate::parser::query::{Condition, Query, SearchType};
 use gosub_interface::config::HasDocument;
+use gosub_interface::css3::CssSystem;
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
+use gosub_shared::errors::CssResult;
 use gosub_shared::node::NodeId;
 
 pub struct DocumentQuery<C: HasDocument> {
@@ -42,6 +44,21 @@ impl<C: HasDocument> DocumentQuery<C> {
         Ok(found_ids)
     }
 
+    /// Returns the first element below `root` matching the CSS selector list `selector`, like
+    /// `querySelector()`. Pass `NodeId::root()` to search the whole document.
+    ///
+    /// The selector is parsed and matched by the document's CSS system, the same matcher the
+    /// cascade uses, so combinators, attribute operators and pseudo-classes behave identically.
+    pub fn query_selector(doc: &C::Document, root: NodeId, selector: &str) -> CssResult<Option<NodeId>> {
+        C::CssSystem::query_selector::<C>(doc, root, selector)
+    }
+
+    /// Returns every element below `root` matching the CSS selector list `selector`, in tree
+    /// order, like `querySelectorAll()`. `root` itself is never included.
+    pub fn query_selector_all(doc: &C::Document, root: NodeId, selector: &str) -> CssResult<Vec<NodeId>> {
+        C::CssSystem::query_selector_all::<C>(doc, root, selector)
+    }
+
     /// Check if a given node's children contain a certain tag name
     pub fn contains_child_tag(doc: &C::Document, node_id: NodeId, tag: &str) -> bool {
         for child_id in doc.children(node_id).to_vec() {
@@ -79,3 +96,74 @@ impl<C: HasDocument> DocumentQuery<C> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DocumentQuery;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::testing::fixture::{parse, Config};
+    use gosub_interface::document::Document;
+    use gosub_shared::node::NodeId;
+
+    fn ids(doc: &DocumentImpl<Config>, nodes: &[NodeId]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|&id| doc.attribute(id, "id").unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn query_selector_all_uses_the_css_matcher() {
+        let doc = parse(
+            r#"<ul id="list"><li id="a" class="x" data-k="one two"></li><li id="b"></li>
+            <li id="c" class="x"><span id="d" lang="en-GB"></span></li></ul><p id="e"></p>"#,
+        );
+
+        let all = |selector: &str| {
+            let found = DocumentQuery::<Config>::query_selector_all(&doc, NodeId::root(), selector).unwrap();
+            ids(&doc, &found)
+        };
+        assert_eq!(all("ul > li.x"), vec!["a", "c"]);
+        assert_eq!(all("ul li span"), vec!["d"]);
+        assert_eq!(all("[data-k~=two], [lang|=en]"), vec!["a", "d"]);
+        assert_eq!(all("li:first-child, li:last-child"), vec!["a", "c"]);
+        assert!(all("table").is_empty());
+    }
+
+    #[test]
+    fn attribute_selectors_compare_values() {
+        let doc = parse(
+            r#"<p id="a" title="Hello"></p><p id="b" title="hello"></p><p id="c" title="x"></p>
+            <p id="d" class="xy x"></p><p id="e" class="xy"></p><p id="f" class="a x b"></p>"#,
+        );
+
+        let all = |selector: &str| {
+            let found = DocumentQuery::<Config>::query_selector_all(&doc, NodeId::root(), selector).unwrap();
+            ids(&doc, &found)
+        };
+        // The `i` flag folds the case of the value, not of the attribute name.
+        assert_eq!(all(r#"[title="HELLO" i]"#), vec!["a", "b"]);
+        assert_eq!(all(r#"[title="Hello"]"#), vec!["a"]);
+        // `~=` looks for the word in the element's value, not the other way around.
+        assert_eq!(all("[class~=x]"), vec!["d", "f"]);
+        assert!(all("[class~=\"a x\"]").is_empty());
+    }
+
+    #[test]
+    fn query_selector_scopes_to_the_node() {
+        let doc = parse(r#"<div id="outer"><p id="a"></p><div id="inner"><p id="b"></p></div></div>"#);
+        let inner = doc.node_by_named_id("inner").unwrap();
+
+        let first = DocumentQuery::<Config>::query_selector(&doc, NodeId::root(), "p").unwrap();
+        assert_eq!(first, doc.node_by_named_id("a"));
+
+        let scoped = DocumentQuery::<Config>::query_selector_all(&doc, inner, "div p").unwrap();
+        assert_eq!(ids(&doc, &scoped), vec!["b"]);
+        assert_eq!(
+            DocumentQuery::<Config>::query_selector(&doc, inner, "div").unwrap(),
+            None
+        );
+
+        assert!(DocumentQuery::<Config>::query_selector(&doc, NodeId::root(), "p {").is_err());
+    }
+}
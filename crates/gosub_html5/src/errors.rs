@@ -24,4 +24,7 @@ pub enum Error {
 
     #[error("query: generic error: {0}")]
     Query(String),
+
+    #[error("markup error: {0}")]
+    Markup(String),
 }
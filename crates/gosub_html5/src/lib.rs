@@ -10,6 +10,8 @@ pub mod dom;
 pub mod errors;
 pub mod node;
 pub mod parser;
+pub mod sanitizer;
+pub mod serializer;
 // Test-fixture harness for the WHATWG html5lib test suites; panicking on a
 // malformed fixture is the desired behavior there, as in any test code.
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
@@ -17,6 +19,7 @@ pub mod testing;
 pub mod tokenizer;
 #[allow(dead_code)]
 pub mod writer;
+pub mod xml;
 
 /// Parses the given HTML string and returns a handle to the resulting DOM tree.
 ///
@@ -2,4 +2,5 @@ pub mod comment;
 pub mod doctype;
 pub mod document;
 pub mod element;
+pub mod shadow_root;
 pub mod text;
@@ -127,6 +127,8 @@ pub struct ElementData {
     pub force_async: bool,
     /// Template contents: NodeId of the fragment root in the same arena (template elements only)
     pub template_contents: Option<NodeId>,
+    /// Shadow root attached to this element, if it is a shadow host
+    pub shadow_root: Option<NodeId>,
 }
 
 impl Debug for ElementData {
@@ -149,11 +151,12 @@ impl ElementData {
         Self {
             node_id: None,
             name: name.into(),
-            namespace: Some(namespace.unwrap_or(HTML_NAMESPACE).into()),
+            namespace: namespace.map(Into::into),
             attributes,
             class_list: classlist,
             force_async: false,
             template_contents: None,
+            shadow_root: None,
         }
     }
 
@@ -0,0 +1,25 @@
+use gosub_interface::node::ShadowRootMode;
+use gosub_shared::node::NodeId;
+
+#[derive(Debug, PartialEq, Clone)]
+/// Data structure for shadow root nodes
+pub struct ShadowRootData {
+    /// Element this shadow root is attached to
+    pub host: NodeId,
+    pub mode: ShadowRootMode,
+}
+
+impl ShadowRootData {
+    #[must_use]
+    pub(crate) fn new(host: NodeId, mode: ShadowRootMode) -> Self {
+        Self { host, mode }
+    }
+
+    pub fn host(&self) -> NodeId {
+        self.host
+    }
+
+    pub fn mode(&self) -> ShadowRootMode {
+        self.mode
+    }
+}
@@ -2,8 +2,9 @@ use crate::node::data::comment::CommentData;
 use crate::node::data::doctype::DocTypeData;
 use crate::node::data::document::DocumentData;
 use crate::node::data::element::ElementData;
+use crate::node::data::shadow_root::ShadowRootData;
 use crate::node::data::text::TextData;
-use gosub_interface::node::{NodeType, QuirksMode};
+use gosub_interface::node::{NodeType, QuirksMode, ShadowRootMode};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::node::NodeId;
 use std::collections::HashMap;
@@ -16,6 +17,7 @@ pub enum NodeDataTypeInternal {
     Text(TextData),
     Comment(CommentData),
     Element(ElementData),
+    ShadowRoot(ShadowRootData),
 }
 
 /// A DOM node stored in the arena
@@ -106,13 +108,26 @@ impl NodeImpl {
         Self::new(location, NodeDataTypeInternal::Text(TextData::with_value(value)))
     }
 
-    /// Shallow clone: same data, no tree links, not registered
+    #[must_use]
+    pub fn new_shadow_root(location: Location, host: NodeId, mode: ShadowRootMode) -> Self {
+        Self::new(
+            location,
+            NodeDataTypeInternal::ShadowRoot(ShadowRootData::new(host, mode)),
+        )
+    }
+
+    /// Shallow clone: same data, no tree links, not registered. A cloned element does not share
+    /// the shadow root of the original.
     pub fn new_from_node(org_node: &Self) -> Self {
+        let mut data = org_node.data.clone();
+        if let NodeDataTypeInternal::Element(ref mut element) = data {
+            element.shadow_root = None;
+        }
         Self {
             id: NodeId::default(),
             parent: None,
             children: Vec::new(),
-            data: org_node.data.clone(),
+            data,
             registered: false,
             location: org_node.location,
         }
@@ -161,6 +176,7 @@ impl NodeImpl {
             NodeDataTypeInternal::Text(_) => NodeType::TextNode,
             NodeDataTypeInternal::Comment(_) => NodeType::CommentNode,
             NodeDataTypeInternal::Element(_) => NodeType::ElementNode,
+            NodeDataTypeInternal::ShadowRoot(_) => NodeType::DocumentFragmentNode,
         }
     }
 
@@ -214,6 +230,13 @@ impl NodeImpl {
         None
     }
 
+    pub fn get_shadow_root_data(&self) -> Option<&ShadowRootData> {
+        if let NodeDataTypeInternal::ShadowRoot(data) = &self.data {
+            return Some(data);
+        }
+        None
+    }
+
     pub fn remove(&mut self, node_id: NodeId) {
         self.children.retain(|x| x != &node_id);
     }
@@ -10,7 +10,7 @@ use crate::node::{HTML_NAMESPACE, MATHML_NAMESPACE, SVG_NAMESPACE};
 use crate::parser::attr_replacements::{
     MATHML_ADJUSTMENTS, SVG_ADJUSTMENTS_ATTRIBUTES, SVG_ADJUSTMENTS_TAGS, XML_ADJUSTMENTS,
 };
-use crate::parser::errors::{ErrorLogger, ParserError};
+use crate::parser::errors::{ErrorLogger, ErrorMode, ParserError};
 use crate::parser::helper::{
     is_html_integration_point, is_mathml_integration_point, is_special, matches_tag_and_attrs_without_order,
 };
@@ -23,12 +23,12 @@ use gosub_interface::css3::{CssOrigin, CssSystem};
 use gosub_interface::document::{Document, DocumentType};
 use gosub_interface::node::NodeType;
 
-use gosub_interface::html5::ParserOptions;
-use gosub_interface::node::QuirksMode;
+use gosub_interface::html5::{DocumentWrite, ParserOptions, ParserScript, ScriptHost, ScriptKind};
+use gosub_interface::node::{QuirksMode, ShadowRootMode};
 use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
 use gosub_shared::config::{Context, ParserConfig};
 use gosub_shared::node::NodeId;
-use gosub_shared::types::{ParseError, Result};
+use gosub_shared::types::{Error, ParseError, Result, Severity};
 use gosub_shared::{timing_start, timing_stop};
 use log::warn;
 use url::Url;
@@ -109,14 +109,20 @@ impl ActiveElement {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct Html5ParserOptions {
     pub scripting_enabled: bool,
+    /// Whether parse errors are ignored, collected, or abort the parse
+    pub error_mode: ErrorMode,
+    /// Whether `<template shadowrootmode>` attaches a shadow root to its parent element
+    pub allow_declarative_shadow_roots: bool,
 }
 
 impl ParserOptions for Html5ParserOptions {
     fn new(scripting: bool) -> Self {
         Self {
             scripting_enabled: scripting,
+            ..Self::default()
         }
     }
 }
@@ -125,6 +131,8 @@ impl Default for Html5ParserOptions {
     fn default() -> Self {
         Self {
             scripting_enabled: true,
+            error_mode: ErrorMode::default(),
+            allow_declarative_shadow_roots: true,
         }
     }
 }
@@ -149,6 +157,9 @@ pub struct Html5Parser<'tokens, C: HasDocument> {
     /// `<image>` -> `<img>` rewrite). Signals that the replacement, not the token moved
     /// out for matching, must survive into a reprocess. See `process_html_content`.
     current_token_rewritten: bool,
+    /// Error details of the current token, captured when it is fetched since the token itself is
+    /// moved out of `current_token` while it is processed
+    token_error: TokenErrorContext,
     /// Stack of open elements
     open_elements: Vec<NodeId>,
     /// Current head element
@@ -157,6 +168,8 @@ pub struct Html5Parser<'tokens, C: HasDocument> {
     form_element: Option<NodeId>,
     /// If true, scripting is enabled
     scripting_enabled: bool,
+    /// If true, `<template shadowrootmode>` attaches a declarative shadow root
+    allow_declarative_shadow_roots: bool,
     /// if true, we can insert a frameset
     frameset_ok: bool,
     /// Foster parenting flag
@@ -179,8 +192,8 @@ pub struct Html5Parser<'tokens, C: HasDocument> {
     script_nesting_level: u32,
     /// If true, the parser is paused
     parser_pause_flag: bool,
-    /// Keeps the position of where any `document.write()` should be inserted when running a script
-    insertion_point: Option<usize>,
+    /// Host that runs the parser-inserted scripts. Without one, scripts are parsed but never run.
+    script_host: Option<&'tokens mut dyn ScriptHost<C>>,
     /// Ignore when next token is LF
     ignore_lf: bool,
     /// When true, the parser is finished and should not consume more tokens (there aren't any)
@@ -207,6 +220,139 @@ impl<C: HasDocument> gosub_interface::html5::Html5Parser<C> for Html5Parser<'_,
     }
 }
 
+/// What tree construction errors need to know about the token being processed
+#[derive(Clone, Copy, Default)]
+struct TokenErrorContext {
+    /// Code reported for errors triggered by this token. Tree construction errors have no codes
+    /// of their own in the spec, so this is a generic code picked by the kind of token.
+    code: Option<ParserError>,
+    /// Where the token ends in the input
+    end: Location,
+    /// True for a start tag with its self-closing flag set
+    self_closing: bool,
+}
+
+impl TokenErrorContext {
+    fn new(token: &Token, end: Location) -> Self {
+        let code = match token {
+            Token::DocType { .. } => ParserError::UnexpectedDoctype,
+            Token::StartTag { .. } => ParserError::UnexpectedStartTag,
+            Token::EndTag { .. } => ParserError::UnexpectedEndTag,
+            Token::Comment { .. } => ParserError::UnexpectedComment,
+            Token::Text { text, .. } if text.contains('\0') => ParserError::UnexpectedNullCharacter,
+            Token::Text { .. } => ParserError::UnexpectedCharacter,
+            Token::Eof { .. } => ParserError::ExpectedClosingTagButGotEof,
+        };
+        Self {
+            code: Some(code),
+            end,
+            self_closing: matches!(
+                token,
+                Token::StartTag {
+                    is_self_closing: true,
+                    ..
+                }
+            ),
+        }
+    }
+}
+
+/// `document.write()` target handed to the script host: markup is inserted into the input stream
+/// at the parser's insertion point, and the insertion point moves past it so that consecutive
+/// writes keep their order.
+struct InsertionPoint<'s> {
+    stream: &'s mut ByteStream,
+    position: usize,
+}
+
+impl DocumentWrite for InsertionPoint<'_> {
+    fn write(&mut self, markup: &str) {
+        self.position = self.stream.insert_str(self.position, markup);
+    }
+}
+
+/// Whether an element can host a shadow root: a custom element or one of the elements listed in
+/// https://dom.spec.whatwg.org/#valid-shadow-host-name
+fn is_valid_shadow_host<C: HasDocument>(doc: &C::Document, id: NodeId) -> bool {
+    const SHADOW_HOST_ELEMENTS: [&str; 18] = [
+        "article",
+        "aside",
+        "blockquote",
+        "body",
+        "div",
+        "footer",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "header",
+        "main",
+        "nav",
+        "p",
+        "section",
+        "span",
+    ];
+
+    if doc.node_type(id) != NodeType::ElementNode || doc.namespace(id) != Some(HTML_NAMESPACE) {
+        return false;
+    }
+    doc.tag_name(id)
+        .is_some_and(|name| name.contains('-') || SHADOW_HOST_ELEMENTS.contains(&name))
+}
+
+/// Script type as determined by the `type` and `language` attributes, or `None` when the script
+/// should not be run at all (a data block or an unknown language).
+fn script_type(type_attr: Option<&str>, language: Option<&str>) -> Option<ScriptType> {
+    let type_attr = match (type_attr, language) {
+        (Some(value), _) => value.trim(),
+        (None, Some(language)) if !language.is_empty() => return is_javascript_mime(&format!("text/{language}")),
+        (None, _) => return Some(ScriptType::Classic),
+    };
+
+    if type_attr.is_empty() {
+        return Some(ScriptType::Classic);
+    }
+    if type_attr.eq_ignore_ascii_case("module") {
+        return Some(ScriptType::Module);
+    }
+    is_javascript_mime(type_attr)
+}
+
+/// https://mimesniff.spec.whatwg.org/#javascript-mime-type-essence-match
+fn is_javascript_mime(mime: &str) -> Option<ScriptType> {
+    const JAVASCRIPT_MIME_TYPES: [&str; 16] = [
+        "application/ecmascript",
+        "application/javascript",
+        "application/x-ecmascript",
+        "application/x-javascript",
+        "text/ecmascript",
+        "text/javascript",
+        "text/javascript1.0",
+        "text/javascript1.1",
+        "text/javascript1.2",
+        "text/javascript1.3",
+        "text/javascript1.4",
+        "text/javascript1.5",
+        "text/jscript",
+        "text/livescript",
+        "text/x-ecmascript",
+        "text/x-javascript",
+    ];
+
+    JAVASCRIPT_MIME_TYPES
+        .iter()
+        .any(|js| js.eq_ignore_ascii_case(mime))
+        .then_some(ScriptType::Classic)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScriptType {
+    Classic,
+    Module,
+}
+
 /// Defines the scopes for `in_scope()`
 #[derive(Clone, Copy)]
 enum Scope {
@@ -289,10 +435,12 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             },
             reprocess_token: false,
             current_token_rewritten: false,
+            token_error: TokenErrorContext::default(),
             open_elements: Vec::new(),
             head_element: None,
             form_element: None,
             scripting_enabled: options.unwrap_or_default().scripting_enabled,
+            allow_declarative_shadow_roots: options.unwrap_or_default().allow_declarative_shadow_roots,
             frameset_ok: true,
             foster_parenting: false,
             script_already_started: false,
@@ -304,7 +452,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             error_logger,
             script_nesting_level: 0,
             parser_pause_flag: false,
-            insertion_point: None,
+            script_host: None,
             ignore_lf: false,
             parser_finished: false,
             context_node_id: None,
@@ -328,10 +476,12 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             },
             reprocess_token: false,
             current_token_rewritten: false,
+            token_error: TokenErrorContext::default(),
             open_elements: Vec::new(),
             head_element: None,
             form_element: None,
             scripting_enabled: true,
+            allow_declarative_shadow_roots: true,
             frameset_ok: true,
             foster_parenting: false,
             script_already_started: false,
@@ -343,7 +493,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             error_logger,
             script_nesting_level: 0,
             parser_pause_flag: false,
-            insertion_point: None,
+            script_host: None,
             ignore_lf: false,
             parser_finished: false,
             context_node_id: None,
@@ -368,7 +518,8 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         // Obsoleted: if doc_weak is none for some reason, it will default to no quirks mode
 
         // 3.
-        let error_logger = Rc::new(RefCell::new(ErrorLogger::new()));
+        let error_mode = options.unwrap_or_default().error_mode;
+        let error_logger = Rc::new(RefCell::new(ErrorLogger::with_mode(error_mode)));
 
         let tokenizer = Tokenizer::new(stream, None, error_logger.clone(), start_location);
         let mut parser = Html5Parser::<C>::init(tokenizer, document, error_logger, options);
@@ -434,9 +585,31 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         stream: &mut ByteStream,
         document: &mut C::Document,
         options: Option<Html5ParserOptions>,
+    ) -> Result<Vec<ParseError>> {
+        Self::parse_document_inner(stream, document, options, None)
+    }
+
+    /// Parses a whole document like `parse_document()`, handing every parser-inserted script to
+    /// `script_host`. Inline and parser-blocking scripts run while the parser is paused at their
+    /// end tag, and anything they `document.write()` is parsed right after that end tag.
+    pub fn parse_document_with_script_host(
+        stream: &mut ByteStream,
+        document: &mut C::Document,
+        options: Option<Html5ParserOptions>,
+        script_host: &mut dyn ScriptHost<C>,
+    ) -> Result<Vec<ParseError>> {
+        Self::parse_document_inner(stream, document, options, Some(script_host))
+    }
+
+    fn parse_document_inner<'b>(
+        stream: &'b mut ByteStream,
+        document: &'b mut C::Document,
+        options: Option<Html5ParserOptions>,
+        script_host: Option<&'b mut dyn ScriptHost<C>>,
     ) -> Result<Vec<ParseError>> {
         // Create a new error logger that will be used in both the tokenizer and the parser
-        let error_logger = Rc::new(RefCell::new(ErrorLogger::new()));
+        let error_mode = options.unwrap_or_default().error_mode;
+        let error_logger = Rc::new(RefCell::new(ErrorLogger::with_mode(error_mode)));
 
         let t_id = match document.url() {
             Some(url) => timing_start!("html5.parse", url.as_str()),
@@ -444,10 +617,17 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         };
         let tokenizer = Tokenizer::new(stream, None, error_logger.clone(), Location::default());
         let mut parser = Html5Parser::<C>::init(tokenizer, document, error_logger, options);
+        parser.script_host = script_host;
 
         let ret = parser.do_parse();
         timing_stop!(t_id);
 
+        if ret.is_ok() {
+            if let Some(host) = parser.script_host.as_deref_mut() {
+                host.parsing_finished(parser.document);
+            }
+        }
+
         ret
     }
 
@@ -463,7 +643,10 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
 
             // If reprocess_token is true, we should process the same token again
             if !self.reprocess_token {
+                self.check_self_closing_acknowledged();
+
                 self.current_token = self.fetch_next_token();
+                self.token_error = TokenErrorContext::new(&self.current_token, self.tokenizer.get_location());
 
                 // If we reprocess a given token, the dispatcher mode should stay the same and
                 // should not be re-evaluated
@@ -484,6 +667,13 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
 
             #[cfg(all(feature = "debug_parser", test))]
             self.display_debug_info();
+
+            let logger = self.error_logger.borrow();
+            if logger.mode() == ErrorMode::FailFast {
+                if let Some(error) = logger.first_error() {
+                    return Err(Error::Parse(format!("{} at {}", error.code, error.location)).into());
+                }
+            }
         }
 
         Ok(self.error_logger.borrow().get_errors())
@@ -666,21 +856,10 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         }
 
         if handle_as_script_endtag {
+            let script_id = current_node_id!(self);
             self.open_elements.pop();
 
-            let old_insertion_point = self.insertion_point;
-            self.insertion_point = Some(self.tokenizer.get_location().offset);
-
-            self.script_nesting_level += 1;
-
-            // @todo: do script stuff
-
-            self.script_nesting_level -= 1;
-            if self.script_nesting_level == 0 {
-                self.parser_pause_flag = false;
-            }
-
-            self.insertion_point = old_insertion_point;
+            self.run_script(script_id);
         }
     }
 
@@ -750,19 +929,19 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                     }
                     Token::StartTag { .. } => {
                         if !self.is_iframesrcdoc() {
-                            self.parse_error(ParserError::ExpectedDocTypeButGotStartTag.as_str());
+                            self.parse_error_code(ParserError::ExpectedDocTypeButGotStartTag);
                         }
                         anything_else = true;
                     }
                     Token::EndTag { .. } => {
                         if !self.is_iframesrcdoc() {
-                            self.parse_error(ParserError::ExpectedDocTypeButGotEndTag.as_str());
+                            self.parse_error_code(ParserError::ExpectedDocTypeButGotEndTag);
                         }
                         anything_else = true;
                     }
                     Token::Text { .. } => {
                         if !self.is_iframesrcdoc() {
-                            self.parse_error(ParserError::ExpectedDocTypeButGotChars.as_str());
+                            self.parse_error_code(ParserError::ExpectedDocTypeButGotChars);
                         }
                         anything_else = true;
                     }
@@ -1058,9 +1237,15 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                         #[allow(clippy::unwrap_used)] // PANIC-SAFE: the is_empty() guard above returned
                         let style_text_node_id = *self.document.children(style_node_id).first().unwrap();
 
-                        // Load stylesheet from text node
+                        // Load stylesheet from text node. A style inside a shadow tree only applies
+                        // to that tree.
                         if let Some(stylesheet) = self.load_inline_stylesheet(CssOrigin::Author, style_text_node_id) {
-                            self.document.add_stylesheet(stylesheet);
+                            let root_id = self.document.tree_root(style_node_id);
+                            if self.document.shadow_host(root_id).is_some() {
+                                self.document.add_shadow_stylesheet(root_id, stylesheet);
+                            } else {
+                                self.document.add_stylesheet(stylesheet);
+                            }
                         }
 
                         self.open_elements.pop();
@@ -1068,24 +1253,12 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                     }
                     Token::EndTag { name, .. } if name == "script" => {
                         // @todo: If the active speculative HTML parser is null and the JavaScript execution context stack is empty, then perform a microtask checkpoint.
-                        // let _script_node = current_node_id!(self);
+                        let script_id = current_node_id!(self);
 
                         self.open_elements.pop();
                         self.insertion_mode = self.original_insertion_mode;
 
-                        let old_insertion_point = self.insertion_point;
-                        self.insertion_point = Some(self.tokenizer.get_location().offset);
-
-                        self.script_nesting_level += 1;
-
-                        // do script stuff
-
-                        self.script_nesting_level -= 1;
-                        if self.script_nesting_level == 0 {
-                            self.parser_pause_flag = false;
-                        }
-
-                        self.insertion_point = old_insertion_point;
+                        self.run_script(script_id);
                     }
                     _ => {
                         self.open_elements.pop();
@@ -1755,6 +1928,98 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         self.scripting_enabled = enabled;
     }
 
+    /// Prepares the script element that has just been popped off the stack and hands it to the
+    /// script host, see https://html.spec.whatwg.org/multipage/scripting.html#prepare-the-script-element
+    fn run_script(&mut self, node_id: NodeId) {
+        // Scripts inserted by the fragment parsing algorithm are marked as already started
+        if std::mem::take(&mut self.script_already_started) {
+            return;
+        }
+        if !self.scripting_enabled || self.script_host.is_none() {
+            return;
+        }
+
+        let attribute = |name: &str| self.document.attribute(node_id, name);
+        let Some(script_type) = script_type(attribute("type"), attribute("language")) else {
+            return;
+        };
+        let module = script_type == ScriptType::Module;
+
+        let src = if self.document.namespace(node_id) == Some(SVG_NAMESPACE) {
+            attribute("href").or_else(|| attribute("xlink:href"))
+        } else {
+            attribute("src")
+        };
+        let src = match src {
+            Some(src) => {
+                let resolved = match self.document.url() {
+                    Some(base) => base.join(src.trim()),
+                    None => Url::parse(src.trim()),
+                };
+                match resolved {
+                    Ok(url) => Some(url),
+                    Err(err) => {
+                        warn!("ignoring script with invalid src {src:?}: {err}");
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let is_async = attribute("async").is_some();
+        let is_defer = attribute("defer").is_some();
+        let kind = match (src.is_some(), module) {
+            (_, true) if is_async => ScriptKind::Async,
+            (_, true) => ScriptKind::Defer,
+            (true, false) if is_async => ScriptKind::Async,
+            (true, false) if is_defer => ScriptKind::Defer,
+            (true, false) => ScriptKind::Blocking,
+            (false, false) => ScriptKind::Inline,
+        };
+
+        let text = self
+            .document
+            .children(node_id)
+            .iter()
+            .filter_map(|child| self.document.text_value(*child))
+            .collect::<String>();
+        let script = ParserScript {
+            node_id,
+            kind,
+            text,
+            src,
+            module,
+        };
+
+        let Some(host) = self.script_host.as_deref_mut() else {
+            return;
+        };
+
+        if matches!(kind, ScriptKind::Async | ScriptKind::Defer) {
+            host.schedule(script);
+            return;
+        }
+
+        // The script runs while the parser is paused. Whatever it writes is inserted directly
+        // after the end tag, which is where the tokenizer stopped.
+        let position = self.tokenizer.stream.char_position();
+
+        self.script_nesting_level += 1;
+        self.parser_pause_flag = true;
+
+        let mut writer = InsertionPoint {
+            stream: &mut *self.tokenizer.stream,
+            position,
+        };
+        host.execute(&script, &mut *self.document, &mut writer);
+
+        self.script_nesting_level -= 1;
+        if self.script_nesting_level == 0 {
+            self.parser_pause_flag = false;
+        }
+    }
+
     fn acknowledge_closing_tag(&mut self, is_self_closing: bool) {
         if is_self_closing {
             self.ack_self_closing = true;
@@ -1856,11 +2121,44 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         }
     }
 
-    /// Send a parse error to the error logger
+    /// Send a parse error about the current token to the error logger
     fn parse_error(&self, message: &str) {
-        self.error_logger
-            .borrow_mut()
-            .add_error(self.current_token.get_location(), message);
+        let code = self.token_error.code.unwrap_or(ParserError::UnexpectedCharacter);
+        self.report(code.as_str(), message, Severity::Error);
+    }
+
+    /// Send a parse error with a specific code to the error logger
+    fn parse_error_code(&self, error: ParserError) {
+        self.report(error.as_str(), error.as_str(), Severity::Error);
+    }
+
+    /// Report valid markup that is not supported yet
+    fn parse_warning(&self, message: &str) {
+        self.report("unsupported-feature", message, Severity::Warning);
+    }
+
+    fn report(&self, code: &str, message: &str, severity: Severity) {
+        let location = self.current_token.get_location();
+        self.error_logger.borrow_mut().add(ParseError {
+            code: code.to_string(),
+            message: message.to_string(),
+            location,
+            end: if self.token_error.end.offset > location.offset {
+                self.token_error.end
+            } else {
+                location
+            },
+            severity,
+        });
+    }
+
+    /// A start tag with a self-closing flag that the tree construction stage did not acknowledge
+    /// (because the element is not void) is a parse error.
+    fn check_self_closing_acknowledged(&mut self) {
+        if self.token_error.self_closing && !self.ack_self_closing {
+            self.parse_error_code(ParserError::NonVoidHtmlElementStartTagWithTrailingSolidus);
+        }
+        self.ack_self_closing = false;
     }
 
     /// Create a new node that is not connected or attached to the document arena
@@ -3083,30 +3381,31 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             Token::EndTag { name, .. } if name == "body" || name == "html" || name == "br" => {
                 anything_else = true;
             }
-            Token::StartTag { name, .. } if name == "template" => {
-                let node_id = self.insert_html_element(token);
-
+            Token::StartTag { name, attributes, .. } if name == "template" => {
                 self.active_formatting_elements_push_marker();
                 self.frameset_ok = false;
                 self.insertion_mode = InsertionMode::InTemplate;
                 self.template_insertion_mode.push(InsertionMode::InTemplate);
 
-                // Let adjusted insert location
-                // intended parent
-                // document = indented parent's node document
-
-                // check shadow root != none
-                // or allow declarative shadow roots == true
-                // or adjusted current node is not topmost element in stack open elements
-                // then insert html element for token
-                // else:
-                //
-
-                {
-                    let cn_id = current_node_id!(self);
-                    if self.document.node_type(node_id) == NodeType::ElementNode {
-                        self.document.set_template_contents(node_id, cn_id);
+                if let Some(mode) = self.declarative_shadow_root_mode(attributes) {
+                    // The template is not inserted; its contents become the shadow root of the
+                    // adjusted current node. If that node cannot host one, fall back to a
+                    // regular template.
+                    let host_id = self.get_adjusted_current_node_id();
+                    if is_valid_shadow_host::<C>(self.document, host_id) {
+                        if let Some(root_id) = self.document.attach_shadow_root(host_id, mode) {
+                            let node_id = self.create_node(token, HTML_NAMESPACE);
+                            self.open_elements.push(node_id);
+                            self.document.set_template_contents(node_id, root_id);
+                            return;
+                        }
                     }
+                    self.parse_error("declarative shadow root could not be attached");
+                }
+
+                let node_id = self.insert_html_element(token);
+                if self.document.node_type(node_id) == NodeType::ElementNode {
+                    self.document.set_template_contents(node_id, node_id);
                 }
             }
             Token::EndTag { name, .. } if name == "template" => {
@@ -4042,6 +4341,24 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
         tokens
     }
 
+    /// The shadow root mode a `<template>` start tag declares, if it should attach a declarative
+    /// shadow root: it carries a valid `shadowrootmode`, declarative shadow roots are allowed, and
+    /// the adjusted current node is not the topmost element of the stack of open elements.
+    fn declarative_shadow_root_mode(&self, attributes: &HashMap<String, String>) -> Option<ShadowRootMode> {
+        let mode = match attributes.get("shadowrootmode")?.cow_to_ascii_lowercase().as_ref() {
+            "open" => ShadowRootMode::Open,
+            "closed" => ShadowRootMode::Closed,
+            _ => return None,
+        };
+
+        if !self.allow_declarative_shadow_roots
+            || self.open_elements.first() == Some(&self.get_adjusted_current_node_id())
+        {
+            return None;
+        }
+        Some(mode)
+    }
+
     /// Load an inline stylesheet from the <style>-node (identified by NodeId)
     fn load_inline_stylesheet(
         &self,
@@ -4150,17 +4467,17 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
     fn handle_link_element(&mut self, attributes: HashMap<String, String>) {
         if attributes.contains_key("rel") && attributes.contains_key("itemprop") {
             // cannot have them both
-            self.parse_error("link element cannot have both 'rel' and 'itemprop' attributes");
+            self.parse_warning("link element cannot have both 'rel' and 'itemprop' attributes");
             return;
         }
 
         if attributes.contains_key("itemprop") {
-            self.parse_error("link element with 'itemprop' attribute not supported yet");
+            self.parse_warning("link element with 'itemprop' attribute not supported yet");
             return;
         }
 
         let Some(rel) = attributes.get("rel").cloned() else {
-            self.parse_error("link element without 'rel' attribute not supported yet");
+            self.parse_warning("link element without 'rel' attribute not supported yet");
             return;
         };
 
@@ -4177,7 +4494,9 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
             "stylesheet",
         ];
         if parser_in_body && !body_ok_types.contains(&rel.as_str()) {
-            self.parse_error(format!("link element with rel attribute '{rel}' is not supported in the body").as_str());
+            self.parse_warning(
+                format!("link element with rel attribute '{rel}' is not supported in the body").as_str(),
+            );
             return;
         }
 
@@ -4193,13 +4512,13 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                     Err(_err) => {
                         // Relative URL
                         let Some(base_url) = self.document.url() else {
-                            self.parse_error("link element without base url not supported yet");
+                            self.parse_warning("link element without base url not supported yet");
                             return;
                         };
                         match base_url.join(href) {
                             Ok(url) => url,
                             Err(_) => {
-                                self.parse_error("link element with invalid href url");
+                                self.parse_warning("link element with invalid href url");
                                 return;
                             }
                         }
@@ -4208,11 +4527,11 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                 if let Some(stylesheet) = self.load_external_stylesheet(CssOrigin::Author, css_url) {
                     self.document.add_stylesheet(stylesheet);
                 } else {
-                    self.parse_error("failed to load external stylesheet");
+                    self.parse_warning("failed to load external stylesheet");
                 }
             }
             _ => {
-                self.parse_error(format!("link element with rel attribute '{rel}' is not supported").as_str());
+                self.parse_warning(format!("link element with rel attribute '{rel}' is not supported").as_str());
             }
         }
     }
@@ -4224,19 +4543,9 @@ mod test {
     use crate::document::builder::DocumentBuilderImpl;
     use crate::document::document_impl::DocumentImpl;
     use crate::node::node_impl::NodeDataTypeInternal;
-    use gosub_css3::system::Css3System;
-    use gosub_interface::config::ModuleConfiguration;
+    use crate::testing::fixture::Config;
     use gosub_shared::byte_stream::Encoding;
 
-    #[derive(Clone, Debug, PartialEq)]
-    struct Config;
-
-    impl ModuleConfiguration for Config {
-        type CssSystem = Css3System;
-        type Document = DocumentImpl<Self>;
-        type HtmlParser = Html5Parser<'static, Self>;
-    }
-
     type Parser<'a> = Html5Parser<'a, Config>;
 
     macro_rules! node_create {
@@ -4561,4 +4870,209 @@ mod test {
         assert_eq!(div.id, NodeId::from(4usize));
         assert_eq!(div.get_element_data().unwrap().name(), "div");
     }
+
+    fn parse_with(html: &str, options: Html5ParserOptions) -> Result<Vec<ParseError>> {
+        let mut stream = ByteStream::from_str(html, Encoding::UTF8);
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        Parser::parse_document(&mut stream, &mut doc, Some(options))
+    }
+
+    #[test]
+    fn tree_construction_errors_carry_codes_and_spans() {
+        let errors = parse_with("<!DOCTYPE html></p><div/><br/>", Html5ParserOptions::default()).unwrap();
+
+        // The stray `</p>` spans columns 16 to 20
+        let end_tag = errors.iter().find(|e| e.code == "unexpected-end-tag").unwrap();
+        assert_eq!(end_tag.severity, Severity::Error);
+        assert_eq!((end_tag.location.column, end_tag.end.column), (16, 20));
+        assert!(end_tag.end.offset > end_tag.location.offset);
+
+        // `<div/>` is reported, the void `<br/>` is not
+        let solidus: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == "non-void-html-element-start-tag-with-trailing-solidus")
+            .collect();
+        assert_eq!(solidus.len(), 1);
+    }
+
+    #[test]
+    fn unsupported_markup_is_a_warning() {
+        let errors = parse_with("<!DOCTYPE html><link itemprop=x>", Html5ParserOptions::default()).unwrap();
+        let warning = errors.iter().find(|e| e.severity == Severity::Warning).unwrap();
+        assert_eq!(warning.code, "unsupported-feature");
+    }
+
+    #[test]
+    fn error_modes() {
+        let html = "<p>no doctype";
+
+        let collected = parse_with(html, Html5ParserOptions::default()).unwrap();
+        assert!(collected.iter().any(|e| e.code == "expected-doctype-but-got-start-tag"));
+
+        let ignored = Html5ParserOptions {
+            error_mode: ErrorMode::Ignore,
+            ..Default::default()
+        };
+        assert!(parse_with(html, ignored).unwrap().is_empty());
+
+        let fail_fast = Html5ParserOptions {
+            error_mode: ErrorMode::FailFast,
+            ..Default::default()
+        };
+        assert!(parse_with(html, fail_fast).is_err());
+    }
+
+    /// Records the scripts it is given. Inline scripts of the form `write:<markup>` write their
+    /// markup through `document.write()`.
+    #[derive(Default)]
+    struct RecordingHost {
+        executed: Vec<ParserScript>,
+        scheduled: Vec<ParserScript>,
+        finished: bool,
+    }
+
+    impl ScriptHost<Config> for RecordingHost {
+        fn execute(
+            &mut self,
+            script: &ParserScript,
+            _document: &mut DocumentImpl<Config>,
+            writer: &mut dyn DocumentWrite,
+        ) {
+            if let Some(markup) = script.text.strip_prefix("write:") {
+                writer.write(markup);
+            }
+            self.executed.push(script.clone());
+        }
+
+        fn schedule(&mut self, script: ParserScript) {
+            self.scheduled.push(script);
+        }
+
+        fn parsing_finished(&mut self, _document: &mut DocumentImpl<Config>) {
+            self.finished = true;
+        }
+    }
+
+    fn parse_with_host(html: &str, host: &mut RecordingHost) -> DocumentImpl<Config> {
+        let mut stream = ByteStream::from_str(html, Encoding::UTF8);
+        let url = Url::parse("https://example.com/dir/page.html").unwrap();
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(Some(url));
+        Parser::parse_document_with_script_host(&mut stream, &mut doc, None, host).unwrap();
+        doc
+    }
+
+    #[test]
+    fn document_write_inserts_after_script() {
+        let mut host = RecordingHost::default();
+        let doc = parse_with_host(
+            "<body><script>write:<p id=one>1</p><p id=two>2</p></script><p id=after>3</p>",
+            &mut host,
+        );
+
+        assert_eq!(host.executed.len(), 1);
+        assert_eq!(host.executed[0].kind, ScriptKind::Inline);
+
+        let ids = ["one", "two", "after"].map(|id| doc.node_by_named_id(id).unwrap());
+        let body = doc.parent(ids[0]).unwrap();
+        let positions = ids.map(|id| doc.children(body).iter().position(|child| *child == id).unwrap());
+        assert!(positions[0] < positions[1] && positions[1] < positions[2]);
+        assert!(host.finished);
+    }
+
+    #[test]
+    fn scripts_are_classified() {
+        let mut host = RecordingHost::default();
+        parse_with_host(
+            r#"<script src=blocking.js></script>
+            <script src=/async.js async></script>
+            <script src=defer.js defer async=""></script>
+            <script src=defer.js defer></script>
+            <script type=module>import "x";</script>
+            <script type="text/plain">not javascript</script>
+            <script language=javascript>inline</script>"#,
+            &mut host,
+        );
+
+        let executed: Vec<_> = host.executed.iter().map(|s| s.kind).collect();
+        assert_eq!(executed, [ScriptKind::Blocking, ScriptKind::Inline]);
+        assert_eq!(
+            host.executed[0].src.as_ref().map(Url::as_str),
+            Some("https://example.com/dir/blocking.js")
+        );
+        assert_eq!(host.executed[1].text, "inline");
+
+        let scheduled: Vec<_> = host.scheduled.iter().map(|s| (s.kind, s.module)).collect();
+        assert_eq!(
+            scheduled,
+            [
+                (ScriptKind::Async, false),
+                (ScriptKind::Async, false),
+                (ScriptKind::Defer, false),
+                (ScriptKind::Defer, true),
+            ]
+        );
+        assert_eq!(
+            host.scheduled[0].src.as_ref().map(Url::as_str),
+            Some("https://example.com/async.js")
+        );
+    }
+
+    #[test]
+    fn declarative_shadow_roots() {
+        let html = r#"<!DOCTYPE html><body>
+            <div id=host><template shadowrootmode=open><style>p { color: red }</style><p id=inner>x</p><slot></slot></template><span id=light>y</span></div>
+            <div id=closed><template shadowrootmode=closed><b id=bold></b></template><template shadowrootmode=open><i id=second></i></template></div>
+            <ul id=list><template shadowrootmode=open></template></ul>"#;
+        let mut stream = ByteStream::from_str(html, Encoding::UTF8);
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        let _ = Parser::parse_document(&mut stream, &mut doc, None);
+
+        // The template is replaced by a shadow root holding its contents; light children stay put
+        let host = doc.node_by_named_id("host").unwrap();
+        let root = doc.shadow_root(host).unwrap();
+        assert_eq!(doc.node_type(root), NodeType::DocumentFragmentNode);
+        assert_eq!(doc.shadow_host(root), Some(host));
+        assert_eq!(doc.shadow_root_mode(root), Some(ShadowRootMode::Open));
+        assert_eq!(doc.children(host), [doc.node_by_named_id("light").unwrap()]);
+
+        let inner = doc.node_by_named_id("inner").unwrap();
+        assert_eq!(doc.parent(inner), Some(root));
+        assert_eq!(doc.tree_root(inner), root);
+
+        // The shadow tree's style only applies to that tree
+        assert_eq!(doc.shadow_stylesheets(root).len(), 1);
+        assert!(doc.stylesheets().is_empty());
+
+        // Only the first template attaches; the second one stays a regular template
+        let closed = doc.node_by_named_id("closed").unwrap();
+        let closed_root = doc.shadow_root(closed).unwrap();
+        assert_eq!(doc.shadow_root_mode(closed_root), Some(ShadowRootMode::Closed));
+        assert_eq!(doc.parent(doc.node_by_named_id("bold").unwrap()), Some(closed_root));
+        let template = doc.children(closed)[0];
+        assert_eq!(doc.tag_name(template), Some("template"));
+        assert_eq!(doc.parent(doc.node_by_named_id("second").unwrap()), Some(template));
+
+        // `ul` is not a valid shadow host
+        let list = doc.node_by_named_id("list").unwrap();
+        assert!(doc.shadow_root(list).is_none());
+        assert_eq!(doc.tag_name(doc.children(list)[0]), Some("template"));
+    }
+
+    #[test]
+    fn declarative_shadow_roots_can_be_disabled() {
+        let mut stream = ByteStream::from_str(
+            "<div id=host><template shadowrootmode=open><p></p></template></div>",
+            Encoding::UTF8,
+        );
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        let options = Html5ParserOptions {
+            allow_declarative_shadow_roots: false,
+            ..Default::default()
+        };
+        let _ = Parser::parse_document(&mut stream, &mut doc, Some(options));
+
+        let host = doc.node_by_named_id("host").unwrap();
+        assert!(doc.shadow_root(host).is_none());
+        assert_eq!(doc.tag_name(doc.children(host)[0]), Some("template"));
+    }
 }
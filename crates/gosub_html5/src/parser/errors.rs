@@ -1,7 +1,9 @@
 use gosub_shared::byte_stream::Location;
-use gosub_shared::types::ParseError;
+use gosub_shared::types::{ParseError, Severity};
+use std::fmt::Write;
 
 /// Possible parser error enumerated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ParserError {
     AbruptDoctypePublicIdentifier,
     AbruptDoctypeSystemIdentifier,
@@ -57,6 +59,15 @@ pub enum ParserError {
     ExpectedDocTypeButGotChars,
     ExpectedDocTypeButGotStartTag,
     ExpectedDocTypeButGotEndTag,
+
+    // Tree construction errors. The specification does not name these, so the codes follow
+    // html5lib and are picked by the kind of token that triggered the error.
+    UnexpectedDoctype,
+    UnexpectedStartTag,
+    UnexpectedEndTag,
+    UnexpectedComment,
+    UnexpectedCharacter,
+    ExpectedClosingTagButGotEof,
 }
 
 impl ParserError {
@@ -132,16 +143,36 @@ impl ParserError {
             ParserError::ExpectedDocTypeButGotChars => "expected-doctype-but-got-chars",
             ParserError::ExpectedDocTypeButGotStartTag => "expected-doctype-but-got-start-tag",
             ParserError::ExpectedDocTypeButGotEndTag => "expected-doctype-but-got-end-tag",
+
+            ParserError::UnexpectedDoctype => "unexpected-doctype",
+            ParserError::UnexpectedStartTag => "unexpected-start-tag",
+            ParserError::UnexpectedEndTag => "unexpected-end-tag",
+            ParserError::UnexpectedComment => "unexpected-comment",
+            ParserError::UnexpectedCharacter => "unexpected-character",
+            ParserError::ExpectedClosingTagButGotEof => "expected-closing-tag-but-got-eof",
         }
     }
 }
 
+/// What the parser does with the parse errors it encounters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Don't record errors at all
+    Ignore,
+    /// Record every error and keep parsing, for linting and conformance checking
+    #[default]
+    Collect,
+    /// Stop parsing at the first error with [`Severity::Error`]
+    FailFast,
+}
+
 #[derive(Clone)]
 pub struct ErrorLogger {
     /// List of errors that occurred during parsing
     errors: Vec<ParseError>,
     /// Messages already recorded per location, for O(1) duplicate detection
     seen: std::collections::HashMap<Location, std::collections::HashSet<String>>,
+    mode: ErrorMode,
 }
 
 impl Default for ErrorLogger {
@@ -151,38 +182,119 @@ impl Default for ErrorLogger {
 }
 
 impl ErrorLogger {
-    /// Creates a new error logger
+    /// Creates a new error logger that collects all errors
     #[must_use]
     pub fn new() -> Self {
+        Self::with_mode(ErrorMode::Collect)
+    }
+
+    #[must_use]
+    pub fn with_mode(mode: ErrorMode) -> Self {
         Self {
             errors: Vec::new(),
             seen: std::collections::HashMap::new(),
+            mode,
         }
     }
 
+    #[must_use]
+    pub fn mode(&self) -> ErrorMode {
+        self.mode
+    }
+
     /// Returns a cloned instance of the errors
     #[must_use]
     pub fn get_errors(&self) -> Vec<ParseError> {
         self.errors.clone()
     }
 
+    /// Returns the first recorded error with [`Severity::Error`], if any
+    #[must_use]
+    pub fn first_error(&self) -> Option<&ParseError> {
+        self.errors.iter().find(|e| e.severity == Severity::Error)
+    }
+
+    /// Adds a new error at a single position, using `code` as its message
+    pub fn add_error(&mut self, location: Location, code: &str) {
+        self.add(ParseError {
+            code: code.to_string(),
+            message: code.to_string(),
+            location,
+            end: location,
+            severity: Severity::Error,
+        });
+    }
+
     /// Adds a new error to the error logger
-    pub fn add_error(&mut self, location: Location, message: &str) {
+    pub fn add(&mut self, error: ParseError) {
+        if self.mode == ErrorMode::Ignore {
+            return;
+        }
+
         // Check if the error already exists, if so, don't add it again
-        if let Some(messages) = self.seen.get(&location) {
-            if messages.contains(message) {
+        if let Some(messages) = self.seen.get(&error.location) {
+            if messages.contains(&error.message) {
                 return;
             }
         }
-        self.seen.entry(location).or_default().insert(message.to_string());
+        self.seen
+            .entry(error.location)
+            .or_default()
+            .insert(error.message.clone());
 
-        self.errors.push(ParseError {
-            message: message.to_string(),
-            location,
-        });
+        self.errors.push(error);
     }
 }
 
+/// Renders `error` the way compilers print diagnostics: a header with the severity and code, the
+/// position within `origin`, and the offending source line with the span underlined.
+///
+/// ```text
+/// error[unexpected-null-character]: null character not allowed in in body insertion mode
+///  --> index.html:3:8
+///   |
+/// 3 |   <p>a\0b</p>
+///   |       ^
+/// ```
+#[must_use]
+pub fn render_diagnostic(error: &ParseError, source: &str, origin: &str) -> String {
+    let mut out = String::new();
+    let _ = write!(out, "{}[{}]", error.severity.as_str(), error.code);
+    if error.message != error.code {
+        let _ = write!(out, ": {}", error.message);
+    }
+    let _ = writeln!(out);
+
+    let line_no = error.location.line.to_string();
+    let gutter = " ".repeat(line_no.len());
+    let _ = writeln!(
+        out,
+        "{gutter}--> {origin}:{}:{}",
+        error.location.line, error.location.column
+    );
+
+    let Some(line) = source.lines().nth(error.location.line.saturating_sub(1)) else {
+        return out;
+    };
+    let start = error.location.column.saturating_sub(1);
+    // Underline up to the end of the span when it ends on the same line, else a single caret
+    let width = if error.end.line == error.location.line && error.end.column > error.location.column {
+        error.end.column - error.location.column
+    } else {
+        1
+    };
+    let indent: String = line
+        .chars()
+        .take(start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let _ = writeln!(out, "{gutter} |");
+    let _ = writeln!(out, "{line_no} | {line}");
+    let _ = writeln!(out, "{gutter} | {indent}{}", "^".repeat(width));
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,4 +367,28 @@ mod tests {
 
         assert_eq!(logger.get_errors().len(), 10);
     }
+
+    #[test]
+    fn ignore_mode_records_nothing() {
+        let mut logger = ErrorLogger::with_mode(ErrorMode::Ignore);
+        logger.add_error(Location::new(1, 1, 0), "test");
+        assert!(logger.get_errors().is_empty());
+        assert!(logger.first_error().is_none());
+    }
+
+    #[test]
+    fn diagnostic_underlines_the_span() {
+        let error = ParseError {
+            code: "unexpected-end-tag".to_string(),
+            message: "end tag not in scope".to_string(),
+            location: Location::new(2, 4, 0),
+            end: Location::new(2, 9, 0),
+            severity: Severity::Error,
+        };
+        let source = "<p>\n<b></em>";
+        let expected =
+            "error[unexpected-end-tag]: end tag not in scope\n --> test.html:2:4\n  |\n2 | <b></em>\n  |    ^^^^^\n";
+
+        assert_eq!(render_diagnostic(&error, source, "test.html"), expected);
+    }
 }
@@ -151,22 +151,11 @@ static LIMITED_QUIRKS_PUB_IDENTIFIER_PREFIX_NOT_MISSING_SYS: &[&str] = &[
 #[cfg(test)]
 mod tests {
     use crate::document::builder::DocumentBuilderImpl;
-    use crate::document::document_impl::DocumentImpl;
     use crate::parser::Html5Parser;
     use crate::parser::QuirksMode;
-    use gosub_css3::system::Css3System;
-    use gosub_interface::config::ModuleConfiguration;
+    use crate::testing::fixture::Config;
     use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
 
-    #[derive(Clone, Debug, PartialEq)]
-    struct Config;
-
-    impl ModuleConfiguration for Config {
-        type CssSystem = Css3System;
-        type Document = DocumentImpl<Self>;
-        type HtmlParser = Html5Parser<'static, Self>;
-    }
-
     type Parser<'a> = Html5Parser<'a, Config>;
 
     #[test]
@@ -30,23 +30,12 @@ pub trait TreeBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::document::document_impl::DocumentImpl;
-    use crate::parser::Html5Parser;
+    use crate::testing::fixture::Config;
     use crate::testing::tree_construction::fixture::{fixture_root_path, read_fixture_from_path};
+    use crate::testing::tree_construction::result::ResultStatus;
     use crate::testing::tree_construction::Harness;
-    use gosub_css3::system::Css3System;
-    use gosub_interface::config::ModuleConfiguration;
     use test_case::test_case;
 
-    #[derive(Clone, Debug, PartialEq)]
-    struct Config;
-
-    impl ModuleConfiguration for Config {
-        type CssSystem = Css3System;
-        type Document = DocumentImpl<Self>;
-        type HtmlParser = Html5Parser<'static, Self>;
-    }
-
     const DISABLED_CASES: &[&str] = &[
         // tests18.dat
         "<!doctype html><template><plaintext>a</template>b",
@@ -139,4 +128,122 @@ mod tests {
             }
         }
     }
+
+    // Only checks the code and position of the `#new-errors` entries, and only for these files,
+    // whose `#new-errors` are all reported by the tokenizer in the data state. `#errors` is
+    // checked by `tree_construction_errors` below.
+    #[test_case("tests6.dat")]
+    #[test_case("tests10.dat")]
+    #[test_case("tests26.dat")]
+    #[test_case("webkit02.dat")]
+    fn tokenizer_new_errors(filename: &str) {
+        let fixture_file = read_fixture_from_path(fixture_root_path().join(filename)).expect("fixture");
+        let mut harness = Harness::with_error_checks();
+
+        for test in fixture_file.tests {
+            if DISABLED_CASES.contains(&test.document_as_str()) {
+                continue;
+            }
+
+            for &scripting_enabled in test.script_modes() {
+                let result = harness
+                    .run_test::<Config>(test.clone(), scripting_enabled)
+                    .expect("problem parsing");
+
+                let failed: Vec<_> = result
+                    .error_results
+                    .iter()
+                    .filter(|r| r.result != ResultStatus::Success)
+                    .collect();
+                assert!(failed.is_empty(), "{}: {failed:#?}", test.spec_data());
+            }
+        }
+    }
+
+    // `#errors` names errors the way html5lib did before the WHATWG codes existed, and its
+    // positions follow html5lib's tokenizer, so the parser only matches part of it. For each file
+    // this pins the number of runs that report as many errors as `#errors` and `#new-errors` list,
+    // and the number of `#errors` positions that have an error reported at them. Update the
+    // numbers when a change makes either go up; a drop is a regression.
+    #[test_case("tests1.dat", 178, 20)]
+    #[test_case("tests2.dat", 74, 10)]
+    #[test_case("tests3.dat", 48, 0)]
+    #[test_case("tests4.dat", 18, 0)]
+    #[test_case("tests5.dat", 32, 0)]
+    #[test_case("tests6.dat", 94, 4)]
+    #[test_case("tests7.dat", 62, 0)]
+    #[test_case("tests8.dat", 16, 0)]
+    #[test_case("tests9.dat", 40, 0)]
+    #[test_case("tests10.dat", 64, 0)]
+    #[test_case("tests11.dat", 26, 0)]
+    #[test_case("tests12.dat", 4, 0)]
+    #[test_case("tests14.dat", 14, 0)]
+    #[test_case("tests15.dat", 18, 0)]
+    #[test_case("tests16.dat", 238, 3)]
+    #[test_case("tests17.dat", 26, 0)]
+    #[test_case("tests18.dat", 57, 102)]
+    #[test_case("tests19.dat", 16, 0)]
+    #[test_case("tests20.dat", 112, 40)]
+    #[test_case("tests21.dat", 0, 12)]
+    #[test_case("tests22.dat", 4, 0)]
+    #[test_case("tests23.dat", 10, 0)]
+    #[test_case("tests24.dat", 16, 0)]
+    #[test_case("tests25.dat", 50, 0)]
+    #[test_case("tests26.dat", 32, 24)]
+    #[test_case("adoption01.dat", 30, 0)]
+    #[test_case("adoption02.dat", 2, 0)]
+    #[test_case("blocks.dat", 96, 0)]
+    #[test_case("comments01.dat", 10, 10)]
+    #[test_case("doctype01.dat", 30, 46)]
+    #[test_case("domjs-unsafe.dat", 64, 16)]
+    #[test_case("entities01.dat", 30, 10)]
+    #[test_case("entities02.dat", 36, 4)]
+    #[test_case("foreign-fragment.dat", 114, 16)]
+    #[test_case("html5test-com.dat", 36, 8)]
+    #[test_case("inbody01.dat", 8, 0)]
+    #[test_case("isindex.dat", 8, 0)]
+    #[test_case("main-element.dat", 6, 4)]
+    #[test_case("math.dat", 0, 0)]
+    #[test_case("menuitem-element.dat", 40, 0)]
+    #[test_case("namespace-sensitivity.dat", 0, 8)]
+    #[test_case("noscript01.dat", 17, 0)]
+    #[test_case("pending-spec-changes.dat", 4, 0)]
+    #[test_case("pending-spec-changes-plain-text-unsafe.dat", 0, 34)]
+    #[test_case("plain-text-unsafe.dat", 10, 124)]
+    #[test_case("quirks01.dat", 8, 0)]
+    #[test_case("ruby.dat", 42, 0)]
+    #[test_case("scriptdata01.dat", 32, 4)]
+    #[test_case("search-element.dat", 6, 4)]
+    #[test_case("svg.dat", 6, 30)]
+    #[test_case("tables01.dat", 36, 20)]
+    #[test_case("template.dat", 188, 52)]
+    #[test_case("tests_innerHTML_1.dat", 162, 0)]
+    #[test_case("tricky01.dat", 12, 0)]
+    #[test_case("webkit01.dat", 84, 18)]
+    #[test_case("webkit02.dat", 60, 58)]
+    fn tree_construction_errors(filename: &str, counts_matched: usize, positions_matched: usize) {
+        let fixture_file = read_fixture_from_path(fixture_root_path().join(filename)).expect("fixture");
+        let mut harness = Harness::with_error_checks();
+
+        let (mut counts, mut positions) = (0, 0);
+        for test in fixture_file.tests {
+            if DISABLED_CASES.contains(&test.document_as_str()) {
+                continue;
+            }
+
+            for &scripting_enabled in test.script_modes() {
+                let result = harness
+                    .run_test::<Config>(test.clone(), scripting_enabled)
+                    .expect("problem parsing");
+                counts += usize::from(result.error_summary.count_matches());
+                positions += result.error_summary.positions_matched;
+            }
+        }
+
+        assert_eq!(
+            (counts, positions),
+            (counts_matched, positions_matched),
+            "(runs with the expected error count, matched positions) in {filename}"
+        );
+    }
 }
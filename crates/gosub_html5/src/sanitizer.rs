@@ -0,0 +1,721 @@
+//! HTML sanitizer.
+//!
+//! [`Sanitizer`] makes untrusted markup (HTML email, user content) safe to render. The markup is
+//! parsed as a fragment and the resulting tree is walked: elements and attributes that are not on
+//! the allowlists of the [`SanitizerConfig`] are dropped, event handler attributes are always
+//! stripped, URL attributes with a scheme that is not allowed (`javascript:` and friends) are
+//! removed, and `style` attributes are either dropped or filtered through the CSS parser.
+
+use crate::document::markup::DocumentMarkup;
+use crate::errors::Error;
+use crate::node::HTML_NAMESPACE;
+use crate::serializer::{HtmlSerializer, SerializerOptions};
+use cow_utils::CowUtils;
+use gosub_css3::tokenizer::{TokenType, Tokenizer};
+use gosub_interface::config::HasDocument;
+use gosub_interface::css3::CssSystem;
+use gosub_interface::document::{Document, DocumentType};
+use gosub_interface::node::NodeType;
+use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
+use gosub_shared::node::NodeId;
+use gosub_shared::types::Result;
+use std::collections::{HashMap, HashSet};
+
+/// Elements that are removed together with their contents whatever the configuration says:
+/// they run script, embed other documents or change how the rest of the page is loaded. The
+/// ones with raw text or hidden contents (`style`, `noscript`, `template`, ...) are parsed
+/// differently when the output is read back, which would let markup hidden in them come alive
+/// (mutation XSS).
+const UNSAFE_ELEMENTS: &[&str] = &[
+    "script",
+    "iframe",
+    "frame",
+    "frameset",
+    "object",
+    "embed",
+    "base",
+    "meta",
+    "link",
+    "style",
+    "noscript",
+    "xmp",
+    "noembed",
+    "noframes",
+    "plaintext",
+    "template",
+];
+
+/// Attributes that hold a URL (or, for `srcset`, a list of them)
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "srcset",
+    "action",
+    "formaction",
+    "cite",
+    "background",
+    "poster",
+    "longdesc",
+    "ping",
+    "xlink:href",
+];
+
+/// CSS functions that fetch resources or run script; declarations using them are dropped
+const UNSAFE_STYLE_FUNCTIONS: &[&str] = &["url", "image", "image-set", "cross-fade", "element", "expression"];
+
+/// Presentational properties that cannot load resources or lay content over the rest of the page
+pub const SAFE_STYLE_PROPERTIES: &[&str] = &[
+    "color",
+    "background-color",
+    "font",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "line-height",
+    "letter-spacing",
+    "word-spacing",
+    "text-align",
+    "text-decoration",
+    "text-indent",
+    "text-transform",
+    "white-space",
+    "vertical-align",
+    "direction",
+    "margin",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "padding",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "border",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "border-color",
+    "border-style",
+    "border-width",
+    "border-radius",
+    "border-collapse",
+    "border-spacing",
+    "width",
+    "height",
+    "min-width",
+    "max-width",
+    "min-height",
+    "max-height",
+    "display",
+    "list-style-type",
+];
+
+/// What happens to `style` attributes
+#[derive(Debug, Clone, PartialEq)]
+pub enum StylePolicy {
+    /// Drop every `style` attribute
+    Drop,
+    /// Keep the declarations that set one of these properties. Declarations the CSS parser
+    /// rejects or that load resources are dropped, as is the attribute once it is empty.
+    Allow(HashSet<String>),
+}
+
+impl StylePolicy {
+    /// Allows the [`SAFE_STYLE_PROPERTIES`]
+    #[must_use]
+    pub fn safe_properties() -> Self {
+        Self::Allow(set(SAFE_STYLE_PROPERTIES))
+    }
+}
+
+/// The allowlists of a [`Sanitizer`]. Element and attribute names are lowercase.
+#[derive(Debug, Clone)]
+pub struct SanitizerConfig {
+    /// HTML elements that are kept
+    pub elements: HashSet<String>,
+    /// HTML elements that are dropped while their (sanitized) children are kept in their place.
+    /// Any other element is dropped with its contents.
+    pub replace_with_children_elements: HashSet<String>,
+    /// Attributes allowed on every kept element
+    pub attributes: HashSet<String>,
+    /// Attributes allowed on specific elements only, by element name
+    pub element_attributes: HashMap<String, HashSet<String>>,
+    /// Keep `data-*` attributes
+    pub data_attributes: bool,
+    /// Schemes allowed in URL attributes, without the colon. Relative URLs are always allowed.
+    pub url_schemes: HashSet<String>,
+    /// Keep comments
+    pub comments: bool,
+    pub styles: StylePolicy,
+}
+
+impl Default for SanitizerConfig {
+    /// A conservative profile modeled on the baseline of the Sanitizer API: text-level and
+    /// structural elements, tables and images, with no forms, no styles and no comments.
+    fn default() -> Self {
+        let element_attributes: [(&str, &[&str]); 16] = [
+            ("a", &["href", "hreflang"]),
+            ("img", &["alt", "src", "width", "height"]),
+            ("td", &["colspan", "rowspan", "headers"]),
+            ("th", &["colspan", "rowspan", "headers", "scope", "abbr"]),
+            ("col", &["span"]),
+            ("colgroup", &["span"]),
+            ("ol", &["start", "reversed", "type"]),
+            ("li", &["value"]),
+            ("blockquote", &["cite"]),
+            ("q", &["cite"]),
+            ("del", &["cite", "datetime"]),
+            ("ins", &["cite", "datetime"]),
+            ("time", &["datetime"]),
+            ("data", &["value"]),
+            ("details", &["open"]),
+            ("table", &["summary"]),
+        ];
+
+        Self {
+            elements: set(&[
+                "a",
+                "abbr",
+                "address",
+                "article",
+                "aside",
+                "b",
+                "bdi",
+                "bdo",
+                "blockquote",
+                "br",
+                "caption",
+                "cite",
+                "code",
+                "col",
+                "colgroup",
+                "data",
+                "dd",
+                "del",
+                "details",
+                "dfn",
+                "div",
+                "dl",
+                "dt",
+                "em",
+                "figcaption",
+                "figure",
+                "footer",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "header",
+                "hgroup",
+                "hr",
+                "i",
+                "img",
+                "ins",
+                "kbd",
+                "li",
+                "main",
+                "mark",
+                "menu",
+                "nav",
+                "ol",
+                "p",
+                "pre",
+                "q",
+                "rp",
+                "rt",
+                "ruby",
+                "s",
+                "samp",
+                "section",
+                "small",
+                "span",
+                "strong",
+                "sub",
+                "summary",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "tfoot",
+                "th",
+                "thead",
+                "time",
+                "tr",
+                "u",
+                "ul",
+                "var",
+                "wbr",
+            ]),
+            replace_with_children_elements: HashSet::new(),
+            attributes: set(&["class", "dir", "lang", "title"]),
+            element_attributes: element_attributes
+                .into_iter()
+                .map(|(element, attributes)| (element.to_string(), set(attributes)))
+                .collect(),
+            data_attributes: false,
+            url_schemes: set(&["http", "https", "mailto", "tel"]),
+            comments: false,
+            styles: StylePolicy::Drop,
+        }
+    }
+}
+
+/// Collects names into an allowlist
+fn set(items: &[&str]) -> HashSet<String> {
+    items.iter().map(ToString::to_string).collect()
+}
+
+/// Sanitizes HTML fragments according to a [`SanitizerConfig`]
+#[derive(Debug, Clone, Default)]
+pub struct Sanitizer {
+    config: SanitizerConfig,
+}
+
+impl Sanitizer {
+    #[must_use]
+    pub fn new(config: SanitizerConfig) -> Self {
+        Self { config }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &SanitizerConfig {
+        &self.config
+    }
+
+    /// Parses `markup` as the children of the `context` element (a `<body>` when `None`) and
+    /// sanitizes the result. Returns the remaining top-level nodes, created in `doc` but not
+    /// attached to it.
+    ///
+    /// Fails when `context` is one of the elements the sanitizer always removes: in a raw text
+    /// element such as `script` or `style` the whole markup parses as a single text node, which
+    /// would run or apply as-is.
+    pub fn sanitize_fragment<C: HasDocument>(
+        &self,
+        doc: &mut C::Document,
+        context: Option<NodeId>,
+        markup: &str,
+    ) -> Result<Vec<NodeId>> {
+        if let Some(context) = context {
+            let name = doc.tag_name(context).unwrap_or_default();
+            if doc.namespace(context) == Some(HTML_NAMESPACE) && UNSAFE_ELEMENTS.contains(&name) {
+                return Err(Error::Markup(format!("cannot sanitize markup for a <{name}> context")).into());
+            }
+        }
+
+        let nodes = DocumentMarkup::<C>::parse_fragment(doc, context, markup)?;
+
+        // Hang the nodes under a scratch element so that the top level is sanitized like the rest
+        let container = doc.create_element("div", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        for &id in &nodes {
+            doc.attach(id, container, None);
+        }
+        self.sanitize_children::<C>(doc, container);
+
+        let nodes = doc.children(container).to_vec();
+        for &id in &nodes {
+            doc.detach(id);
+        }
+        doc.remove(container);
+        Ok(nodes)
+    }
+
+    /// Parses and sanitizes `markup` as the contents of a `<body>`, and serializes the result
+    pub fn sanitize_to_string<C: HasDocument>(&self, markup: &str) -> Result<String> {
+        let mut doc = C::Document::new(DocumentType::HTML, None);
+        let body = doc.create_element("body", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        for id in self.sanitize_fragment::<C>(&mut doc, None, markup)? {
+            doc.attach(id, body, None);
+        }
+        // Escape everything that could be read back as markup: `<` in attribute values and the
+        // text of any raw text element that slipped through.
+        let serializer = HtmlSerializer::new(SerializerOptions {
+            escape_lt_in_attrs: true,
+            escape_rcdata: true,
+            ..Default::default()
+        });
+        Ok(serializer.serialize_children::<C>(&doc, body))
+    }
+
+    /// Sanitizes the descendants of `parent` in place. `parent` itself is left alone.
+    pub fn sanitize_children<C: HasDocument>(&self, doc: &mut C::Document, parent: NodeId) {
+        for child in doc.children(parent).to_vec() {
+            match doc.node_type(child) {
+                NodeType::TextNode => {}
+                NodeType::CommentNode if self.config.comments => {}
+                NodeType::ElementNode => self.sanitize_element::<C>(doc, child),
+                _ => doc.remove(child),
+            }
+        }
+    }
+
+    fn sanitize_element<C: HasDocument>(&self, doc: &mut C::Document, id: NodeId) {
+        let name = doc.tag_name(id).unwrap_or_default().to_string();
+        // Foreign (SVG and MathML) elements are never kept
+        let safe = doc.namespace(id) == Some(HTML_NAMESPACE) && !UNSAFE_ELEMENTS.contains(&name.as_str());
+
+        if safe && self.config.elements.contains(&name) {
+            self.sanitize_attributes::<C>(doc, id, &name);
+            self.sanitize_children::<C>(doc, id);
+        } else if safe && self.config.replace_with_children_elements.contains(&name) {
+            self.sanitize_children::<C>(doc, id);
+            if let Some(parent) = doc.parent(id) {
+                let index = doc.children(parent).iter().position(|&child| child == id).unwrap_or(0);
+                for (offset, child) in doc.children(id).to_vec().into_iter().enumerate() {
+                    doc.detach(child);
+                    doc.attach(child, parent, Some(index + offset));
+                }
+            }
+            doc.remove(id);
+        } else {
+            doc.remove(id);
+        }
+    }
+
+    fn sanitize_attributes<C: HasDocument>(&self, doc: &mut C::Document, id: NodeId, element: &str) {
+        let attributes: Vec<(String, String)> = doc
+            .attributes(id)
+            .map(|attributes| {
+                attributes
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (name, value) in attributes {
+            if name == "style" {
+                match self.sanitize_style::<C>(&value) {
+                    Some(style) if style != value => doc.set_attribute(id, &name, &style),
+                    Some(_) => {}
+                    None => doc.remove_attribute(id, &name),
+                }
+            } else if !self.attribute_allowed(element, &name)
+                || (URL_ATTRIBUTES.contains(&name.as_str()) && !self.url_allowed(&name, &value))
+            {
+                doc.remove_attribute(id, &name);
+            }
+        }
+    }
+
+    fn attribute_allowed(&self, element: &str, attribute: &str) -> bool {
+        // Event handlers are never allowed
+        if attribute.starts_with("on") {
+            return false;
+        }
+        self.config.attributes.contains(attribute)
+            || self
+                .config
+                .element_attributes
+                .get(element)
+                .is_some_and(|attributes| attributes.contains(attribute))
+            || (self.config.data_attributes && attribute.starts_with("data-"))
+    }
+
+    /// Whether every URL in the value of the URL attribute `name` has an allowed scheme
+    fn url_allowed(&self, name: &str, value: &str) -> bool {
+        let allowed = |url: &str| url_scheme(url).is_none_or(|scheme| self.config.url_schemes.contains(&scheme));
+        if name == "srcset" {
+            // Comma-separated image candidates, each a URL optionally followed by a descriptor
+            value
+                .split(',')
+                .all(|candidate| candidate.split_whitespace().next().is_none_or(allowed))
+        } else {
+            allowed(value)
+        }
+    }
+
+    /// The declarations of the `style` attribute `style` that the policy allows, serialized
+    /// again, or `None` when there are none
+    fn sanitize_style<C: HasDocument>(&self, style: &str) -> Option<String> {
+        let StylePolicy::Allow(properties) = &self.config.styles else {
+            return None;
+        };
+
+        let declarations: Vec<String> = C::CssSystem::parse_style_attribute(style)
+            .into_iter()
+            .filter(|declaration| properties.contains(&declaration.property))
+            .filter(|declaration| !uses_unsafe_function(&declaration.value))
+            .map(|declaration| {
+                let important = if declaration.important { " !important" } else { "" };
+                format!("{}: {}{important}", declaration.property, declaration.value)
+            })
+            .collect();
+
+        (!declarations.is_empty()).then(|| declarations.join("; "))
+    }
+}
+
+/// Whether the CSS `value` calls one of the [`UNSAFE_STYLE_FUNCTIONS`]. The value is tokenized
+/// the way the style engine will read it, so escapes (`u\72l(`) and comments inside it cannot
+/// disguise a function name.
+fn uses_unsafe_function(value: &str) -> bool {
+    let mut stream = ByteStream::from_str(value, Encoding::UTF8);
+    let mut tokenizer = Tokenizer::new(&mut stream, Location::default());
+    loop {
+        match tokenizer.consume().token_type {
+            TokenType::Url(_) | TokenType::BadUrl(_) => return true,
+            TokenType::Function(name)
+                if UNSAFE_STYLE_FUNCTIONS
+                    .iter()
+                    .any(|function| name.eq_ignore_ascii_case(function)) =>
+            {
+                return true
+            }
+            TokenType::Eof => return false,
+            _ => {}
+        }
+    }
+}
+
+/// The scheme of `url`, lowercased, or `None` for a relative URL. Like the URL parser, leading
+/// control characters and spaces are skipped and tabs and newlines anywhere are ignored, so
+/// `" java\tscript:"` still has the `javascript` scheme.
+fn url_scheme(url: &str) -> Option<String> {
+    let url: String = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    let (scheme, _) = url.split_once(':')?;
+
+    // Anything before the colon that is not a valid scheme makes it a relative URL
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.cow_to_ascii_lowercase().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::builder::DocumentBuilderImpl;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::parser::Html5Parser;
+    use crate::testing::fixture::Config;
+
+    fn sanitize(sanitizer: &Sanitizer, markup: &str) -> String {
+        sanitizer.sanitize_to_string::<Config>(markup).unwrap()
+    }
+
+    /// Parses `html` as a document and asserts that it has no scripts and no event handlers
+    fn assert_inert(html: &str) {
+        let mut stream = ByteStream::from_str(html, Encoding::UTF8);
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        let _ = Html5Parser::<Config>::parse_document(&mut stream, &mut doc, None);
+
+        let mut pending = vec![doc.root()];
+        while let Some(id) = pending.pop() {
+            if doc.node_type(id) == NodeType::ElementNode {
+                let name = doc.tag_name(id).unwrap_or_default();
+                assert!(!UNSAFE_ELEMENTS.contains(&name), "{name} in {html}");
+                let handlers = doc
+                    .attributes(id)
+                    .is_some_and(|attributes| attributes.keys().any(|name| name.starts_with("on")));
+                assert!(!handlers, "event handler in {html}");
+            }
+            pending.extend_from_slice(doc.children(id));
+        }
+    }
+
+    #[test]
+    fn default_profile_drops_unsafe_markup() {
+        let sanitizer = Sanitizer::default();
+
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<p>Hi<script>alert(1)</script> <b>there</b></p><iframe src=x></iframe>"
+            ),
+            "<p>Hi <b>there</b></p>"
+        );
+        // Unknown elements go with their contents, event handlers and comments are stripped
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<div onclick=\"steal()\">a<!-- note --><blink>b</blink></div>"
+            ),
+            "<div>a</div>"
+        );
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<svg><a href=\"https://x.test/\">x</a></svg><form><i>y</i></form>"
+            ),
+            ""
+        );
+    }
+
+    #[test]
+    fn javascript_urls_are_neutralized() {
+        let sanitizer = Sanitizer::default();
+
+        for markup in [
+            "<a href=\"javascript:alert(1)\">x</a>",
+            "<a href=\" JaVaScRiPt:alert(1)\">x</a>",
+            "<a href=\"java&#9;script:alert(1)\">x</a>",
+            "<a href=\"data:text/html,<script>alert(1)</script>\">x</a>",
+        ] {
+            assert_eq!(sanitize(&sanitizer, markup), "<a>x</a>", "{markup}");
+        }
+        assert_eq!(
+            sanitize(&sanitizer, "<a href=\"mailto:me@x.test\">x</a>"),
+            "<a href=\"mailto:me@x.test\">x</a>"
+        );
+        assert_eq!(
+            sanitize(&sanitizer, "<a href=\"docs/a:b\">x</a>"),
+            "<a href=\"docs/a:b\">x</a>"
+        );
+    }
+
+    #[test]
+    fn inline_styles_are_filtered_by_policy() {
+        let markup = "<p style=\"color: red; position: fixed; background-color: url(https://track.test/); \
+                      font-weight: bold !important\">x</p>";
+
+        assert_eq!(sanitize(&Sanitizer::default(), markup), "<p>x</p>");
+
+        let sanitizer = Sanitizer::new(SanitizerConfig {
+            styles: StylePolicy::safe_properties(),
+            ..Default::default()
+        });
+        assert_eq!(
+            sanitize(&sanitizer, markup),
+            "<p style=\"color: red; font-weight: bold !important\">x</p>"
+        );
+        assert_eq!(
+            sanitize(&sanitizer, "<p style=\"position: absolute\">x</p>"),
+            "<p>x</p>"
+        );
+    }
+
+    #[test]
+    fn allowlists_are_configurable() {
+        let mut config = SanitizerConfig {
+            replace_with_children_elements: set(&["font", "center"]),
+            data_attributes: true,
+            comments: true,
+            ..SanitizerConfig::default()
+        };
+        config.url_schemes.insert("cid".to_string());
+        let sanitizer = Sanitizer::new(config);
+
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<center><font color=red>Hello <img src=\"cid:logo\" data-id=1></font></center><!--x-->"
+            ),
+            "Hello <img data-id=\"1\" src=\"cid:logo\"><!--x-->"
+        );
+    }
+
+    #[test]
+    fn raw_text_elements_are_always_dropped() {
+        let mut config = SanitizerConfig::default();
+        config
+            .elements
+            .extend(set(&["style", "noscript", "template", "xmp", "link"]));
+        let sanitizer = Sanitizer::new(config);
+
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<style>p{}</style><noscript>x</noscript><template><b>t</b></template><xmp>x</xmp><p>y</p>"
+            ),
+            "<p>y</p>"
+        );
+    }
+
+    #[test]
+    fn output_stays_safe_when_parsed_again() {
+        let sanitizer = Sanitizer::default();
+        let payloads = [
+            "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\">",
+            "<xmp><p title=\"</xmp><img src=x onerror=alert(1)>\">",
+            "<noembed><p title=\"</noembed><img src=x onerror=alert(1)>\">",
+            "<template><img src=x onerror=alert(1)></template>",
+            "<svg></p><style><a id=\"</style><img src=1 onerror=alert(1)>\">",
+            "<math><mtext><table><mglyph><style><!--</style><img title=\"--&gt;&lt;/mglyph&gt;&lt;img&Tab;src=1&Tab;onerror=alert(1)&gt;\">",
+            "<p title=\"&lt;/p&gt;&lt;img src=x onerror=alert(1)&gt;\">x</p>",
+            "<plaintext><img src=x onerror=alert(1)>",
+        ];
+
+        for payload in payloads {
+            let output = sanitize(&sanitizer, payload);
+            assert_inert(&output);
+            // A second pass must not change anything either
+            assert_eq!(sanitize(&sanitizer, &output), output, "{payload}");
+        }
+        assert_eq!(
+            sanitize(&sanitizer, payloads[6]),
+            "<p title=\"&lt;/p>&lt;img src=x onerror=alert(1)>\">x</p>"
+        );
+    }
+
+    #[test]
+    fn sanitize_fragment_returns_detached_nodes() {
+        let mut doc = DocumentImpl::<Config>::new(DocumentType::HTML, None);
+        let nodes = Sanitizer::default()
+            .sanitize_fragment::<Config>(&mut doc, None, "<p>a</p><script></script>text")
+            .unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(doc.tag_name(nodes[0]), Some("p"));
+        assert_eq!(doc.text_value(nodes[1]), Some("text"));
+        assert!(nodes.iter().all(|&id| doc.parent(id).is_none()));
+    }
+
+    #[test]
+    fn raw_text_contexts_are_rejected() {
+        let mut doc = DocumentImpl::<Config>::new(DocumentType::HTML, None);
+        let sanitizer = Sanitizer::default();
+        for name in ["script", "style", "xmp"] {
+            let context = doc.create_element(name, Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+            assert!(sanitizer
+                .sanitize_fragment::<Config>(&mut doc, Some(context), "alert(1)")
+                .is_err());
+        }
+
+        let context = doc.create_element("div", Some(HTML_NAMESPACE), HashMap::new(), Location::default());
+        let nodes = sanitizer
+            .sanitize_fragment::<Config>(&mut doc, Some(context), "<b>x</b>")
+            .unwrap();
+        assert_eq!(nodes.len(), 1);
+    }
+
+    #[test]
+    fn style_functions_are_found_by_the_tokenizer() {
+        let sanitizer = Sanitizer::new(SanitizerConfig {
+            styles: StylePolicy::safe_properties(),
+            ..Default::default()
+        });
+        for value in [
+            "url(https://track.test/)",
+            "URL(\"https://track.test/\")",
+            "u\\72l(https://track.test/)",
+            "\\75\\72\\6c(https://track.test/)",
+            "image-set(\"a.png\" 1x)",
+            "linear-gradient(red, url(a.png))",
+        ] {
+            assert!(uses_unsafe_function(value), "{value}");
+            let markup = format!("<p style='background-color: {value}'>x</p>");
+            assert_eq!(sanitize(&sanitizer, &markup), "<p>x</p>", "{value}");
+        }
+        assert!(!uses_unsafe_function("\"url(x)\" /* image(x) */ my-url"));
+
+        // Names that merely contain an unsafe function, and text that only looks like a call
+        assert_eq!(
+            sanitize(
+                &sanitizer,
+                "<p style='font-family: \"url(x)\"; color: rgb(0, 0, 0)'>x</p>"
+            ),
+            "<p style=\"font-family: &quot;url(x)&quot;; color: rgb(0, 0, 0)\">x</p>"
+        );
+    }
+}
@@ -0,0 +1,940 @@
+//! HTML serialization.
+//!
+//! The serializer works on a flat stream of [`SerializerToken`]s, the same shape the html5lib
+//! serializer consumes, so a DOM subtree and a hand-written token list go through the same code.
+//! With [`SerializerOptions::default`] the output follows the HTML fragment serialization
+//! algorithm (<https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments>).
+//! [`SerializerOptions::html5lib`] switches on the minimizing behavior of html5lib's serializer:
+//! unquoted attribute values where that is safe, minimized boolean attributes and omitted
+//! optional tags. The optional filters (charset meta injection, attribute sorting, whitespace
+//! collapsing and optional tag omission) run in that order, before the tokens are written out.
+
+use crate::node::{HTML_NAMESPACE, XLINK_NAMESPACE, XMLNS_NAMESPACE, XML_NAMESPACE};
+use gosub_interface::config::HasDocument;
+use gosub_interface::document::Document;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+
+/// Elements that have no end tag and whose children are never serialized.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input", "keygen", "link",
+    "meta", "param", "source", "track", "wbr",
+];
+
+/// HTML elements whose text content is written verbatim instead of escaped. `noscript` only
+/// counts when scripting is enabled, see [`SerializerOptions::scripting_enabled`].
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+];
+
+/// Elements in which the whitespace filter leaves text untouched.
+const SPACE_PRESERVING_ELEMENTS: &[&str] = &["pre", "textarea"];
+
+/// Elements whose start tag closes an open `p`, allowing its end tag to be omitted.
+const P_CLOSING_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "datagrid",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "fieldset",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+fn is_space(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+/// Whether `attr` is a boolean attribute of `element`, so that its value can be dropped when
+/// [`SerializerOptions::minimize_boolean_attributes`] is set.
+fn is_boolean_attribute(element: &str, attr: &str) -> bool {
+    if matches!(attr, "irrelevant" | "itemscope") {
+        return true;
+    }
+    let attrs: &[&str] = match element {
+        "style" => &["scoped"],
+        "img" => &["ismap"],
+        "audio" | "video" => &["autoplay", "controls"],
+        "script" => &["defer", "async"],
+        "details" => &["open"],
+        "datagrid" => &["multiple", "disabled"],
+        "command" => &["hidden", "disabled", "checked", "default"],
+        "hr" => &["noshade"],
+        "menu" => &["autosubmit"],
+        "fieldset" | "optgroup" | "output" => &["disabled", "readonly"],
+        "option" => &["disabled", "readonly", "selected"],
+        "button" => &["disabled", "autofocus"],
+        "input" => &["disabled", "readonly", "required", "autofocus", "checked", "ismap"],
+        "select" => &["disabled", "readonly", "autofocus", "multiple"],
+        "iframe" => &["seamless"],
+        _ => &[],
+    };
+    attrs.contains(&attr)
+}
+
+/// An attribute on a start or empty tag token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerializerAttribute {
+    /// Namespace URI, for attributes on foreign elements such as `xlink:href`.
+    pub namespace: Option<String>,
+    pub name: String,
+    pub value: String,
+}
+
+impl SerializerAttribute {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            namespace: None,
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// The attribute's serialized name: its local name, prefixed according to its namespace.
+    #[must_use]
+    pub fn qualified_name(&self) -> String {
+        match self.namespace.as_deref() {
+            Some(XML_NAMESPACE) => format!("xml:{}", self.name),
+            Some(XMLNS_NAMESPACE) if self.name == "xmlns" => self.name.clone(),
+            Some(XMLNS_NAMESPACE) => format!("xmlns:{}", self.name),
+            Some(XLINK_NAMESPACE) => format!("xlink:{}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// A unit of serializer input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SerializerToken {
+    Doctype {
+        name: String,
+        public_id: Option<String>,
+        system_id: Option<String>,
+    },
+    StartTag {
+        /// Namespace URI of the element; `None` stands for the HTML namespace.
+        namespace: Option<String>,
+        name: String,
+        attributes: Vec<SerializerAttribute>,
+    },
+    /// A void element: a start tag that never has an end tag.
+    EmptyTag {
+        name: String,
+        attributes: Vec<SerializerAttribute>,
+    },
+    EndTag {
+        name: String,
+    },
+    /// Text that does not start or end with whitespace.
+    Characters(String),
+    /// Text made only of ASCII whitespace.
+    SpaceCharacters(String),
+    Comment(String),
+}
+
+impl SerializerToken {
+    /// Whether this is the start tag of an HTML element whose text is raw text, leaving the
+    /// `escape_rcdata` and `noscript` options aside.
+    fn starts_raw_text(&self) -> bool {
+        match self {
+            SerializerToken::StartTag { namespace, name, .. } => {
+                is_html_namespace(namespace.as_deref()) && RAW_TEXT_ELEMENTS.contains(&name.as_str())
+            }
+            _ => false,
+        }
+    }
+
+    fn tag_name(&self) -> Option<&str> {
+        match self {
+            SerializerToken::StartTag { name, .. }
+            | SerializerToken::EmptyTag { name, .. }
+            | SerializerToken::EndTag { name } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+fn is_html_namespace(namespace: Option<&str>) -> bool {
+    namespace.is_none_or(|namespace| namespace == HTML_NAMESPACE)
+}
+
+/// Splits `data` into leading whitespace, the text in between and trailing whitespace, the way
+/// tree walkers hand text to the serializer. The optional tag and whitespace rules depend on it.
+pub fn push_text(tokens: &mut Vec<SerializerToken>, data: &str) {
+    let middle = data.trim_start_matches(is_space);
+    let left = &data[..data.len() - middle.len()];
+    let trimmed = middle.trim_end_matches(is_space);
+    let right = &middle[trimmed.len()..];
+
+    if !left.is_empty() {
+        tokens.push(SerializerToken::SpaceCharacters(left.to_string()));
+    }
+    if !trimmed.is_empty() {
+        tokens.push(SerializerToken::Characters(trimmed.to_string()));
+    }
+    if !right.is_empty() {
+        tokens.push(SerializerToken::SpaceCharacters(right.to_string()));
+    }
+}
+
+/// When attribute values are wrapped in quotes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeQuoting {
+    Always,
+    /// Only when the value is empty or contains characters that end an unquoted value.
+    WhenNeeded,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerializerOptions {
+    pub quote_attr_values: AttributeQuoting,
+    pub quote_char: char,
+    /// Pick whichever quote character avoids escaping, overriding `quote_char`.
+    pub use_best_quote_char: bool,
+    /// Write boolean attributes such as `disabled` without a value.
+    pub minimize_boolean_attributes: bool,
+    /// Write void elements as `<br />`.
+    pub use_trailing_solidus: bool,
+    pub escape_lt_in_attrs: bool,
+    /// Escape text inside raw text elements such as `script` and `style`.
+    pub escape_rcdata: bool,
+    /// Escape U+00A0 as `&nbsp;` in text and attribute values. XML parsers do not know the
+    /// entity, so leave this off for output that is read back as XML.
+    pub escape_nbsp: bool,
+    pub omit_optional_tags: bool,
+    /// Collapse whitespace runs to a single space outside `pre`, `textarea` and raw text.
+    pub strip_whitespace: bool,
+    pub alphabetical_attributes: bool,
+    /// Make the output declare this character encoding in a `<meta charset>` inside `head`.
+    pub inject_meta_charset: Option<String>,
+    /// Whether the markup is read back with scripting enabled, in which case `noscript` content
+    /// is raw text rather than markup.
+    pub scripting_enabled: bool,
+    /// Write the public and system identifiers of a doctype. The fragment serialization
+    /// algorithm writes only its name.
+    pub doctype_ids: bool,
+}
+
+impl Default for SerializerOptions {
+    /// The HTML fragment serialization algorithm.
+    fn default() -> Self {
+        Self {
+            quote_attr_values: AttributeQuoting::Always,
+            quote_char: '"',
+            use_best_quote_char: false,
+            minimize_boolean_attributes: false,
+            use_trailing_solidus: false,
+            escape_lt_in_attrs: false,
+            escape_rcdata: false,
+            escape_nbsp: true,
+            omit_optional_tags: false,
+            strip_whitespace: false,
+            alphabetical_attributes: false,
+            inject_meta_charset: None,
+            scripting_enabled: true,
+            doctype_ids: false,
+        }
+    }
+}
+
+impl SerializerOptions {
+    /// The defaults of html5lib's serializer, which produce the shortest equivalent markup.
+    #[must_use]
+    pub fn html5lib() -> Self {
+        Self {
+            quote_attr_values: AttributeQuoting::WhenNeeded,
+            use_best_quote_char: true,
+            minimize_boolean_attributes: true,
+            escape_nbsp: false,
+            omit_optional_tags: true,
+            doctype_ids: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HtmlSerializer {
+    options: SerializerOptions,
+}
+
+impl HtmlSerializer {
+    #[must_use]
+    pub fn new(options: SerializerOptions) -> Self {
+        Self { options }
+    }
+
+    /// Serializes `node_id` including itself, like `outerHTML`.
+    pub fn serialize_node<C: HasDocument>(&self, doc: &C::Document, node_id: NodeId) -> String {
+        let mut tokens = Vec::new();
+        walk_node::<C>(doc, node_id, &mut tokens);
+        self.serialize_tokens(tokens)
+    }
+
+    /// Serializes the children of `node_id`, like `innerHTML`. For a `template` element these
+    /// are the children of its template contents.
+    pub fn serialize_children<C: HasDocument>(&self, doc: &C::Document, node_id: NodeId) -> String {
+        let mut tokens = Vec::new();
+        for child in serialized_children::<C>(doc, node_id) {
+            walk_node::<C>(doc, child, &mut tokens);
+        }
+        self.serialize_tokens(tokens)
+    }
+
+    /// Runs the enabled filters over `tokens` and writes the result.
+    pub fn serialize_tokens(&self, tokens: Vec<SerializerToken>) -> String {
+        let mut tokens = tokens;
+        if let Some(encoding) = &self.options.inject_meta_charset {
+            tokens = inject_meta_charset(tokens, encoding);
+        }
+        if self.options.alphabetical_attributes {
+            sort_attributes(&mut tokens);
+        }
+        if self.options.strip_whitespace {
+            strip_whitespace(&mut tokens);
+        }
+        if self.options.omit_optional_tags {
+            tokens = omit_optional_tags(tokens);
+        }
+
+        let mut out = String::new();
+        // Open elements, innermost last, with whether their text is raw: text is raw when its
+        // parent is a raw text element.
+        let mut open: Vec<(&str, bool)> = Vec::new();
+        for token in &tokens {
+            match token {
+                SerializerToken::Doctype {
+                    name,
+                    public_id,
+                    system_id,
+                } => self.write_doctype(&mut out, name, public_id.as_deref(), system_id.as_deref()),
+                SerializerToken::StartTag { name, attributes, .. } => {
+                    if !VOID_ELEMENTS.contains(&name.as_str()) {
+                        open.push((name, token.starts_raw_text() && self.is_raw_text(name)));
+                    }
+                    self.write_tag(&mut out, name, attributes);
+                }
+                SerializerToken::EmptyTag { name, attributes } => self.write_tag(&mut out, name, attributes),
+                SerializerToken::EndTag { name } => {
+                    if let Some(index) = open.iter().rposition(|(open, _)| open == name) {
+                        open.truncate(index);
+                    }
+                    out.push_str("</");
+                    out.push_str(name);
+                    out.push('>');
+                }
+                SerializerToken::SpaceCharacters(data) => out.push_str(data),
+                SerializerToken::Characters(data) if open.last().is_some_and(|(_, raw)| *raw) => {
+                    out.push_str(data);
+                }
+                SerializerToken::Characters(data) => self.escape_text(&mut out, data),
+                SerializerToken::Comment(data) => {
+                    out.push_str("<!--");
+                    out.push_str(data);
+                    out.push_str("-->");
+                }
+            }
+        }
+        out
+    }
+
+    /// Whether the text children of the raw text element `name` are written verbatim.
+    fn is_raw_text(&self, name: &str) -> bool {
+        if self.options.escape_rcdata {
+            return false;
+        }
+        name != "noscript" || self.options.scripting_enabled
+    }
+
+    fn write_doctype(&self, out: &mut String, name: &str, public_id: Option<&str>, system_id: Option<&str>) {
+        let public_id = public_id.filter(|id| self.options.doctype_ids && !id.is_empty());
+        let system_id = system_id.filter(|id| self.options.doctype_ids && !id.is_empty());
+
+        out.push_str("<!DOCTYPE ");
+        out.push_str(name);
+        if let Some(public_id) = public_id {
+            out.push_str(" PUBLIC \"");
+            out.push_str(public_id);
+            out.push('"');
+        } else if system_id.is_some() {
+            out.push_str(" SYSTEM");
+        }
+        if let Some(system_id) = system_id {
+            let quote = if system_id.contains('"') { '\'' } else { '"' };
+            out.push(' ');
+            out.push(quote);
+            out.push_str(system_id);
+            out.push(quote);
+        }
+        out.push('>');
+    }
+
+    fn write_tag(&self, out: &mut String, name: &str, attributes: &[SerializerAttribute]) {
+        out.push('<');
+        out.push_str(name);
+        for attr in attributes {
+            out.push(' ');
+            out.push_str(&attr.qualified_name());
+            if self.options.minimize_boolean_attributes && is_boolean_attribute(name, &attr.name) {
+                continue;
+            }
+            out.push('=');
+            self.write_attribute_value(out, &attr.value);
+        }
+        if self.options.use_trailing_solidus && VOID_ELEMENTS.contains(&name) {
+            out.push_str(" /");
+        }
+        out.push('>');
+    }
+
+    fn write_attribute_value(&self, out: &mut String, value: &str) {
+        let quoted = match self.options.quote_attr_values {
+            AttributeQuoting::Always => true,
+            AttributeQuoting::WhenNeeded => {
+                value.is_empty()
+                    || value
+                        .chars()
+                        .any(|c| is_space(c) || matches!(c, '"' | '\'' | '=' | '>' | '`'))
+            }
+        };
+
+        let mut quote = self.options.quote_char;
+        if quoted && self.options.use_best_quote_char {
+            if value.contains('\'') && !value.contains('"') {
+                quote = '"';
+            } else if value.contains('"') && !value.contains('\'') {
+                quote = '\'';
+            }
+        }
+
+        if quoted {
+            out.push(quote);
+        }
+        for c in value.chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' if self.options.escape_lt_in_attrs => out.push_str("&lt;"),
+                '\u{A0}' if self.options.escape_nbsp => out.push_str("&nbsp;"),
+                '"' if quoted && quote == '"' => out.push_str("&quot;"),
+                '\'' if quoted && quote == '\'' => out.push_str("&#39;"),
+                _ => out.push(c),
+            }
+        }
+        if quoted {
+            out.push(quote);
+        }
+    }
+
+    fn escape_text(&self, out: &mut String, data: &str) {
+        for c in data.chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '\u{A0}' if self.options.escape_nbsp => out.push_str("&nbsp;"),
+                _ => out.push(c),
+            }
+        }
+    }
+}
+
+/// The nodes serialized as the content of `node_id`: a template's contents, or its children.
+fn serialized_children<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> Vec<NodeId> {
+    let contents = if doc.tag_name(node_id) == Some("template") && doc.namespace(node_id) == Some(HTML_NAMESPACE) {
+        doc.template_contents(node_id)
+    } else {
+        None
+    };
+    doc.children(contents.unwrap_or(node_id)).to_vec()
+}
+
+/// Appends the tokens for `node_id` and its subtree to `tokens`.
+fn walk_node<C: HasDocument>(doc: &C::Document, node_id: NodeId, tokens: &mut Vec<SerializerToken>) {
+    match doc.node_type(node_id) {
+        NodeType::DocumentNode | NodeType::DocumentFragmentNode => {
+            for child in serialized_children::<C>(doc, node_id) {
+                walk_node::<C>(doc, child, tokens);
+            }
+        }
+        NodeType::DocTypeNode => tokens.push(SerializerToken::Doctype {
+            name: doc.doctype_name(node_id).unwrap_or_default().to_string(),
+            public_id: doc.doctype_public_id(node_id).map(str::to_string),
+            system_id: doc.doctype_system_id(node_id).map(str::to_string),
+        }),
+        NodeType::TextNode => push_text(tokens, doc.text_value(node_id).unwrap_or_default()),
+        NodeType::CommentNode => tokens.push(SerializerToken::Comment(
+            doc.comment_value(node_id).unwrap_or_default().to_string(),
+        )),
+        NodeType::ElementNode => {
+            let Some(name) = doc.tag_name(node_id) else {
+                return;
+            };
+            let name = name.to_string();
+
+            let namespace = doc.namespace(node_id);
+
+            // The DOM keeps attributes in a hash map; sort them so the output is stable.
+            let mut attributes: Vec<SerializerAttribute> = doc
+                .attributes(node_id)
+                .map(|attrs| attrs.iter().map(|(name, value)| dom_attribute(name, value)).collect())
+                .unwrap_or_default();
+            attributes.sort_by(|a, b| {
+                (a.namespace.as_deref().unwrap_or_default(), &a.name)
+                    .cmp(&(b.namespace.as_deref().unwrap_or_default(), &b.name))
+            });
+
+            if namespace == Some(HTML_NAMESPACE) && VOID_ELEMENTS.contains(&name.as_str()) {
+                tokens.push(SerializerToken::EmptyTag { name, attributes });
+                return;
+            }
+
+            tokens.push(SerializerToken::StartTag {
+                namespace: namespace.map(str::to_string),
+                name: name.clone(),
+                attributes,
+            });
+            for child in serialized_children::<C>(doc, node_id) {
+                walk_node::<C>(doc, child, tokens);
+            }
+            tokens.push(SerializerToken::EndTag { name });
+        }
+    }
+}
+
+/// The serializer attribute for a DOM attribute. The parser stores the attributes it adjusts on
+/// foreign elements as `"prefix local"`, see `adjust_foreign_attributes`.
+fn dom_attribute(name: &str, value: &str) -> SerializerAttribute {
+    let (namespace, local) = match name.split_once(' ') {
+        Some(("xlink", local)) => (Some(XLINK_NAMESPACE), local),
+        Some(("xml", local)) => (Some(XML_NAMESPACE), local),
+        Some(("xmlns", "")) => (Some(XMLNS_NAMESPACE), "xmlns"),
+        Some(("xmlns", local)) => (Some(XMLNS_NAMESPACE), local),
+        _ => (None, name),
+    };
+    SerializerAttribute {
+        namespace: namespace.map(str::to_string),
+        ..SerializerAttribute::new(local, value)
+    }
+}
+
+/// Points existing charset declarations inside `head` at `encoding`, and adds
+/// `<meta charset>` at the start of `head` when there is none.
+fn inject_meta_charset(tokens: Vec<SerializerToken>, encoding: &str) -> Vec<SerializerToken> {
+    let meta = || SerializerToken::EmptyTag {
+        name: "meta".to_string(),
+        attributes: vec![SerializerAttribute::new("charset", encoding)],
+    };
+
+    let mut out = Vec::with_capacity(tokens.len() + 1);
+    let mut pending: Vec<SerializerToken> = Vec::new();
+    let mut in_head = false;
+    let mut meta_found = false;
+
+    for mut token in tokens {
+        match &mut token {
+            SerializerToken::StartTag { name, .. } if name.eq_ignore_ascii_case("head") => in_head = true,
+            SerializerToken::EmptyTag { name, attributes } if name.eq_ignore_ascii_case("meta") => {
+                let mut http_equiv_content_type = false;
+                let mut charset_replaced = false;
+                for attr in attributes.iter_mut().filter(|attr| attr.namespace.is_none()) {
+                    if attr.name.eq_ignore_ascii_case("charset") {
+                        attr.value = encoding.to_string();
+                        charset_replaced = true;
+                        break;
+                    }
+                    if attr.name == "http-equiv" && attr.value.eq_ignore_ascii_case("content-type") {
+                        http_equiv_content_type = true;
+                    }
+                }
+                if charset_replaced {
+                    meta_found = true;
+                } else if http_equiv_content_type {
+                    if let Some(content) = attributes
+                        .iter_mut()
+                        .find(|attr| attr.namespace.is_none() && attr.name == "content")
+                    {
+                        content.value = format!("text/html; charset={encoding}");
+                        meta_found = true;
+                    }
+                }
+            }
+            SerializerToken::EmptyTag { name, attributes } if name.eq_ignore_ascii_case("head") && !meta_found => {
+                out.push(SerializerToken::StartTag {
+                    namespace: None,
+                    name: "head".to_string(),
+                    attributes: std::mem::take(attributes),
+                });
+                out.push(meta());
+                out.push(SerializerToken::EndTag {
+                    name: "head".to_string(),
+                });
+                meta_found = true;
+                continue;
+            }
+            SerializerToken::EndTag { name } if name.eq_ignore_ascii_case("head") && !pending.is_empty() => {
+                let mut pending = std::mem::take(&mut pending).into_iter();
+                out.extend(pending.next());
+                if !meta_found {
+                    out.push(meta());
+                }
+                out.extend(pending);
+                meta_found = true;
+                in_head = false;
+            }
+            _ => {}
+        }
+
+        if in_head {
+            pending.push(token);
+        } else {
+            out.push(token);
+        }
+    }
+    out.extend(pending);
+    out
+}
+
+fn sort_attributes(tokens: &mut [SerializerToken]) {
+    for token in tokens {
+        if let SerializerToken::StartTag { attributes, .. } | SerializerToken::EmptyTag { attributes, .. } = token {
+            attributes.sort_by(|a, b| {
+                (a.namespace.as_deref().unwrap_or_default(), &a.name)
+                    .cmp(&(b.namespace.as_deref().unwrap_or_default(), &b.name))
+            });
+        }
+    }
+}
+
+/// Collapses whitespace to single spaces, except inside `pre`, `textarea` and raw text elements.
+fn strip_whitespace(tokens: &mut [SerializerToken]) {
+    let mut preserve = 0usize;
+    for token in tokens {
+        let raw_text = token.starts_raw_text();
+        match token {
+            SerializerToken::StartTag { name, .. }
+                if preserve > 0 || SPACE_PRESERVING_ELEMENTS.contains(&name.as_str()) || raw_text =>
+            {
+                preserve += 1;
+            }
+            SerializerToken::EndTag { .. } if preserve > 0 => preserve -= 1,
+            SerializerToken::SpaceCharacters(data) if preserve == 0 && !data.is_empty() => *data = " ".to_string(),
+            SerializerToken::Characters(data) if preserve == 0 => *data = collapse_spaces(data),
+            _ => {}
+        }
+    }
+}
+
+fn collapse_spaces(data: &str) -> String {
+    let mut out = String::with_capacity(data.len());
+    let mut in_space = false;
+    for c in data.chars() {
+        if is_space(c) {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Drops start and end tags the parser would imply anyway
+/// (<https://html.spec.whatwg.org/multipage/syntax.html#optional-tags>). Start tags with
+/// attributes are always kept.
+fn omit_optional_tags(tokens: Vec<SerializerToken>) -> Vec<SerializerToken> {
+    let mut out = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).and_then(|i| tokens.get(i));
+        let next = tokens.get(i + 1);
+        let omit = match token {
+            SerializerToken::StartTag { name, attributes, .. } => {
+                attributes.is_empty() && is_optional_start(name, previous, next)
+            }
+            SerializerToken::EndTag { name } => is_optional_end(name, next),
+            _ => false,
+        };
+        if !omit {
+            out.push(token.clone());
+        }
+    }
+    out
+}
+
+fn is_optional_start(name: &str, previous: Option<&SerializerToken>, next: Option<&SerializerToken>) -> bool {
+    use SerializerToken as T;
+
+    let next_name = next.and_then(SerializerToken::tag_name);
+    match name {
+        // Unless the first thing inside is whitespace or a comment.
+        "html" => !matches!(next, Some(T::Comment(_) | T::SpaceCharacters(_))),
+        // When the first thing inside is an element; we also omit it when the head is empty.
+        "head" => match next {
+            Some(T::StartTag { .. } | T::EmptyTag { .. }) => true,
+            Some(T::EndTag { name }) => name == "head",
+            _ => false,
+        },
+        // Unless the first thing inside is whitespace or a comment, or a script or style that
+        // would otherwise end up in the head.
+        "body" => match next {
+            Some(T::Comment(_) | T::SpaceCharacters(_)) => false,
+            Some(T::StartTag { .. }) => !matches!(next_name, Some("script" | "style")),
+            _ => true,
+        },
+        // When the first thing inside is a `col`. A preceding colgroup keeps its end tag
+        // instead, see `is_optional_end`.
+        "colgroup" => matches!(next, Some(T::StartTag { .. } | T::EmptyTag { .. })) && next_name == Some("col"),
+        // When the first thing inside is a `tr`, and no table section end tag was just written.
+        "tbody" => {
+            if let Some(T::EndTag { name }) = previous {
+                if matches!(name.as_str(), "tbody" | "thead" | "tfoot") {
+                    return false;
+                }
+            }
+            matches!(next, Some(T::StartTag { .. })) && next_name == Some("tr")
+        }
+        _ => false,
+    }
+}
+
+fn is_optional_end(name: &str, next: Option<&SerializerToken>) -> bool {
+    use SerializerToken as T;
+
+    let next_start = match next {
+        Some(T::StartTag { name, .. }) => Some(name.as_str()),
+        _ => None,
+    };
+    // Whether the parent element ends right after this one.
+    let at_parent_end = matches!(next, Some(T::EndTag { .. }) | None);
+
+    match name {
+        "html" | "head" | "body" => !matches!(next, Some(T::Comment(_) | T::SpaceCharacters(_))),
+        "li" | "optgroup" | "tr" => match next_start {
+            Some(next_name) => next_name == name,
+            None => at_parent_end,
+        },
+        "dt" | "dd" => match next_start {
+            Some(next_name) => matches!(next_name, "dt" | "dd"),
+            None => name == "dd" && at_parent_end,
+        },
+        "p" => match next {
+            Some(T::StartTag { name, .. } | T::EmptyTag { name, .. }) => P_CLOSING_ELEMENTS.contains(&name.as_str()),
+            _ => at_parent_end,
+        },
+        "option" => match next_start {
+            Some(next_name) => matches!(next_name, "option" | "optgroup"),
+            None => at_parent_end,
+        },
+        "rt" | "rp" => match next_start {
+            Some(next_name) => matches!(next_name, "rt" | "rp"),
+            None => at_parent_end,
+        },
+        "colgroup" => match next {
+            Some(T::Comment(_) | T::SpaceCharacters(_)) => false,
+            Some(T::StartTag { name, .. }) => name != "colgroup",
+            _ => true,
+        },
+        "thead" | "tbody" => match next_start {
+            Some(next_name) => matches!(next_name, "tbody" | "tfoot"),
+            None => name == "tbody" && at_parent_end,
+        },
+        "tfoot" => match next_start {
+            Some(next_name) => next_name == "tbody",
+            None => at_parent_end,
+        },
+        "td" | "th" => match next_start {
+            Some(next_name) => matches!(next_name, "td" | "th"),
+            None => at_parent_end,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::testing::fixture::{parse, Config};
+    use crate::testing::serializer::fixture_from_filename;
+    use crate::writer::DocumentWriter;
+    use test_case::test_case;
+
+    fn body(doc: &DocumentImpl<Config>) -> NodeId {
+        let html = doc.children(doc.root())[1];
+        doc.children(html)[1]
+    }
+
+    #[test_case("core.test")]
+    #[test_case("injectmeta.test")]
+    #[test_case("optionaltags.test")]
+    #[test_case("options.test")]
+    #[test_case("whitespace.test")]
+    fn html5lib_serializer(filename: &str) {
+        let fixture = fixture_from_filename(filename).unwrap();
+        for test in fixture.tests {
+            test.assert_valid();
+        }
+    }
+
+    #[test]
+    fn fragment_serialization_escapes_text_and_attributes() {
+        let doc = parse("<!DOCTYPE html><body><p title='a \"b\" &amp; c'>1 &lt; 2 &amp;&nbsp;3</p>");
+        let out = HtmlSerializer::default().serialize_children::<Config>(&doc, body(&doc));
+        assert_eq!(out, "<p title=\"a &quot;b&quot; &amp; c\">1 &lt; 2 &amp;&nbsp;3</p>");
+    }
+
+    #[test]
+    fn void_raw_text_and_template_contents() {
+        let doc = parse(
+            "<!DOCTYPE html><body><br><img src=x><script>if (a < b && c) {}</script>\
+             <template><b>t</b></template>",
+        );
+        let out = HtmlSerializer::default().serialize_children::<Config>(&doc, body(&doc));
+        assert_eq!(
+            out,
+            "<br><img src=\"x\"><script>if (a < b && c) {}</script><template><b>t</b></template>"
+        );
+    }
+
+    #[test]
+    fn noscript_is_raw_text_only_with_scripting() {
+        let tokens = || {
+            vec![
+                SerializerToken::StartTag {
+                    namespace: None,
+                    name: "noscript".into(),
+                    attributes: vec![],
+                },
+                SerializerToken::Characters("a < b".into()),
+                SerializerToken::EndTag {
+                    name: "noscript".into(),
+                },
+            ]
+        };
+        assert_eq!(
+            HtmlSerializer::default().serialize_tokens(tokens()),
+            "<noscript>a < b</noscript>"
+        );
+        let options = SerializerOptions {
+            scripting_enabled: false,
+            ..SerializerOptions::default()
+        };
+        assert_eq!(
+            HtmlSerializer::new(options).serialize_tokens(tokens()),
+            "<noscript>a &lt; b</noscript>"
+        );
+    }
+
+    #[test]
+    fn raw_text_follows_the_parent_element() {
+        let start = |name: &str| SerializerToken::StartTag {
+            namespace: None,
+            name: name.into(),
+            attributes: vec![],
+        };
+        let end = |name: &str| SerializerToken::EndTag { name: name.into() };
+        let text = |data: &str| SerializerToken::Characters(data.into());
+
+        // A raw text element with an element child, as a script could build it.
+        let tokens = vec![
+            start("style"),
+            text("a<b"),
+            start("b"),
+            text("c<d"),
+            end("b"),
+            text("e<f"),
+            end("style"),
+            text("g<h"),
+        ];
+        assert_eq!(
+            HtmlSerializer::default().serialize_tokens(tokens),
+            "<style>a<b<b>c&lt;d</b>e<f</style>g&lt;h"
+        );
+    }
+
+    #[test]
+    fn foreign_elements_keep_namespaces() {
+        let doc = parse(
+            "<!DOCTYPE html><body><style>a < b</style><svg><style>a &lt; b</style>\
+             <a xlink:href='#x' xml:lang=en></a></svg>",
+        );
+        let out = HtmlSerializer::default().serialize_children::<Config>(&doc, body(&doc));
+        assert_eq!(
+            out,
+            "<style>a < b</style><svg><style>a &lt; b</style><a xlink:href=\"#x\" xml:lang=\"en\"></a></svg>"
+        );
+    }
+
+    #[test]
+    fn doctype_ids_are_opt_in() {
+        let doc =
+            parse("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \"http://www.w3.org/TR/html4/strict.dtd\"><p>");
+        let doctype = doc.children(doc.root())[0];
+        assert_eq!(
+            HtmlSerializer::default().serialize_node::<Config>(&doc, doctype),
+            "<!DOCTYPE html>"
+        );
+        let options = SerializerOptions {
+            doctype_ids: true,
+            ..SerializerOptions::default()
+        };
+        assert_eq!(
+            HtmlSerializer::new(options).serialize_node::<Config>(&doc, doctype),
+            "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \"http://www.w3.org/TR/html4/strict.dtd\">"
+        );
+    }
+
+    #[test]
+    fn document_writer_output_is_xml_safe() {
+        let doc = parse("<!DOCTYPE html><body><svg><text>a&nbsp;b</text></svg>");
+        let svg = doc.children(body(&doc))[0];
+        assert_eq!(
+            DocumentWriter::write_from_node::<Config>(svg, &doc),
+            "<svg><text>a\u{A0}b</text></svg>"
+        );
+    }
+
+    #[test]
+    fn whole_document_round_trips() {
+        let html = "<!DOCTYPE html><html><head><title>x</title></head><body><p class=\"a\">y</p></body></html>";
+        let doc = parse(html);
+        assert_eq!(
+            HtmlSerializer::default().serialize_node::<Config>(&doc, doc.root()),
+            html
+        );
+        assert_eq!(
+            HtmlSerializer::new(SerializerOptions::html5lib()).serialize_node::<Config>(&doc, doc.root()),
+            "<!DOCTYPE html><title>x</title><p class=a>y"
+        );
+    }
+}
@@ -1,4 +1,7 @@
 //! Testing harness and utilities for testing the engine
+#[cfg(test)]
+pub(crate) mod fixture;
+pub mod serializer;
 pub mod tokenizer;
 pub mod tree_construction;
 
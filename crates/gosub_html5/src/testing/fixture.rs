@@ -0,0 +1,21 @@
+//! Module configuration and helpers shared by the unit tests of this crate
+
+use crate::document::document_impl::DocumentImpl;
+use crate::parser::Html5Parser;
+use gosub_css3::system::Css3System;
+use gosub_interface::config::ModuleConfiguration;
+
+/// Wires the gosub CSS system, document and HTML parser together
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config;
+
+impl ModuleConfiguration for Config {
+    type CssSystem = Css3System;
+    type Document = DocumentImpl<Self>;
+    type HtmlParser = Html5Parser<'static, Self>;
+}
+
+/// Parses `html` as a full document
+pub fn parse(html: &str) -> DocumentImpl<Config> {
+    crate::html_compile::<Config>(html)
+}
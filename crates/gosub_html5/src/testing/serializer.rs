@@ -0,0 +1,138 @@
+use super::FIXTURE_ROOT;
+use crate::serializer::{
+    push_text, AttributeQuoting, HtmlSerializer, SerializerAttribute, SerializerOptions, SerializerToken,
+};
+use gosub_shared::types::Result;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureFile {
+    pub tests: Vec<TestSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestSpec {
+    pub description: String,
+    #[serde(default)]
+    pub options: Map<String, Value>,
+    pub input: Vec<Value>,
+    /// Any of these is an acceptable serialization.
+    pub expected: Vec<String>,
+}
+
+impl TestSpec {
+    /// The serializer options for this test: html5lib's defaults overridden by the test's own.
+    pub fn serializer_options(&self) -> SerializerOptions {
+        let mut options = SerializerOptions {
+            // The html5lib runner sorts attributes so that expectations are deterministic
+            alphabetical_attributes: true,
+            ..SerializerOptions::html5lib()
+        };
+        let flag = |name: &str| self.options.get(name).and_then(Value::as_bool);
+
+        if let Some(quote_char) = self.options.get("quote_char").and_then(Value::as_str) {
+            options.quote_char = quote_char.chars().next().unwrap();
+            options.use_best_quote_char = false;
+        }
+        if flag("quote_attr_values") == Some(true) {
+            options.quote_attr_values = AttributeQuoting::Always;
+        }
+        if let Some(value) = flag("minimize_boolean_attributes") {
+            options.minimize_boolean_attributes = value;
+        }
+        if let Some(value) = flag("use_trailing_solidus") {
+            options.use_trailing_solidus = value;
+        }
+        if let Some(value) = flag("escape_lt_in_attrs") {
+            options.escape_lt_in_attrs = value;
+        }
+        if let Some(value) = flag("escape_rcdata") {
+            options.escape_rcdata = value;
+        }
+        if let Some(value) = flag("strip_whitespace") {
+            options.strip_whitespace = value;
+        }
+        // html5lib injects the charset meta whenever an encoding is given, unless disabled
+        if flag("inject_meta_charset") != Some(false) {
+            options.inject_meta_charset = self.options.get("encoding").and_then(Value::as_str).map(str::to_string);
+        }
+        options
+    }
+
+    pub fn tokens(&self) -> Vec<SerializerToken> {
+        let mut tokens = Vec::new();
+        for token in &self.input {
+            let token = token.as_array().unwrap();
+            let str_at = |i: usize| token.get(i).and_then(Value::as_str).map(str::to_string);
+            match token[0].as_str().unwrap() {
+                "StartTag" => tokens.push(SerializerToken::StartTag {
+                    namespace: str_at(1),
+                    name: str_at(2).unwrap(),
+                    attributes: attributes(&token[3]),
+                }),
+                "EmptyTag" => tokens.push(SerializerToken::EmptyTag {
+                    name: str_at(1).unwrap(),
+                    attributes: attributes(&token[2]),
+                }),
+                "EndTag" => tokens.push(SerializerToken::EndTag {
+                    name: str_at(2).unwrap(),
+                }),
+                "Characters" => push_text(&mut tokens, &str_at(1).unwrap()),
+                "Comment" => tokens.push(SerializerToken::Comment(str_at(1).unwrap())),
+                "Doctype" => tokens.push(SerializerToken::Doctype {
+                    name: str_at(1).unwrap(),
+                    public_id: str_at(2),
+                    system_id: str_at(3),
+                }),
+                other => panic!("unknown serializer token type {other}"),
+            }
+        }
+        tokens
+    }
+
+    pub fn serialize(&self) -> String {
+        HtmlSerializer::new(self.serializer_options()).serialize_tokens(self.tokens())
+    }
+
+    pub fn assert_valid(&self) {
+        let output = self.serialize();
+        assert!(
+            self.expected.contains(&output),
+            "{}: got {output:?}, expected one of {:?}",
+            self.description,
+            self.expected
+        );
+    }
+}
+
+/// Attributes are either `{}` or a list of `{namespace, name, value}` objects.
+fn attributes(value: &Value) -> Vec<SerializerAttribute> {
+    let Some(list) = value.as_array() else {
+        return Vec::new();
+    };
+    list.iter()
+        .map(|attr| SerializerAttribute {
+            namespace: attr["namespace"].as_str().map(str::to_string),
+            name: attr["name"].as_str().unwrap().to_string(),
+            value: attr["value"].as_str().unwrap().to_string(),
+        })
+        .collect()
+}
+
+pub fn fixture_from_filename(filename: &str) -> Result<FixtureFile> {
+    let path = PathBuf::from(FIXTURE_ROOT).join("serializer").join(filename);
+    fixture_from_path(&path)
+}
+
+pub fn fixture_from_path<P>(path: &P) -> Result<FixtureFile>
+where
+    P: AsRef<Path>,
+{
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
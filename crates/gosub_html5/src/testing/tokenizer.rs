@@ -267,7 +267,7 @@ impl TestSpec {
     fn assert_error(&self, tokenizer: &Tokenizer, expected: &TokenError) {
         // Iterate all generated errors to see if we have an exact match
         for actual in tokenizer.get_error_logger().get_errors() {
-            if actual.message == expected.code
+            if actual.code == expected.code
                 && actual.location.line == expected.line
                 && actual.location.column == expected.col
             {
@@ -278,7 +278,7 @@ impl TestSpec {
         // Try and find an error that matches the code, but has a different line/pos. Even though
         // it's not always correct, it might be an off-by-one position.
         for actual in tokenizer.get_error_logger().get_errors() {
-            if actual.message == expected.code
+            if actual.code == expected.code
                 && (actual.location.line != expected.line || actual.location.column != expected.col)
             {
                 panic!("[{}]: wanted {:?}, got {:?}", self.description, expected, actual);
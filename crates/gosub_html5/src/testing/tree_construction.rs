@@ -12,9 +12,9 @@ use gosub_interface::document::Document;
 use gosub_interface::html5::{Html5Parser, ParserOptions};
 use gosub_shared::byte_stream::{ByteStream, Config, Encoding, Location};
 use gosub_shared::types::{ParseError, Result};
-use parser::{ScriptMode, TestSpec};
+use parser::{ErrorSpec, ScriptMode, TestSpec};
 use result::TestResult;
-use result::{ResultStatus, TreeLineResult};
+use result::{ErrorMessagePosition, ErrorResult, ErrorSummary, ResultStatus, TreeLineResult};
 use std::collections::HashMap;
 
 type ParseResult<T> = Result<(T, Vec<ParseError>)>;
@@ -61,6 +61,8 @@ pub struct Harness {
     test: Test,
     /// Next line in the document array
     next_document_line: usize,
+    /// Whether the `#errors` and `#new-errors` expectations are checked as well
+    check_errors: bool,
 }
 
 impl Default for Harness {
@@ -76,6 +78,19 @@ impl Harness {
         Self {
             test: Test::default(),
             next_document_line: 0,
+            check_errors: false,
+        }
+    }
+
+    /// A harness that also checks the reported parse errors. Every `#new-errors` entry must be
+    /// reported with the same code and start position. `#errors` uses html5lib's own error names,
+    /// so only the number of errors and their positions are compared, see
+    /// [`TestResult::error_summary`].
+    #[must_use]
+    pub fn with_error_checks() -> Self {
+        Self {
+            check_errors: true,
+            ..Self::new()
         }
     }
 
@@ -211,10 +226,14 @@ impl Harness {
     fn generate_test_result<C: HasDocument>(
         &mut self,
         document: C::Document,
-        _parse_errors: &[ParseError],
+        parse_errors: &[ParseError],
         tree_root: gosub_shared::node::NodeId,
     ) -> TestResult {
         let mut result = TestResult::default();
+        if self.check_errors {
+            result.error_results = self.error_results(parse_errors);
+            result.error_summary = self.error_summary(parse_errors);
+        }
 
         let generator = TreeOutputGenerator::<C>::new(document);
         let actual = generator.generate_from(tree_root);
@@ -257,4 +276,80 @@ impl Harness {
 
         result
     }
+
+    fn error_summary(&self, parse_errors: &[ParseError]) -> ErrorSummary {
+        let mut summary = ErrorSummary {
+            expected: self.test.spec.errors.len() + self.test.spec.new_errors.len(),
+            actual: parse_errors.len(),
+            ..ErrorSummary::default()
+        };
+
+        for spec in &self.test.spec.errors {
+            let pos = match spec {
+                ErrorSpec::Location { pos, .. } | ErrorSpec::Span { start: pos, .. } => pos,
+                _ => continue,
+            };
+            summary.positioned += 1;
+            if parse_errors
+                .iter()
+                .any(|e| e.location.line == pos.line && e.location.column == pos.col)
+            {
+                summary.positions_matched += 1;
+            }
+        }
+
+        summary
+    }
+
+    fn error_results(&self, parse_errors: &[ParseError]) -> Vec<ErrorResult> {
+        let mut results = Vec::new();
+
+        for (index, spec) in self.test.spec.new_errors.iter().enumerate() {
+            let (code, pos) = match spec {
+                ErrorSpec::Location { pos, message } => (message, *pos),
+                ErrorSpec::Span { start, message, .. } => (message, *start),
+                _ => continue,
+            };
+            let expected = ErrorMessagePosition {
+                message: code.clone(),
+                line: pos.line,
+                col: pos.col,
+            };
+
+            let exact = parse_errors
+                .iter()
+                .find(|e| e.code == *code && e.location.line == pos.line && e.location.column == pos.col);
+            let (status, actual) = match exact.or_else(|| parse_errors.iter().find(|e| e.code == *code)) {
+                Some(actual) => (
+                    if exact.is_some() {
+                        ResultStatus::Success
+                    } else {
+                        ResultStatus::IncorrectPosition
+                    },
+                    ErrorMessagePosition {
+                        message: actual.code.clone(),
+                        line: actual.location.line,
+                        col: actual.location.column,
+                    },
+                ),
+                None => (
+                    ResultStatus::Missing,
+                    ErrorMessagePosition {
+                        message: String::new(),
+                        line: 0,
+                        col: 0,
+                    },
+                ),
+            };
+
+            results.push(ErrorResult {
+                actual,
+                expected,
+                index,
+                result: status,
+            });
+        }
+
+        results
+    }
 }
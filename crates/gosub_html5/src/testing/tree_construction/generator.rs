@@ -94,7 +94,7 @@ impl<C: HasDocument> TreeOutputGenerator<C> {
                 };
                 format!("<!DOCTYPE {}>", doctype_text.trim())
             }
-            NodeType::DocumentNode => String::new(),
+            NodeType::DocumentNode | NodeType::DocumentFragmentNode => String::new(),
         }
     }
 }
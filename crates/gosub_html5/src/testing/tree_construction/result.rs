@@ -49,6 +49,26 @@ pub struct ErrorResult {
     pub result: ResultStatus,
 }
 
+/// How the errors reported by the parser compare with the `#errors` section of a test
+#[derive(Debug, Default)]
+pub struct ErrorSummary {
+    /// Number of errors listed in `#errors` and `#new-errors`
+    pub expected: usize,
+    /// Number of errors reported by the parser
+    pub actual: usize,
+    /// Number of `#errors` entries that have a line and column
+    pub positioned: usize,
+    /// Number of those entries for which an error was reported at the same line and column
+    pub positions_matched: usize,
+}
+
+impl ErrorSummary {
+    #[must_use]
+    pub fn count_matches(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
 /// A combined result from a single test with all the result from the tree and parser errors
 #[derive(Debug, Default)]
 pub struct TestResult {
@@ -56,6 +76,8 @@ pub struct TestResult {
     pub tree_results: Vec<TreeLineResult>,
     /// Results of each error generated by the parser
     pub error_results: Vec<ErrorResult>,
+    /// Comparison with the `#errors` section, only filled in when errors are checked
+    pub error_summary: ErrorSummary,
 }
 
 impl TestResult {
@@ -2,7 +2,7 @@ pub mod state;
 pub mod token;
 
 mod character_reference;
-mod replacement_tables;
+pub(crate) mod replacement_tables;
 
 #[cfg(test)]
 mod test_cases;
@@ -0,0 +1,1388 @@
+//! XML parser for `application/xhtml+xml`, `image/svg+xml` and other XML documents.
+//!
+//! Builds the same [`Document`] tree as the HTML parser, but follows the XML rules: names are
+//! case-sensitive, namespaces come from `xmlns` declarations, and any well-formedness error is
+//! fatal. When the input is not well-formed, the partial tree is thrown away and replaced by an
+//! error document that points at the offending line (the "yellow screen of death").
+//!
+//! Documents whose root element is not in a namespace we can render (XHTML, SVG or MathML) are
+//! shown as a tree of their source, unless they bring their own `<?xml-stylesheet?>`.
+//!
+//! Elements are created with their local name and namespace URI; the prefix is not kept.
+//! Attributes keep their qualified name (`xlink:href`), like the HTML parser does for foreign
+//! attributes.
+
+use std::collections::HashMap;
+
+use crate::node::{HTML_NAMESPACE, MATHML_NAMESPACE, SVG_NAMESPACE, XMLNS_NAMESPACE, XML_NAMESPACE};
+use crate::tokenizer::replacement_tables::TOKEN_NAMED_CHARS;
+use gosub_interface::config::HasDocument;
+use gosub_interface::css3::{CssOrigin, CssSystem};
+use gosub_interface::document::{Document, DocumentType};
+use gosub_interface::node::{NodeType, QuirksMode};
+use gosub_shared::byte_stream::Character::{Ch, StreamEnd, Surrogate};
+use gosub_shared::byte_stream::{ByteStream, Character, Location, Stream};
+use gosub_shared::config::{Context, ParserConfig};
+use gosub_shared::node::NodeId;
+use gosub_shared::types::{ParseError, Result, Severity};
+use log::warn;
+
+/// Maximum length of the expansion of a single entity reference. Protects against
+/// "billion laughs" style entity definitions.
+const MAX_ENTITY_EXPANSION: usize = 64 * 1024;
+
+/// Maximum length of all entity expansions in a document together, so that many references to
+/// an entity just below [`MAX_ENTITY_EXPANSION`] cannot blow up the document either.
+const MAX_DOCUMENT_ENTITY_EXPANSION: usize = 1024 * 1024;
+
+/// UA stylesheet for the error document and the tree view
+const XML_VIEWER_CSS: &str = r#"
+.xml-error { background-color: #ffffe0; color: #000000; font-family: sans-serif; padding: 8px; }
+.xml-error h1 { color: #a00000; font-size: 16px; }
+.xml-error pre { color: #0000a0; }
+.xml-tree { font-family: monospace; font-size: 13px; }
+.xml-tree .element { margin-left: 16px; }
+.xml-tree .tag { color: #881280; }
+.xml-tree .attribute-name { color: #994500; }
+.xml-tree .attribute-value { color: #1a1aa6; }
+.xml-tree .comment { color: #236e25; }
+.xml-tree-header { font-family: sans-serif; border-bottom: 1px solid #cccccc; padding-bottom: 4px; }
+"#;
+
+#[derive(Clone, Debug)]
+pub struct XmlParserOptions {
+    /// Show documents without a vocabulary we can render as a tree of their source
+    pub tree_view: bool,
+}
+
+impl Default for XmlParserOptions {
+    fn default() -> Self {
+        Self { tree_view: true }
+    }
+}
+
+/// A well-formedness error. Parsing stops at the first one.
+#[derive(Debug)]
+struct XmlError {
+    code: &'static str,
+    message: String,
+    location: Location,
+}
+
+impl XmlError {
+    fn new(code: &'static str, message: impl Into<String>, location: Location) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            location,
+        }
+    }
+}
+
+type XmlResult<T> = std::result::Result<T, XmlError>;
+
+/// An element that has been opened but not closed yet
+struct OpenElement {
+    node_id: NodeId,
+    /// Qualified name, to match the end tag against
+    qname: String,
+    /// Namespace declarations made on this element (prefix, "" for the default namespace)
+    namespaces: Vec<(String, String)>,
+}
+
+/// An attribute as written in the start tag
+struct RawAttribute {
+    qname: String,
+    value: String,
+    location: Location,
+}
+
+pub struct XmlParser<'a, C: HasDocument> {
+    stream: &'a mut ByteStream,
+    document: &'a mut C::Document,
+    open_elements: Vec<OpenElement>,
+    /// Text seen since the last markup, with the location where it starts
+    pending_text: String,
+    pending_text_location: Location,
+    /// General entities declared in the internal DTD subset
+    entities: HashMap<String, String>,
+    /// Length of all entity expansions so far, checked against [`MAX_DOCUMENT_ENTITY_EXPANSION`]
+    expanded_len: usize,
+    /// True when the doctype refers to a known XHTML or MathML DTD, whose named character
+    /// references we know without loading the DTD
+    html_entities: bool,
+    doctype_seen: bool,
+    root_element: Option<NodeId>,
+    /// True when the document has an `<?xml-stylesheet?>` processing instruction
+    has_stylesheet_pi: bool,
+}
+
+impl<'a, C: HasDocument> XmlParser<'a, C> {
+    /// Parses the XML document in `stream` into `document`. A document that is not well-formed
+    /// is replaced by an error document, and the error is returned in the list.
+    pub fn parse_document(
+        stream: &'a mut ByteStream,
+        document: &'a mut C::Document,
+        options: Option<XmlParserOptions>,
+    ) -> Result<Vec<ParseError>> {
+        let options = options.unwrap_or_default();
+
+        document.set_doctype(DocumentType::XML);
+        document.set_quirks_mode(QuirksMode::NoQuirks);
+
+        let mut parser = Self {
+            stream,
+            document,
+            open_elements: Vec::new(),
+            pending_text: String::new(),
+            pending_text_location: Location::default(),
+            entities: HashMap::new(),
+            expanded_len: 0,
+            html_entities: false,
+            doctype_seen: false,
+            root_element: None,
+            has_stylesheet_pi: false,
+        };
+
+        match parser.parse() {
+            Ok(()) => {
+                if options.tree_view && parser.needs_tree_view() {
+                    parser.render_tree_view();
+                }
+                Ok(Vec::new())
+            }
+            Err(error) => {
+                parser.render_error_document(&error);
+                Ok(vec![ParseError {
+                    code: error.code.to_string(),
+                    message: error.message,
+                    location: error.location,
+                    end: error.location,
+                    severity: Severity::Error,
+                }])
+            }
+        }
+    }
+
+    fn parse(&mut self) -> XmlResult<()> {
+        if self.stream.read() == Ch('\u{FEFF}') {
+            self.stream.next();
+        }
+        if self.starts_with("<?xml") && self.stream.look_ahead(5).is_whitespace() {
+            self.parse_xml_declaration()?;
+        }
+
+        loop {
+            if self.open_elements.is_empty() {
+                if !self.parse_prolog_or_epilog()? {
+                    return Ok(());
+                }
+            } else {
+                self.parse_content()?;
+            }
+        }
+    }
+
+    /// Parses markup outside of the root element. Returns false at the end of the document.
+    fn parse_prolog_or_epilog(&mut self) -> XmlResult<bool> {
+        self.skip_whitespace();
+        let location = self.location();
+
+        match self.stream.read() {
+            StreamEnd if self.root_element.is_none() => {
+                Err(XmlError::new("no-root-element", "no root element found", location))
+            }
+            StreamEnd => Ok(false),
+            Ch('<') if self.starts_with("<?") => {
+                self.parse_processing_instruction()?;
+                Ok(true)
+            }
+            Ch('<') if self.starts_with("<!--") => {
+                self.parse_comment(self.document.root())?;
+                Ok(true)
+            }
+            Ch('<') if self.starts_with("<!DOCTYPE") => {
+                if self.doctype_seen || self.root_element.is_some() {
+                    return Err(XmlError::new("misplaced-doctype", "doctype not allowed here", location));
+                }
+                self.parse_doctype()?;
+                Ok(true)
+            }
+            Ch('<') if is_name_start_char(self.char_at(1)) => {
+                if self.root_element.is_some() {
+                    return Err(XmlError::new(
+                        "junk-after-document-element",
+                        "junk after document element",
+                        location,
+                    ));
+                }
+                self.parse_start_tag()?;
+                Ok(true)
+            }
+            _ if self.root_element.is_some() => Err(XmlError::new(
+                "junk-after-document-element",
+                "junk after document element",
+                location,
+            )),
+            _ => Err(XmlError::new("syntax-error", "syntax error", location)),
+        }
+    }
+
+    /// Parses markup and text inside an element
+    fn parse_content(&mut self) -> XmlResult<()> {
+        let location = self.location();
+
+        match self.stream.read() {
+            StreamEnd => {
+                let qname = self.open_elements.last().map_or("", |e| e.qname.as_str());
+                Err(XmlError::new(
+                    "unclosed-element",
+                    format!("no element found, expected </{qname}>"),
+                    location,
+                ))
+            }
+            Ch('<') if self.starts_with("</") => {
+                self.flush_text();
+                self.parse_end_tag()
+            }
+            Ch('<') if self.starts_with("<!--") => {
+                self.flush_text();
+                self.parse_comment(self.current_node())
+            }
+            Ch('<') if self.starts_with("<![CDATA[") => self.parse_cdata(),
+            Ch('<') if self.starts_with("<?") => {
+                self.flush_text();
+                self.parse_processing_instruction()
+            }
+            Ch('<') if is_name_start_char(self.char_at(1)) => {
+                self.flush_text();
+                self.parse_start_tag()
+            }
+            Ch('<') => Err(XmlError::new(
+                "not-well-formed",
+                "not well-formed (invalid token)",
+                location,
+            )),
+            Ch('&') => {
+                let expanded = self.parse_reference()?;
+                self.push_text(&expanded, location);
+                Ok(())
+            }
+            _ => self.parse_text(),
+        }
+    }
+
+    fn parse_xml_declaration(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(5);
+        let declaration = self.read_until("?>", location, "unclosed-xml-declaration")?;
+        if !declaration.contains("version") {
+            return Err(XmlError::new(
+                "malformed-xml-declaration",
+                "XML declaration without version",
+                location,
+            ));
+        }
+        Ok(())
+    }
+
+    fn parse_processing_instruction(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(2);
+        let target = self.read_name()?;
+        if target.eq_ignore_ascii_case("xml") {
+            return Err(XmlError::new(
+                "misplaced-xml-declaration",
+                "XML or text declaration not at start of entity",
+                location,
+            ));
+        }
+        let data = self.read_until("?>", location, "unclosed-processing-instruction")?;
+        if !data.is_empty() && !data.starts_with(|c: char| c.is_ascii_whitespace()) {
+            return Err(XmlError::new(
+                "not-well-formed",
+                "not well-formed (invalid token)",
+                location,
+            ));
+        }
+
+        // There are no processing instruction nodes; only note whether the document styles itself
+        if target == "xml-stylesheet" {
+            self.has_stylesheet_pi = true;
+        }
+        Ok(())
+    }
+
+    fn parse_comment(&mut self, parent: NodeId) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(4);
+        let comment = self.read_until("--", location, "unclosed-comment")?;
+        if self.stream.read() != Ch('>') {
+            return Err(XmlError::new(
+                "malformed-comment",
+                "'--' is not allowed inside a comment",
+                location,
+            ));
+        }
+        self.stream.next();
+
+        let node_id = self.document.create_comment(&comment, location);
+        self.document.attach(node_id, parent, None);
+        Ok(())
+    }
+
+    fn parse_cdata(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(9);
+        let text = self.read_until("]]>", location, "unclosed-cdata-section")?;
+        self.push_text(&text, location);
+        Ok(())
+    }
+
+    fn parse_text(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        let mut text = String::new();
+        loop {
+            if self.starts_with("]]>") {
+                return Err(XmlError::new(
+                    "not-well-formed",
+                    "']]>' is not allowed in text",
+                    self.location(),
+                ));
+            }
+            match self.stream.read() {
+                Ch('<' | '&') | StreamEnd => break,
+                _ => text.push(self.read_char()?),
+            }
+        }
+        self.push_text(&text, location);
+        Ok(())
+    }
+
+    fn parse_doctype(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(9);
+        if !self.skip_whitespace() {
+            return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+        }
+        let name = self.read_name()?;
+        self.skip_whitespace();
+
+        let mut public_id = None;
+        let mut system_id = None;
+        if self.starts_with("PUBLIC") {
+            self.stream.next_n(6);
+            self.skip_whitespace();
+            public_id = Some(self.read_quoted()?);
+            self.skip_whitespace();
+            system_id = Some(self.read_quoted()?);
+        } else if self.starts_with("SYSTEM") {
+            self.stream.next_n(6);
+            self.skip_whitespace();
+            system_id = Some(self.read_quoted()?);
+        }
+        self.skip_whitespace();
+
+        if self.stream.read() == Ch('[') {
+            self.stream.next();
+            self.parse_internal_subset()?;
+            self.skip_whitespace();
+        }
+        if self.stream.read_and_next() != Ch('>') {
+            return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+        }
+
+        // Like other browsers, we know the entities of the XHTML and MathML DTDs without loading them
+        self.html_entities = public_id.as_deref().is_some_and(|id| {
+            id.starts_with("-//W3C//DTD XHTML") || id.starts_with("-//W3C//DTD MathML") || id.starts_with("-//WAPFORUM")
+        });
+
+        let node_id = self
+            .document
+            .create_doctype(&name, public_id.as_deref(), system_id.as_deref(), location);
+        self.document.attach(node_id, self.document.root(), None);
+        self.doctype_seen = true;
+        Ok(())
+    }
+
+    /// Parses the internal DTD subset, up to and including the closing `]`. Only general entity
+    /// declarations are used, all other declarations are skipped.
+    fn parse_internal_subset(&mut self) -> XmlResult<()> {
+        loop {
+            self.skip_whitespace();
+            let location = self.location();
+
+            if self.stream.read() == Ch(']') {
+                self.stream.next();
+                return Ok(());
+            }
+            if self.starts_with("<!--") {
+                self.stream.next_n(4);
+                self.read_until("-->", location, "unclosed-comment")?;
+            } else if self.starts_with("<?") {
+                self.stream.next_n(2);
+                self.read_until("?>", location, "unclosed-processing-instruction")?;
+            } else if self.starts_with("<!ENTITY") {
+                self.stream.next_n(8);
+                self.parse_entity_declaration(location)?;
+            } else if self.starts_with("<!") {
+                self.skip_declaration(location)?;
+            } else if self.stream.read() == Ch('%') {
+                // Parameter entity reference
+                self.stream.next();
+                self.read_name()?;
+                if self.stream.read_and_next() != Ch(';') {
+                    return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+                }
+            } else if self.stream.read() == StreamEnd {
+                return Err(XmlError::new("unclosed-doctype", "unclosed doctype", location));
+            } else {
+                return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+            }
+        }
+    }
+
+    fn parse_entity_declaration(&mut self, location: Location) -> XmlResult<()> {
+        if !self.skip_whitespace() {
+            return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+        }
+        if self.stream.read() == Ch('%') {
+            // Parameter entities are not supported
+            return self.skip_declaration(location);
+        }
+        let name = self.read_name()?;
+        self.skip_whitespace();
+
+        if !matches!(self.stream.read(), Ch('"' | '\'')) {
+            // External entity: we do not load those
+            return self.skip_declaration(location);
+        }
+        let value = self.read_quoted()?;
+        self.skip_whitespace();
+        if self.stream.read_and_next() != Ch('>') {
+            return Err(XmlError::new("malformed-doctype", "syntax error in doctype", location));
+        }
+
+        // Only character references are expanded in the value; entity references are kept as
+        // written and expanded where the entity is used.
+        let value = self.expand_char_references(&value, location)?;
+        // The first declaration of an entity is binding
+        self.entities.entry(name).or_insert(value);
+        Ok(())
+    }
+
+    /// Skips a markup declaration up to its closing `>`, minding quoted strings
+    fn skip_declaration(&mut self, location: Location) -> XmlResult<()> {
+        let mut quote = None;
+        loop {
+            match (self.stream.read_and_next(), quote) {
+                (StreamEnd, _) => return Err(XmlError::new("unclosed-doctype", "unclosed doctype", location)),
+                (Ch(c), Some(q)) if c == q => quote = None,
+                (Ch(c @ ('"' | '\'')), None) => quote = Some(c),
+                (Ch('>'), None) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Expands the character references in a literal entity value
+    fn expand_char_references(&self, value: &str, location: Location) -> XmlResult<String> {
+        let mut out = String::new();
+        let mut rest = value;
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            let Some(semicolon) = rest[amp..].find(';') else {
+                return Err(XmlError::new(
+                    "not-well-formed",
+                    "not well-formed (invalid token)",
+                    location,
+                ));
+            };
+            let name = &rest[amp + 1..amp + semicolon];
+            if name.starts_with('#') {
+                if let Reference::Char(c) = self.resolve_reference(name, location)? {
+                    out.push(c);
+                }
+            } else {
+                out.push_str(&rest[amp..=amp + semicolon]);
+            }
+            rest = &rest[amp + semicolon + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Expands the references in the replacement text of an entity
+    fn expand_references(&self, value: &str, location: Location, depth: usize) -> XmlResult<String> {
+        if depth > 8 {
+            return Err(XmlError::new(
+                "recursive-entity-reference",
+                "recursive entity reference",
+                location,
+            ));
+        }
+
+        let mut out = String::new();
+        let mut rest = value;
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            let Some(semicolon) = rest[amp..].find(';') else {
+                return Err(XmlError::new(
+                    "not-well-formed",
+                    "not well-formed (invalid token)",
+                    location,
+                ));
+            };
+            let name = &rest[amp + 1..amp + semicolon];
+            match self.resolve_reference(name, location)? {
+                Reference::Char(c) => out.push(c),
+                Reference::Entity(text) => out.push_str(&self.expand_references(&text, location, depth + 1)?),
+            }
+            if out.len() > MAX_ENTITY_EXPANSION {
+                return Err(XmlError::new(
+                    "entity-expansion-limit",
+                    "entity expansion exceeds the limit",
+                    location,
+                ));
+            }
+            rest = &rest[amp + semicolon + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Parses a character or entity reference at `&` and returns its replacement text
+    fn parse_reference(&mut self) -> XmlResult<String> {
+        let location = self.location();
+        self.stream.next();
+
+        let mut name = String::new();
+        loop {
+            match self.stream.read_and_next() {
+                Ch(';') => break,
+                Ch(c) if c == '#' || is_name_char(Ch(c)) => name.push(c),
+                _ => {
+                    return Err(XmlError::new(
+                        "not-well-formed",
+                        "not well-formed (invalid token)",
+                        location,
+                    ))
+                }
+            }
+        }
+
+        match self.resolve_reference(&name, location)? {
+            Reference::Char(c) => Ok(c.to_string()),
+            Reference::Entity(text) => {
+                let expanded = self.expand_references(&text, location, 1)?;
+                self.expanded_len += expanded.len();
+                if self.expanded_len > MAX_DOCUMENT_ENTITY_EXPANSION {
+                    return Err(XmlError::new(
+                        "entity-expansion-limit",
+                        "entity expansions exceed the document limit",
+                        location,
+                    ));
+                }
+                Ok(expanded)
+            }
+        }
+    }
+
+    fn resolve_reference(&self, name: &str, location: Location) -> XmlResult<Reference> {
+        if let Some(number) = name.strip_prefix('#') {
+            let code = match number.strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => number.parse::<u32>(),
+            };
+            return match code.ok().and_then(char::from_u32).filter(|c| is_xml_char(*c)) {
+                Some(c) => Ok(Reference::Char(c)),
+                None => Err(XmlError::new(
+                    "bad-character-reference",
+                    "reference to invalid character number",
+                    location,
+                )),
+            };
+        }
+
+        let predefined = match name {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "apos" => Some('\''),
+            "quot" => Some('"'),
+            _ => None,
+        };
+        if let Some(c) = predefined {
+            return Ok(Reference::Char(c));
+        }
+        if let Some(text) = self.entities.get(name) {
+            return Ok(Reference::Entity(text.clone()));
+        }
+        if self.html_entities {
+            if let Some(text) = TOKEN_NAMED_CHARS.get(format!("{name};").as_str()) {
+                // Named characters expand to text that contains no further references
+                return Ok(Reference::Entity((*text).to_string()));
+            }
+        }
+
+        Err(XmlError::new(
+            "undefined-entity",
+            format!("undefined entity &{name};"),
+            location,
+        ))
+    }
+
+    fn parse_start_tag(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next();
+        let qname = self.read_name()?;
+
+        let mut attributes: Vec<RawAttribute> = Vec::new();
+        let self_closing = loop {
+            let had_whitespace = self.skip_whitespace();
+            match self.stream.read() {
+                Ch('>') => {
+                    self.stream.next();
+                    break false;
+                }
+                Ch('/') if self.stream.look_ahead(1) == Ch('>') => {
+                    self.stream.next_n(2);
+                    break true;
+                }
+                StreamEnd => {
+                    return Err(XmlError::new("unclosed-token", "unclosed token", location));
+                }
+                c if had_whitespace && is_name_start_char(c) => {
+                    let attribute = self.parse_attribute()?;
+                    if attributes.iter().any(|a| a.qname == attribute.qname) {
+                        return Err(XmlError::new(
+                            "duplicate-attribute",
+                            format!("duplicate attribute {}", attribute.qname),
+                            attribute.location,
+                        ));
+                    }
+                    attributes.push(attribute);
+                }
+                _ => {
+                    return Err(XmlError::new(
+                        "not-well-formed",
+                        "not well-formed (invalid token)",
+                        self.location(),
+                    ))
+                }
+            }
+        };
+
+        // Namespace declarations are in scope for the element itself and its attributes
+        let mut namespaces = Vec::new();
+        for attribute in &attributes {
+            let prefix = if attribute.qname == "xmlns" {
+                ""
+            } else if let Some(prefix) = attribute.qname.strip_prefix("xmlns:") {
+                if attribute.value.is_empty() {
+                    return Err(XmlError::new(
+                        "unbound-prefix",
+                        format!("must not undeclare prefix {prefix}"),
+                        attribute.location,
+                    ));
+                }
+                if prefix == "xmlns" || (prefix == "xml") != (attribute.value == XML_NAMESPACE) {
+                    return Err(XmlError::new(
+                        "reserved-prefix",
+                        format!("reserved prefix {prefix} must not be redeclared"),
+                        attribute.location,
+                    ));
+                }
+                prefix
+            } else {
+                continue;
+            };
+            namespaces.push((prefix.to_string(), attribute.value.clone()));
+        }
+        self.open_elements.push(OpenElement {
+            node_id: NodeId::root(),
+            qname: qname.clone(),
+            namespaces,
+        });
+
+        let (prefix, local_name) = split_qname(&qname, location)?;
+        let namespace = self.lookup_namespace(prefix.unwrap_or(""), location)?;
+        if prefix.is_some() && namespace.is_none() {
+            return Err(XmlError::new(
+                "unbound-prefix",
+                format!("unbound prefix in {qname}"),
+                location,
+            ));
+        }
+
+        let mut expanded_names = Vec::new();
+        let mut attribute_map = HashMap::new();
+        for attribute in attributes {
+            let (prefix, local) = split_qname(&attribute.qname, attribute.location)?;
+            if let Some(prefix) = prefix.filter(|p| *p != "xmlns") {
+                let Some(ns) = self.lookup_namespace(prefix, attribute.location)? else {
+                    return Err(XmlError::new(
+                        "unbound-prefix",
+                        format!("unbound prefix in {}", attribute.qname),
+                        attribute.location,
+                    ));
+                };
+                let expanded = (ns, local.to_string());
+                if expanded_names.contains(&expanded) {
+                    return Err(XmlError::new(
+                        "duplicate-attribute",
+                        format!("duplicate attribute {}", attribute.qname),
+                        attribute.location,
+                    ));
+                }
+                expanded_names.push(expanded);
+            }
+            attribute_map.insert(attribute.qname, attribute.value);
+        }
+
+        let node_id = self
+            .document
+            .create_element(local_name, namespace.as_deref(), attribute_map, location);
+        let parent = self.parent_node();
+        self.document.attach(node_id, parent, None);
+        if parent == self.document.root() {
+            self.root_element = Some(node_id);
+        }
+
+        if self_closing {
+            self.open_elements.pop();
+        } else if let Some(open) = self.open_elements.last_mut() {
+            open.node_id = node_id;
+        }
+        Ok(())
+    }
+
+    fn parse_attribute(&mut self) -> XmlResult<RawAttribute> {
+        let location = self.location();
+        let qname = self.read_name()?;
+        self.skip_whitespace();
+        if self.stream.read_and_next() != Ch('=') {
+            return Err(XmlError::new(
+                "not-well-formed",
+                "not well-formed (invalid token)",
+                location,
+            ));
+        }
+        self.skip_whitespace();
+
+        let quote = match self.stream.read_and_next() {
+            Ch(q @ ('"' | '\'')) => q,
+            _ => {
+                return Err(XmlError::new(
+                    "not-well-formed",
+                    "not well-formed (invalid token)",
+                    location,
+                ))
+            }
+        };
+        let mut value = String::new();
+        loop {
+            match self.stream.read() {
+                Ch(c) if c == quote => {
+                    self.stream.next();
+                    break;
+                }
+                Ch('<') => {
+                    return Err(XmlError::new(
+                        "not-well-formed",
+                        "'<' is not allowed in attribute values",
+                        self.location(),
+                    ))
+                }
+                Ch('&') => value.push_str(&self.parse_reference()?),
+                StreamEnd => return Err(XmlError::new("unclosed-token", "unclosed token", location)),
+                // Attribute value normalization
+                Ch('\t' | '\n' | '\r') => {
+                    self.stream.next();
+                    value.push(' ');
+                }
+                _ => value.push(self.read_char()?),
+            }
+        }
+
+        Ok(RawAttribute { qname, value, location })
+    }
+
+    fn parse_end_tag(&mut self) -> XmlResult<()> {
+        let location = self.location();
+        self.stream.next_n(2);
+        let qname = self.read_name()?;
+        self.skip_whitespace();
+        if self.stream.read_and_next() != Ch('>') {
+            return Err(XmlError::new(
+                "not-well-formed",
+                "not well-formed (invalid token)",
+                location,
+            ));
+        }
+
+        match self.open_elements.pop() {
+            Some(open) if open.qname == qname => {
+                self.element_closed(open.node_id);
+                Ok(())
+            }
+            Some(open) => Err(XmlError::new(
+                "mismatched-tag",
+                format!("mismatched tag, expected </{}>", open.qname),
+                location,
+            )),
+            None => Err(XmlError::new("mismatched-tag", "mismatched tag", location)),
+        }
+    }
+
+    /// Loads the stylesheet of a closed `<style>` element
+    fn element_closed(&mut self, node_id: NodeId) {
+        let namespace = self.document.namespace(node_id);
+        if self.document.tag_name(node_id) != Some("style")
+            || !matches!(namespace, Some(HTML_NAMESPACE | SVG_NAMESPACE))
+        {
+            return;
+        }
+
+        let css = self
+            .document
+            .children(node_id)
+            .iter()
+            .filter_map(|child| self.document.text_value(*child))
+            .collect::<String>();
+        let source_url = match self.document.url() {
+            Some(url) => format!("{url}#inline"),
+            None => "<unknown>#inline".into(),
+        };
+        if let Some(stylesheet) = parse_stylesheet::<C>(&css, CssOrigin::Author, &source_url) {
+            self.document.add_stylesheet(stylesheet);
+        }
+    }
+
+    fn push_text(&mut self, text: &str, location: Location) {
+        if self.pending_text.is_empty() {
+            self.pending_text_location = location;
+        }
+        self.pending_text.push_str(text);
+    }
+
+    fn flush_text(&mut self) {
+        if self.pending_text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.pending_text);
+        let node_id = self.document.create_text(&text, self.pending_text_location);
+        let parent = self.current_node();
+        self.document.attach(node_id, parent, None);
+    }
+
+    /// The element that content is currently added to
+    fn current_node(&self) -> NodeId {
+        self.open_elements
+            .last()
+            .map_or_else(|| self.document.root(), |open| open.node_id)
+    }
+
+    /// The parent for an element whose start tag has just been pushed
+    fn parent_node(&self) -> NodeId {
+        match self.open_elements.len() {
+            0 | 1 => self.document.root(),
+            n => self.open_elements[n - 2].node_id,
+        }
+    }
+
+    /// Looks up the namespace bound to `prefix` ("" for the default namespace)
+    fn lookup_namespace(&self, prefix: &str, location: Location) -> XmlResult<Option<String>> {
+        match prefix {
+            "xml" => return Ok(Some(XML_NAMESPACE.to_string())),
+            "xmlns" => return Ok(Some(XMLNS_NAMESPACE.to_string())),
+            _ => {}
+        }
+
+        for open in self.open_elements.iter().rev() {
+            if let Some((_, uri)) = open.namespaces.iter().find(|(p, _)| p == prefix) {
+                // `xmlns=""` undeclares the default namespace
+                return Ok((!uri.is_empty()).then(|| uri.clone()));
+            }
+        }
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+        Err(XmlError::new(
+            "unbound-prefix",
+            format!("unbound prefix {prefix}"),
+            location,
+        ))
+    }
+
+    fn read_name(&mut self) -> XmlResult<String> {
+        let location = self.location();
+        if !is_name_start_char(self.stream.read()) {
+            return Err(XmlError::new(
+                "not-well-formed",
+                "not well-formed (invalid token)",
+                location,
+            ));
+        }
+        let mut name = String::new();
+        while is_name_char(self.stream.read()) {
+            name.push(char::from(self.stream.read_and_next()));
+        }
+        Ok(name)
+    }
+
+    fn read_quoted(&mut self) -> XmlResult<String> {
+        let location = self.location();
+        let quote = match self.stream.read_and_next() {
+            Ch(q @ ('"' | '\'')) => q,
+            _ => {
+                return Err(XmlError::new(
+                    "not-well-formed",
+                    "not well-formed (invalid token)",
+                    location,
+                ))
+            }
+        };
+        self.read_until(&quote.to_string(), location, "unclosed-token")
+    }
+
+    /// Reads characters up to `end`, and consumes `end` itself
+    fn read_until(&mut self, end: &str, location: Location, code: &'static str) -> XmlResult<String> {
+        let mut out = String::new();
+        loop {
+            if self.starts_with(end) {
+                self.stream.next_n(end.chars().count());
+                return Ok(out);
+            }
+            if self.stream.read() == StreamEnd {
+                return Err(XmlError::new(code, "unexpected end of document", location));
+            }
+            out.push(self.read_char()?);
+        }
+    }
+
+    /// Reads the current character, which must be allowed in XML
+    fn read_char(&mut self) -> XmlResult<char> {
+        let location = self.location();
+        match self.stream.read_and_next() {
+            Ch(c) if is_xml_char(c) => Ok(c),
+            Ch(_) | Surrogate(_) => Err(XmlError::new(
+                "invalid-character",
+                "not well-formed (invalid token)",
+                location,
+            )),
+            StreamEnd => Err(XmlError::new("unexpected-eof", "unexpected end of document", location)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let mut skipped = false;
+        while matches!(self.stream.read(), Ch(' ' | '\t' | '\n' | '\r')) {
+            self.stream.next();
+            skipped = true;
+        }
+        skipped
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.stream.look_ahead(i) == Ch(c))
+    }
+
+    fn char_at(&self, offset: usize) -> Character {
+        self.stream.look_ahead(offset)
+    }
+
+    fn location(&self) -> Location {
+        self.stream.location()
+    }
+
+    fn needs_tree_view(&self) -> bool {
+        let Some(root) = self.root_element else {
+            return false;
+        };
+        !self.has_stylesheet_pi
+            && !matches!(
+                self.document.namespace(root),
+                Some(HTML_NAMESPACE | SVG_NAMESPACE | MATHML_NAMESPACE)
+            )
+    }
+
+    /// Removes everything from the document
+    fn clear_document(&mut self) {
+        let root = self.document.root();
+        for child in self.document.children(root).to_vec() {
+            self.document.detach(child);
+        }
+    }
+
+    /// Creates an XHTML element with the given class and attaches it to `parent`
+    fn append_element(&mut self, parent: NodeId, name: &str, class: Option<&str>) -> NodeId {
+        let mut attributes = HashMap::new();
+        if let Some(class) = class {
+            attributes.insert("class".to_string(), class.to_string());
+        }
+        let node_id = self
+            .document
+            .create_element(name, Some(HTML_NAMESPACE), attributes, Location::default());
+        self.document.attach(node_id, parent, None);
+        node_id
+    }
+
+    fn append_text(&mut self, parent: NodeId, text: &str) {
+        let node_id = self.document.create_text(text, Location::default());
+        self.document.attach(node_id, parent, None);
+    }
+
+    /// Replaces the document with an XHTML skeleton, and returns its `<body>`
+    fn create_viewer_document(&mut self, title: &str, body_class: &str) -> NodeId {
+        self.clear_document();
+
+        let root = self.document.root();
+        let html = self.append_element(root, "html", None);
+        let head = self.append_element(html, "head", None);
+        let title_id = self.append_element(head, "title", None);
+        self.append_text(title_id, title);
+        let body = self.append_element(html, "body", Some(body_class));
+
+        if let Some(stylesheet) = parse_stylesheet::<C>(XML_VIEWER_CSS, CssOrigin::UserAgent, "gosub://xml-viewer.css")
+        {
+            self.document.add_stylesheet(stylesheet);
+        }
+        body
+    }
+
+    /// Replaces the (partial) document with an error page for `error`
+    fn render_error_document(&mut self, error: &XmlError) {
+        let source_line = self.source_line(error.location.line);
+        let url = self
+            .document
+            .url()
+            .map_or_else(|| "unknown".to_string(), |url| url.to_string());
+
+        let body = self.create_viewer_document("XML Parsing Error", "xml-error");
+        let heading = self.append_element(body, "h1", None);
+        self.append_text(heading, &format!("XML Parsing Error: {}", error.message));
+        let location = self.append_element(body, "p", None);
+        self.append_text(location, &format!("Location: {url}"));
+        let position = self.append_element(body, "p", None);
+        self.append_text(
+            position,
+            &format!("Line Number {}, Column {}:", error.location.line, error.location.column),
+        );
+        let pre = self.append_element(body, "pre", None);
+        let marker = "-".repeat(error.location.column.saturating_sub(1));
+        self.append_text(pre, &format!("{source_line}\n{marker}^"));
+    }
+
+    /// Returns the text of the given (1-based) source line
+    fn source_line(&mut self, line: usize) -> String {
+        self.stream.reset_stream();
+        while self.location().line < line && self.stream.read_and_next() != StreamEnd {}
+
+        let mut text = String::new();
+        while let Ch(c) = self.stream.read_and_next() {
+            if c == '\n' {
+                break;
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    /// Replaces the document with a tree view of its elements
+    fn render_tree_view(&mut self) {
+        let Some(root_element) = self.root_element else {
+            return;
+        };
+
+        let body = self.create_viewer_document("XML Document", "xml-tree");
+        let header = self.append_element(body, "p", Some("xml-tree-header"));
+        self.append_text(
+            header,
+            "This XML file does not appear to have any style information associated with it. \
+             The document tree is shown below.",
+        );
+        self.append_tree_node(body, root_element);
+    }
+
+    fn append_tree_node(&mut self, parent: NodeId, node_id: NodeId) {
+        match self.document.node_type(node_id) {
+            NodeType::ElementNode => {
+                let name = self.document.tag_name(node_id).unwrap_or_default().to_string();
+                let mut attributes: Vec<(String, String)> = self
+                    .document
+                    .attributes(node_id)
+                    .map(|attrs| attrs.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    .unwrap_or_default();
+                attributes.sort();
+                let children = self.document.children(node_id).to_vec();
+
+                let container = self.append_element(parent, "div", Some("element"));
+                let open = self.append_element(container, "span", Some("tag"));
+                self.append_text(open, &format!("<{name}"));
+                for (key, value) in attributes {
+                    self.append_text(open, " ");
+                    let key_id = self.append_element(open, "span", Some("attribute-name"));
+                    self.append_text(key_id, &key);
+                    self.append_text(open, "=\"");
+                    let value_id = self.append_element(open, "span", Some("attribute-value"));
+                    self.append_text(value_id, &value);
+                    self.append_text(open, "\"");
+                }
+
+                if children.is_empty() {
+                    self.append_text(open, "/>");
+                    return;
+                }
+                self.append_text(open, ">");
+                for child in children {
+                    self.append_tree_node(container, child);
+                }
+                let close = self.append_element(container, "span", Some("tag"));
+                self.append_text(close, &format!("</{name}>"));
+            }
+            NodeType::TextNode => {
+                let text = self.document.text_value(node_id).unwrap_or_default().trim().to_string();
+                if !text.is_empty() {
+                    let span = self.append_element(parent, "span", Some("text"));
+                    self.append_text(span, &text);
+                }
+            }
+            NodeType::CommentNode => {
+                let comment = self.document.comment_value(node_id).unwrap_or_default().to_string();
+                let div = self.append_element(parent, "div", Some("comment"));
+                self.append_text(div, &format!("<!--{comment}-->"));
+            }
+            NodeType::DocumentNode | NodeType::DocTypeNode | NodeType::DocumentFragmentNode => {}
+        }
+    }
+}
+
+enum Reference {
+    Char(char),
+    /// Replacement text of an entity, which may contain further references
+    Entity(String),
+}
+
+fn parse_stylesheet<C: HasDocument>(
+    css: &str,
+    origin: CssOrigin,
+    source_url: &str,
+) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
+    let config = ParserConfig {
+        context: Context::Stylesheet,
+        location: Default::default(),
+        source: Some(source_url.to_string()),
+        ignore_errors: true,
+        match_values: false,
+    };
+
+    match C::CssSystem::parse_str(css, config, origin, source_url) {
+        Ok(stylesheet) => Some(stylesheet),
+        Err(err) => {
+            warn!("Error while parsing CSS stylesheet: {err} ");
+            None
+        }
+    }
+}
+
+/// Splits a qualified name into its prefix and local name
+fn split_qname(qname: &str, location: Location) -> XmlResult<(Option<&str>, &str)> {
+    match qname.split_once(':') {
+        None => Ok((None, qname)),
+        Some((prefix, local)) if !prefix.is_empty() && !local.is_empty() && !local.contains(':') => {
+            Ok((Some(prefix), local))
+        }
+        Some(_) => Err(XmlError::new(
+            "invalid-qualified-name",
+            format!("{qname} is not a valid qualified name"),
+            location,
+        )),
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-Char
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// https://www.w3.org/TR/xml/#NT-NameStartChar
+fn is_name_start_char(c: Character) -> bool {
+    let Ch(c) = c else {
+        return false;
+    };
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}' | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}' | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}' | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}' | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+/// https://www.w3.org/TR/xml/#NT-NameChar
+fn is_name_char(c: Character) -> bool {
+    is_name_start_char(c)
+        || matches!(
+            c,
+            Ch('-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::builder::DocumentBuilderImpl;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::testing::fixture::Config;
+    use gosub_shared::byte_stream::Encoding;
+
+    fn parse(xml: &str) -> (DocumentImpl<Config>, Vec<ParseError>) {
+        let mut stream = ByteStream::from_str(xml, Encoding::UTF8);
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        let errors = XmlParser::<Config>::parse_document(&mut stream, &mut doc, None).unwrap();
+        (doc, errors)
+    }
+
+    fn root_element(doc: &DocumentImpl<Config>) -> NodeId {
+        *doc.children(doc.root())
+            .iter()
+            .find(|id| doc.node_type(**id) == NodeType::ElementNode)
+            .unwrap()
+    }
+
+    #[test]
+    fn xhtml_document() {
+        let (doc, errors) = parse(
+            r##"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:svg="http://www.w3.org/2000/svg">
+  <body id="b"><P>caps&nbsp;&amp;&#x41;<![CDATA[<raw>]]></P><svg:rect xlink:href="#x" xmlns:xlink="http://www.w3.org/1999/xlink"/></body>
+</html>"##,
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(doc.doctype(), DocumentType::XML);
+
+        let html = root_element(&doc);
+        assert_eq!(doc.tag_name(html), Some("html"));
+        assert_eq!(doc.namespace(html), Some(HTML_NAMESPACE));
+
+        let body = doc.node_by_named_id("b").unwrap();
+        let children = doc.children(body);
+        // Element names are case-sensitive in XML
+        assert_eq!(doc.tag_name(children[0]), Some("P"));
+        let text = doc.children(children[0])[0];
+        assert_eq!(doc.text_value(text), Some("caps\u{a0}&A<raw>"));
+
+        assert_eq!(doc.tag_name(children[1]), Some("rect"));
+        assert_eq!(doc.namespace(children[1]), Some(SVG_NAMESPACE));
+        assert_eq!(doc.attribute(children[1], "xlink:href"), Some("#x"));
+    }
+
+    #[test]
+    fn standalone_svg() {
+        let (doc, errors) = parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><style>rect { fill: red; }</style><rect width="10" height="10"/></svg>"#,
+        );
+        assert!(errors.is_empty());
+
+        let svg = root_element(&doc);
+        assert_eq!(doc.namespace(svg), Some(SVG_NAMESPACE));
+        assert_eq!(doc.attribute(svg, "width"), Some("10"));
+        assert_eq!(doc.stylesheets().len(), 1);
+    }
+
+    #[test]
+    fn internal_entities() {
+        let (doc, errors) =
+            parse(r#"<!DOCTYPE r [ <!ENTITY who "world"> <!ENTITY greeting "hello &who;"> ]><r a="&greeting;"/>"#);
+        assert!(errors.is_empty(), "{errors:?}");
+        // No vocabulary we know, so this is shown as a tree
+        assert_eq!(doc.tag_name(root_element(&doc)), Some("html"));
+        assert!(doc.write().contains("hello world"));
+    }
+
+    #[test]
+    fn entity_values_keep_entity_references() {
+        let (doc, errors) = parse(
+            r#"<!DOCTYPE svg [ <!ENTITY e "&amp;lt;"> <!ENTITY c "&#38;lt;"> ]><svg xmlns="http://www.w3.org/2000/svg" a="&e;" b="&c;"/>"#,
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        let svg = root_element(&doc);
+        // `&amp;` is expanded where `e` is used, `&#38;` already when `c` is declared
+        assert_eq!(doc.attribute(svg, "a"), Some("&lt;"));
+        assert_eq!(doc.attribute(svg, "b"), Some("<"));
+    }
+
+    #[test]
+    fn entity_expansion_budget() {
+        let value = "x".repeat(MAX_ENTITY_EXPANSION - 1);
+        let within = format!(r#"<!DOCTYPE r [ <!ENTITY e "{value}"> ]><r>{}</r>"#, "&e;".repeat(4));
+        assert!(parse(&within).1.is_empty());
+
+        let count = MAX_DOCUMENT_ENTITY_EXPANSION / MAX_ENTITY_EXPANSION + 1;
+        let beyond = format!(
+            r#"<!DOCTYPE r [ <!ENTITY e "{value}"> ]><r>{}</r>"#,
+            "&e;".repeat(count)
+        );
+        let (_, errors) = parse(&beyond);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "entity-expansion-limit");
+    }
+
+    #[test]
+    fn generic_xml_tree_view() {
+        let (doc, _) = parse(r#"<?xml version="1.0"?><feed><entry id="1">text</entry><!-- note --><empty/></feed>"#);
+        let serialized = doc.write();
+
+        assert!(serialized.contains("does not appear to have any style information"));
+        assert!(serialized.contains("&lt;feed&gt;"));
+        assert!(serialized.contains("&lt;/feed&gt;"));
+        assert!(serialized.contains("&lt;empty/&gt;"));
+        assert!(serialized.contains("&lt;!-- note --&gt;"));
+
+        // With a stylesheet, the document is left alone
+        let (doc, _) = parse(r#"<?xml-stylesheet href="s.css"?><feed/>"#);
+        assert_eq!(doc.tag_name(root_element(&doc)), Some("feed"));
+        assert_eq!(doc.namespace(root_element(&doc)), None);
+    }
+
+    #[test]
+    fn well_formedness_errors() {
+        let cases = [
+            ("<a><b></a>", "mismatched-tag", 1, 7),
+            ("<a>", "unclosed-element", 1, 4),
+            ("", "no-root-element", 1, 1),
+            ("<a/><b/>", "junk-after-document-element", 1, 5),
+            ("<a x='1' x='2'/>", "duplicate-attribute", 1, 10),
+            ("<p:a/>", "unbound-prefix", 1, 1),
+            ("<a>&nbsp;</a>", "undefined-entity", 1, 4),
+            ("<a b=1/>", "not-well-formed", 1, 4),
+            ("<a>\n  <!-- x -- y --></a>", "malformed-comment", 2, 3),
+            ("<a> <?xml version='1.0'?></a>", "misplaced-xml-declaration", 1, 5),
+        ];
+
+        for (xml, code, line, column) in cases {
+            let (_, errors) = parse(xml);
+            assert_eq!(errors.len(), 1, "{xml}");
+            assert_eq!(errors[0].code, code, "{xml}");
+            assert_eq!(
+                (errors[0].location.line, errors[0].location.column),
+                (line, column),
+                "{xml}"
+            );
+        }
+    }
+
+    #[test]
+    fn error_document() {
+        let (doc, errors) = parse("<root>\n  <child></root>");
+        assert_eq!(errors[0].code, "mismatched-tag");
+
+        let serialized = doc.write();
+        assert!(serialized.contains("XML Parsing Error: mismatched tag, expected &lt;/child&gt;"));
+        assert!(serialized.contains("Line Number 2, Column 10:"));
+        assert!(serialized.contains("  &lt;child&gt;&lt;/root&gt;\n---------^"));
+        assert!(!serialized.contains("<root>"));
+    }
+}
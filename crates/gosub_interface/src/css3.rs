@@ -1,5 +1,6 @@
 use crate::config::HasDocument;
 use gosub_shared::async_executor::{WasmNotSend, WasmNotSendSync};
+use gosub_shared::byte_stream::Location;
 use gosub_shared::config::ParserConfig;
 use gosub_shared::errors::CssResult;
 use gosub_shared::node::NodeId;
@@ -35,6 +36,132 @@ pub struct HoverFingerprints {
     pub ids: std::collections::HashSet<String>,
 }
 
+/// Page box descriptors of an `@page` rule, resolved to CSS pixels.
+///
+/// Returned by [`CssStylesheet::page_rules`] so the print path can size its pages without knowing
+/// how the CSS implementation stores at-rules.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageRule {
+    /// Page pseudo-classes the rule is restricted to (`first`, `left`, `right`, `blank`), without
+    /// the colon. Empty when the rule applies to every page.
+    pub pseudo_classes: Vec<String>,
+    /// The `size` descriptor as `(width, height)`, when given.
+    pub size: Option<(f32, f32)>,
+    /// Top, right, bottom and left page margins, when given.
+    pub margin: [Option<f32>; 4],
+}
+
+/// The conditions on an `@import` rule, as returned by [`CssStylesheet::import_conditions`]. Two
+/// imports of the same URL with equal conditions pull in the same rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ImportConditions {
+    /// Cascade layer from `layer` or `layer(name)`
+    pub layer: Option<String>,
+    /// Media query list the import is restricted to, serialized as CSS
+    pub media: Option<String>,
+}
+
+impl ImportConditions {
+    /// Whether the import has neither a layer nor a media query
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.layer.is_none() && self.media.is_none()
+    }
+}
+
+/// A rule that matches an element, as reported by [`CssSystem::matched_rules`] for inspectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedRule {
+    /// The selector of the rule's selector list that matched, as CSS text. Empty for the
+    /// element's `style` attribute.
+    pub selector: String,
+    /// URL (or file path) of the stylesheet the rule comes from
+    pub sheet_url: String,
+    /// Where the rule starts in its stylesheet
+    pub location: Location,
+    /// Specificity of the matching selector as `(ids, classes, types)`
+    pub specificity: (u32, u32, u32),
+    pub origin: CssOrigin,
+    /// True for the element's `style` attribute, which outranks every selector of its origin
+    pub inline: bool,
+    /// The rule's declarations, in source order
+    pub declarations: Vec<MatchedDeclaration>,
+}
+
+/// A declaration of a [`MatchedRule`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedDeclaration {
+    pub property: String,
+    pub value: String,
+    pub important: bool,
+    /// True when a declaration with higher precedence sets every property this one sets, so it
+    /// has no effect on the element
+    pub overridden: bool,
+}
+
+/// A declaration of a `style` attribute, as returned by [`CssSystem::parse_style_attribute`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleDeclaration {
+    /// The property name, lowercased
+    pub property: String,
+    /// The value as written in the attribute, without `!important`
+    pub value: String,
+    pub important: bool,
+}
+
+/// The environment `@media` feature queries are evaluated against.
+///
+/// Set by the engine from the tab's viewport, device and user preferences before styles are
+/// computed, and passed to [`CssSystem::media_restyle_roots`] when it changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaFeatures {
+    /// Width of the layout viewport in CSS pixels (`width`, `aspect-ratio`, `orientation`)
+    pub width: f32,
+    /// Height of the layout viewport in CSS pixels
+    pub height: f32,
+    /// Device pixels per CSS pixel (`resolution`, `-webkit-device-pixel-ratio`)
+    pub device_pixel_ratio: f32,
+    /// `prefers-color-scheme`
+    pub color_scheme: ColorScheme,
+    /// `prefers-reduced-motion: reduce`
+    pub reduced_motion: bool,
+    /// The primary pointing device can hover over elements (`hover`, `any-hover`)
+    pub hover: bool,
+    /// Accuracy of the primary pointing device (`pointer`, `any-pointer`)
+    pub pointer: PointerAccuracy,
+}
+
+impl Default for MediaFeatures {
+    fn default() -> Self {
+        Self {
+            width: 1280.0,
+            height: 800.0,
+            device_pixel_ratio: 1.0,
+            color_scheme: ColorScheme::Light,
+            reduced_motion: false,
+            hover: true,
+            pointer: PointerAccuracy::Fine,
+        }
+    }
+}
+
+/// The color scheme the user prefers (`prefers-color-scheme`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Accuracy of a pointing device (`pointer`): none, a finger (coarse) or a mouse (fine).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerAccuracy {
+    None,
+    Coarse,
+    #[default]
+    Fine,
+}
+
 /// The `CssSystem` trait is a trait that defines all things CSS3 that are used by other non-css3 crates. This is the main trait that
 /// is used to parse CSS3 files. It contains sub elements like the Stylesheet trait that is used in for instance the Document trait.
 pub trait CssSystem: Clone + Debug + 'static {
@@ -69,12 +196,69 @@ pub trait CssSystem: Clone + Debug + 'static {
         None
     }
 
+    /// Returns the elements below `root` (excluding `root` itself) that match the selector list
+    /// `selector`, in document order, using the same matcher as the cascade.
+    ///
+    /// # Errors
+    /// Returns an error when `selector` cannot be parsed.
+    fn query_selector_all<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        root: NodeId,
+        selector: &str,
+    ) -> CssResult<Vec<NodeId>>;
+
+    /// Returns the first element below `root` that matches the selector list `selector`, in
+    /// document order. The default implementation takes the first result of
+    /// [`CssSystem::query_selector_all`].
+    ///
+    /// # Errors
+    /// Returns an error when `selector` cannot be parsed.
+    fn query_selector<C: HasDocument<CssSystem = Self>>(
+        doc: &C::Document,
+        root: NodeId,
+        selector: &str,
+    ) -> CssResult<Option<NodeId>> {
+        Ok(Self::query_selector_all::<C>(doc, root, selector)?.first().copied())
+    }
+
     fn load_default_useragent_stylesheet() -> Self::Stylesheet;
 
     /// Scan `sheets` and collect the [`HoverFingerprints`] - the element types/classes/ids that
     /// are the subject of a `:hover` rule. Lets the engine cheaply decide whether a hover change
     /// can affect styling without re-running selector matching.
     fn hover_fingerprints(sheets: &[Self::Stylesheet]) -> HoverFingerprints;
+
+    /// Parses the contents of a `style` attribute into its declarations, in source order. A
+    /// declaration that does not parse, or that sets an unknown property, is left out. The
+    /// default implementation returns no declarations.
+    fn parse_style_attribute(_style: &str) -> Vec<StyleDeclaration> {
+        Vec::new()
+    }
+
+    /// Returns the rules that match element `id`, highest precedence first, with the declarations
+    /// that lost the cascade marked as overridden. Meant for inspectors; styling goes through
+    /// [`CssSystem::properties_from_node`]. The default implementation reports no rules.
+    fn matched_rules<C: HasDocument<CssSystem = Self>>(
+        _doc: &C::Document,
+        _id: NodeId,
+        _sheets: &[Self::Stylesheet],
+    ) -> Vec<MatchedRule> {
+        Vec::new()
+    }
+
+    /// Returns the roots of the subtrees whose styles change when the media features go from
+    /// `old` to `new`: elements matched by a rule whose `@media` queries flip, or, when the
+    /// viewport size changes, by a rule using viewport-relative units. Everything outside these
+    /// subtrees keeps its computed style. `None` means every style may change. The default
+    /// implementation returns `None`.
+    fn media_restyle_roots<C: HasDocument<CssSystem = Self>>(
+        _doc: &C::Document,
+        _sheets: &[Self::Stylesheet],
+        _old: &MediaFeatures,
+        _new: &MediaFeatures,
+    ) -> Option<Vec<NodeId>> {
+        None
+    }
 }
 
 pub trait CssStylesheet: PartialEq + Debug {
@@ -87,10 +271,40 @@ pub trait CssStylesheet: PartialEq + Debug {
     /// `@font-face` web fonts declared in this stylesheet, as
     /// `(family, source_urls, unicode_range)` tuples. The source URLs are unresolved
     /// (relative to the stylesheet's own URL); `unicode_range` is the raw descriptor or
-    /// `None` when the face covers all code points.
+    /// `None` when the face covers all code points. Faces whose media queries do not match the
+    /// current media are left out.
     fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
         Vec::new()
     }
+
+    /// `@page` rules declared in this stylesheet, in source order, minus those in `@media` blocks
+    /// (or imported under a media query) that do not match the current media.
+    fn page_rules(&self) -> Vec<PageRule> {
+        Vec::new()
+    }
+
+    /// URLs of the `@import` rules at the top of this stylesheet, in source order, minus those
+    /// whose `supports()` condition fails. The URLs are unresolved (relative to the stylesheet's
+    /// own URL).
+    fn imports(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The conditions on the `index`-th entry of [`imports`](Self::imports) (media queries, cascade
+    /// layer), empty when it has none.
+    fn import_conditions(&self, _index: usize) -> ImportConditions {
+        ImportConditions::default()
+    }
+
+    /// Restricts `imported`, the sheet loaded for the `index`-th entry of [`imports`](Self::imports),
+    /// to the conditions on that `@import`, so e.g. `@import "x.css" print` only styles (and only
+    /// provides fonts and page rules to) print, and puts its rules in the cascade layer of a
+    /// layered import.
+    fn apply_import(&self, _index: usize, _imported: &mut Self)
+    where
+        Self: Sized,
+    {
+    }
 }
 
 pub trait CssPropertyMap<S: CssSystem>: Default + Debug + WasmNotSend {
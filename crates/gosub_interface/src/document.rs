@@ -1,17 +1,20 @@
 use crate::config::HasCssSystem;
 use crate::css3::CssSystem;
-use crate::node::{NodeType, QuirksMode};
+use crate::mutation::MutationRecord;
+use crate::node::{NodeType, QuirksMode, ShadowRootMode};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::node::NodeId;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use url::Url;
 
-/// Whether this is a regular HTML document or a fragment (e.g. iframe srcdoc)
+/// Whether this is a regular HTML document, a fragment (e.g. iframe srcdoc) or an XML document
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum DocumentType {
     HTML,
     IframeSrcDoc,
+    /// Parsed by the XML parser (XHTML, SVG and other XML documents)
+    XML,
 }
 
 /// Storage-agnostic document interface.
@@ -85,6 +88,77 @@ pub trait Document<C: HasCssSystem>: Sized + Display + Debug + PartialEq + 'stat
     fn template_contents(&self, id: NodeId) -> Option<NodeId>;
     fn set_template_contents(&mut self, id: NodeId, fragment: NodeId);
 
+    // Shadow DOM
+
+    /// Attach a new, empty shadow root to `host` and return it. Returns `None` when `host` is not
+    /// an element or already hosts a shadow root.
+    fn attach_shadow_root(&mut self, host: NodeId, mode: ShadowRootMode) -> Option<NodeId>;
+    /// The shadow root hosted by `host` (regardless of its mode)
+    fn shadow_root(&self, host: NodeId) -> Option<NodeId>;
+    /// The host element of shadow root `root`
+    fn shadow_host(&self, root: NodeId) -> Option<NodeId>;
+    fn shadow_root_mode(&self, root: NodeId) -> Option<ShadowRootMode>;
+
+    /// Root of the tree `id` lives in: the document root, a shadow root, or the topmost ancestor
+    /// of a detached subtree.
+    fn tree_root(&self, id: NodeId) -> NodeId {
+        let mut current = id;
+        while let Some(parent) = self.parent(current) {
+            current = parent;
+        }
+        current
+    }
+
+    /// The `<slot>` a child of a shadow host is assigned to: the first slot in the shadow tree
+    /// whose `name` matches the child's `slot` attribute (text nodes go to the default slot).
+    fn assigned_slot(&self, id: NodeId) -> Option<NodeId> {
+        let name = match self.node_type(id) {
+            NodeType::ElementNode => self.attribute(id, "slot").unwrap_or_default(),
+            NodeType::TextNode => "",
+            _ => return None,
+        };
+        let root = self.shadow_root(self.parent(id)?)?;
+
+        // Pre-order walk; children are pushed in reverse so they pop in tree order
+        let mut stack: Vec<NodeId> = self.children(root).iter().rev().copied().collect();
+        while let Some(current) = stack.pop() {
+            if self.tag_name(current) == Some("slot") && self.attribute(current, "name").unwrap_or_default() == name {
+                return Some(current);
+            }
+            stack.extend(self.children(current).iter().rev().copied());
+        }
+        None
+    }
+
+    /// The nodes assigned to `slot`, in tree order. Empty when `slot` is not a slot in a shadow
+    /// tree, or when nothing is assigned to it (its own children are then rendered as fallback).
+    fn assigned_nodes(&self, slot: NodeId) -> Vec<NodeId> {
+        if self.tag_name(slot) != Some("slot") {
+            return Vec::new();
+        }
+        let Some(host) = self.shadow_host(self.tree_root(slot)) else {
+            return Vec::new();
+        };
+        self.children(host)
+            .iter()
+            .copied()
+            .filter(|&child| self.assigned_slot(child) == Some(slot))
+            .collect()
+    }
+
+    /// Parent of `id` in the flat tree: children of a shadow root hang off the host, and children
+    /// of a shadow host hang off the slot they are assigned to (`None` when they are not slotted).
+    fn flat_tree_parent(&self, id: NodeId) -> Option<NodeId> {
+        let parent = self.parent(id)?;
+        if let Some(host) = self.shadow_host(parent) {
+            return Some(host);
+        }
+        if self.shadow_root(parent).is_some() {
+            return self.assigned_slot(id);
+        }
+        Some(parent)
+    }
+
     // Text / comment / doctype data
 
     fn text_value(&self, id: NodeId) -> Option<&str>;
@@ -132,6 +206,22 @@ pub trait Document<C: HasCssSystem>: Sized + Display + Debug + PartialEq + 'stat
 
     fn stylesheets(&self) -> &[<C::CssSystem as CssSystem>::Stylesheet];
     fn add_stylesheet(&mut self, sheet: <C::CssSystem as CssSystem>::Stylesheet);
+    /// Inserts `sheet` at `index` in cascade order, e.g. an `@import`ed sheet right before the
+    /// sheet importing it. Panics if `index > stylesheets().len()`.
+    fn insert_stylesheet(&mut self, index: usize, sheet: <C::CssSystem as CssSystem>::Stylesheet);
+
+    /// Stylesheets scoped to shadow root `root` (its `<style>` elements). These only apply to the
+    /// shadow tree itself and, through `:host` and `::slotted()`, to its host and slotted nodes.
+    fn shadow_stylesheets(&self, root: NodeId) -> &[<C::CssSystem as CssSystem>::Stylesheet];
+    fn add_shadow_stylesheet(&mut self, root: NodeId, sheet: <C::CssSystem as CssSystem>::Stylesheet);
+
+    // Mutation journal
+
+    /// Starts or stops journaling tree changes. Recording is off by default so that building the
+    /// document does not fill the journal; stopping discards the records not taken yet.
+    fn record_mutations(&mut self, enabled: bool);
+    /// Drains the changes recorded since the last call, oldest first.
+    fn take_mutations(&mut self) -> Vec<MutationRecord>;
 
     // Serialisation
 
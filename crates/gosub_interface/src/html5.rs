@@ -2,6 +2,7 @@ use crate::config::HasDocument;
 use gosub_shared::byte_stream::{ByteStream, Location};
 use gosub_shared::node::NodeId;
 use gosub_shared::types::{ParseError, Result};
+use url::Url;
 
 pub trait Html5Parser<C: HasDocument> {
     type Options: ParserOptions;
@@ -20,3 +21,55 @@ pub trait Html5Parser<C: HasDocument> {
 pub trait ParserOptions {
     fn new(scripting: bool) -> Self;
 }
+
+/// How a parser-inserted script is run, as decided by the "prepare the script element" steps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptKind {
+    /// Inline script: executed immediately, while the parser waits
+    Inline,
+    /// External script without `async` or `defer`: blocks the parser until it has been fetched and run
+    Blocking,
+    /// External script with `async`: runs whenever it is available, without blocking the parser
+    Async,
+    /// External (or module) script with `defer`: runs in order once parsing has finished
+    Defer,
+}
+
+/// A script element that the tree builder has finished parsing
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParserScript {
+    /// The `<script>` element in the document
+    pub node_id: NodeId,
+    pub kind: ScriptKind,
+    /// Inline source text (empty for external scripts)
+    pub text: String,
+    /// Resolved `src` URL for external scripts
+    pub src: Option<Url>,
+    /// True for `type="module"` scripts
+    pub module: bool,
+}
+
+/// Insertion point of the parser. Markup written here is parsed directly after the `</script>`
+/// end tag of the running script, as `document.write()` requires.
+pub trait DocumentWrite {
+    fn write(&mut self, markup: &str);
+
+    fn writeln(&mut self, markup: &str) {
+        self.write(markup);
+        self.write("\n");
+    }
+}
+
+/// Runs the scripts that the tree builder encounters. Inline and parser-blocking scripts are
+/// handed to `execute` while parsing is paused; async and defer scripts are handed to `schedule`
+/// and must be run by the host itself.
+pub trait ScriptHost<C: HasDocument> {
+    /// Executes an inline or parser-blocking script. The parser resumes once this returns.
+    fn execute(&mut self, script: &ParserScript, document: &mut C::Document, writer: &mut dyn DocumentWrite);
+
+    /// Queues an async or defer script
+    fn schedule(&mut self, script: ParserScript);
+
+    /// Called when the parser has stopped, which is when deferred scripts should run
+    fn parsing_finished(&mut self, _document: &mut C::Document) {}
+}
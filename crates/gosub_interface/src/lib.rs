@@ -6,5 +6,6 @@ pub mod font_system;
 pub mod html5;
 pub mod input;
 pub mod layout;
+pub mod mutation;
 pub mod node;
 pub mod render;
@@ -0,0 +1,123 @@
+//! Mutation journal records.
+//!
+//! While recording is switched on (`Document::record_mutations`), a document journals every
+//! change made to its tree. Consumers drain the journal with `Document::take_mutations`, much
+//! like a DOM `MutationObserver`, and turn the records into an [`Invalidation`] so that only
+//! the affected subtrees are restyled and laid out again. The engine's browsing context does so
+//! for the edits made through it, keeping the styles of everything outside the restyled subtrees.
+
+use crate::config::HasCssSystem;
+use crate::document::Document;
+use gosub_shared::node::NodeId;
+use std::collections::HashSet;
+
+/// A single change to the document tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum MutationRecord {
+    /// Nodes were added to or removed from the children of `target`. The siblings are the
+    /// nodes around the added or removed ones right after the change.
+    ChildList {
+        target: NodeId,
+        added: Vec<NodeId>,
+        removed: Vec<NodeId>,
+        previous_sibling: Option<NodeId>,
+        next_sibling: Option<NodeId>,
+    },
+    /// An attribute of `target` was set or removed. `old_value` is `None` when the attribute
+    /// did not exist before.
+    Attributes {
+        target: NodeId,
+        name: String,
+        old_value: Option<String>,
+    },
+    /// The text of a text node changed
+    CharacterData { target: NodeId, old_value: String },
+}
+
+impl MutationRecord {
+    /// The node whose children, attributes or text changed
+    pub fn target(&self) -> NodeId {
+        match self {
+            Self::ChildList { target, .. } | Self::Attributes { target, .. } | Self::CharacterData { target, .. } => {
+                *target
+            }
+        }
+    }
+}
+
+/// What a batch of mutations invalidates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Invalidation {
+    /// Roots of the subtrees whose computed styles must be recomputed
+    pub restyle: Vec<NodeId>,
+    /// Roots of the subtrees that must be laid out again. Covers every restyled subtree.
+    pub relayout: Vec<NodeId>,
+}
+
+impl Invalidation {
+    /// Collects the subtrees touched by `records`. Changes to nodes no longer connected to the
+    /// document are dropped, and a subtree nested in another invalidated subtree is folded into
+    /// it.
+    ///
+    /// - A child list change restyles the parent's subtree: structural pseudo-classes and
+    ///   sibling combinators of its other children may now match differently.
+    /// - An attribute change restyles the element's subtree (descendants can match on it).
+    /// - Both also restyle the subtrees of the target's following siblings, which sibling
+    ///   combinators (`.a + b`, `:empty ~ b`) can match against the target.
+    /// - A text change does not affect styles; it relayouts the text's parent.
+    pub fn from_records<C: HasCssSystem, D: Document<C>>(doc: &D, records: &[MutationRecord]) -> Self {
+        let mut restyle = Vec::new();
+        let mut relayout = Vec::new();
+        for record in records {
+            let target = record.target();
+            match record {
+                MutationRecord::ChildList { .. } | MutationRecord::Attributes { .. } => {
+                    let mut current = Some(target);
+                    while let Some(id) = current {
+                        restyle.push(id);
+                        relayout.push(id);
+                        current = doc.next_sibling(id);
+                    }
+                }
+                MutationRecord::CharacterData { .. } => relayout.push(doc.parent(target).unwrap_or(target)),
+            }
+        }
+
+        Self {
+            restyle: subtree_roots(doc, restyle),
+            relayout: subtree_roots(doc, relayout),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.restyle.is_empty() && self.relayout.is_empty()
+    }
+}
+
+/// Parent of `id` with shadow roots hopping to their host, so that a change inside a shadow tree
+/// is folded into an invalidated host
+fn composed_parent<C: HasCssSystem, D: Document<C>>(doc: &D, id: NodeId) -> Option<NodeId> {
+    doc.parent(id).or_else(|| doc.shadow_host(id))
+}
+
+/// Deduplicates `nodes`, dropping the disconnected ones and the ones with an ancestor in the set.
+/// Keeps the order in which the nodes were first seen.
+fn subtree_roots<C: HasCssSystem, D: Document<C>>(doc: &D, nodes: Vec<NodeId>) -> Vec<NodeId> {
+    let set: HashSet<NodeId> = nodes.iter().copied().collect();
+    let mut seen = HashSet::new();
+
+    nodes
+        .into_iter()
+        .filter(|&id| seen.insert(id))
+        .filter(|&id| {
+            let mut current = id;
+            while let Some(parent) = composed_parent(doc, current) {
+                if set.contains(&parent) {
+                    return false;
+                }
+                current = parent;
+            }
+            current == doc.root()
+        })
+        .collect()
+}
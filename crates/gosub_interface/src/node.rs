@@ -12,4 +12,14 @@ pub enum NodeType {
     TextNode,
     CommentNode,
     ElementNode,
+    /// A shadow root. Shadow roots hang off their host element instead of being one of its
+    /// children, so they never show up when walking `children()`.
+    DocumentFragmentNode,
+}
+
+/// Whether a shadow root is exposed to scripts through its host (`element.shadowRoot`)
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ShadowRootMode {
+    Open,
+    Closed,
 }
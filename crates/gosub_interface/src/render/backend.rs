@@ -466,6 +466,16 @@ pub trait RenderBackend: Send {
         Box::new(())
     }
 
+    /// Renders a paginated document into a vector PDF and returns the file's bytes.
+    ///
+    /// `document` is the render pipeline's `PrintDocument` (page geometry plus per-page paint
+    /// commands), type-erased for the same reason as [`Self::create_rasterizer`]; backends
+    /// downcast it. Default is unsupported; only backends with a vector output surface override it.
+    fn print_to_pdf(&self, document: &dyn Any) -> anyhow::Result<Vec<u8>> {
+        let _ = document;
+        anyhow::bail!("print_to_pdf not supported by backend '{}'", self.name())
+    }
+
     /// How the engine should drive [`Self::create_rasterizer`] over the tile set.
     /// Defaults to [`RasterStrategy::None`] (no rasterization).
     fn raster_strategy(&self) -> RasterStrategy {
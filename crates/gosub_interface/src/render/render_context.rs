@@ -24,4 +24,10 @@ pub trait RenderContext {
     fn scroll_offset(&self) -> (f64, f64) {
         (0.0, 0.0)
     }
+
+    /// Page zoom factor (1.0 = 100%). The paint scene is laid out in unzoomed CSS pixels, so GPU
+    /// backends scale it by this factor; the scroll offset is already in zoomed pixels.
+    fn page_zoom(&self) -> f64 {
+        1.0
+    }
 }
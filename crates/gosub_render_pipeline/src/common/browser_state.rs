@@ -11,6 +11,34 @@ pub enum WireframeState {
     Both,
 }
 
+/// Debug overlays painted on top of the page, toggled per tab. All off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugOverlays {
+    /// Margin, border, padding and content outlines of the hovered element.
+    pub box_model: bool,
+    /// Tile boundaries, labelled with their tile and layer ids.
+    pub tiles: bool,
+    /// Layer bounds, labelled with their layer id and z-order.
+    pub layers: bool,
+    /// Tint the tiles repainted by the last frame.
+    pub repaint_flash: bool,
+}
+
+impl DebugOverlays {
+    /// Every overlay switched on.
+    pub const ALL: DebugOverlays = DebugOverlays {
+        box_model: true,
+        tiles: true,
+        layers: true,
+        repaint_flash: true,
+    };
+
+    /// Whether any overlay is switched on.
+    pub fn any(&self) -> bool {
+        self.box_model || self.tiles || self.layers || self.repaint_flash
+    }
+}
+
 /// Per-tab render settings passed through the pipeline instead of living in a global.
 pub struct BrowserState {
     /// Indexed by layer id; `true` means the layer is drawn.
@@ -1,5 +1,6 @@
 use crate::common::document::style::{
-    intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap, Unit, Value,
+    intern, legacy_break_keyword, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap,
+    Unit, Value,
 };
 use cow_utils::CowUtils;
 use gosub_shared::css_colors::named_color_hex;
@@ -324,6 +325,21 @@ fn apply_style_kv(style: &mut NodeStyle, key: &str, value: &str) {
         "white-space" => style.set(StyleProperty::WhiteSpace, parse_style_str(value)),
         "text-transform" => style.set(StyleProperty::TextTransform, parse_style_str(value)),
         "mix-blend-mode" => style.set(StyleProperty::MixBlendMode, parse_style_str(value)),
+        "break-before" => style.set(StyleProperty::BreakBefore, parse_style_str(value)),
+        "break-after" => style.set(StyleProperty::BreakAfter, parse_style_str(value)),
+        "break-inside" => style.set(StyleProperty::BreakInside, parse_style_str(value)),
+        "page-break-before" => style.set(
+            StyleProperty::BreakBefore,
+            Value::Keyword(intern(legacy_break_keyword(value.trim()))),
+        ),
+        "page-break-after" => style.set(
+            StyleProperty::BreakAfter,
+            Value::Keyword(intern(legacy_break_keyword(value.trim()))),
+        ),
+        "page-break-inside" => style.set(
+            StyleProperty::BreakInside,
+            Value::Keyword(intern(legacy_break_keyword(value.trim()))),
+        ),
         "text-decoration" | "text-decoration-line" => {
             let has_underline = value.contains("underline");
             let has_line_through = value.contains("line-through");
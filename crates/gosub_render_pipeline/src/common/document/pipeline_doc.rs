@@ -1,16 +1,18 @@
 use crate::common::document::node::{AttrMap, ElementData, Node, NodeType};
 use crate::common::document::style::{
-    intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap, Unit, Value,
+    intern, legacy_break_keyword, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap,
+    Unit, Value,
 };
 use crate::painter::commands::color::Color;
 use crate::painter::commands::gradient::{ColorStop, Gradient, LinearGradient, Tiling};
 use cow_utils::CowUtils;
-use gosub_interface::config::HasDocument;
+use gosub_interface::config::{HasCssSystem, HasDocument};
 use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
 use gosub_interface::document::Document as _;
-use gosub_interface::node::NodeType as GosubNodeType;
+use gosub_interface::node::{NodeType as GosubNodeType, QuirksMode};
 use gosub_shared::node::NodeId;
 use parking_lot::Mutex;
+use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -664,6 +666,10 @@ impl Default for BgImageLayout {
 // ── PipelineDocument trait ────────────────────────────────────────────────────
 
 pub trait PipelineDocument: Send + Sync {
+    /// The implementation behind the trait object, for callers that need to reach it, e.g. to
+    /// carry a [`GosubDocumentAdapter`]'s cached styles over to an edited document.
+    fn as_any(&self) -> &dyn Any;
+
     fn root(&self) -> Option<NodeId>;
     fn children(&self, id: NodeId) -> Vec<NodeId>;
     fn node_kind(&self, id: NodeId) -> PipelineNodeKind;
@@ -678,6 +684,11 @@ pub trait PipelineDocument: Send + Sync {
         None
     }
 
+    /// The quirks mode of the document, which switches on the legacy style and layout quirks.
+    fn quirks_mode(&self) -> QuirksMode {
+        QuirksMode::NoQuirks
+    }
+
     /// Returns the own (explicitly-set) value for `prop` on node `id`, without recursing.
     fn get_own_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value>;
 
@@ -701,6 +712,18 @@ pub trait PipelineDocument: Send + Sync {
     /// Cheaper than `clear_style_cache` for hover repaints where only a few elements changed.
     fn invalidate_style_for_nodes(&self, _ids: &[NodeId]) {}
 
+    /// Invalidates the cached styles of `roots` and everything below them, e.g. the restyle
+    /// roots of an `Invalidation` built from the document's mutation journal.
+    fn invalidate_style_for_subtrees(&self, roots: &[NodeId]) {
+        let mut ids = Vec::new();
+        let mut stack = roots.to_vec();
+        while let Some(id) = stack.pop() {
+            ids.push(id);
+            stack.extend(self.children(id));
+        }
+        self.invalidate_style_for_nodes(&ids);
+    }
+
     /// Returns the computed value for `prop` on node `id`:
     ///  1. own value if set,
     ///  2. parent's computed value if the property is inherited,
@@ -720,7 +743,7 @@ pub trait PipelineDocument: Send + Sync {
             v
         } else {
             let meta = prop.meta();
-            if meta.inherited {
+            if meta.inherited && !self.blocks_font_inheritance(id, prop) {
                 if let Some(parent) = self.parent(id) {
                     return self.get_style(parent, prop);
                 }
@@ -749,6 +772,21 @@ pub trait PipelineDocument: Send + Sync {
         }
     }
 
+    /// In quirks mode a `table` does not inherit its font and text properties, so legacy layout
+    /// tables start out from the initial values whatever the surrounding text uses.
+    fn blocks_font_inheritance(&self, id: NodeId, prop: &StyleProperty) -> bool {
+        matches!(
+            prop,
+            StyleProperty::FontSize
+                | StyleProperty::FontWeight
+                | StyleProperty::FontStyle
+                | StyleProperty::LineHeight
+                | StyleProperty::WhiteSpace
+                | StyleProperty::TextAlign
+        ) && self.quirks_mode() == QuirksMode::Quirks
+            && self.tag_name(id).is_some_and(|tag| tag.eq_ignore_ascii_case("table"))
+    }
+
     /// The computed `font-size` of `id` in px, or 16px if unresolvable. Resolving
     /// `font-size` only ever recurses to the *parent* (never to `id` itself), so this is
     /// safe to call while resolving font-relative units on other properties of `id`.
@@ -883,6 +921,9 @@ fn resolve_content<S: CssSystem>(p: &S::Property) -> Option<String> {
 
 // ── GosubDocumentAdapter ──────────────────────────────────────────────────────
 
+type PropertyMap<C> = <<C as HasCssSystem>::CssSystem as CssSystem>::PropertyMap;
+type PseudoBoxes<C> = HashMap<(NodeId, bool), Option<Arc<PseudoBox<PropertyMap<C>>>>>;
+
 /// Adapts any `gosub_interface::document::Document<C>` into a `PipelineDocument`.
 pub struct GosubDocumentAdapter<C>
 where
@@ -896,8 +937,20 @@ where
     inline_style_cache: Mutex<HashMap<NodeId, NodeStyle>>,
     /// Materialized `::before` / `::after` pseudo-boxes, keyed by `(owner, is_after)`.
     /// `None` means "no generated box". Populated lazily.
-    #[allow(clippy::type_complexity)]
-    pseudo_cache: Mutex<HashMap<(NodeId, bool), Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>>>>,
+    pseudo_cache: Mutex<PseudoBoxes<C>>,
+}
+
+/// The styles a [`GosubDocumentAdapter`] has cached, taken out of it with
+/// [`GosubDocumentAdapter::take_styles`] so that an adapter for an edited version of the same
+/// document can start from them.
+pub struct CachedStyles<C>
+where
+    C: HasDocument,
+    <C::CssSystem as CssSystem>::PropertyMap: Send + Sync,
+{
+    styles: HashMap<NodeId, Arc<PropertyMap<C>>>,
+    inline_styles: HashMap<NodeId, NodeStyle>,
+    pseudo_boxes: PseudoBoxes<C>,
 }
 
 impl<C> GosubDocumentAdapter<C>
@@ -915,6 +968,29 @@ where
         }
     }
 
+    /// Moves the cached styles out of this adapter, which then computes them again on demand.
+    pub fn take_styles(&self) -> CachedStyles<C> {
+        CachedStyles {
+            styles: std::mem::take(&mut *self.style_cache.lock()),
+            inline_styles: std::mem::take(&mut *self.inline_style_cache.lock()),
+            pseudo_boxes: std::mem::take(&mut *self.pseudo_cache.lock()),
+        }
+    }
+
+    /// An adapter for `doc` that starts from `styles`, taken from an adapter for an earlier
+    /// version of the same document. The styles of `restyle` and their subtrees, e.g. the restyle
+    /// roots of the `Invalidation` built from the edits in between, are computed again.
+    pub fn with_styles(doc: Arc<C::Document>, styles: CachedStyles<C>, restyle: &[NodeId]) -> Self {
+        let adapter = Self {
+            doc,
+            style_cache: Mutex::new(styles.styles),
+            inline_style_cache: Mutex::new(styles.inline_styles),
+            pseudo_cache: Mutex::new(styles.pseudo_boxes),
+        };
+        adapter.invalidate_style_for_subtrees(restyle);
+        adapter
+    }
+
     /// `None` if no rule generates one. Computed and cached on first access.
     fn pseudo_box(
         &self,
@@ -1092,6 +1168,23 @@ where
             return None;
         }
 
+        // The legacy `page-break-*` properties alias `break-*`, with `always` meaning `page`.
+        let legacy_break = match prop {
+            StyleProperty::BreakBefore => Some("page-break-before"),
+            StyleProperty::BreakAfter => Some("page-break-after"),
+            StyleProperty::BreakInside => Some("page-break-inside"),
+            _ => None,
+        };
+        if let Some(legacy) = legacy_break {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, css_name) {
+                if let Some(v) = css_property_to_value::<C::CssSystem>(p, prop) {
+                    return Some(v);
+                }
+            }
+            let keyword = <_ as CssPropertyMap<C::CssSystem>>::get(map, legacy)?.as_string()?;
+            return Some(Value::Keyword(intern(legacy_break_keyword(keyword))));
+        }
+
         if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, css_name) {
             if let Some(v) = css_property_to_value::<C::CssSystem>(p, prop) {
                 return Some(v);
@@ -1110,6 +1203,54 @@ where
         None
     }
 
+    /// Children of `id` in the flat tree: a shadow host renders its shadow tree instead of its own
+    /// children, and a slot is replaced by the nodes assigned to it (or by its fallback content),
+    /// as `slot { display: contents }` asks for.
+    fn flat_children(&self, id: NodeId) -> Vec<NodeId> {
+        let children = match self.doc.shadow_root(id) {
+            Some(root) => self.doc.children(root),
+            None => self.doc.children(id),
+        };
+        let mut out = Vec::with_capacity(children.len());
+        for &child in children {
+            self.push_flat_child(child, &mut out);
+        }
+        out
+    }
+
+    fn push_flat_child(&self, id: NodeId, out: &mut Vec<NodeId>) {
+        if !self.is_shadow_slot(id) {
+            out.push(id);
+            return;
+        }
+        let assigned = self.doc.assigned_nodes(id);
+        let replacement = if assigned.is_empty() {
+            self.doc.children(id)
+        } else {
+            assigned.as_slice()
+        };
+        for &child in replacement {
+            self.push_flat_child(child, out);
+        }
+    }
+
+    /// Parent of `id` in the flat tree, skipping the slots it was distributed through. `None` for
+    /// a child of a shadow host that is not assigned to any slot; it is not rendered.
+    fn flat_parent(&self, id: NodeId) -> Option<NodeId> {
+        let mut current = id;
+        loop {
+            let parent = self.doc.flat_tree_parent(current)?;
+            if !self.is_shadow_slot(parent) {
+                return Some(parent);
+            }
+            current = parent;
+        }
+    }
+
+    fn is_shadow_slot(&self, id: NodeId) -> bool {
+        self.doc.tag_name(id) == Some("slot") && self.doc.shadow_host(self.doc.tree_root(id)).is_some()
+    }
+
     fn find_child_by_tag(&self, parent: NodeId, tag: &str) -> Option<NodeId> {
         self.doc
             .children(parent)
@@ -1125,6 +1266,10 @@ where
     C::Document: Send + Sync,
     <C::CssSystem as CssSystem>::PropertyMap: Send + Sync,
 {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn root(&self) -> Option<NodeId> {
         self.html_node_id().or_else(|| Some(self.doc.root()))
     }
@@ -1154,7 +1299,7 @@ where
         if self.pseudo_box(id, false).is_some() {
             out.push(encode_pseudo(id, ROLE_BEFORE_ELEM));
         }
-        out.extend(self.doc.children(id).iter().copied());
+        out.extend(self.flat_children(id));
         if self.pseudo_box(id, true).is_some() {
             out.push(encode_pseudo(id, ROLE_AFTER_ELEM));
         }
@@ -1174,10 +1319,14 @@ where
             GosubNodeType::TextNode => PipelineNodeKind::Text,
             GosubNodeType::CommentNode | GosubNodeType::DocTypeNode => PipelineNodeKind::Comment,
             GosubNodeType::ElementNode => PipelineNodeKind::Element,
-            GosubNodeType::DocumentNode => PipelineNodeKind::Element,
+            GosubNodeType::DocumentNode | GosubNodeType::DocumentFragmentNode => PipelineNodeKind::Element,
         }
     }
 
+    fn quirks_mode(&self) -> QuirksMode {
+        self.doc.quirks_mode()
+    }
+
     fn tag_name(&self, id: NodeId) -> Option<String> {
         // Pseudo-elements have no tag name.
         if is_pseudo_id(u64::from(id)) {
@@ -1210,7 +1359,7 @@ where
                 owner
             });
         }
-        self.doc.parent(id)
+        self.flat_parent(id)
     }
 
     fn get_own_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
@@ -1440,8 +1589,8 @@ where
             });
         }
 
-        let parent_id = self.doc.parent(id);
-        let children = self.doc.children(id).to_vec();
+        let parent_id = self.flat_parent(id);
+        let children = self.flat_children(id);
 
         let node_type = match self.doc.node_type(id) {
             GosubNodeType::TextNode => {
@@ -24,6 +24,15 @@ pub fn lookup(id: u32) -> String {
     interner().lock()[id as usize].clone()
 }
 
+/// Maps a legacy `page-break-*` keyword onto its `break-*` equivalent (`always` → `page`).
+pub fn legacy_break_keyword(keyword: &str) -> &str {
+    if keyword.eq_ignore_ascii_case("always") {
+        "page"
+    } else {
+        keyword
+    }
+}
+
 // ── Sub-enums ────────────────────────────────────────────────────────────────
 
 #[derive(Clone, Debug, PartialEq)]
@@ -299,6 +308,9 @@ pub enum StyleProperty {
     ZIndex,
     LetterSpacing,
     MixBlendMode,
+    BreakBefore,
+    BreakAfter,
+    BreakInside,
 }
 
 impl StyleProperty {
@@ -383,6 +395,9 @@ impl StyleProperty {
             StyleProperty::ZIndex => 75,
             StyleProperty::LetterSpacing => 76,
             StyleProperty::MixBlendMode => 77,
+            StyleProperty::BreakBefore => 78,
+            StyleProperty::BreakAfter => 79,
+            StyleProperty::BreakInside => 80,
         }
     }
 
@@ -393,6 +408,11 @@ impl StyleProperty {
     pub fn css_name(&self) -> &'static str {
         self.meta().name
     }
+
+    /// Every property, in id order.
+    pub fn all() -> impl Iterator<Item = StyleProperty> {
+        (0..PROPERTIES.len()).filter_map(|id| u8::try_from(id).ok().and_then(from_id))
+    }
 }
 
 // ── Property registry ─────────────────────────────────────────────────────────
@@ -917,6 +937,24 @@ static PROPERTIES: &[PropertyMeta] = &[
         inherited: false,
         initial_kind: InitialKind::Keyword("normal"),
     },
+    // 78 break-before - only consulted when paginating for print
+    PropertyMeta {
+        name: "break-before",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 79 break-after
+    PropertyMeta {
+        name: "break-after",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 80 break-inside
+    PropertyMeta {
+        name: "break-inside",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
 ];
 
 // ── NodeStyle - replaces StylePropertyList ────────────────────────────────────
@@ -1056,6 +1094,9 @@ fn from_id(id: u8) -> Option<StyleProperty> {
         75 => Some(StyleProperty::ZIndex),
         76 => Some(StyleProperty::LetterSpacing),
         77 => Some(StyleProperty::MixBlendMode),
+        78 => Some(StyleProperty::BreakBefore),
+        79 => Some(StyleProperty::BreakAfter),
+        80 => Some(StyleProperty::BreakInside),
         _ => None,
     }
 }
@@ -156,6 +156,19 @@ impl LayerList {
         None
     }
 
+    /// Anchors of the elements in `fixed` and `sticky` layers, to map their page-space boxes into
+    /// the viewport (see `anchored_tile_pos`). Elements not listed scroll with the page.
+    pub fn pinned_element_anchors(&self) -> HashMap<LayoutElementId, TileAnchor> {
+        let mut anchors = HashMap::new();
+        for layer in self.layers.read().values() {
+            if layer.anchor == TileAnchor::Scroll {
+                continue;
+            }
+            anchors.extend(layer.elements.iter().map(|&id| (id, layer.anchor)));
+        }
+        anchors
+    }
+
     /// Sticky constraint for a `position: sticky` element, else `None`. The cage should be the
     /// containing block's content box; we approximate it with the parent's, as there are no
     /// sub-scroll-containers yet. A root sticky element gets a zero-slack cage and never sticks.
@@ -1,7 +1,7 @@
 use cow_utils::CowUtils;
 
 use crate::common::document::node::{Node, NodeId as DomNodeId, NodeType};
-use crate::common::document::pipeline_doc::BgSize;
+use crate::common::document::pipeline_doc::{BgSize, PipelineDocument};
 use crate::common::document::style::{lookup, FontWeight, StyleProperty, TextAlign, Unit, Value};
 use crate::common::font::{FontAlignment, FontInfo};
 use crate::common::geo;
@@ -19,6 +19,7 @@ use crate::layouter::{
 use crate::rendertree_builder::{RenderNodeId, RenderTree};
 use gosub_fontmanager::ParleyFontSystem;
 use gosub_interface::font_system::FontSystem;
+use gosub_interface::node::QuirksMode;
 use parking_lot::{Mutex, RwLock};
 use std::borrow::Borrow;
 use std::collections::HashMap;
@@ -41,6 +42,24 @@ fn parse_px_attr(v: &str) -> Option<f32> {
 // letter_spacing_bits). Floats are stored as their bit pattern so the tuple is Hash + Eq.
 type MeasureKey = (String, String, u32, u32, i32, u32, u32);
 
+/// The line height calculation quirk: in quirks and limited-quirks mode collapsible whitespace
+/// does not make its line any taller when the inline box holding it has no vertical padding or
+/// border. In quirks mode the same goes for whitespace directly inside a block.
+fn whitespace_ignores_line_height(doc: &dyn PipelineDocument, parent: &Node) -> bool {
+    match doc.quirks_mode() {
+        QuirksMode::NoQuirks => false,
+        mode if !parent.is_inline_element() => mode == QuirksMode::Quirks,
+        _ => [
+            StyleProperty::PaddingTop,
+            StyleProperty::PaddingBottom,
+            StyleProperty::BorderTopWidth,
+            StyleProperty::BorderBottomWidth,
+        ]
+        .iter()
+        .all(|prop| doc.get_style_f32(parent.node_id, prop) == 0.0),
+    }
+}
+
 /// CSS `text-align` on a block, as `justify_content` for the anonymous flex containers holding its
 /// line boxes. A line box *is* that container, so this is what positions a run too short to fill it
 /// - a run that wraps already fills the line and is aligned by the shaper instead.
@@ -89,6 +108,11 @@ pub struct TaffyLayouter {
     measure_cache: HashMap<MeasureKey, Size<f32>>,
     /// Reverse index used by the table post-processing pass.
     dom_to_layout_mapping: HashMap<DomNodeId, LayoutElementId>,
+    /// Viewport height the quirks-mode height quirks fill; `None` outside quirks mode.
+    quirks_viewport_height: Option<f32>,
+    /// Height that percentage heights of the children of the element being built resolve
+    /// against under the percentage height quirk, while its containing blocks have auto heights.
+    quirks_height_basis: Option<f32>,
 }
 
 /// Apply the CSS `text-transform` keyword to a text run. `uppercase`/`lowercase` map the whole
@@ -201,6 +225,8 @@ impl TaffyLayouter {
             font_system,
             measure_cache: HashMap::new(),
             dom_to_layout_mapping: HashMap::new(),
+            quirks_viewport_height: None,
+            quirks_height_basis: None,
         }
     }
 
@@ -244,6 +270,12 @@ impl CanLayout for TaffyLayouter {
                 root_dimension: geo::Dimension::ZERO,
             };
         };
+        // The height quirks resolve against the viewport while the styles are converted.
+        self.quirks_viewport_height = viewport
+            .filter(|_| render_tree.doc.quirks_mode() == QuirksMode::Quirks)
+            .map(|viewport| viewport.height as f32);
+        self.quirks_height_basis = None;
+
         // let root_id = RenderNodeId::new(2);
         let mut layout_tree = self.generate_tree(render_tree, root_id);
 
@@ -673,7 +705,9 @@ impl TaffyLayouter {
             .doc
             .get_node_by_id(DomNodeId::from(render_node.node_id))?;
 
-        let (taffy_context, taffy_style) = self.extract_taffy_data(layout_tree, &dom_node)?;
+        let (taffy_context, mut taffy_style) = self.extract_taffy_data(layout_tree, &dom_node)?;
+        let children_height_basis =
+            self.apply_height_quirks(&*layout_tree.render_tree.doc, dom_node.node_id, &mut taffy_style);
 
         // `text-align` inherits, so this is the block's computed value; the line boxes below are
         // anonymous and have no style of their own to read.
@@ -703,6 +737,7 @@ impl TaffyLayouter {
         let Ok(leaf_id) = result else {
             return None;
         };
+        let outer_height_basis = std::mem::replace(&mut self.quirks_height_basis, children_height_basis);
 
         let background_media = self.resolve_background_media(layout_tree, dom_node.node_id);
 
@@ -849,6 +884,7 @@ impl TaffyLayouter {
         // Strip trailing whitespace and deal with any remaining inline elements
         current_inline_group.truncate(current_inline_group.len().saturating_sub(trailing_ws_count));
         self.process_inlines(&current_inline_group, &mut element_node, leaf_id, line_justify);
+        self.quirks_height_basis = outer_height_basis;
 
         // The layout-tree is the structure handed to the rest of the pipeline; taffy stays
         // internal to this layouter so other layout engines can be swapped in.
@@ -871,6 +907,53 @@ impl TaffyLayouter {
         Some((layout_element_id, leaf_id))
     }
 
+    /// Applies the quirks-mode height quirks to the style of `id`: the root `html` fills the
+    /// viewport and `body` fills `html`, both as a minimum so they still grow with their content,
+    /// and a percentage height whose containing block has an auto height resolves against the
+    /// nearest ancestor with a known height instead of computing to `auto`.
+    ///
+    /// Returns the height percentage heights of the children resolve against, or `None` when
+    /// taffy can resolve them itself.
+    fn apply_height_quirks(&self, doc: &dyn PipelineDocument, id: DomNodeId, style: &mut Style) -> Option<f32> {
+        let viewport_height = self.quirks_viewport_height?;
+        let is_root = doc.html_node_id() == Some(id);
+        let basis = if is_root {
+            viewport_height
+        } else {
+            self.quirks_height_basis?
+        };
+        if matches!(style.position, taffy::Position::Absolute) {
+            return None;
+        }
+
+        match doc.get_style(id, &StyleProperty::Height) {
+            Value::Unit(percentage, Unit::Percent) => {
+                style.size.height = Dimension::from_length(basis * percentage / 100.0);
+                None
+            }
+            Value::Unit(..) | Value::Number(_) => None,
+            _ if is_root || doc.body_node_id() == Some(id) => {
+                let px = |prop: StyleProperty| match doc.get_style(id, &prop) {
+                    Value::Unit(v, Unit::Px) | Value::Number(v) => v,
+                    _ => 0.0,
+                };
+                let margins = px(StyleProperty::MarginTop) + px(StyleProperty::MarginBottom);
+                let edges = px(StyleProperty::PaddingTop)
+                    + px(StyleProperty::PaddingBottom)
+                    + px(StyleProperty::BorderTopWidth)
+                    + px(StyleProperty::BorderBottomWidth);
+                let content_height = (basis - margins - edges).max(0.0);
+                let min_height = match style.box_sizing {
+                    taffy::BoxSizing::BorderBox => content_height + edges,
+                    taffy::BoxSizing::ContentBox => content_height,
+                };
+                style.min_size.height = Dimension::from_length(min_height);
+                Some(content_height)
+            }
+            _ => Some(basis),
+        }
+    }
+
     /// Resolves the element's CSS `background-image` (if any) to a media id: reads the computed
     /// value, resolves the URL against the document base URL, and loads it into the media store.
     /// Returns `None` when there is no background image or it fails to load.
@@ -1181,6 +1264,12 @@ impl TaffyLayouter {
                     let space_width = (font_size * 0.3) as f32;
                     taffy_style.size.width = Dimension::from_length(space_width);
                     taffy_style.flex_shrink = 0.0;
+                    if parent_node
+                        .as_ref()
+                        .is_some_and(|parent| whitespace_ignores_line_height(&**doc, parent))
+                    {
+                        taffy_style.size.height = Dimension::from_length(0.0);
+                    }
                 }
                 // if inline_element_counter > 0 {
                 //     // If we are in an inline container, we need to add a space between the text nodes
@@ -2,6 +2,7 @@ pub mod common;
 pub mod layering;
 pub mod layouter;
 pub mod painter;
+pub mod print;
 pub mod rasterizer;
 pub mod render;
 pub mod rendertree_builder;
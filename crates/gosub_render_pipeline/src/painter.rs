@@ -1,4 +1,5 @@
 pub mod commands;
+pub mod debug_overlay;
 
 use crate::common::browser_state::{BrowserState, WireframeState};
 use crate::common::document::node::NodeId;
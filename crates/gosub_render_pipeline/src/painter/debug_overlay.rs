@@ -0,0 +1,210 @@
+//! Debug overlays painted on top of the page with ordinary paint commands, so every backend draws
+//! them: the hovered element's box model, tile and layer bounds, and a tint over repainted tiles.
+
+use crate::common::browser_state::DebugOverlays;
+use crate::common::font::{FontAlignment, FontInfo};
+use crate::common::geo::Rect;
+use crate::layering::layer::LayerId;
+use crate::layouter::LayoutElementId;
+use crate::painter::commands::border::{Border, BorderStyle};
+use crate::painter::commands::brush::Brush;
+use crate::painter::commands::color::Color;
+use crate::painter::commands::rectangle::Rectangle;
+use crate::painter::commands::text::Text;
+use crate::painter::commands::PaintCommand;
+use crate::painter::Painter;
+use crate::render::backend::TileAnchor;
+use crate::tiler::{TileId, TileList, TileState};
+
+const LABEL_FONT_SIZE: f64 = 10.0;
+const LABEL_HEIGHT: f64 = 14.0;
+/// Rough advance of one monospace glyph at [`LABEL_FONT_SIZE`], for sizing label backgrounds.
+const LABEL_CHAR_WIDTH: f64 = 6.0;
+
+/// A tile as the overlay sees it: its bounds and whether the last frame repainted it.
+#[derive(Clone, Debug)]
+pub struct OverlayTile {
+    pub id: TileId,
+    pub layer_id: LayerId,
+    /// Bounds in layer coordinates (CSS pixels).
+    pub rect: Rect,
+    pub repainted: bool,
+}
+
+impl OverlayTile {
+    /// Snapshot the tiles of `tile_list`, ordered by id. Call it between painting and
+    /// rasterization: tiles still `Dirty` at that point are the ones this frame repainted.
+    pub fn collect(tile_list: &TileList) -> Vec<OverlayTile> {
+        let mut tiles: Vec<OverlayTile> = tile_list
+            .arena
+            .values()
+            .map(|tile| OverlayTile {
+                id: tile.id,
+                layer_id: tile.layer_id,
+                rect: tile.rect,
+                repainted: tile.state == TileState::Dirty,
+            })
+            .collect();
+        tiles.sort_by_key(|tile| tile.id.as_u64());
+        tiles
+    }
+}
+
+impl Painter {
+    /// Paint commands for the enabled `overlays`, in layer coordinates.
+    ///
+    /// Commands for pinned (`fixed`/`sticky`) layers are wrapped in a
+    /// [`PaintCommand::PushLayer`] group carrying that layer's anchor, like [`Painter::paint_all`]
+    /// does, so they track their layer while scrolling. Tile overlays need `tiles`; the scene path
+    /// has no tiles and passes an empty slice.
+    pub fn paint_debug_overlay(
+        &self,
+        overlays: DebugOverlays,
+        hovered: Option<LayoutElementId>,
+        tiles: &[OverlayTile],
+    ) -> Vec<PaintCommand> {
+        let mut groups: Vec<(TileAnchor, Vec<PaintCommand>)> = Vec::new();
+        let mut emit =
+            |anchor: TileAnchor, commands: Vec<PaintCommand>| match groups.iter_mut().find(|(a, _)| *a == anchor) {
+                Some((_, group)) => group.extend(commands),
+                None => groups.push((anchor, commands)),
+            };
+
+        let layer_ids = self.layer_list.layer_ids.read().clone();
+        let layer_anchor = |layer_id: LayerId| self.layer_list.layer_anchor(layer_id);
+
+        if overlays.repaint_flash {
+            for tile in tiles.iter().filter(|tile| tile.repainted) {
+                let flash = Rectangle::new(tile.rect).with_background(Brush::Solid(Color::from_rgba8(255, 0, 128, 64)));
+                emit(layer_anchor(tile.layer_id), vec![PaintCommand::rectangle(flash)]);
+            }
+        }
+
+        if overlays.tiles {
+            let color = Color::from_rgb8(0, 160, 255);
+            for tile in tiles {
+                let label = format!("tile {} / layer {}", tile.id.as_u64(), tile.layer_id.as_u64());
+                let mut commands = vec![outline(tile.rect, color.clone())];
+                commands.extend(self.label(tile.rect, &label, color.clone()));
+                emit(layer_anchor(tile.layer_id), commands);
+            }
+        }
+
+        if overlays.layers {
+            let color = Color::from_rgb8(200, 0, 200);
+            let layers = self.layer_list.layers.read();
+            for layer_id in &layer_ids {
+                let Some(layer) = layers.get(layer_id) else {
+                    continue;
+                };
+                let Some(bounds) = layer
+                    .elements
+                    .iter()
+                    .filter_map(|&id| self.layer_list.layout_tree.get_node_by_id(id))
+                    .map(|el| el.box_model.border_box)
+                    .filter(|r| r.width > 0.0 && r.height > 0.0)
+                    .reduce(|a, b| union(&a, &b))
+                else {
+                    continue;
+                };
+                let mut label = format!("layer {} z={}", layer_id.as_u64(), layer.order);
+                if layer.opacity < 1.0 {
+                    label.push_str(&format!(" opacity={:.2}", layer.opacity));
+                }
+                let mut commands = vec![outline(bounds, color.clone())];
+                commands.extend(self.label(bounds, &label, color.clone()));
+                emit(layer.anchor, commands);
+            }
+        }
+
+        if overlays.box_model {
+            if let Some(el) = hovered.and_then(|id| self.layer_list.layout_tree.get_node_by_id(id)) {
+                let anchor = self
+                    .layer_list
+                    .pinned_element_anchors()
+                    .get(&el.id)
+                    .copied()
+                    .unwrap_or_default();
+                let bm = &el.box_model;
+                emit(
+                    anchor,
+                    vec![
+                        outline(bm.margin_box, Color::from_rgb8(246, 178, 107)),
+                        outline(bm.border_box, Color::from_rgb8(255, 229, 153)),
+                        outline(bm.padding_box, Color::from_rgb8(147, 196, 125)),
+                        outline(bm.content_box, Color::from_rgb8(111, 168, 220)),
+                    ],
+                );
+            }
+        }
+
+        let mut out = Vec::new();
+        for (anchor, commands) in groups {
+            let pinned = !matches!(anchor, TileAnchor::Scroll);
+            if pinned {
+                out.push(PaintCommand::PushLayer { opacity: 1.0, anchor });
+            }
+            out.extend(commands);
+            if pinned {
+                out.push(PaintCommand::PopLayer);
+            }
+        }
+        out
+    }
+
+    /// A small monospace caption on a dark backdrop at the top-left corner of `rect`.
+    fn label(&self, rect: Rect, text: &str, color: Color) -> Vec<PaintCommand> {
+        let width = text.chars().count() as f64 * LABEL_CHAR_WIDTH + 4.0;
+        let backdrop = Rect::new(rect.x, rect.y, width, LABEL_HEIGHT);
+        let font_info = FontInfo {
+            family: "monospace".to_string(),
+            size: LABEL_FONT_SIZE,
+            weight: 400,
+            width: 100,
+            slant: 0,
+            line_height: LABEL_HEIGHT,
+            letter_spacing: 0.0,
+            alignment: FontAlignment::Start,
+            underline: false,
+            line_through: false,
+        };
+        let text_rect = Rect::new(rect.x + 2.0, rect.y, width - 4.0, LABEL_HEIGHT);
+        let shaped = self.shape_text(text, &font_info, text_rect.width, text_rect.width);
+        vec![
+            PaintCommand::rectangle(
+                Rectangle::new(backdrop).with_background(Brush::Solid(Color::from_rgba8(0, 0, 0, 160))),
+            ),
+            PaintCommand::text(Text::new(
+                text_rect,
+                text,
+                &font_info,
+                Brush::Solid(color),
+                text_rect.width,
+                shaped,
+            )),
+        ]
+    }
+}
+
+/// A 1px outline of `rect`.
+fn outline(rect: Rect, color: Color) -> PaintCommand {
+    let border = Border::new(
+        1.0,
+        BorderStyle::Solid,
+        [
+            Brush::Solid(color.clone()),
+            Brush::Solid(color.clone()),
+            Brush::Solid(color.clone()),
+            Brush::Solid(color),
+        ],
+    );
+    PaintCommand::rectangle(Rectangle::new(rect).with_border(border))
+}
+
+fn union(a: &Rect, b: &Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.width).max(b.x + b.width);
+    let bottom = (a.y + a.height).max(b.y + b.height);
+    Rect::new(x, y, right - x, bottom - y)
+}
@@ -0,0 +1,383 @@
+//! Pagination for print output.
+//!
+//! The print path lays the document out once at the first page's content width (with `@media
+//! print` rules applied), then slices the laid-out page vertically into pages. Pages can differ
+//! in size and margins (`@page :first`, `:left` and `:right`); each slice is as tall as the page
+//! area of the page it lands on. Slices prefer to end at
+//! forced breaks (`break-before/after: page`) and avoid cutting through boxes that ask for it
+//! (`break-inside: avoid`) or that cannot be split (text runs, images). Each page gets the paint
+//! commands of the elements it shows; a backend that supports vector output turns the resulting
+//! [`PrintDocument`] into a PDF (see `RenderBackend::print_to_pdf`).
+
+use crate::common::browser_state::BrowserState;
+use crate::common::document::node::NodeId;
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use crate::common::media::MediaStore;
+use crate::layering::layer::LayerList;
+use crate::layouter::{ElementContext, LayoutElementId, LayoutTree};
+use crate::painter::commands::PaintCommand;
+use crate::painter::Painter;
+use crate::render::backend::TileAnchor;
+use std::sync::Arc;
+
+/// Slices thinner than this (CSS px) are rounding noise, not content.
+const EPSILON: f64 = 0.5;
+
+/// Upper bound on the number of pages, so a runaway layout cannot produce an unbounded document.
+const MAX_PAGES: usize = 10_000;
+
+/// Page box geometry in CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSetup {
+    pub width: f64,
+    pub height: f64,
+    /// Top, right, bottom and left margins.
+    pub margin: [f64; 4],
+}
+
+impl PageSetup {
+    /// Width of the page area the document is laid out into.
+    pub fn content_width(&self) -> f64 {
+        (self.width - self.margin[1] - self.margin[3]).max(1.0)
+    }
+
+    /// Height of the page area each page shows.
+    pub fn content_height(&self) -> f64 {
+        (self.height - self.margin[0] - self.margin[2]).max(1.0)
+    }
+}
+
+/// Page box geometry of every page of a document: the first page, and the left and right pages
+/// after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSetups {
+    pub first: PageSetup,
+    pub left: PageSetup,
+    pub right: PageSetup,
+}
+
+impl PageSetups {
+    /// The same page box for every page.
+    pub fn uniform(setup: PageSetup) -> Self {
+        Self {
+            first: setup,
+            left: setup,
+            right: setup,
+        }
+    }
+
+    /// The page box of the page at `index` (0-based). The first page is a right page, as in a
+    /// left-to-right document.
+    pub fn page(&self, index: usize) -> PageSetup {
+        match index {
+            0 => self.first,
+            _ if index % 2 == 1 => self.left,
+            _ => self.right,
+        }
+    }
+}
+
+/// One printed page: the vertical slice `top..bottom` of the laid-out document and the commands
+/// that paint into it.
+#[derive(Debug, Clone)]
+pub struct PrintPage {
+    /// Page box of this page
+    pub setup: PageSetup,
+    pub top: f64,
+    pub bottom: f64,
+    /// Commands in document coordinates. `position: fixed` layers are wrapped in a
+    /// [`PaintCommand::PushLayer`] with [`TileAnchor::Fixed`] and repeat on every page at their
+    /// viewport position, so backends must not offset them by `top`.
+    pub commands: Vec<PaintCommand>,
+}
+
+/// A paginated document, ready for a backend's vector output.
+pub struct PrintDocument {
+    pub pages: Vec<PrintPage>,
+    pub media_store: Arc<MediaStore>,
+}
+
+/// Paginates `layer_list` into pages of `setups` and paints every page.
+pub fn paginate(
+    layer_list: &Arc<LayerList>,
+    painter: &Painter,
+    state: &BrowserState,
+    setups: &PageSetups,
+    media_store: Arc<MediaStore>,
+) -> PrintDocument {
+    let tree = &layer_list.layout_tree;
+    let constraints = break_constraints(tree, setups.first.content_height());
+    let slices = slice_pages(
+        tree.root_dimension.height,
+        |index| setups.page(index).content_height(),
+        &constraints.forced,
+        &constraints.avoid,
+    );
+
+    let layer_ids = layer_list.layer_ids.read().clone();
+    let layers = layer_list.layers.read();
+
+    let pages = slices
+        .into_iter()
+        .enumerate()
+        .map(|(index, (top, bottom))| {
+            let mut commands = Vec::new();
+            for layer_id in &layer_ids {
+                let Some(layer) = layers.get(layer_id) else {
+                    continue;
+                };
+                let fixed = matches!(layer.anchor, TileAnchor::Fixed);
+                let grouped = fixed || layer.opacity < 1.0;
+                let elements: Vec<LayoutElementId> = layer
+                    .elements
+                    .iter()
+                    .copied()
+                    .filter(|&id| fixed || element_intersects(tree, id, top, bottom))
+                    .collect();
+                if elements.is_empty() {
+                    continue;
+                }
+                if grouped {
+                    commands.push(PaintCommand::PushLayer {
+                        opacity: layer.opacity,
+                        anchor: if fixed { TileAnchor::Fixed } else { TileAnchor::Scroll },
+                    });
+                }
+                for id in elements {
+                    commands.extend(painter.paint_element(id, state));
+                }
+                if grouped {
+                    commands.push(PaintCommand::PopLayer);
+                }
+            }
+            PrintPage {
+                setup: setups.page(index),
+                top,
+                bottom,
+                commands,
+            }
+        })
+        .collect();
+
+    PrintDocument { pages, media_store }
+}
+
+fn element_intersects(tree: &LayoutTree, id: LayoutElementId, top: f64, bottom: f64) -> bool {
+    tree.get_node_by_id(id).is_some_and(|node| {
+        let rect = node.box_model.margin_box;
+        rect.y < bottom && rect.y + rect.height > top
+    })
+}
+
+/// Break opportunities gathered from the layout tree.
+#[derive(Debug, Default)]
+struct BreakConstraints {
+    /// Offsets where a page must end.
+    forced: Vec<f64>,
+    /// Ranges no page may end inside.
+    avoid: Vec<(f64, f64)>,
+}
+
+fn break_keyword(tree: &LayoutTree, node_id: NodeId, prop: StyleProperty) -> String {
+    match tree.render_tree.doc.get_style(node_id, &prop) {
+        Value::Keyword(kw) => lookup(kw),
+        _ => String::new(),
+    }
+}
+
+fn is_forced_break(keyword: &str) -> bool {
+    matches!(keyword, "page" | "left" | "right" | "recto" | "verso" | "all")
+}
+
+fn is_avoid_break(keyword: &str) -> bool {
+    matches!(keyword, "avoid" | "avoid-page")
+}
+
+fn break_constraints(tree: &LayoutTree, content_height: f64) -> BreakConstraints {
+    let mut constraints = BreakConstraints::default();
+    collect_break_constraints(tree, tree.root_id, content_height, &mut constraints);
+    constraints
+}
+
+fn collect_break_constraints(tree: &LayoutTree, id: LayoutElementId, content_height: f64, out: &mut BreakConstraints) {
+    let Some(node) = tree.get_node_by_id(id) else {
+        return;
+    };
+    let rect = node.box_model.border_box;
+    let (top, bottom) = (rect.y, rect.y + rect.height);
+
+    match &node.context {
+        // Text runs and replaced content cannot be split across pages.
+        ElementContext::Text(_) | ElementContext::Image(_) | ElementContext::Svg(_) => {
+            if rect.height <= content_height {
+                out.avoid.push((top, bottom));
+            }
+            return;
+        }
+        ElementContext::None => {}
+    }
+
+    if is_avoid_break(&break_keyword(tree, node.dom_node_id, StyleProperty::BreakInside)) {
+        out.avoid.push((top, bottom));
+    }
+
+    let children: Vec<_> = node
+        .children
+        .iter()
+        .filter_map(|child| tree.get_node_by_id(*child))
+        .collect();
+    for (i, child) in children.iter().enumerate() {
+        let child_top = child.box_model.border_box.y;
+
+        if matches!(child.context, ElementContext::None) {
+            let before = break_keyword(tree, child.dom_node_id, StyleProperty::BreakBefore);
+            let after = break_keyword(tree, child.dom_node_id, StyleProperty::BreakAfter);
+            if is_forced_break(&before) {
+                out.forced.push(child.box_model.margin_box.y);
+            }
+            if is_forced_break(&after) {
+                let margin_box = child.box_model.margin_box;
+                out.forced.push(margin_box.y + margin_box.height);
+            }
+            // `avoid` between two siblings: a page may not end anywhere in the gap between them,
+            // so glue the earlier box to the top of the later one.
+            if is_avoid_break(&before) {
+                if let Some(prev) = i.checked_sub(1).map(|p| children[p]) {
+                    out.avoid.push((prev.box_model.border_box.y, child_top + 1.0));
+                }
+            }
+            if is_avoid_break(&after) {
+                if let Some(next) = children.get(i + 1) {
+                    out.avoid.push((child_top, next.box_model.border_box.y + 1.0));
+                }
+            }
+        }
+
+        collect_break_constraints(tree, child.id, content_height, out);
+    }
+}
+
+/// Cuts a document of `height` into pages, each showing at most the `content_height` of its
+/// page (by 0-based index).
+///
+/// A page ends at the first forced break inside it. Otherwise it ends where it runs out of room,
+/// moved up to the start of any `avoid` range that the cut would split — as long as that range
+/// starts below the page top and fits on the page by itself, so an oversized box still gets cut
+/// rather than stalling pagination. An empty document prints as a single blank page.
+fn slice_pages(
+    height: f64,
+    content_height: impl Fn(usize) -> f64,
+    forced: &[f64],
+    avoid: &[(f64, f64)],
+) -> Vec<(f64, f64)> {
+    let mut forced: Vec<f64> = forced.iter().copied().filter(|y| *y > EPSILON && *y < height).collect();
+    forced.sort_by(f64::total_cmp);
+
+    let mut pages = Vec::new();
+    let mut top = 0.0;
+    while top < height - EPSILON && pages.len() < MAX_PAGES {
+        let content_height = content_height(pages.len());
+        let mut bottom = (top + content_height).min(height);
+        if let Some(&f) = forced.iter().find(|&&f| f > top + EPSILON) {
+            bottom = bottom.min(f);
+        }
+
+        if bottom < height {
+            while let Some(start) = avoid
+                .iter()
+                .filter(|(s, e)| *s > top + EPSILON && *s < bottom && *e > bottom && e - s <= content_height)
+                .map(|(s, _)| *s)
+                .min_by(f64::total_cmp)
+            {
+                bottom = start;
+            }
+        }
+
+        pages.push((top, bottom));
+        top = bottom;
+    }
+
+    if pages.is_empty() {
+        pages.push((0.0, content_height(0)));
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_at_page_height() {
+        let pages = slice_pages(250.0, |_| 100.0, &[], &[]);
+        assert_eq!(pages, vec![(0.0, 100.0), (100.0, 200.0), (200.0, 250.0)]);
+    }
+
+    #[test]
+    fn forced_breaks_end_pages_early() {
+        let pages = slice_pages(250.0, |_| 100.0, &[40.0, 40.0, 0.0], &[]);
+        assert_eq!(pages, vec![(0.0, 40.0), (40.0, 140.0), (140.0, 240.0), (240.0, 250.0)]);
+    }
+
+    #[test]
+    fn avoid_ranges_move_the_cut_up() {
+        // A box spanning 80..130 would be cut at 100; the page ends at 80 instead.
+        let pages = slice_pages(200.0, |_| 100.0, &[], &[(80.0, 130.0)]);
+        assert_eq!(pages, vec![(0.0, 80.0), (80.0, 180.0), (180.0, 200.0)]);
+
+        // Ranges taller than a page, or starting at the page top, can't be honored.
+        let pages = slice_pages(200.0, |_| 100.0, &[], &[(50.0, 190.0), (0.0, 120.0)]);
+        assert_eq!(pages, vec![(0.0, 100.0), (100.0, 200.0)]);
+    }
+
+    #[test]
+    fn pages_can_differ_in_height() {
+        // A taller first page, then alternating left and right pages
+        let setup = |height| PageSetup {
+            width: 100.0,
+            height,
+            margin: [10.0, 0.0, 10.0, 0.0],
+        };
+        let setups = PageSetups {
+            first: setup(170.0),
+            left: setup(70.0),
+            right: setup(120.0),
+        };
+        let pages = slice_pages(400.0, |index| setups.page(index).content_height(), &[], &[]);
+        assert_eq!(
+            pages,
+            vec![
+                (0.0, 150.0),
+                (150.0, 200.0),
+                (200.0, 300.0),
+                (300.0, 350.0),
+                (350.0, 400.0)
+            ]
+        );
+
+        // An avoid range taller than the page it would be cut on is cut anyway
+        let pages = slice_pages(
+            200.0,
+            |index| if index == 0 { 50.0 } else { 150.0 },
+            &[],
+            &[(40.0, 120.0)],
+        );
+        assert_eq!(pages, vec![(0.0, 50.0), (50.0, 200.0)]);
+    }
+
+    #[test]
+    fn empty_document_prints_one_page() {
+        assert_eq!(slice_pages(0.0, |_| 100.0, &[], &[]), vec![(0.0, 100.0)]);
+    }
+
+    #[test]
+    fn content_box_subtracts_margins() {
+        let setup = PageSetup {
+            width: 800.0,
+            height: 1000.0,
+            margin: [10.0, 20.0, 30.0, 40.0],
+        };
+        assert_eq!(setup.content_width(), 740.0);
+        assert_eq!(setup.content_height(), 960.0);
+    }
+}
@@ -59,6 +59,7 @@ use crate::render::backend::CachedTile;
 
 /// A single rasterized tile with its page-coordinate position, ready to blit.
 pub struct BakedTile {
+    /// Position in page coordinates, already multiplied by the tile's raster scale (page zoom).
     pub page_x: f64,
     pub page_y: f64,
     /// Owning layer id. Carry-over between renders must key tiles by position *and* layer:
@@ -291,6 +292,10 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
     // baked texture cut for the old dimensions.
     fnv!(&tile.rect.width.to_bits().to_le_bytes());
     fnv!(&tile.rect.height.to_bits().to_le_bytes());
+    // Likewise the raster scale and DPR: a zoom or device change redraws the same commands at a
+    // new resolution.
+    fnv!(&tile.scale.to_bits().to_le_bytes());
+    fnv!(&tile.device_pixel_ratio.unwrap_or(0).to_le_bytes());
 
     (tile.rect.x.to_bits(), tile.rect.y.to_bits(), tile.layer_id.as_u64(), h)
 }
@@ -333,8 +338,8 @@ pub fn rasterize_sequential(
         if let (Some(texture_id), true) = (tile.texture_id, tile.state == TileState::Ready) {
             if let Some(tex) = texture_store.get(texture_id) {
                 tiles.push(BakedTile {
-                    page_x: tile.rect.x,
-                    page_y: tile.rect.y,
+                    page_x: tile.rect.x * tile.scale,
+                    page_y: tile.rect.y * tile.scale,
                     layer_id: tile.layer_id.as_u64(),
                     width: tex.width as u32,
                     height: tex.height as u32,
@@ -394,8 +399,8 @@ pub fn rasterize_parallel(
 
             if let Some(&(w, h, ref data)) = prev_tile_cache.get(&key) {
                 let baked = BakedTile {
-                    page_x: tile.rect.x,
-                    page_y: tile.rect.y,
+                    page_x: tile.rect.x * tile.scale,
+                    page_y: tile.rect.y * tile.scale,
                     layer_id: tile.layer_id.as_u64(),
                     width: w,
                     height: h,
@@ -412,8 +417,8 @@ pub fn rasterize_parallel(
                 .rasterize(tile, &mut local_store, media_store)
                 .and_then(|tid| local_store.get(tid))
                 .map(|tex| BakedTile {
-                    page_x: tile.rect.x,
-                    page_y: tile.rect.y,
+                    page_x: tile.rect.x * tile.scale,
+                    page_y: tile.rect.y * tile.scale,
                     layer_id: tile.layer_id.as_u64(),
                     width: tex.width as u32,
                     height: tex.height as u32,
@@ -364,6 +364,177 @@ mod rendertree_from_engine {
         assert_eq!(url_of(plain), "none", "plain element should be `none`");
     }
 
+    #[test]
+    fn quirks_mode_selectors_and_unitless_lengths() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::document::style::{StyleProperty, Unit, Value};
+
+        let style = r#"<style>.Box { width: 120; } #MAIN { height: 40; }</style>
+            <div class="box" id="main">content</div>"#;
+        let width_and_height = |html: &str| {
+            let mut doc = html_compile::<Config>(html);
+            doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+            let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+            let id = find_node_by_id_attr(&adapter.doc, adapter.doc.root(), "main").expect("find #main");
+            (
+                adapter.get_style(id, &StyleProperty::Width),
+                adapter.get_style(id, &StyleProperty::Height),
+            )
+        };
+
+        // Without a doctype the document is in quirks mode.
+        let (width, height) = width_and_height(style);
+        assert!(
+            matches!(width, Value::Unit(w, Unit::Px) if (w - 120.0).abs() < 0.5),
+            "got {width:?}"
+        );
+        assert!(
+            matches!(height, Value::Unit(h, Unit::Px) if (h - 40.0).abs() < 0.5),
+            "got {height:?}"
+        );
+
+        let (width, height) = width_and_height(&format!("<!DOCTYPE html>{style}"));
+        assert!(!matches!(width, Value::Unit(..)), "got {width:?}");
+        assert!(!matches!(height, Value::Unit(..)), "got {height:?}");
+    }
+
+    #[test]
+    fn quirks_mode_tables_do_not_inherit_font_size() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::document::style::{StyleProperty, Unit, Value};
+
+        let markup = r#"<style>body { font-size: 24px; }</style>
+            <table><tr><td id="cell">x</td></tr></table>"#;
+        let cell_font_size = |html: &str| {
+            let mut doc = html_compile::<Config>(html);
+            doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+            let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+            let id = find_node_by_id_attr(&adapter.doc, adapter.doc.root(), "cell").expect("find #cell");
+            match adapter.get_style(id, &StyleProperty::FontSize) {
+                Value::Unit(px, Unit::Px) => px,
+                other => panic!("unexpected font-size {other:?}"),
+            }
+        };
+
+        assert!((cell_font_size(markup) - 16.0).abs() < 0.5);
+        assert!((cell_font_size(&format!("<!DOCTYPE html>{markup}")) - 24.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn media_feature_changes_restyle_only_affected_elements() {
+        use gosub_interface::css3::{ColorScheme, MediaFeatures};
+
+        let mut doc = html_compile::<Config>(
+            r#"<!DOCTYPE html><style>
+                @media (max-width: 600px) { .narrow { color: red; } }
+                @media (prefers-color-scheme: dark) { .themed { color: white; } }
+                .fluid { width: 50vw; }
+            </style>
+            <div id="narrow" class="narrow"><p id="inner">x</p></div>
+            <div id="themed" class="themed"></div>
+            <div id="fluid" class="fluid"></div>
+            <div id="inline" style="height: 10vh"></div>
+            <div id="still"></div>"#,
+        );
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        let find = |id: &str| find_node_by_id_attr(&doc, doc.root(), id).expect("find element");
+
+        let wide = MediaFeatures {
+            width: 1000.0,
+            ..MediaFeatures::default()
+        };
+        let narrow = MediaFeatures {
+            width: 500.0,
+            ..MediaFeatures::default()
+        };
+        let roots = Css3System::media_restyle_roots::<Config>(&doc, doc.stylesheets(), &wide, &narrow).unwrap();
+        assert_eq!(roots, vec![find("narrow"), find("fluid"), find("inline")]);
+
+        let dark = MediaFeatures {
+            color_scheme: ColorScheme::Dark,
+            ..wide
+        };
+        let roots = Css3System::media_restyle_roots::<Config>(&doc, doc.stylesheets(), &wide, &dark).unwrap();
+        assert_eq!(roots, vec![find("themed")]);
+
+        let roots = Css3System::media_restyle_roots::<Config>(&doc, doc.stylesheets(), &wide, &wide).unwrap();
+        assert!(roots.is_empty());
+    }
+
+    /// Lay out `html` in an 800×600 viewport and return the border-box heights of the elements
+    /// with the given ids.
+    fn laid_out_heights(html: &str, ids: &[&str]) -> Vec<f64> {
+        use crate::common::geo::Dimension;
+        use crate::layouter::taffy::TaffyLayouter;
+        use crate::layouter::CanLayout;
+
+        let mut doc = html_compile::<Config>(html);
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        let dom_ids: Vec<_> = ids
+            .iter()
+            .map(|id| find_node_by_id_attr(&doc, doc.root(), id).expect("find element"))
+            .collect();
+
+        let mut rt = RenderTree::new(Arc::new(GosubDocumentAdapter::<Config>::new(Arc::new(doc))));
+        rt.parse().expect("failed to build render tree");
+        let layout_tree = TaffyLayouter::new().layout(rt, Some(Dimension::new(800.0, 600.0)), 1.0);
+
+        dom_ids
+            .iter()
+            .map(|&dom_id| {
+                layout_tree
+                    .arena
+                    .values()
+                    .find(|node| node.dom_node_id == dom_id)
+                    .expect("element was laid out")
+                    .box_model
+                    .border_box
+                    .height
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quirks_mode_percentage_heights_fill_the_viewport() {
+        let markup = r#"<style>#half { height: 50%; }</style>
+            <body id="body"><div id="half"></div></body>"#;
+        let ids = ["body", "half"];
+
+        // `html` fills the viewport and `body` fills `html` less its 8px margins, so the
+        // percentage resolves against the body's height rather than computing to `auto`.
+        let heights = laid_out_heights(&format!("<html id=\"root\">{markup}"), &["root", ids[0], ids[1]]);
+        assert!((heights[0] - 600.0).abs() < 0.5, "html: {heights:?}");
+        assert!((heights[1] - 584.0).abs() < 0.5, "body: {heights:?}");
+        assert!((heights[2] - 292.0).abs() < 0.5, "#half: {heights:?}");
+
+        // In no-quirks mode all three have auto heights and the div is empty.
+        let heights = laid_out_heights(&format!("<!DOCTYPE html>{markup}"), &ids);
+        assert!(heights[0].abs() < 0.5, "body: {heights:?}");
+        assert!(heights[1].abs() < 0.5, "#half: {heights:?}");
+    }
+
+    #[test]
+    fn quirks_mode_percentage_heights_grow_with_content() {
+        // The quirk sets a minimum height: content taller than the viewport still stretches body.
+        let heights = laid_out_heights(
+            r#"<body id="body"><div style="height: 1000px"></div></body>"#,
+            &["body"],
+        );
+        assert!((heights[0] - 1000.0).abs() < 0.5, "body: {heights:?}");
+    }
+
+    #[test]
+    fn quirks_mode_whitespace_ignores_line_height() {
+        let markup = r#"<style>div { line-height: 20px; }</style><div id="blank"> </div>"#;
+
+        // Whitespace directly inside a block does not make the line any taller in quirks mode.
+        let heights = laid_out_heights(markup, &["blank"]);
+        assert!(heights[0].abs() < 0.5, "quirks: {heights:?}");
+
+        let heights = laid_out_heights(&format!("<!DOCTYPE html>{markup}"), &["blank"]);
+        assert!(heights[0] > 0.5, "no-quirks: {heights:?}");
+    }
+
     fn find_node_by_id_attr(
         doc: &DocumentImpl<Config>,
         node: gosub_shared::node::NodeId,
@@ -21,6 +21,9 @@ impl TileId {
     pub const fn new(val: u64) -> Self {
         Self(val)
     }
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
 }
 
 impl AddAssign<u64> for TileId {
@@ -99,6 +102,12 @@ pub struct Tile {
     pub rect: Rect,
     /// Background color of the whole canvas, not of this tile. We should deal with this differently.
     pub bgcolor: Option<(f32, f32, f32, f32)>,
+    /// Raster scale applied on top of the device pixel ratio (page zoom). `rect` stays in layout
+    /// (CSS) pixels; the rasterizer draws it at `rect × scale × dpr` physical pixels.
+    pub scale: f64,
+    /// Device pixel ratio to rasterize at. `None` uses the backend's ratio; set when a tab
+    /// emulates a device with its own DPR.
+    pub device_pixel_ratio: Option<u32>,
 }
 
 /// Each layer has a list of tiles. Each tile has a list of elements that are laid out in that tile.
@@ -133,6 +142,10 @@ pub struct TileList {
     next_node_id: Arc<RwLock<TileId>>,
 
     pub default_tile_dimension: Dimension,
+    /// Raster scale stamped on every generated tile (see [`Tile::scale`]). Defaults to 1.0.
+    pub raster_scale: f64,
+    /// Device pixel ratio stamped on every generated tile (see [`Tile::device_pixel_ratio`]).
+    pub device_pixel_ratio: Option<u32>,
 }
 
 impl Debug for TileList {
@@ -142,6 +155,8 @@ impl Debug for TileList {
             .field("arena", &self.arena)
             .field("next_node_id", &self.next_node_id)
             .field("default_tile_dimension", &self.default_tile_dimension)
+            .field("raster_scale", &self.raster_scale)
+            .field("device_pixel_ratio", &self.device_pixel_ratio)
             .finish()
     }
 }
@@ -198,6 +213,8 @@ impl TileList {
             arena: HashMap::new(),
             next_node_id: Arc::new(RwLock::new(TileId::new(0))),
             default_tile_dimension: dimension,
+            raster_scale: 1.0,
+            device_pixel_ratio: None,
         }
     }
 
@@ -210,6 +227,8 @@ impl TileList {
             arena: HashMap::new(),
             next_node_id: Arc::new(RwLock::new(TileId::new(0))),
             default_tile_dimension: dimension,
+            raster_scale: 1.0,
+            device_pixel_ratio: None,
         }
     }
 
@@ -292,6 +311,8 @@ impl TileList {
                         texture_id: None,
                         rect: Rect::new(x as f64 * tile_w, y as f64 * tile_h, tile_w, tile_h),
                         bgcolor,
+                        scale: self.raster_scale,
+                        device_pixel_ratio: self.device_pixel_ratio,
                     };
 
                     self.arena.insert(tile_id, tile);
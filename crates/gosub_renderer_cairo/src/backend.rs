@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use gosub_render_pipeline::print::PrintDocument;
 use gosub_render_pipeline::rasterizer::{erase_rasterizer, RasterStrategy};
 use gosub_render_pipeline::render::backend::{
     ErasedSurface, ExternalHandle, PixelFormat, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
@@ -197,6 +198,13 @@ impl RenderBackend for CairoBackend {
         erase_rasterizer(Box::new(crate::CairoRasterizer::with_font_system(font_system)))
     }
 
+    fn print_to_pdf(&self, document: &dyn Any) -> Result<Vec<u8>> {
+        let document = document
+            .downcast_ref::<PrintDocument>()
+            .ok_or_else(|| anyhow!("CairoBackend::print_to_pdf expects a PrintDocument"))?;
+        crate::pdf::render_pdf(document)
+    }
+
     fn raster_strategy(&self) -> RasterStrategy {
         RasterStrategy::ParallelCached
     }
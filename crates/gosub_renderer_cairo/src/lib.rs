@@ -1,4 +1,5 @@
 pub mod backend;
+pub mod pdf;
 pub mod rasterizer;
 
 pub use backend::{CairoBackend, CairoSurface};
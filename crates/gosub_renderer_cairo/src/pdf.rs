@@ -0,0 +1,241 @@
+//! PDF output for paginated documents.
+//!
+//! Every [`PrintPage`] becomes one PDF page of its own size. Paint commands go through the same
+//! painters as tile rasterization, drawn against a synthetic tile that covers the page's slice of
+//! the document, so text, rectangles and SVGs stay vector data in the PDF.
+
+use crate::rasterizer::paint_command;
+use anyhow::{anyhow, Result};
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::layering::layer::LayerId;
+use gosub_render_pipeline::painter::commands::PaintCommand;
+use gosub_render_pipeline::print::{PrintDocument, PrintPage};
+use gosub_render_pipeline::render::backend::TileAnchor;
+use gosub_render_pipeline::tiler::{Tile, TileId, TileState};
+
+/// PDF user space is in points (1/72in); CSS pixels are 1/96in.
+const PX_TO_PT: f64 = 72.0 / 96.0;
+
+/// Renders `doc` to a PDF file.
+pub fn render_pdf(doc: &PrintDocument) -> Result<Vec<u8>> {
+    let (width, height) = doc
+        .pages
+        .first()
+        .map_or((1.0, 1.0), |page| (page.setup.width, page.setup.height));
+    let surface = cairo::PdfSurface::for_stream(width * PX_TO_PT, height * PX_TO_PT, Vec::<u8>::new())?;
+    let cr = cairo::Context::new(&surface)?;
+
+    for page in &doc.pages {
+        // Must be set before anything is drawn on the page
+        surface.set_size(page.setup.width * PX_TO_PT, page.setup.height * PX_TO_PT)?;
+        draw_page(&cr, doc, page)?;
+        cr.show_page()?;
+    }
+
+    drop(cr);
+    let stream = surface
+        .finish_output_stream()
+        .map_err(|e| anyhow!("failed to finish PDF stream: {e}"))?;
+    stream
+        .downcast::<Vec<u8>>()
+        .map(|bytes| *bytes)
+        .map_err(|_| anyhow!("unexpected PDF stream type"))
+}
+
+/// Draws `page` in points, with the content clipped to the page area inside the margins.
+fn draw_page(cr: &cairo::Context, doc: &PrintDocument, page: &PrintPage) -> Result<()> {
+    let setup = page.setup;
+    cr.save()?;
+    cr.scale(PX_TO_PT, PX_TO_PT);
+    cr.translate(setup.margin[3], setup.margin[0]);
+    cr.rectangle(0.0, 0.0, setup.content_width(), setup.content_height());
+    cr.clip();
+
+    paint_page(cr, doc, page)?;
+
+    cr.restore()?;
+    Ok(())
+}
+
+fn paint_page(cr: &cairo::Context, doc: &PrintDocument, page: &PrintPage) -> Result<()> {
+    let width = page.setup.content_width();
+    // Scrolling content is painted relative to the page's slice; fixed content relative to the
+    // page area's origin, so it repeats at the same spot on every page.
+    let scroll_tile = page_tile(Rect::new(0.0, page.top, width, page.bottom - page.top));
+    let fixed_tile = page_tile(Rect::new(0.0, 0.0, width, page.setup.content_height()));
+
+    let mut groups = Vec::new();
+    for command in &page.commands {
+        match command {
+            PaintCommand::PushLayer { opacity, anchor } => {
+                cr.push_group();
+                groups.push((*opacity, matches!(anchor, TileAnchor::Fixed)));
+            }
+            PaintCommand::PopLayer => {
+                let Some((opacity, _)) = groups.pop() else {
+                    continue;
+                };
+                cr.pop_group_to_source()?;
+                cr.paint_with_alpha(f64::from(opacity))?;
+            }
+            _ => {
+                let fixed = groups.iter().any(|(_, fixed)| *fixed);
+                let tile = if fixed { &fixed_tile } else { &scroll_tile };
+                paint_command(cr, tile, command, &doc.media_store, 1);
+            }
+        }
+    }
+
+    // Close groups a malformed command list left open so the page still gets its content.
+    while let Some((opacity, _)) = groups.pop() {
+        cr.pop_group_to_source()?;
+        cr.paint_with_alpha(f64::from(opacity))?;
+    }
+    Ok(())
+}
+
+fn page_tile(rect: Rect) -> Tile {
+    Tile {
+        id: TileId::new(0),
+        layer_id: LayerId::new(0),
+        elements: Vec::new(),
+        texture_id: None,
+        state: TileState::Dirty,
+        rect,
+        bgcolor: None,
+        scale: 1.0,
+        device_pixel_ratio: Some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_render_pipeline::common::media::MediaStore;
+    use gosub_render_pipeline::painter::commands::brush::Brush;
+    use gosub_render_pipeline::painter::commands::color::Color;
+    use gosub_render_pipeline::painter::commands::rectangle::Rectangle;
+    use gosub_render_pipeline::print::PageSetup;
+    use std::sync::Arc;
+
+    /// A 96×96px page (72pt, so one pixel per point) with a 24px margin all around
+    const SETUP: PageSetup = PageSetup {
+        width: 96.0,
+        height: 96.0,
+        margin: [24.0; 4],
+    };
+
+    fn red_box(x: f64, y: f64) -> PaintCommand {
+        PaintCommand::rectangle(
+            Rectangle::new(Rect::new(x, y, 10.0, 10.0)).with_background(Brush::Solid(Color::from_rgb8(255, 0, 0))),
+        )
+    }
+
+    fn document(pages: Vec<PrintPage>) -> PrintDocument {
+        PrintDocument {
+            pages,
+            media_store: Arc::new(MediaStore::new()),
+        }
+    }
+
+    /// Draws `page` onto a 72×72pt image (one pixel per point) and returns the alpha of the
+    /// pixels at `points`.
+    fn alpha_at(doc: &PrintDocument, page: &PrintPage, points: &[(usize, usize)]) -> Vec<u8> {
+        let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 72, 72).unwrap();
+        {
+            let cr = cairo::Context::new(&surface).unwrap();
+            draw_page(&cr, doc, page).unwrap();
+        }
+        surface.flush();
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        // ARGB32 pixels are native-endian words with alpha in the high byte
+        points
+            .iter()
+            .map(|(x, y)| {
+                let offset = y * stride + x * 4;
+                let pixel = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+                (pixel >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pages_show_their_slice_inside_the_margins() {
+        // A box at the top of the second page's slice (document y 100..110)
+        let page = PrintPage {
+            setup: SETUP,
+            top: 100.0,
+            bottom: 148.0,
+            commands: vec![red_box(0.0, 100.0)],
+        };
+        let doc = document(vec![page.clone()]);
+
+        // At the page area's origin (24px = 18pt), not in the margin
+        assert_eq!(alpha_at(&doc, &page, &[(20, 20), (10, 10)]), [255, 0]);
+    }
+
+    #[test]
+    fn fixed_layers_repeat_at_the_page_area_origin() {
+        let page = PrintPage {
+            setup: SETUP,
+            top: 100.0,
+            bottom: 148.0,
+            commands: vec![
+                PaintCommand::PushLayer {
+                    opacity: 1.0,
+                    anchor: TileAnchor::Fixed,
+                },
+                red_box(0.0, 0.0),
+                PaintCommand::PopLayer,
+            ],
+        };
+        let doc = document(vec![page.clone()]);
+
+        assert_eq!(alpha_at(&doc, &page, &[(20, 20)]), [255]);
+    }
+
+    #[test]
+    fn content_outside_the_page_area_is_clipped() {
+        // Wider than the 48px page area: the part over the right margin is cut off
+        let page = PrintPage {
+            setup: SETUP,
+            top: 0.0,
+            bottom: 48.0,
+            commands: vec![PaintCommand::rectangle(
+                Rectangle::new(Rect::new(0.0, 0.0, 96.0, 10.0))
+                    .with_background(Brush::Solid(Color::from_rgb8(255, 0, 0))),
+            )],
+        };
+        let doc = document(vec![page.clone()]);
+
+        // Page area spans 18..54pt
+        assert_eq!(alpha_at(&doc, &page, &[(50, 20), (60, 20)]), [255, 0]);
+    }
+
+    #[test]
+    fn pdf_has_every_page() {
+        let first = PrintPage {
+            setup: SETUP,
+            top: 0.0,
+            bottom: 48.0,
+            commands: vec![red_box(0.0, 0.0)],
+        };
+        let second = PrintPage {
+            setup: PageSetup { width: 192.0, ..SETUP },
+            top: 48.0,
+            bottom: 96.0,
+            commands: vec![red_box(0.0, 50.0)],
+        };
+        let pdf = render_pdf(&document(vec![first, second])).unwrap();
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(String::from_utf8_lossy(&pdf).trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn empty_document_is_a_valid_pdf() {
+        let pdf = render_pdf(&document(Vec::new())).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+}
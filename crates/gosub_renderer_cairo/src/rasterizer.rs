@@ -51,11 +51,14 @@ impl Rasterable for CairoRasterizer {
     }
 
     fn rasterize(&self, tile: &Tile, texture_store: &mut TextureStore, media_store: &MediaStore) -> Option<TextureId> {
-        let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed) as i32;
+        let dpr = tile
+            .device_pixel_ratio
+            .unwrap_or_else(|| DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed)) as i32;
 
-        // Tile surface is created at physical pixel resolution (CSS pixels × DPR).
-        let tile_w = tile.rect.width as i32 * dpr;
-        let tile_h = tile.rect.height as i32 * dpr;
+        // Tile surface is created at physical pixel resolution (CSS pixels × DPR × page zoom).
+        let scale = dpr as f64 * tile.scale;
+        let tile_w = (tile.rect.width * scale).round() as i32;
+        let tile_h = (tile.rect.height * scale).round() as i32;
 
         let Ok(mut surface) = cairo::ImageSurface::create(cairo::Format::ARgb32, tile_w, tile_h) else {
             log::error!("Failed to create Cairo image surface");
@@ -68,26 +71,11 @@ impl Rasterable for CairoRasterizer {
                 return None;
             };
             // Scale the context so all CSS-pixel coordinates map to physical pixels.
-            cr.scale(dpr as f64, dpr as f64);
+            cr.scale(scale, scale);
 
             for element in &tile.elements {
                 for command in &element.paint_commands {
-                    match command {
-                        // The tile path applies layer opacity/anchor at composite, so these
-                        // scene-only group markers never appear here - ignore them.
-                        PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => {}
-                        PaintCommand::Svg(command) => {
-                            svg::do_paint_svg(&cr.clone(), tile, &command.rect, command.media_id, media_store, dpr);
-                        }
-                        PaintCommand::Rectangle(command) => {
-                            rectangle::do_paint_rectangle(&cr.clone(), tile, command, media_store);
-                        }
-                        PaintCommand::Text(command) => {
-                            if let Err(e) = text::glyphs::do_paint_text(&cr, tile, command, media_store) {
-                                log::warn!("Failed to paint text: {:?}", e);
-                            }
-                        }
-                    }
+                    paint_command(&cr, tile, command, media_store, dpr);
                 }
             }
 
@@ -112,3 +100,30 @@ impl Rasterable for CairoRasterizer {
         Some(texture_id)
     }
 }
+
+/// Paints one command onto `cr`, translated so `tile.rect`'s origin lands on the context origin.
+/// Shared by tile rasterization and PDF output.
+pub(crate) fn paint_command(
+    cr: &cairo::Context,
+    tile: &Tile,
+    command: &PaintCommand,
+    media_store: &MediaStore,
+    dpr: i32,
+) {
+    match command {
+        // The tile path applies layer opacity/anchor at composite, and the PDF writer handles
+        // the groups itself, so these scene-only markers are ignored here.
+        PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => {}
+        PaintCommand::Svg(command) => {
+            svg::do_paint_svg(&cr.clone(), tile, &command.rect, command.media_id, media_store, dpr);
+        }
+        PaintCommand::Rectangle(command) => {
+            rectangle::do_paint_rectangle(&cr.clone(), tile, command, media_store);
+        }
+        PaintCommand::Text(command) => {
+            if let Err(e) = text::glyphs::do_paint_text(cr, tile, command, media_store) {
+                log::warn!("Failed to paint text: {:?}", e);
+            }
+        }
+    }
+}
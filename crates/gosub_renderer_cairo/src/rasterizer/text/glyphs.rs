@@ -211,6 +211,8 @@ mod tests {
             state: gosub_render_pipeline::tiler::TileState::Dirty,
             rect: GeoRect::new(0.0, 0.0, 200.0, 60.0),
             bgcolor: None,
+            scale: 1.0,
+            device_pixel_ratio: None,
         };
 
         let media_store = MediaStore::new();
@@ -48,9 +48,14 @@ impl Rasterable for SkiaRasterizer {
         // Rasterize at physical resolution (CSS px × DPR) so text/edges are crisp on HiDPI. The
         // compositor places these physical-sized tiles at physical positions (mirrors Cairo); at
         // DPR=1 this is a no-op.
-        let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed).max(1);
-        let width = tile.rect.width as u32 * dpr;
-        let height = tile.rect.height as u32 * dpr;
+        let dpr = tile
+            .device_pixel_ratio
+            .unwrap_or_else(|| DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed))
+            .max(1);
+        // Page zoom rasterizes the same CSS-pixel tile at a higher resolution.
+        let scale = dpr as f64 * tile.scale;
+        let width = (tile.rect.width * scale).round() as u32;
+        let height = (tile.rect.height * scale).round() as u32;
 
         if tile.layer_id != LayerId::new(0) && tile.elements.is_empty() {
             return None;
@@ -81,7 +86,7 @@ impl Rasterable for SkiaRasterizer {
         }
 
         // Draw in CSS coordinates scaled up to physical pixels, so commands stay DPR-agnostic.
-        canvas.scale((dpr as f32, dpr as f32));
+        canvas.scale((scale as f32, scale as f32));
         canvas.clip_rect(
             Rect::new(0.0, 0.0, tile.rect.width as f32, tile.rect.height as f32),
             None,
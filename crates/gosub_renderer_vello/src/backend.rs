@@ -160,19 +160,28 @@ impl<C: WgpuContextProvider + Send + Sync> VelloBackend<C> {
     /// Returns `None` when the context provides no paint scene (i.e. the CPU/display-list path).
     fn build_scene_from_paint_commands(&self, ctx: &dyn RenderContext) -> Option<Scene> {
         let ps = ctx.paint_scene()?.downcast_ref::<PaintScene>()?;
+        // The scene is in unzoomed layout pixels: bring scroll and viewport into that space and
+        // scale the finished scene up by the zoom factor.
+        let zoom = ctx.page_zoom();
         let (sx, sy) = ctx.scroll_offset();
+        let (sx, sy) = (sx / zoom, sy / zoom);
         let (vw, vh) = {
             let vp = ctx.viewport();
             (vp.width, vp.height)
         };
-        let size = Dimension::new(vw as f64, vh as f64);
+        let size = Dimension::new(vw as f64 / zoom, vh as f64 / zoom);
         // Scroll is applied as a scene translate, so scrolling needs no re-layout/re-paint.
         let affine = Affine::translate(Vec2::new(-sx, -sy));
 
         let mut scene = Scene::new();
         // Text commands carry their pre-shaped glyph runs, so scene building needs no font system.
         crate::rasterizer::paint_commands_to_scene(&mut scene, &ps.commands, size, affine, (sx, sy), &ps.media_store);
-        Some(scene)
+        if (zoom - 1.0).abs() < f64::EPSILON {
+            return Some(scene);
+        }
+        let mut zoomed = Scene::new();
+        zoomed.append(&scene, Some(Affine::scale(zoom)));
+        Some(zoomed)
     }
 
     fn build_scene(
@@ -117,11 +117,16 @@ impl Rasterable for VelloRasterizer {
         let mut scene = Scene::new();
 
         let tile_size = Dimension::new(tile.rect.width, tile.rect.height);
+        // Texture size in pixels: the CSS-pixel tile scaled by the page zoom.
+        let (tex_w, tex_h) = (
+            (tile_size.width * tile.scale).round(),
+            (tile_size.height * tile.scale).round(),
+        );
 
-        let clip = Rect::new(0.0, 0.0, tile_size.width, tile_size.height);
+        let clip = Rect::new(0.0, 0.0, tex_w, tex_h);
         scene.push_clip_layer(Fill::NonZero, Affine::IDENTITY, &clip);
 
-        let affine = Affine::translate(Vec2::new(-tile.rect.x, -tile.rect.y));
+        let affine = Affine::scale(tile.scale) * Affine::translate(Vec2::new(-tile.rect.x, -tile.rect.y));
 
         for element in &tile.elements {
             // The tile path applies opacity/anchor at composite, so per-element commands carry no
@@ -143,12 +148,12 @@ impl Rasterable for VelloRasterizer {
 
         // The tile stays GPU-resident - no readback. The engine only ever sees the opaque id, which
         // it carries through the normal tile cache and hands back to `composite_tiles`.
-        let texture = crate::gpu_tiles::create_tile_texture(device, tile_size.width as u32, tile_size.height as u32);
+        let texture = crate::gpu_tiles::create_tile_texture(device, tex_w as u32, tex_h as u32);
 
         let render_params = RenderParams {
             base_color: Color::new([0.0, 0.0, 0.0, 0.0]),
-            width: tile.rect.width as u32,
-            height: tile.rect.height as u32,
+            width: tex_w as u32,
+            height: tex_h as u32,
             antialiasing_method: AaConfig::Area,
         };
 
@@ -166,8 +171,8 @@ impl Rasterable for VelloRasterizer {
         let gpu_id = self.resources.store_tile(texture);
 
         let texture_id = texture_store.add_gpu(
-            tile_size.width as usize,
-            tile_size.height as usize,
+            tex_w as usize,
+            tex_h as usize,
             gpu_id,
             gosub_render_pipeline::render::backend::PixelFormat::Rgba8,
         );
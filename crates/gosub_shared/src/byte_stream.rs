@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::char::REPLACEMENT_CHARACTER;
 use std::fmt::{Debug, Display, Formatter};
 use std::io::Read;
@@ -111,21 +112,26 @@ impl Default for Config {
 pub struct ByteStream {
     /// Raw bytes of the source data
     buffer: Vec<u8>,
-    /// Pre-decoded characters (filled by decode_buffer)
-    chars: Vec<Character>,
-    /// Byte offset of each decoded character in `buffer`
-    char_byte_offsets: Vec<usize>,
-    /// First byte of `buffer` not yet turned into a `Character`. Lets `append_str`
-    /// resume decoding instead of re-scanning the whole buffer (O(n) total, not O(n^2)).
+    /// Decoded UTF-8 text, for when it differs from the source: with a non-UTF-8 encoding, once
+    /// invalid UTF-8 has been repaired, or once text has been inserted. While `None`, the text is
+    /// the decoded prefix of `buffer` itself and is read in place without copying.
+    decoded: Option<Vec<u8>>,
+    /// First byte of `buffer` not yet turned into text. Lets `append_str` resume decoding
+    /// instead of re-scanning the whole buffer (O(n) total, not O(n^2)).
     decoded_bytes: usize,
-    /// char_pos of the first character on each line (index 0 = line 1)
+    /// Maps text offsets back to byte offsets in `buffer`, sorted by text offset. Never empty; a
+    /// single identity run while the text is read in place.
+    offset_runs: Vec<OffsetRun>,
+    /// Text offset and value of every lone UTF-16 surrogate. The text holds U+FFFD in their place.
+    surrogates: Vec<(usize, u16)>,
+    /// Text offset of the first character on each line (index 0 = line 1)
     line_starts: Vec<usize>,
-    /// Cached index into `line_starts` from the last `location()` call (lookup hint)
-    last_line_idx: std::cell::Cell<usize>,
-    /// Number of `chars` already scanned by `extend_line_starts` (incremental watermark)
-    lines_scanned_chars: usize,
-    /// Current position in the decoded chars array
-    char_pos: usize,
+    /// Number of text bytes already scanned by `extend_line_starts` (incremental watermark)
+    lines_scanned: usize,
+    /// Text offset, line index and column of the last `location()` call (lookup hint)
+    last_location: Cell<(usize, usize, usize)>,
+    /// Current position, as a byte offset in the text
+    pos: usize,
     /// True when the stream is closed (no more data will be added)
     closed: bool,
     /// Current encoding
@@ -134,9 +140,39 @@ pub struct ByteStream {
     config: Config,
 }
 
+/// A stretch of text, starting at text offset `text`, in which every `text_unit` bytes of text
+/// were decoded from `source_unit` bytes of the buffer, starting at byte offset `source`. Text
+/// that is not in the buffer at all (see [`ByteStream::insert_str`]) has a `source_unit` of 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OffsetRun {
+    text: usize,
+    source: usize,
+    text_unit: usize,
+    source_unit: usize,
+}
+
+impl OffsetRun {
+    /// Text and source offsets are the same: valid UTF-8 read in place
+    const IDENTITY: Self = Self {
+        text: 0,
+        source: 0,
+        text_unit: 1,
+        source_unit: 1,
+    };
+
+    /// Byte offset in the buffer of the character at text offset `text`, which lies in this run
+    fn source_at(&self, text: usize) -> usize {
+        // Skip the division for runs read in place, which is what nearly every lookup hits
+        if self.text_unit == self.source_unit {
+            return self.source + (text - self.text);
+        }
+        self.source + (text - self.text) / self.text_unit * self.source_unit
+    }
+}
+
 /// Opaque snapshot of stream position for mark/reset.
 pub struct StreamMark {
-    char_pos: usize,
+    pos: usize,
 }
 
 /// Generic stream trait
@@ -182,38 +218,34 @@ impl Default for ByteStream {
 }
 
 impl Stream for ByteStream {
+    #[inline]
     fn read(&self) -> Character {
-        if self.char_pos < self.chars.len() {
-            self.chars[self.char_pos]
-        } else {
-            StreamEnd
-        }
+        self.char_at(self.pos).map_or(StreamEnd, |(ch, _)| ch)
     }
 
+    #[inline]
     fn read_and_next(&mut self) -> Character {
-        let ch = self.read();
-        if matches!(ch, StreamEnd) {
-            return ch;
-        }
-        self.char_pos += 1;
-
-        if self.config.cr_lf_as_one && ch == Ch(CHAR_CR) && self.read() == Ch(CHAR_LF) {
-            self.char_pos += 1;
-            Ch(CHAR_LF)
-        } else if self.config.replace_cr_as_lf && ch == Ch(CHAR_CR) && self.read() != Ch(CHAR_LF) {
-            Ch(CHAR_LF)
-        } else {
-            ch
+        let Some((ch, len)) = self.char_at(self.pos) else {
+            return StreamEnd;
+        };
+        self.pos += len;
+
+        if ch == Ch(CHAR_CR) {
+            let followed_by_lf = self.text().get(self.pos) == Some(&b'\n');
+            if self.config.cr_lf_as_one && followed_by_lf {
+                self.pos += 1;
+                return Ch(CHAR_LF);
+            }
+            if self.config.replace_cr_as_lf && !followed_by_lf {
+                return Ch(CHAR_LF);
+            }
         }
+        ch
     }
 
     fn look_ahead(&self, offset: usize) -> Character {
-        let pos = self.char_pos + offset;
-        if pos < self.chars.len() {
-            self.chars[pos]
-        } else {
-            StreamEnd
-        }
+        self.char_at(self.advance(self.pos, offset))
+            .map_or(StreamEnd, |(ch, _)| ch)
     }
 
     fn next(&mut self) {
@@ -221,7 +253,7 @@ impl Stream for ByteStream {
     }
 
     fn next_n(&mut self, offset: usize) {
-        self.char_pos = (self.char_pos + offset).min(self.chars.len());
+        self.pos = self.advance(self.pos, offset);
     }
 
     fn prev(&mut self) {
@@ -230,29 +262,26 @@ impl Stream for ByteStream {
 
     fn prev_n(&mut self, n: usize) {
         for _ in 0..n {
-            self.char_pos = self.char_pos.saturating_sub(1);
+            let text = self.text();
+            let mut pos = text[..self.pos]
+                .iter()
+                .rposition(|&byte| !is_continuation(byte))
+                .unwrap_or(0);
             // read_and_next() consumes CR+LF as a single step, advancing by 2.
             // Stepping back from after the pair lands on LF; skip the CR too.
-            if self.config.cr_lf_as_one
-                && self.read() == Ch(CHAR_LF)
-                && self.char_pos > 0
-                && self.chars[self.char_pos - 1] == Ch(CHAR_CR)
-            {
-                self.char_pos -= 1;
+            if self.config.cr_lf_as_one && text.get(pos) == Some(&b'\n') && pos > 0 && text[pos - 1] == b'\r' {
+                pos -= 1;
             }
+            self.pos = pos;
         }
     }
 
     fn seek_bytes(&mut self, offset: usize) {
-        let pos = self.char_byte_offsets.partition_point(|&b| b < offset);
-        self.char_pos = pos.min(self.chars.len());
+        self.pos = self.text_offset(offset);
     }
 
     fn tell_bytes(&self) -> usize {
-        self.char_byte_offsets
-            .get(self.char_pos)
-            .copied()
-            .unwrap_or(self.buffer.len())
+        self.source_offset(self.pos)
     }
 
     fn get_slice(&mut self, len: usize) -> Vec<Character> {
@@ -266,7 +295,7 @@ impl Stream for ByteStream {
     }
 
     fn reset_stream(&mut self) {
-        self.char_pos = 0;
+        self.pos = 0;
     }
 
     fn close(&mut self) {
@@ -277,34 +306,43 @@ impl Stream for ByteStream {
         self.closed
     }
 
+    #[inline]
     fn exhausted(&self) -> bool {
-        self.char_pos >= self.chars.len()
+        self.pos >= self.text().len()
     }
 
+    #[inline]
     fn eof(&self) -> bool {
         self.closed() && self.exhausted()
     }
 
     fn location(&self) -> Location {
-        // Find the last line that starts at or before char_pos. The stream advances
-        // mostly monotonically, so start from the cached line index of the previous
-        // call and walk from there - amortized O(1) instead of a binary search per call.
-        let mut idx = self.last_line_idx.get().min(self.line_starts.len() - 1);
-        while idx > 0 && self.line_starts[idx] > self.char_pos {
+        // Find the last line that starts at or before pos. The stream advances mostly
+        // monotonically, so start from the line of the previous call and walk from there -
+        // amortized O(1) instead of a binary search per call. For the same reason the column
+        // is counted on from the previous call when it was on the same line.
+        let (last_pos, last_idx, last_column) = self.last_location.get();
+        let mut idx = last_idx.min(self.line_starts.len() - 1);
+        while idx > 0 && self.line_starts[idx] > self.pos {
             idx -= 1;
         }
-        while idx + 1 < self.line_starts.len() && self.line_starts[idx + 1] <= self.char_pos {
+        while idx + 1 < self.line_starts.len() && self.line_starts[idx + 1] <= self.pos {
             idx += 1;
         }
-        self.last_line_idx.set(idx);
+
+        let text = self.text();
+        let line_start = self.line_starts[idx];
+        let column = if idx == last_idx && line_start <= last_pos && last_pos <= self.pos {
+            last_column + count_chars(&text[last_pos..self.pos])
+        } else {
+            count_chars(&text[line_start..self.pos]) + 1
+        };
+        self.last_location.set((self.pos, idx, column));
+
         Location {
             line: idx + 1,
-            column: self.char_pos - self.line_starts[idx] + 1,
-            offset: self
-                .char_byte_offsets
-                .get(self.char_pos)
-                .copied()
-                .unwrap_or(self.buffer.len()),
+            column,
+            offset: self.source_offset(self.pos),
         }
     }
 }
@@ -314,14 +352,15 @@ impl ByteStream {
     pub fn new(encoding: Encoding, config: Option<Config>) -> Self {
         Self {
             config: config.unwrap_or_default(),
-            char_pos: 0,
+            pos: 0,
             buffer: Vec::new(),
-            chars: Vec::new(),
-            char_byte_offsets: Vec::new(),
+            decoded: (encoding != Encoding::UTF8).then(Vec::new),
             decoded_bytes: 0,
+            offset_runs: vec![OffsetRun::IDENTITY],
+            surrogates: Vec::new(),
             line_starts: vec![0],
-            last_line_idx: std::cell::Cell::new(0),
-            lines_scanned_chars: 0,
+            lines_scanned: 0,
+            last_location: Cell::new((0, 0, 1)),
             closed: false,
             encoding,
         }
@@ -339,183 +378,290 @@ impl ByteStream {
 
     /// Take a snapshot of the current position for later restoration.
     pub fn mark(&self) -> StreamMark {
-        StreamMark {
-            char_pos: self.char_pos,
-        }
+        StreamMark { pos: self.pos }
     }
 
     /// Restore position to a previously saved mark.
     pub fn reset_to_mark(&mut self, mark: StreamMark) {
-        self.char_pos = mark.char_pos;
+        self.pos = mark.pos;
+    }
+
+    /// The decoded text, always valid UTF-8
+    #[inline]
+    fn text(&self) -> &[u8] {
+        match &self.decoded {
+            Some(decoded) => decoded,
+            None => &self.buffer[..self.decoded_bytes],
+        }
+    }
+
+    /// Decodes the character at text offset `pos`, returning it with its length in bytes.
+    /// ASCII is returned straight away; only other characters go through UTF-8 decoding.
+    #[inline]
+    fn char_at(&self, pos: usize) -> Option<(Character, usize)> {
+        let text = self.text();
+        let first = *text.get(pos)?;
+        if first.is_ascii() {
+            return Some((Ch(char::from(first)), 1));
+        }
+
+        // The text is valid UTF-8, so the sequence is complete and well-formed
+        let len = utf8_len(first);
+        let cont = |byte: u8| u32::from(byte & 0x3F);
+        let code_point = match *text.get(pos..pos + len)? {
+            [a, b] => (u32::from(a & 0x1F) << 6) | cont(b),
+            [a, b, c] => (u32::from(a & 0x0F) << 12) | (cont(b) << 6) | cont(c),
+            [a, b, c, d] => (u32::from(a & 0x07) << 18) | (cont(b) << 12) | (cont(c) << 6) | cont(d),
+            _ => u32::from(REPLACEMENT_CHARACTER),
+        };
+        let ch = char::from_u32(code_point).unwrap_or(REPLACEMENT_CHARACTER);
+        if ch == REPLACEMENT_CHARACTER {
+            if let Ok(idx) = self.surrogates.binary_search_by_key(&pos, |&(offset, _)| offset) {
+                return Some((Surrogate(self.surrogates[idx].1), len));
+            }
+        }
+        Some((Ch(ch), len))
+    }
+
+    /// Text offset `n` characters after `pos`, clamped to the end of the text
+    fn advance(&self, mut pos: usize, n: usize) -> usize {
+        let text = self.text();
+        for _ in 0..n {
+            match text.get(pos) {
+                Some(&byte) => pos += utf8_len(byte),
+                None => break,
+            }
+        }
+        pos
+    }
+
+    /// Byte offset in the buffer of the character at text offset `pos`. The end of the text
+    /// maps to the end of the buffer.
+    fn source_offset(&self, pos: usize) -> usize {
+        if pos >= self.text().len() {
+            return self.buffer.len();
+        }
+        let idx = self.offset_runs.partition_point(|run| run.text <= pos);
+        self.offset_runs
+            .get(idx.saturating_sub(1))
+            .map_or(pos, |run| run.source_at(pos))
+    }
+
+    /// Text offset of the first character that starts at or after byte `offset` of the buffer
+    fn text_offset(&self, offset: usize) -> usize {
+        let text = self.text();
+        let idx = self.offset_runs.partition_point(|run| run.source < offset);
+        let next_start = self.offset_runs.get(idx).map_or(text.len(), |run| run.text);
+
+        // The character may still lie in the run before, which starts ahead of `offset`
+        let Some(run) = idx.checked_sub(1).and_then(|i| self.offset_runs.get(i)) else {
+            return next_start;
+        };
+        if run.source_unit == 0 {
+            return next_start;
+        }
+        let mut pos = run.text + (offset - run.source).div_ceil(run.source_unit) * run.text_unit;
+        // Identity runs span multibyte characters; round up to the next one
+        while text.get(pos).is_some_and(|&byte| is_continuation(byte)) {
+            pos += 1;
+        }
+        pos.min(next_start)
     }
 
     /// Reset all decode state and decode `self.buffer` from scratch. Used by the
     /// full-load paths (`read_from_str`, `read_from_file`, `set_encoding`).
     fn decode_buffer(&mut self) {
-        self.chars.clear();
-        self.char_byte_offsets.clear();
+        self.decoded = (self.encoding != Encoding::UTF8).then(Vec::new);
         self.decoded_bytes = 0;
+        self.offset_runs.clear();
+        self.offset_runs.push(OffsetRun::IDENTITY);
+        self.surrogates.clear();
         self.line_starts.clear();
         self.line_starts.push(0);
-        self.lines_scanned_chars = 0;
-        self.last_line_idx.set(0);
-        self.decode_from(0);
+        self.lines_scanned = 0;
+        self.last_location.set((0, 0, 1));
+        self.pos = 0;
+        self.decode_pending();
     }
 
-    /// Decode `self.buffer[byte_pos..]`, appending to whatever is already decoded.
-    /// `byte_pos` must be a decode boundary (normally `self.decoded_bytes`). Advances
-    /// `self.decoded_bytes` past the bytes consumed and extends the line table for the
-    /// newly-decoded characters. This makes `append_str` O(new bytes) rather than
-    /// re-scanning the whole buffer on every call.
-    fn decode_from(&mut self, mut byte_pos: usize) {
+    /// Decode `self.buffer[self.decoded_bytes..]`, appending to whatever is already decoded,
+    /// and extend the line table for the new text. This makes `append_str` O(new bytes)
+    /// rather than re-scanning the whole buffer on every call.
+    fn decode_pending(&mut self) {
         match self.encoding {
             Encoding::Unknown => {
-                byte_pos = self.buffer.len();
+                self.decoded_bytes = self.buffer.len();
             }
             Encoding::Latin1 => {
-                while byte_pos < self.buffer.len() {
-                    let byte = self.buffer[byte_pos];
-                    self.char_byte_offsets.push(byte_pos);
+                while let Some(&byte) = self.buffer.get(self.decoded_bytes) {
                     if self.config.replace_high_ascii && byte > 127 {
-                        self.chars.push(Ch('?'));
+                        self.push_char('?', 1);
                     } else {
-                        self.chars.push(Ch(byte as char));
+                        self.push_char(char::from(byte), 1);
                     }
-                    byte_pos += 1;
                 }
             }
-            Encoding::UTF8 => {
-                // Decode the longest valid prefix in bulk, then handle any invalid or
-                // incomplete sequence and continue. Resumes from `byte_pos`, so appended
-                // data is decoded without re-scanning the whole buffer.
-                while byte_pos < self.buffer.len() {
-                    match std::str::from_utf8(&self.buffer[byte_pos..]) {
-                        Ok(s) => {
-                            for (off, ch) in s.char_indices() {
-                                self.char_byte_offsets.push(byte_pos + off);
-                                self.chars.push(Ch(ch));
-                            }
-                            byte_pos = self.buffer.len();
-                        }
-                        Err(e) => {
-                            let valid_up_to = e.valid_up_to();
-                            // SAFETY: from_utf8 validated bytes up to `valid_up_to`.
-                            #[allow(unsafe_code)]
-                            let s = unsafe {
-                                std::str::from_utf8_unchecked(&self.buffer[byte_pos..byte_pos + valid_up_to])
-                            };
-                            for (off, ch) in s.char_indices() {
-                                self.char_byte_offsets.push(byte_pos + off);
-                                self.chars.push(Ch(ch));
-                            }
-                            byte_pos += valid_up_to;
-
-                            match e.error_len() {
-                                Some(n) => {
-                                    self.char_byte_offsets.push(byte_pos);
-                                    self.chars.push(Ch(REPLACEMENT_CHARACTER));
-                                    byte_pos += n;
-                                }
-                                None => {
-                                    // Incomplete sequence at the end of the buffer. On a closed
-                                    // stream it decodes to a single replacement character; on an
-                                    // open stream we stop and leave the tail for the next append.
-                                    if self.closed {
-                                        self.char_byte_offsets.push(byte_pos);
-                                        self.chars.push(Ch(REPLACEMENT_CHARACTER));
-                                        byte_pos = self.buffer.len();
-                                    }
-                                    break;
-                                }
-                            }
-                        }
+            Encoding::UTF8 => self.decode_utf8(),
+            Encoding::UTF16LE => self.decode_utf16(u16::from_le_bytes),
+            Encoding::UTF16BE => self.decode_utf16(u16::from_be_bytes),
+        }
+        self.extend_line_starts();
+    }
+
+    /// Valid UTF-8 is taken as is: in place, or copied over in bulk once the text is decoded
+    /// into a buffer of its own. Only an invalid sequence forces that copy, as it has to be
+    /// replaced.
+    fn decode_utf8(&mut self) {
+        while self.decoded_bytes < self.buffer.len() {
+            let start = self.decoded_bytes;
+            let (valid_up_to, error) = match std::str::from_utf8(&self.buffer[start..]) {
+                Ok(_) => (self.buffer.len() - start, None),
+                Err(e) => (e.valid_up_to(), Some(e.error_len())),
+            };
+
+            if valid_up_to > 0 && self.decoded.is_some() {
+                let text = self.text().len();
+                self.push_run(OffsetRun {
+                    text,
+                    source: start,
+                    ..OffsetRun::IDENTITY
+                });
+                let buffer = &self.buffer;
+                self.decoded
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(&buffer[start..start + valid_up_to]);
+            }
+            self.decoded_bytes += valid_up_to;
+
+            match error {
+                None => break,
+                Some(Some(len)) => self.push_char(REPLACEMENT_CHARACTER, len),
+                Some(None) => {
+                    // Incomplete sequence at the end of the buffer. On a closed stream it
+                    // decodes to a single replacement character; on an open stream we stop
+                    // and leave the tail for the next append.
+                    if self.closed {
+                        self.push_char(REPLACEMENT_CHARACTER, self.buffer.len() - self.decoded_bytes);
                     }
+                    break;
                 }
             }
-            Encoding::UTF16LE => {
-                while byte_pos + 2 <= self.buffer.len() {
-                    let cu = u16::from_le_bytes([self.buffer[byte_pos], self.buffer[byte_pos + 1]]);
-                    let next = if byte_pos + 4 <= self.buffer.len() {
-                        Some(u16::from_le_bytes([
-                            self.buffer[byte_pos + 2],
-                            self.buffer[byte_pos + 3],
-                        ]))
-                    } else {
-                        None
-                    };
-                    let (ch, len) = decode_utf16_char(cu, || next);
-                    self.char_byte_offsets.push(byte_pos);
-                    self.chars.push(ch);
-                    byte_pos += len;
+        }
+    }
+
+    fn decode_utf16(&mut self, code_unit: fn([u8; 2]) -> u16) {
+        let unit_at = |buffer: &[u8], pos: usize| {
+            buffer
+                .get(pos..pos + 2)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(code_unit)
+        };
+
+        while let Some(cu) = unit_at(&self.buffer, self.decoded_bytes) {
+            let (ch, len) = decode_utf16_char(cu, || unit_at(&self.buffer, self.decoded_bytes + 2));
+            match ch {
+                Ch(ch) => self.push_char(ch, len),
+                Surrogate(surrogate) => {
+                    self.surrogates.push((self.text().len(), surrogate));
+                    self.push_char(REPLACEMENT_CHARACTER, len);
                 }
+                StreamEnd => break,
             }
-            Encoding::UTF16BE => {
-                while byte_pos + 2 <= self.buffer.len() {
-                    let cu = u16::from_be_bytes([self.buffer[byte_pos], self.buffer[byte_pos + 1]]);
-                    let next = if byte_pos + 4 <= self.buffer.len() {
-                        Some(u16::from_be_bytes([
-                            self.buffer[byte_pos + 2],
-                            self.buffer[byte_pos + 3],
-                        ]))
-                    } else {
-                        None
-                    };
-                    let (ch, len) = decode_utf16_char(cu, || next);
-                    self.char_byte_offsets.push(byte_pos);
-                    self.chars.push(ch);
-                    byte_pos += len;
-                }
+        }
+    }
+
+    /// Appends `ch` to the decoded text, as decoded from the next `source_len` bytes of the
+    /// buffer. Switches the stream over to decoded text when it was read in place.
+    fn push_char(&mut self, ch: char, source_len: usize) {
+        let mut utf8 = [0; 4];
+        let encoded = ch.encode_utf8(&mut utf8).as_bytes();
+        let (text_unit, source_unit) = if encoded.len() == source_len {
+            (1, 1)
+        } else {
+            (encoded.len(), source_len)
+        };
+
+        let text = self.text().len();
+        self.push_run(OffsetRun {
+            text,
+            source: self.decoded_bytes,
+            text_unit,
+            source_unit,
+        });
+        self.decoded_mut().extend_from_slice(encoded);
+        self.decoded_bytes += source_len;
+    }
+
+    /// Adds a run for the text from `run.text` on, which must be the end of the text. It is
+    /// merged into the last run when that one already maps it the same way.
+    fn push_run(&mut self, run: OffsetRun) {
+        if let Some(last) = self.offset_runs.last_mut() {
+            if last.text == run.text {
+                // The last run has no text yet
+                *last = run;
+                return;
+            }
+            if (last.text_unit, last.source_unit) == (run.text_unit, run.source_unit)
+                && (run.text - last.text).is_multiple_of(last.text_unit)
+                && last.source_at(run.text) == run.source
+            {
+                return;
             }
         }
-        self.decoded_bytes = byte_pos;
-        self.extend_line_starts();
+        self.offset_runs.push(run);
+    }
+
+    /// The decoded text as a buffer of its own, copying the text read in place if needed
+    fn decoded_mut(&mut self) -> &mut Vec<u8> {
+        let in_place = &self.buffer[..self.decoded_bytes];
+        self.decoded.get_or_insert_with(|| in_place.to_vec())
     }
 
-    /// Extend the `line_starts` table to cover any characters decoded since the last
-    /// call, respecting CR/LF config. `line_starts[n]` is the char_pos of the first
-    /// character on line n+1. Only the newly-decoded tail is scanned (incremental).
+    /// Extend the `line_starts` table to cover any text decoded since the last call,
+    /// respecting CR/LF config. `line_starts[n]` is the text offset of the first character
+    /// on line n+1. Only the newly-decoded tail is scanned (incremental).
     fn extend_line_starts(&mut self) {
-        let mut i = self.lines_scanned_chars;
+        let text = match &self.decoded {
+            Some(decoded) => decoded.as_slice(),
+            None => &self.buffer[..self.decoded_bytes],
+        };
+
+        let mut i = self.lines_scanned;
         // A CR at the previous boundary was classified without its following character
         // available; re-examine it now that more characters may have arrived. Drop any
         // line-start it provisionally produced before rescanning from the CR.
-        if i > 0 && self.chars[i - 1] == Ch(CHAR_CR) {
+        if i > 0 && text[i - 1] == b'\r' {
             i -= 1;
             while self.line_starts.last().is_some_and(|&v| v > i) {
                 self.line_starts.pop();
             }
         }
-        while i < self.chars.len() {
-            match self.chars[i] {
-                Ch(CHAR_CR)
-                    if self.config.cr_lf_as_one && i + 1 < self.chars.len() && self.chars[i + 1] == Ch(CHAR_LF) =>
-                {
+        // CR and LF never occur inside a multibyte UTF-8 sequence, so scanning bytes is enough
+        while i < text.len() {
+            match text[i] {
+                b'\r' if self.config.cr_lf_as_one && text.get(i + 1) == Some(&b'\n') => {
                     self.line_starts.push(i + 2);
                     i += 2;
                     continue;
                 }
-                Ch(CHAR_CR)
-                    if self.config.replace_cr_as_lf
-                        && (i + 1 >= self.chars.len() || self.chars[i + 1] != Ch(CHAR_LF)) =>
-                {
+                b'\r' if self.config.replace_cr_as_lf && text.get(i + 1) != Some(&b'\n') => {
                     self.line_starts.push(i + 1);
                 }
-                Ch(CHAR_LF) => {
+                b'\n' => {
                     self.line_starts.push(i + 1);
                 }
                 _ => {}
             }
             i += 1;
         }
-        self.lines_scanned_chars = self.chars.len();
+        self.lines_scanned = text.len();
     }
 
     pub fn read_from_file(&mut self, mut f: impl Read) -> io::Result<()> {
         self.buffer.clear();
         f.read_to_end(&mut self.buffer)?;
-        self.close();
+        self.closed = true;
         self.decode_buffer();
-        self.reset_stream();
         Ok(())
     }
 
@@ -526,33 +672,107 @@ impl ByteStream {
             self.encoding = enc;
         }
         self.decode_buffer();
-        self.reset_stream();
     }
 
     pub fn append_str(&mut self, s: &str) {
-        self.buffer.extend_from_slice(s.as_bytes());
+        self.append_bytes(s.as_bytes());
+    }
+
+    /// Appends raw bytes in the stream's encoding. A multi-byte sequence that is split over two
+    /// appends is decoded once the rest of it arrives.
+    pub fn append_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
         // Resume decoding from the first undecoded byte instead of re-scanning the
-        // whole buffer. char_pos indexes already-decoded chars, so it stays valid.
-        self.decode_from(self.decoded_bytes);
+        // whole buffer. pos is an offset in the already-decoded text, so it stays valid.
+        self.decode_pending();
+    }
+
+    /// Returns the current position as an offset in the decoded text, for use with
+    /// [`ByteStream::insert_str`]
+    #[must_use]
+    pub fn char_position(&self) -> usize {
+        self.pos
+    }
+
+    /// Inserts `s` into the decoded text at offset `pos` (clamped to the end), and returns the
+    /// offset right after the inserted text. This is how `document.write` feeds markup back to
+    /// the tokenizer.
+    ///
+    /// The inserted characters are not part of the source: they all report the byte offset of
+    /// the insertion point, and line numbers of the text after them stay those of the source.
+    /// The raw buffer is left alone, so a later [`ByteStream::set_encoding`] (which re-decodes
+    /// the buffer) drops inserted text.
+    pub fn insert_str(&mut self, pos: usize, s: &str) -> usize {
+        let text_len = self.text().len();
+        let pos = pos.min(text_len);
+        let n = s.len();
+        if n == 0 {
+            return pos;
+        }
+        let source = if pos < text_len {
+            self.source_offset(pos)
+        } else {
+            self.decoded_bytes
+        };
+
+        // Runs from `pos` on move past the inserted text. When `pos` falls inside a run, the
+        // rest of that run continues after the inserted text.
+        let split = self.offset_runs.partition_point(|run| run.text < pos);
+        for run in &mut self.offset_runs[split..] {
+            run.text += n;
+        }
+        let mut inserted = vec![OffsetRun {
+            text: pos,
+            source,
+            text_unit: 1,
+            source_unit: 0,
+        }];
+        if self.offset_runs.get(split).is_none_or(|run| run.text != pos + n) {
+            if let Some(&run) = split.checked_sub(1).and_then(|i| self.offset_runs.get(i)) {
+                inserted.push(OffsetRun {
+                    text: pos + n,
+                    source,
+                    ..run
+                });
+            }
+        }
+        self.offset_runs.splice(split..split, inserted);
+
+        self.decoded_mut().splice(pos..pos, s.bytes());
+        for (offset, _) in self.surrogates.iter_mut().filter(|(offset, _)| *offset >= pos) {
+            *offset += n;
+        }
+        for start in self.line_starts.iter_mut().filter(|start| **start > pos) {
+            *start += n;
+        }
+        if self.lines_scanned >= pos {
+            self.lines_scanned += n;
+        }
+        if self.pos > pos {
+            self.pos += n;
+        }
+        self.last_location.set((0, 0, 1));
+
+        pos + n
     }
 
     pub fn close(&mut self) {
         self.closed = true;
         // Resume from the trailing incomplete sequence (if any) so it resolves to a
         // replacement character. O(tail), not a full re-decode.
-        self.decode_from(self.decoded_bytes);
+        self.decode_pending();
     }
 
     pub fn read_from_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
         self.buffer = bytes.to_vec();
-        self.close();
-        self.reset_stream();
+        self.closed = true;
+        self.decode_buffer();
         Ok(())
     }
 
     #[cfg(test)]
     fn chars_left(&self) -> usize {
-        self.chars.len() - self.char_pos
+        count_chars(&self.text()[self.pos..])
     }
 }
 
@@ -600,11 +820,8 @@ impl ByteStream {
         let current_byte_offset = self.tell_bytes();
         self.encoding = e;
         self.decode_buffer();
-        // Remap char_pos to the same byte offset in the newly-decoded buffer
-        let new_pos = self.char_byte_offsets.partition_point(|&b| b < current_byte_offset);
-        // self.char_pos = new_pos.min(self.chars.len());
-        self.reset_stream();
-        self.next_n(new_pos);
+        // Move to the same byte offset in the newly-decoded text
+        self.seek_bytes(current_byte_offset);
     }
 }
 
@@ -665,6 +882,26 @@ fn decode_utf16_char(cu: u16, next_cu: impl FnOnce() -> Option<u16>) -> (Charact
     }
 }
 
+/// Length of the UTF-8 sequence that starts with `byte`
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xFF => 4,
+        _ => 1,
+    }
+}
+
+/// True for the continuation bytes of a multibyte UTF-8 sequence
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Number of characters in the UTF-8 `bytes`
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| !is_continuation(byte)).count()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1378,4 +1615,90 @@ mod test {
         stream.read_and_next();
         assert_eq!(stream.location().offset, 2);
     }
+
+    #[test]
+    fn test_insert_str_at_current_position() {
+        let mut stream = ByteStream::from_str("ab\ncd", Encoding::UTF8);
+        stream.next(); // skip 'a'
+
+        let pos = stream.char_position();
+        let pos = stream.insert_str(pos, "X");
+        assert_eq!(stream.insert_str(pos, "Y"), 3);
+
+        let mut out = String::new();
+        while let Ch(c) = stream.read_and_next() {
+            out.push(c);
+        }
+        assert_eq!(out, "XYb\ncd");
+
+        // Text after the insertion keeps its source line and byte offset
+        assert_eq!(stream.location().line, 2);
+        assert_eq!(stream.location().offset, 5);
+    }
+
+    #[test]
+    fn test_valid_utf8_is_read_in_place() {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.append_str("a\u{e9}");
+        stream.append_bytes(&[0xF0, 0x9F]); // first half of '😀'
+        stream.append_bytes(&[0x98, 0x80]);
+        stream.close();
+
+        // No copy of the text and no offset table beyond the identity mapping
+        assert!(stream.decoded.is_none());
+        assert_eq!(stream.offset_runs, vec![OffsetRun::IDENTITY]);
+
+        assert_eq!(stream.read_and_next(), Ch('a'));
+        assert_eq!(stream.read_and_next(), Ch('é'));
+        assert_eq!(stream.tell_bytes(), 3);
+        assert_eq!(stream.read_and_next(), Ch('😀'));
+        assert!(stream.eof());
+    }
+
+    #[test]
+    fn test_repaired_utf8_keeps_source_offsets() {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        // 'a', invalid byte, 'b', truncated 3-byte sequence, 'c'
+        stream.read_from_bytes(b"a\xFFb\xE4\xB8c").unwrap();
+
+        let mut read = Vec::new();
+        while !stream.eof() {
+            read.push((stream.location().offset, stream.read_and_next()));
+        }
+        assert_eq!(
+            read,
+            vec![
+                (0, Ch('a')),
+                (1, Ch(REPLACEMENT_CHARACTER)),
+                (2, Ch('b')),
+                (3, Ch(REPLACEMENT_CHARACTER)),
+                (5, Ch('c')),
+            ]
+        );
+        assert_eq!(stream.tell_bytes(), 6);
+
+        // Seeking into the middle of the truncated sequence rounds up to the next character
+        stream.seek_bytes(4);
+        assert_eq!(stream.read(), Ch('c'));
+    }
+
+    #[test]
+    fn test_insert_str_into_transcoded_text() {
+        let mut stream = ByteStream::new(Encoding::Latin1, None);
+        stream.read_from_bytes(b"caf\xE9 ok").unwrap();
+        stream.next_n(4);
+        assert_eq!(stream.location(), Location::new(1, 5, 4));
+
+        let pos = stream.char_position();
+        stream.insert_str(pos, "\u{2014}");
+        assert_eq!(stream.read_and_next(), Ch('\u{2014}'));
+        // The inserted text reports the insertion point, the text after it its source offset
+        assert_eq!(stream.location(), Location::new(1, 6, 4));
+        assert_eq!(stream.read_and_next(), Ch(' '));
+        assert_eq!(stream.tell_bytes(), 5);
+
+        stream.reset_stream();
+        stream.seek_bytes(3);
+        assert_eq!(stream.read_and_next(), Ch('é'));
+    }
 }
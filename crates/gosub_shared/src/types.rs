@@ -4,13 +4,39 @@ use crate::byte_stream::Location;
 use std::ops::Add;
 use thiserror::Error;
 
-/// Parser error that defines an error (message) on the given position
+/// How serious a parse error is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Valid markup that the engine does not (fully) support yet
+    Warning,
+    /// Non-conforming markup: a parse error as defined by the specification
+    #[default]
+    Error,
+}
+
+impl Severity {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Parser error that defines an error (message) on the given span
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParseError {
+    /// Error code, like `unexpected-null-character`. Tokenizer errors use the WHATWG codes, tree
+    /// construction errors (which the specification leaves unnamed) use html5lib's naming.
+    pub code: String,
     /// Parse error message
     pub message: String,
-    /// Location of the error
+    /// Location of the error (start of the span)
     pub location: Location,
+    /// End of the span (exclusive). Equal to `location` for errors at a single position.
+    pub end: Location,
+    pub severity: Severity,
 }
 
 /// Serious errors and errors from third-party libraries
@@ -0,0 +1,151 @@
+//! `browsingContext.captureScreenshot`: turns the latest frame of a tab into a PNG.
+
+use anyhow::{anyhow, bail, Result};
+use gosub_render_pipeline::render::backend::ExternalHandle;
+use gosub_render_pipeline::render::{argb_u32_to_rgba8, composite_tiles, DefaultCompositor, TileTarget};
+use gosub_shared::tab_id::TabId;
+use image::{ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+/// Where the server gets the frames it captures. Implemented for the engine's
+/// [`DefaultCompositor`] and for any `Fn(TabId) -> Option<ExternalHandle>`, so embedders with their
+/// own compositor can plug it in.
+pub trait FrameSource {
+    /// Returns the most recent frame submitted for `tab_id`, if any.
+    fn frame(&self, tab_id: TabId) -> Option<ExternalHandle>;
+}
+
+impl FrameSource for DefaultCompositor {
+    fn frame(&self, tab_id: TabId) -> Option<ExternalHandle> {
+        self.frame_for(tab_id)
+    }
+}
+
+impl<F: Fn(TabId) -> Option<ExternalHandle>> FrameSource for F {
+    fn frame(&self, tab_id: TabId) -> Option<ExternalHandle> {
+        self(tab_id)
+    }
+}
+
+/// The `origin` parameter of `captureScreenshot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureOrigin {
+    /// The visible viewport at the current scroll position.
+    Viewport,
+    /// The whole document, from the top of the page.
+    Document,
+}
+
+/// Encodes `handle` as a PNG. Tile caches are composited onto white; `Document` captures the full
+/// page height and is only available for tile caches, which hold the whole page.
+pub fn capture_png(handle: &ExternalHandle, origin: CaptureOrigin) -> Result<Vec<u8>> {
+    let image = match handle {
+        ExternalHandle::TileCache {
+            viewport_width,
+            viewport_height,
+            dpr,
+            scroll_x,
+            scroll_y,
+            page_height,
+            tiles,
+        } => {
+            let dpr = (*dpr).max(1);
+            let (scroll, css_height) = match origin {
+                CaptureOrigin::Viewport => ((*scroll_x, *scroll_y), *viewport_height as f32),
+                CaptureOrigin::Document => ((0.0, 0.0), page_height.max(*viewport_height as f32)),
+            };
+            let width = (*viewport_width * dpr).max(1) as usize;
+            let height = ((css_height * dpr as f32).ceil() as usize).max(1);
+
+            let mut buf = vec![0xFFFF_FFFFu32; width * height];
+            composite_tiles(
+                tiles,
+                dpr,
+                scroll,
+                &mut TileTarget {
+                    buf: &mut buf,
+                    stride: width,
+                    origin_x: 0,
+                    origin_y: 0,
+                    width,
+                    height,
+                },
+            );
+            RgbaImage::from_raw(width as u32, height as u32, argb_u32_to_rgba8(&buf))
+                .ok_or_else(|| anyhow!("composited buffer does not match its size"))?
+        }
+        ExternalHandle::CpuPixelsOwned {
+            width,
+            height,
+            stride,
+            pixels,
+            format,
+        } => {
+            if origin == CaptureOrigin::Document {
+                bail!("document captures need a tile-caching backend");
+            }
+            let row_len = *width as usize * 4;
+            let mut rgba = Vec::with_capacity(row_len * *height as usize);
+            for row in pixels.chunks(*stride as usize).take(*height as usize) {
+                let row = row
+                    .get(..row_len)
+                    .ok_or_else(|| anyhow!("pixel row shorter than the frame width"))?;
+                rgba.extend_from_slice(&format.to_rgba(row));
+            }
+            // The frame is opaque page content; drop whatever alpha the rasterizer left behind.
+            for px in rgba.chunks_exact_mut(4) {
+                px[3] = 255;
+            }
+            RgbaImage::from_raw(*width, *height, rgba).ok_or_else(|| anyhow!("frame is smaller than its size"))?
+        }
+        _ => bail!("frames of this backend cannot be read back on the CPU"),
+    };
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_render_pipeline::render::backend::PixelFormat;
+
+    #[test]
+    fn encodes_cpu_frames_with_padded_rows() {
+        // 2x1 frame, BGRA (little-endian ARGB32), stride padded to 12 bytes.
+        let handle = ExternalHandle::CpuPixelsOwned {
+            width: 2,
+            height: 1,
+            stride: 12,
+            pixels: vec![0, 0, 255, 255, 255, 0, 0, 255, 9, 9, 9, 9],
+            format: PixelFormat::PreMulArgb32,
+        };
+        let png = capture_png(&handle, CaptureOrigin::Viewport).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+
+        assert!(capture_png(&handle, CaptureOrigin::Document).is_err());
+    }
+
+    #[test]
+    fn empty_tile_cache_is_white() {
+        let handle = ExternalHandle::TileCache {
+            viewport_width: 4,
+            viewport_height: 3,
+            dpr: 2,
+            scroll_x: 0.0,
+            scroll_y: 0.0,
+            page_height: 10.0,
+            tiles: Default::default(),
+        };
+        let viewport = image::load_from_memory(&capture_png(&handle, CaptureOrigin::Viewport).unwrap()).unwrap();
+        assert_eq!((viewport.width(), viewport.height()), (8, 6));
+        assert_eq!(viewport.to_rgba8().get_pixel(3, 3).0, [255, 255, 255, 255]);
+
+        let document = image::load_from_memory(&capture_png(&handle, CaptureOrigin::Document).unwrap()).unwrap();
+        assert_eq!(document.height(), 20);
+    }
+}
@@ -0,0 +1,428 @@
+//! `input.performActions`: turns BiDi input sources into ticks of [`TabCommand`]s.
+//!
+//! Actions from all sources are grouped by index into ticks, as the spec requires. Each tick lists
+//! the commands to send and how long it lasts (the longest `pause` or `pointerMove` duration in it).
+//! Pointer position, pressed buttons and keys, and modifiers persist between calls in
+//! [`InputState`] so `input.releaseActions` can undo them.
+
+use crate::protocol::{BidiError, BidiResult, ErrorCode};
+use gosub_engine::events::{Modifiers, MouseButton, TabCommand};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Special keys from the WebDriver key table: codepoint, `key` value and `code` value.
+const SPECIAL_KEYS: &[(char, &str, &str)] = &[
+    ('\u{E000}', "Unidentified", ""),
+    ('\u{E002}', "Help", "Help"),
+    ('\u{E003}', "Backspace", "Backspace"),
+    ('\u{E004}', "Tab", "Tab"),
+    ('\u{E005}', "Clear", "NumpadClear"),
+    ('\u{E006}', "Enter", "NumpadEnter"),
+    ('\u{E007}', "Enter", "Enter"),
+    ('\u{E008}', "Shift", "ShiftLeft"),
+    ('\u{E009}', "Control", "ControlLeft"),
+    ('\u{E00A}', "Alt", "AltLeft"),
+    ('\u{E00B}', "Pause", "Pause"),
+    ('\u{E00C}', "Escape", "Escape"),
+    ('\u{E00D}', " ", "Space"),
+    ('\u{E00E}', "PageUp", "PageUp"),
+    ('\u{E00F}', "PageDown", "PageDown"),
+    ('\u{E010}', "End", "End"),
+    ('\u{E011}', "Home", "Home"),
+    ('\u{E012}', "ArrowLeft", "ArrowLeft"),
+    ('\u{E013}', "ArrowUp", "ArrowUp"),
+    ('\u{E014}', "ArrowRight", "ArrowRight"),
+    ('\u{E015}', "ArrowDown", "ArrowDown"),
+    ('\u{E016}', "Insert", "Insert"),
+    ('\u{E017}', "Delete", "Delete"),
+    ('\u{E031}', "F1", "F1"),
+    ('\u{E032}', "F2", "F2"),
+    ('\u{E033}', "F3", "F3"),
+    ('\u{E034}', "F4", "F4"),
+    ('\u{E035}', "F5", "F5"),
+    ('\u{E036}', "F6", "F6"),
+    ('\u{E037}', "F7", "F7"),
+    ('\u{E038}', "F8", "F8"),
+    ('\u{E039}', "F9", "F9"),
+    ('\u{E03A}', "F10", "F10"),
+    ('\u{E03B}', "F11", "F11"),
+    ('\u{E03C}', "F12", "F12"),
+    ('\u{E03D}', "Meta", "MetaLeft"),
+    ('\u{E050}', "Shift", "ShiftRight"),
+    ('\u{E051}', "Control", "ControlRight"),
+    ('\u{E052}', "Alt", "AltRight"),
+    ('\u{E053}', "Meta", "MetaRight"),
+];
+
+/// Input state kept per browsing context across `input.performActions` calls.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    x: f32,
+    y: f32,
+    modifiers: Modifiers,
+    buttons: Vec<u64>,
+    keys: Vec<String>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            modifiers: Modifiers::empty(),
+            buttons: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+/// One tick of input: commands to dispatch, then how long to wait before the next tick.
+#[derive(Debug, Default)]
+pub struct Tick {
+    pub commands: Vec<TabCommand>,
+    pub duration: Duration,
+}
+
+impl InputState {
+    /// Plans the `actions` parameter of `input.performActions`.
+    pub fn plan(&mut self, params: &Value) -> BidiResult<Vec<Tick>> {
+        let sources = params
+            .get("actions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| BidiError::invalid_argument("missing list parameter 'actions'"))?;
+
+        let mut ticks: Vec<Tick> = Vec::new();
+        for source in sources {
+            let kind = source.get("type").and_then(Value::as_str).unwrap_or("");
+            if kind == "pointer" {
+                let pointer_type = source
+                    .pointer("/parameters/pointerType")
+                    .and_then(Value::as_str)
+                    .unwrap_or("mouse");
+                if pointer_type != "mouse" {
+                    return Err(BidiError::new(
+                        ErrorCode::UnsupportedOperation,
+                        format!("pointer type '{pointer_type}' is not supported"),
+                    ));
+                }
+            }
+
+            let actions = source
+                .get("actions")
+                .and_then(Value::as_array)
+                .ok_or_else(|| BidiError::invalid_argument("input source is missing its actions"))?;
+            for (index, action) in actions.iter().enumerate() {
+                if ticks.len() <= index {
+                    ticks.resize_with(index + 1, Tick::default);
+                }
+                let tick = &mut ticks[index];
+                let duration = match kind {
+                    "none" => self.none_action(action)?,
+                    "key" => self.key_action(action, &mut tick.commands)?,
+                    "pointer" => self.pointer_action(action, &mut tick.commands)?,
+                    "wheel" => self.wheel_action(action, &mut tick.commands)?,
+                    _ => {
+                        return Err(BidiError::invalid_argument(format!(
+                            "unknown input source type '{kind}'"
+                        )))
+                    }
+                };
+                tick.duration = tick.duration.max(duration);
+            }
+        }
+        Ok(ticks)
+    }
+
+    /// Releases every pressed key and button, in reverse order of pressing
+    /// (`input.releaseActions`).
+    pub fn release(&mut self) -> Vec<TabCommand> {
+        let mut commands = Vec::new();
+        while let Some(button) = self.buttons.pop() {
+            if let Some(button) = mouse_button(button) {
+                commands.push(TabCommand::MouseUp {
+                    x: self.x,
+                    y: self.y,
+                    button,
+                });
+            }
+        }
+        while let Some(value) = self.keys.pop() {
+            if let Some((key, code, modifier)) = key_for(&value) {
+                self.modifiers.remove(modifier);
+                commands.push(TabCommand::KeyUp {
+                    key,
+                    code,
+                    modifiers: self.modifiers,
+                });
+            }
+        }
+        commands
+    }
+
+    fn none_action(&mut self, action: &Value) -> BidiResult<Duration> {
+        match action_type(action)? {
+            "pause" => duration(action),
+            other => Err(unknown_action(other)),
+        }
+    }
+
+    fn key_action(&mut self, action: &Value, commands: &mut Vec<TabCommand>) -> BidiResult<Duration> {
+        let kind = action_type(action)?;
+        if kind == "pause" {
+            return duration(action);
+        }
+        let value = action
+            .get("value")
+            .and_then(Value::as_str)
+            .ok_or_else(|| BidiError::invalid_argument("key action is missing its value"))?;
+        let (key, code, modifier) =
+            key_for(value).ok_or_else(|| BidiError::invalid_argument(format!("'{value}' is not a single key")))?;
+
+        match kind {
+            "keyDown" => {
+                self.modifiers.insert(modifier);
+                self.keys.push(value.to_string());
+                commands.push(TabCommand::KeyDown {
+                    key,
+                    code,
+                    modifiers: self.modifiers,
+                });
+            }
+            "keyUp" => {
+                self.modifiers.remove(modifier);
+                self.keys.retain(|k| k != value);
+                commands.push(TabCommand::KeyUp {
+                    key,
+                    code,
+                    modifiers: self.modifiers,
+                });
+            }
+            other => return Err(unknown_action(other)),
+        }
+        Ok(Duration::ZERO)
+    }
+
+    fn pointer_action(&mut self, action: &Value, commands: &mut Vec<TabCommand>) -> BidiResult<Duration> {
+        match action_type(action)? {
+            "pause" => duration(action),
+            "pointerMove" => {
+                let x = number(action, "x")?;
+                let y = number(action, "y")?;
+                match action.get("origin") {
+                    None | Some(Value::Null) => (self.x, self.y) = (x, y),
+                    Some(Value::String(origin)) if origin == "viewport" => (self.x, self.y) = (x, y),
+                    Some(Value::String(origin)) if origin == "pointer" => {
+                        self.x += x;
+                        self.y += y;
+                    }
+                    Some(Value::Object(_)) => {
+                        return Err(BidiError::new(
+                            ErrorCode::UnsupportedOperation,
+                            "element origins are not supported",
+                        ))
+                    }
+                    Some(_) => return Err(BidiError::invalid_argument("invalid pointer origin")),
+                }
+                commands.push(TabCommand::MouseMove { x: self.x, y: self.y });
+                duration(action)
+            }
+            kind @ ("pointerDown" | "pointerUp") => {
+                let index = action
+                    .get("button")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| BidiError::invalid_argument("pointer action is missing its button"))?;
+                let button = mouse_button(index)
+                    .ok_or_else(|| BidiError::invalid_argument(format!("unsupported mouse button {index}")))?;
+                let (x, y) = (self.x, self.y);
+                if kind == "pointerDown" {
+                    self.buttons.push(index);
+                    commands.push(TabCommand::MouseDown { x, y, button });
+                } else {
+                    self.buttons.retain(|b| *b != index);
+                    commands.push(TabCommand::MouseUp { x, y, button });
+                }
+                Ok(Duration::ZERO)
+            }
+            other => Err(unknown_action(other)),
+        }
+    }
+
+    fn wheel_action(&mut self, action: &Value, commands: &mut Vec<TabCommand>) -> BidiResult<Duration> {
+        match action_type(action)? {
+            "pause" => duration(action),
+            "scroll" => {
+                // The engine scrolls the whole page; the wheel position only matters for nested
+                // scrollers, which it does not have yet.
+                commands.push(TabCommand::MouseScroll {
+                    delta_x: number(action, "deltaX")?,
+                    delta_y: number(action, "deltaY")?,
+                });
+                duration(action)
+            }
+            other => Err(unknown_action(other)),
+        }
+    }
+}
+
+/// Maps a BiDi key value to `(key, code, modifier)`. Returns `None` when `value` is not exactly
+/// one character.
+fn key_for(value: &str) -> Option<(String, String, Modifiers)> {
+    let mut chars = value.chars();
+    let ch = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+
+    if let Some((_, key, code)) = SPECIAL_KEYS.iter().find(|(c, _, _)| *c == ch) {
+        let modifier = match *key {
+            "Shift" => Modifiers::SHIFT,
+            "Control" => Modifiers::CONTROL,
+            "Alt" => Modifiers::ALT,
+            "Meta" => Modifiers::META,
+            _ => Modifiers::empty(),
+        };
+        return Some((key.to_string(), code.to_string(), modifier));
+    }
+
+    let code = match ch {
+        'a'..='z' | 'A'..='Z' => format!("Key{}", ch.to_ascii_uppercase()),
+        '0'..='9' => format!("Digit{ch}"),
+        ' ' => "Space".to_string(),
+        _ => String::new(),
+    };
+    Some((ch.to_string(), code, Modifiers::empty()))
+}
+
+fn mouse_button(index: u64) -> Option<MouseButton> {
+    match index {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+fn action_type(action: &Value) -> BidiResult<&str> {
+    action
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| BidiError::invalid_argument("action is missing its type"))
+}
+
+fn duration(action: &Value) -> BidiResult<Duration> {
+    match action.get("duration") {
+        None | Some(Value::Null) => Ok(Duration::ZERO),
+        Some(value) => value
+            .as_u64()
+            .map(Duration::from_millis)
+            .ok_or_else(|| BidiError::invalid_argument("duration must be a non-negative integer")),
+    }
+}
+
+fn number(action: &Value, name: &str) -> BidiResult<f32> {
+    match action.get(name) {
+        None => Ok(0.0),
+        Some(value) => value
+            .as_f64()
+            .map(|n| n as f32)
+            .ok_or_else(|| BidiError::invalid_argument(format!("'{name}' must be a number"))),
+    }
+}
+
+fn unknown_action(kind: &str) -> BidiError {
+    BidiError::invalid_argument(format!("unknown action type '{kind}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pointer_click_becomes_move_down_up() {
+        let mut state = InputState::default();
+        let ticks = state
+            .plan(&json!({"actions": [{
+                "type": "pointer",
+                "id": "mouse",
+                "actions": [
+                    {"type": "pointerMove", "x": 10, "y": 20, "duration": 50},
+                    {"type": "pointerDown", "button": 0},
+                    {"type": "pointerUp", "button": 0},
+                ],
+            }]}))
+            .unwrap();
+
+        assert_eq!(ticks.len(), 3);
+        assert_eq!(ticks[0].duration, Duration::from_millis(50));
+        assert!(matches!(ticks[0].commands[..], [TabCommand::MouseMove { x, y }] if x == 10.0 && y == 20.0));
+        assert!(matches!(
+            ticks[1].commands[..],
+            [TabCommand::MouseDown { x, button: MouseButton::Left, .. }] if x == 10.0
+        ));
+        assert!(matches!(ticks[2].commands[..], [TabCommand::MouseUp { .. }]));
+        assert!(state.release().is_empty());
+    }
+
+    #[test]
+    fn relative_moves_use_the_current_position() {
+        let mut state = InputState::default();
+        state
+            .plan(&json!({"actions": [{"type": "pointer", "actions": [
+                {"type": "pointerMove", "x": 10, "y": 10},
+                {"type": "pointerMove", "x": 5, "y": -3, "origin": "pointer"},
+            ]}]}))
+            .unwrap();
+        assert_eq!((state.x, state.y), (15.0, 7.0));
+    }
+
+    #[test]
+    fn modifiers_apply_to_later_keys_and_are_released() {
+        let mut state = InputState::default();
+        let ticks = state
+            .plan(&json!({"actions": [{"type": "key", "id": "kb", "actions": [
+                {"type": "keyDown", "value": "\u{E008}"},
+                {"type": "keyDown", "value": "a"},
+                {"type": "keyUp", "value": "a"},
+            ]}]}))
+            .unwrap();
+
+        match &ticks[1].commands[0] {
+            TabCommand::KeyDown { key, code, modifiers } => {
+                assert_eq!(key, "a");
+                assert_eq!(code, "KeyA");
+                assert_eq!(*modifiers, Modifiers::SHIFT);
+            }
+            other => panic!("unexpected command {other:?}"),
+        }
+
+        let released = state.release();
+        assert!(matches!(&released[..], [TabCommand::KeyUp { key, modifiers, .. }]
+            if key == "Shift" && modifiers.is_empty()));
+    }
+
+    #[test]
+    fn sources_are_merged_into_ticks() {
+        let mut state = InputState::default();
+        let ticks = state
+            .plan(&json!({"actions": [
+                {"type": "none", "actions": [{"type": "pause", "duration": 100}]},
+                {"type": "wheel", "actions": [{"type": "scroll", "x": 0, "y": 0, "deltaX": 0, "deltaY": 120}]},
+            ]}))
+            .unwrap();
+
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].duration, Duration::from_millis(100));
+        assert!(matches!(ticks[0].commands[..], [TabCommand::MouseScroll { delta_y, .. }] if delta_y == 120.0));
+    }
+
+    #[test]
+    fn rejects_unsupported_input() {
+        let mut state = InputState::default();
+        let touch = json!({"actions": [{"type": "pointer", "parameters": {"pointerType": "touch"}, "actions": []}]});
+        assert_eq!(state.plan(&touch).unwrap_err().code, ErrorCode::UnsupportedOperation);
+
+        let word = json!({"actions": [{"type": "key", "actions": [{"type": "keyDown", "value": "ab"}]}]});
+        assert_eq!(state.plan(&word).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+}
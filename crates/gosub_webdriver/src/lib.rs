@@ -0,0 +1,35 @@
+//! WebDriver BiDi automation server for the Gosub engine.
+//!
+//! [`BidiServer`] listens for WebSocket connections on the loopback interface and speaks a subset
+//! of [WebDriver BiDi](https://w3c.github.io/webdriver-bidi/), mapped onto a [`Zone`] and its
+//! [`TabHandle`]s, so standard automation clients can drive the engine:
+//!
+//! - `session.*` - `status`, `new`, `end`, `subscribe`, `unsubscribe`
+//! - `browsingContext.*` - `create`, `getTree`, `navigate`, `captureScreenshot`, `close`, plus the
+//!   `contextCreated`/`contextDestroyed`, `navigationStarted`, `domContentLoaded`, `load` and
+//!   `navigationFailed` events
+//! - `network.*` events - `beforeRequestSent`, `responseStarted`, `responseCompleted` and
+//!   `fetchError`, built from the engine's resource events
+//! - `input.performActions` / `input.releaseActions` - mouse, wheel and key sources, dispatched as
+//!   [`TabCommand`] mouse and key input
+//!
+//! `script.evaluate` answers `unsupported operation` until the engine has scripting.
+//!
+//! Browsing contexts are tabs: the context id is the tab id. A client only sees the contexts it
+//! created, and they are closed when it disconnects. Browser-originated upgrades (those with an
+//! `Origin` header) are refused so web pages cannot reach the server through localhost.
+//!
+//! [`Zone`]: gosub_engine::zone::Zone
+//! [`TabHandle`]: gosub_engine::tab::TabHandle
+//! [`TabCommand`]: gosub_engine::events::TabCommand
+
+mod capture;
+mod input;
+mod protocol;
+mod server;
+mod session;
+pub mod websocket;
+
+pub use capture::{capture_png, CaptureOrigin, FrameSource};
+pub use protocol::{BidiError, ErrorCode};
+pub use server::BidiServer;
@@ -0,0 +1,202 @@
+//! BiDi message envelopes: parsing commands and serializing responses and events.
+
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Error codes from the WebDriver BiDi specification that this server can return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidArgument,
+    InvalidSessionId,
+    NoSuchFrame,
+    SessionNotCreated,
+    UnableToCaptureScreen,
+    UnknownCommand,
+    UnknownError,
+    UnsupportedOperation,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidArgument => "invalid argument",
+            ErrorCode::InvalidSessionId => "invalid session id",
+            ErrorCode::NoSuchFrame => "no such frame",
+            ErrorCode::SessionNotCreated => "session not created",
+            ErrorCode::UnableToCaptureScreen => "unable to capture screen",
+            ErrorCode::UnknownCommand => "unknown command",
+            ErrorCode::UnknownError => "unknown error",
+            ErrorCode::UnsupportedOperation => "unsupported operation",
+        }
+    }
+}
+
+/// An error response: a spec error code plus a human readable message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidiError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl BidiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidArgument, message)
+    }
+}
+
+impl fmt::Display for BidiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for BidiError {}
+
+pub type BidiResult<T> = Result<T, BidiError>;
+
+/// A command sent by the client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub id: u64,
+    pub method: String,
+    pub params: Value,
+}
+
+impl Command {
+    /// Parses a command message. On failure, returns the command id when it could be read so the
+    /// error can be addressed to it.
+    pub fn parse(text: &str) -> Result<Self, (Option<u64>, BidiError)> {
+        let value: Value = serde_json::from_str(text)
+            .map_err(|e| (None, BidiError::invalid_argument(format!("malformed JSON: {e}"))))?;
+        let Value::Object(mut object) = value else {
+            return Err((None, BidiError::invalid_argument("command must be a JSON object")));
+        };
+
+        let id = object
+            .get("id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| (None, BidiError::invalid_argument("command is missing a numeric id")))?;
+        let method = match object.remove("method") {
+            Some(Value::String(method)) => method,
+            _ => return Err((Some(id), BidiError::invalid_argument("command is missing a method"))),
+        };
+        let params = match object.remove("params") {
+            Some(params @ Value::Object(_)) => params,
+            None => Value::Object(Map::new()),
+            Some(_) => return Err((Some(id), BidiError::invalid_argument("params must be an object"))),
+        };
+
+        Ok(Self { id, method, params })
+    }
+}
+
+/// Serializes a success response.
+pub fn success(id: u64, result: Value) -> String {
+    json!({ "type": "success", "id": id, "result": result }).to_string()
+}
+
+/// Serializes an error response. `id` is null when the offending command had none.
+pub fn error(id: Option<u64>, error: &BidiError) -> String {
+    json!({
+        "type": "error",
+        "id": id,
+        "error": error.code.as_str(),
+        "message": error.message,
+    })
+    .to_string()
+}
+
+/// Serializes an event.
+pub fn event(method: &str, params: Value) -> String {
+    json!({ "type": "event", "method": method, "params": params }).to_string()
+}
+
+/// Milliseconds since the Unix epoch, as used by event timestamps.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Returns the required string parameter `name`.
+pub fn str_param<'a>(params: &'a Value, name: &str) -> BidiResult<&'a str> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| BidiError::invalid_argument(format!("missing string parameter '{name}'")))
+}
+
+/// Returns the optional string parameter `name`, failing when it is present with another type.
+pub fn opt_str_param<'a>(params: &'a Value, name: &str) -> BidiResult<Option<&'a str>> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(BidiError::invalid_argument(format!(
+            "parameter '{name}' must be a string"
+        ))),
+    }
+}
+
+/// Returns the string list parameter `name`.
+pub fn str_list_param(params: &Value, name: &str) -> BidiResult<Vec<String>> {
+    let list = params
+        .get(name)
+        .and_then(Value::as_array)
+        .ok_or_else(|| BidiError::invalid_argument(format!("missing list parameter '{name}'")))?;
+    list.iter()
+        .map(|v| {
+            v.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| BidiError::invalid_argument(format!("'{name}' must only contain strings")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        let cmd = Command::parse(r#"{"id":3,"method":"session.status","params":{"a":1}}"#).unwrap();
+        assert_eq!(cmd.id, 3);
+        assert_eq!(cmd.method, "session.status");
+        assert_eq!(cmd.params["a"], 1);
+
+        let cmd = Command::parse(r#"{"id":4,"method":"session.status"}"#).unwrap();
+        assert!(cmd.params.as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_errors_keep_the_id_when_possible() {
+        assert_eq!(Command::parse("nope").unwrap_err().0, None);
+        assert_eq!(Command::parse(r#"{"method":"x"}"#).unwrap_err().0, None);
+        let (id, err) = Command::parse(r#"{"id":7,"params":{}}"#).unwrap_err();
+        assert_eq!(id, Some(7));
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn serializes_envelopes() {
+        let ok: Value = serde_json::from_str(&success(1, json!({}))).unwrap();
+        assert_eq!(ok, json!({"type":"success","id":1,"result":{}}));
+
+        let err: Value =
+            serde_json::from_str(&error(Some(2), &BidiError::new(ErrorCode::NoSuchFrame, "gone"))).unwrap();
+        assert_eq!(err["error"], "no such frame");
+        assert_eq!(err["message"], "gone");
+
+        let ev: Value = serde_json::from_str(&event("browsingContext.load", json!({"url":"x"}))).unwrap();
+        assert_eq!(ev["type"], "event");
+        assert_eq!(ev["method"], "browsingContext.load");
+    }
+}
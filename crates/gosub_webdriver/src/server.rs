@@ -0,0 +1,196 @@
+//! The listening socket and the per-connection loop.
+
+use crate::capture::FrameSource;
+use crate::session::Session;
+use crate::websocket::{self, Message};
+use gosub_engine::events::EngineEvent;
+use gosub_engine::html::RenderConfiguration;
+use gosub_engine::zone::Zone;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::timeout;
+
+/// How long a client gets to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A WebDriver BiDi endpoint on the loopback interface.
+///
+/// Connections are served one at a time: each gets its own session, and the contexts it created
+/// are closed when it disconnects. Clients connecting meanwhile complete their handshake right
+/// away and wait until the current session ends.
+pub struct BidiServer {
+    listener: TcpListener,
+}
+
+impl BidiServer {
+    /// Listens on `127.0.0.1:port`. Pass `0` to pick a free port, see [`BidiServer::local_addr`].
+    pub async fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await?;
+        Ok(Self { listener })
+    }
+
+    /// The address clients connect to (`ws://{addr}/session`).
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves clients until accepting fails.
+    ///
+    /// `events` must be subscribed to the engine the zone belongs to (see
+    /// `GosubEngine::subscribe_events`); `frames` supplies the frames screenshots are taken from,
+    /// usually the compositor the engine draws into.
+    ///
+    /// ```no_run,ignore
+    /// let server = BidiServer::bind(9222).await?;
+    /// let events = engine.subscribe_events();
+    /// server.serve(&mut zone, events, compositor.as_ref()).await?;
+    /// ```
+    pub async fn serve<C: RenderConfiguration>(
+        self,
+        zone: &mut Zone<C>,
+        mut events: broadcast::Receiver<EngineEvent>,
+        frames: &dyn FrameSource,
+    ) -> io::Result<()> {
+        // Accepting and handshaking run on their own tasks, so a client that stalls the handshake
+        // holds up neither new connections nor the session being served.
+        let (ready_tx, mut ready_rx) = mpsc::channel(8);
+        let acceptor = tokio::spawn(accept_connections(self.listener, ready_tx));
+
+        while let Some((stream, peer)) = ready_rx.recv().await {
+            // Events from before the connection are of no interest to the new session.
+            events = events.resubscribe();
+            let mut session = Session::new(zone, frames);
+            if let Err(e) = run_connection(stream, &mut session, &mut events).await {
+                log::warn!("BiDi connection with {peer} ended with an error: {e}");
+            }
+            session.close_all().await;
+            log::info!("BiDi client {peer} disconnected");
+        }
+
+        match acceptor.await {
+            Ok(e) => Err(e),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+}
+
+/// Accepts clients and hands those that complete the WebSocket handshake in time to `ready`.
+/// Returns the error that stopped accepting.
+async fn accept_connections(listener: TcpListener, ready: mpsc::Sender<(TcpStream, SocketAddr)>) -> io::Error {
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => return e,
+        };
+        let ready = ready.clone();
+        tokio::spawn(async move {
+            match timeout(HANDSHAKE_TIMEOUT, websocket::handshake(&mut stream)).await {
+                Ok(Ok(path)) => {
+                    log::info!("BiDi client {peer} connected on {path}");
+                    let _ = ready.send((stream, peer)).await;
+                }
+                Ok(Err(e)) => log::warn!("BiDi handshake with {peer} failed: {e}"),
+                Err(_) => log::warn!("BiDi handshake with {peer} timed out"),
+            }
+        });
+    }
+}
+
+async fn run_connection<C: RenderConfiguration>(
+    stream: TcpStream,
+    session: &mut Session<'_, C>,
+    events: &mut broadcast::Receiver<EngineEvent>,
+) -> io::Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+
+    // Reading runs on its own task so a half-received frame is never dropped by the `select!`
+    // below, which would desynchronize the stream.
+    let (message_tx, mut message_rx) = mpsc::channel(64);
+    let reader_task = tokio::spawn(async move {
+        let mut partial = None;
+        loop {
+            let message = websocket::read_message(&mut reader, &mut partial).await;
+            let done = !matches!(
+                message,
+                Ok(Message::Text(_) | Message::Binary(_) | Message::Ping(_) | Message::Pong(_))
+            );
+            if message_tx.send(message).await.is_err() || done {
+                break;
+            }
+        }
+    });
+
+    let result = loop {
+        tokio::select! {
+            message = message_rx.recv() => {
+                let replies = match message {
+                    Some(Ok(Message::Text(text))) => session.handle_text(&text).await,
+                    Some(Ok(Message::Ping(payload))) => {
+                        websocket::write_pong(&mut writer, &payload).await?;
+                        continue;
+                    }
+                    Some(Ok(Message::Binary(_) | Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close)) | None => break Ok(()),
+                    Some(Err(e)) => break Err(e),
+                };
+                for reply in replies {
+                    websocket::write_text(&mut writer, &reply).await?;
+                }
+                if session.ended() {
+                    break Ok(());
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("BiDi session missed {skipped} engine events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break Ok(()),
+                };
+                for message in session.handle_event(event) {
+                    websocket::write_text(&mut writer, &message).await?;
+                }
+            }
+        }
+    };
+
+    reader_task.abort();
+    // The peer may already be gone; closing is best effort.
+    let _ = websocket::write_close(&mut writer).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn stalled_handshake_does_not_block_other_clients() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (ready_tx, mut ready_rx) = mpsc::channel(8);
+        tokio::spawn(accept_connections(listener, ready_tx));
+
+        // Connects but never sends its upgrade request.
+        let _stalled = TcpStream::connect(addr).await.unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(
+                b"GET /session HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let ready = timeout(Duration::from_secs(2), ready_rx.recv()).await.unwrap();
+        let (_, peer) = ready.unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+    }
+}
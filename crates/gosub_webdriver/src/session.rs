@@ -0,0 +1,665 @@
+//! A BiDi session: command dispatch and the translation of engine events into BiDi events.
+//!
+//! Browsing contexts are tabs of the served [`Zone`]; the context id is the tab id. Only tabs
+//! created through `browsingContext.create` are visible to the client.
+
+use crate::capture::{capture_png, CaptureOrigin, FrameSource};
+use crate::input::{InputState, Tick};
+use crate::protocol::{self, opt_str_param, str_list_param, str_param, BidiError, BidiResult, Command, ErrorCode};
+use base64::Engine;
+use gosub_engine::events::{EngineEvent, NavigationEvent, ResourceEvent, TabCommand};
+use gosub_engine::html::RenderConfiguration;
+use gosub_engine::net::types::Initiator;
+use gosub_engine::tab::{TabDefaults, TabHandle};
+use gosub_engine::zone::Zone;
+use gosub_render_pipeline::render::Viewport;
+use gosub_shared::tab_id::TabId;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Viewport of contexts created by `browsingContext.create`.
+const DEFAULT_VIEWPORT: (u32, u32) = (1280, 800);
+
+/// Frame rate new contexts draw at, so screenshots have a frame to read.
+const DRAW_FPS: u16 = 30;
+
+/// Event modules the server can emit; `session.subscribe` accepts these or any event within them.
+const EVENT_MODULES: &[&str] = &["browsingContext", "network"];
+
+/// Session ids only need to be unique within this process.
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+struct Context {
+    tab: TabHandle,
+    url: String,
+    /// Id of the navigation in progress, as reported by `NavigationEvent::Started`.
+    navigation: Option<String>,
+    input: InputState,
+}
+
+/// A `browsingContext.navigate` waiting for its navigation to finish.
+struct PendingNavigation {
+    command_id: u64,
+    tab_id: TabId,
+}
+
+pub(crate) struct Session<'a, C: RenderConfiguration> {
+    zone: &'a mut Zone<C>,
+    frames: &'a dyn FrameSource,
+    session_id: Option<String>,
+    contexts: HashMap<TabId, Context>,
+    /// Creation order of `contexts`, so `getTree` is stable.
+    order: Vec<TabId>,
+    subscriptions: HashMap<String, Vec<String>>,
+    next_subscription: u64,
+    pending: Vec<PendingNavigation>,
+    /// HTTP status per request, remembered from `Headers` for `responseCompleted`.
+    statuses: HashMap<String, u16>,
+    ended: bool,
+}
+
+impl<'a, C: RenderConfiguration> Session<'a, C> {
+    pub(crate) fn new(zone: &'a mut Zone<C>, frames: &'a dyn FrameSource) -> Self {
+        Self {
+            zone,
+            frames,
+            session_id: None,
+            contexts: HashMap::new(),
+            order: Vec::new(),
+            subscriptions: HashMap::new(),
+            next_subscription: 1,
+            pending: Vec::new(),
+            statuses: HashMap::new(),
+            ended: false,
+        }
+    }
+
+    /// True once `session.end` has been answered; the connection should close.
+    pub(crate) fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Handles one text message and returns the messages to send back.
+    pub(crate) async fn handle_text(&mut self, text: &str) -> Vec<String> {
+        let command = match Command::parse(text) {
+            Ok(command) => command,
+            Err((id, err)) => return vec![protocol::error(id, &err)],
+        };
+
+        let mut out = Vec::new();
+        match self.dispatch(&command, &mut out).await {
+            Ok(Some(result)) => out.insert(0, protocol::success(command.id, result)),
+            // Answered later, when the navigation finishes.
+            Ok(None) => {}
+            Err(err) => out.insert(0, protocol::error(Some(command.id), &err)),
+        }
+        out
+    }
+
+    /// Translates an engine event, returning the BiDi events and deferred responses it produces.
+    pub(crate) fn handle_event(&mut self, event: EngineEvent) -> Vec<String> {
+        let mut out = Vec::new();
+        match event {
+            EngineEvent::Navigation { tab_id, event } => self.navigation_event(tab_id, event, &mut out),
+            EngineEvent::Resource { tab_id, event } => self.resource_event(tab_id, event, &mut out),
+            EngineEvent::LocationChanged { tab_id, url } => {
+                if let Some(context) = self.contexts.get_mut(&tab_id) {
+                    context.url = url;
+                }
+            }
+            EngineEvent::TabClosed { tab_id, .. } => self.forget(tab_id, &mut out),
+            _ => {}
+        }
+        out
+    }
+
+    /// Closes every context this session created.
+    pub(crate) async fn close_all(&mut self) {
+        for tab_id in std::mem::take(&mut self.order) {
+            self.zone.close_tab(tab_id).await;
+        }
+        self.contexts.clear();
+        self.pending.clear();
+    }
+
+    async fn dispatch(&mut self, command: &Command, out: &mut Vec<String>) -> BidiResult<Option<Value>> {
+        let params = &command.params;
+        match command.method.as_str() {
+            "session.status" => {
+                return Ok(Some(json!({
+                    "ready": self.session_id.is_none(),
+                    "message": if self.session_id.is_none() { "ready" } else { "session already started" },
+                })))
+            }
+            "session.new" => return self.new_session().map(Some),
+            _ if self.session_id.is_none() => {
+                return Err(BidiError::new(
+                    ErrorCode::InvalidSessionId,
+                    "no session has been started",
+                ))
+            }
+            _ => {}
+        }
+
+        match command.method.as_str() {
+            "session.end" => {
+                self.close_all().await;
+                self.ended = true;
+                Ok(Some(json!({})))
+            }
+            "session.subscribe" => self.subscribe(params).map(Some),
+            "session.unsubscribe" => self.unsubscribe(params).map(|()| Some(json!({}))),
+            "browsingContext.create" => self.create_context(params, out).await.map(Some),
+            "browsingContext.getTree" => Ok(Some(self.tree())),
+            "browsingContext.close" => {
+                let tab_id = self.context_param(params)?;
+                self.zone.close_tab(tab_id).await;
+                self.forget(tab_id, out);
+                Ok(Some(json!({})))
+            }
+            "browsingContext.navigate" => self.navigate(command.id, params).await,
+            "browsingContext.captureScreenshot" => self.screenshot(params).map(Some),
+            "input.performActions" => self.perform_actions(params).await.map(|()| Some(json!({}))),
+            "input.releaseActions" => {
+                let tab_id = self.context_param(params)?;
+                let commands = self.context_mut(tab_id)?.input.release();
+                self.send_all(tab_id, commands).await?;
+                Ok(Some(json!({})))
+            }
+            "script.evaluate" | "script.callFunction" => Err(BidiError::new(
+                ErrorCode::UnsupportedOperation,
+                "script evaluation is not available in this engine build",
+            )),
+            method => Err(BidiError::new(
+                ErrorCode::UnknownCommand,
+                format!("unknown command '{method}'"),
+            )),
+        }
+    }
+
+    fn new_session(&mut self) -> BidiResult<Value> {
+        if self.session_id.is_some() {
+            return Err(BidiError::new(
+                ErrorCode::SessionNotCreated,
+                "only one session per connection is supported",
+            ));
+        }
+        let session_id = format!("gosub-{}", NEXT_SESSION.fetch_add(1, Ordering::Relaxed));
+        self.session_id = Some(session_id.clone());
+        Ok(json!({
+            "sessionId": session_id,
+            "capabilities": {
+                "acceptInsecureCerts": false,
+                "browserName": "gosub",
+                "browserVersion": env!("CARGO_PKG_VERSION"),
+                "platformName": std::env::consts::OS,
+                "setWindowRect": false,
+                "userAgent": "",
+            },
+        }))
+    }
+
+    fn subscribe(&mut self, params: &Value) -> BidiResult<Value> {
+        let events = str_list_param(params, "events")?;
+        if params.get("contexts").is_some() {
+            return Err(BidiError::new(
+                ErrorCode::UnsupportedOperation,
+                "per-context subscriptions are not supported",
+            ));
+        }
+        if let Some(unknown) = events
+            .iter()
+            .find(|name| !EVENT_MODULES.contains(&name.split('.').next().unwrap_or("")))
+        {
+            return Err(BidiError::invalid_argument(format!("unknown event '{unknown}'")));
+        }
+
+        let id = self.next_subscription.to_string();
+        self.next_subscription += 1;
+        self.subscriptions.insert(id.clone(), events);
+        Ok(json!({ "subscription": id }))
+    }
+
+    fn unsubscribe(&mut self, params: &Value) -> BidiResult<()> {
+        if params.get("subscriptions").is_some() {
+            let ids = str_list_param(params, "subscriptions")?;
+            if let Some(unknown) = ids.iter().find(|id| !self.subscriptions.contains_key(*id)) {
+                return Err(BidiError::invalid_argument(format!("unknown subscription '{unknown}'")));
+            }
+            for id in ids {
+                self.subscriptions.remove(&id);
+            }
+            return Ok(());
+        }
+
+        let events = str_list_param(params, "events")?;
+        for list in self.subscriptions.values_mut() {
+            list.retain(|name| !events.contains(name));
+        }
+        self.subscriptions.retain(|_, list| !list.is_empty());
+        Ok(())
+    }
+
+    /// True when a subscription covers `method`, either by name or by its module.
+    fn subscribed(&self, method: &str) -> bool {
+        let module = method.split('.').next().unwrap_or(method);
+        self.subscriptions
+            .values()
+            .flatten()
+            .any(|name| name == method || name == module)
+    }
+
+    fn emit(&self, out: &mut Vec<String>, method: &str, params: Value) {
+        if self.subscribed(method) {
+            out.push(protocol::event(method, params));
+        }
+    }
+
+    async fn create_context(&mut self, params: &Value, out: &mut Vec<String>) -> BidiResult<Value> {
+        match str_param(params, "type")? {
+            "tab" | "window" => {}
+            other => return Err(BidiError::invalid_argument(format!("unknown context type '{other}'"))),
+        }
+
+        let (width, height) = DEFAULT_VIEWPORT;
+        let defaults = TabDefaults {
+            url: None,
+            title: None,
+            viewport: Some(Viewport::new(0, 0, width, height)),
+        };
+        let tab = self
+            .zone
+            .create_tab(defaults, None)
+            .await
+            .map_err(|e| BidiError::new(ErrorCode::UnknownError, format!("cannot create tab: {e}")))?;
+        let tab_id = tab.tab_id;
+        tab.send(TabCommand::ResumeDrawing { fps: DRAW_FPS })
+            .await
+            .map_err(unknown_error)?;
+
+        self.contexts.insert(
+            tab_id,
+            Context {
+                tab,
+                url: "about:blank".to_string(),
+                navigation: None,
+                input: InputState::default(),
+            },
+        );
+        self.order.push(tab_id);
+        self.emit(
+            out,
+            "browsingContext.contextCreated",
+            context_info(tab_id, "about:blank"),
+        );
+        Ok(json!({ "context": tab_id.to_string() }))
+    }
+
+    fn tree(&self) -> Value {
+        let contexts: Vec<Value> = self
+            .order
+            .iter()
+            .filter_map(|tab_id| {
+                let context = self.contexts.get(tab_id)?;
+                Some(context_info(*tab_id, &context.url))
+            })
+            .collect();
+        json!({ "contexts": contexts })
+    }
+
+    async fn navigate(&mut self, command_id: u64, params: &Value) -> BidiResult<Option<Value>> {
+        let tab_id = self.context_param(params)?;
+        let url = str_param(params, "url")?;
+        url::Url::parse(url).map_err(|e| BidiError::invalid_argument(format!("invalid url '{url}': {e}")))?;
+        let wait = opt_str_param(params, "wait")?.unwrap_or("none");
+        if !matches!(wait, "none" | "interactive" | "complete") {
+            return Err(BidiError::invalid_argument(format!("unknown wait condition '{wait}'")));
+        }
+
+        let context = self.context_mut(tab_id)?;
+        context.navigation = None;
+        context.tab.navigate(url).await.map_err(unknown_error)?;
+
+        if wait == "none" {
+            return Ok(Some(json!({ "navigation": Value::Null, "url": url })));
+        }
+        // The engine reports the main document as one milestone, so `interactive` and `complete`
+        // both resolve on `NavigationEvent::Finished`.
+        self.pending.push(PendingNavigation { command_id, tab_id });
+        Ok(None)
+    }
+
+    fn screenshot(&self, params: &Value) -> BidiResult<Value> {
+        let tab_id = self.context_param(params)?;
+        self.context(tab_id)?;
+        let origin = match opt_str_param(params, "origin")?.unwrap_or("viewport") {
+            "viewport" => CaptureOrigin::Viewport,
+            "document" => CaptureOrigin::Document,
+            other => return Err(BidiError::invalid_argument(format!("unknown origin '{other}'"))),
+        };
+        if let Some(format) = params.pointer("/format/type").and_then(Value::as_str) {
+            if format != "image/png" {
+                return Err(BidiError::new(
+                    ErrorCode::UnsupportedOperation,
+                    format!("screenshot format '{format}' is not supported"),
+                ));
+            }
+        }
+        if params.get("clip").is_some() {
+            return Err(BidiError::new(
+                ErrorCode::UnsupportedOperation,
+                "screenshot clips are not supported",
+            ));
+        }
+
+        let frame = self.frames.frame(tab_id).ok_or_else(|| {
+            BidiError::new(
+                ErrorCode::UnableToCaptureScreen,
+                "the context has not rendered a frame yet",
+            )
+        })?;
+        let png =
+            capture_png(&frame, origin).map_err(|e| BidiError::new(ErrorCode::UnableToCaptureScreen, e.to_string()))?;
+        Ok(json!({ "data": base64::engine::general_purpose::STANDARD.encode(png) }))
+    }
+
+    async fn perform_actions(&mut self, params: &Value) -> BidiResult<()> {
+        let tab_id = self.context_param(params)?;
+        let ticks = self.context_mut(tab_id)?.input.plan(params)?;
+        for Tick { commands, duration } in ticks {
+            self.send_all(tab_id, commands).await?;
+            if !duration.is_zero() {
+                tokio::time::sleep(duration).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_all(&self, tab_id: TabId, commands: Vec<TabCommand>) -> BidiResult<()> {
+        let context = self.context(tab_id)?;
+        for command in commands {
+            context.tab.send(command).await.map_err(unknown_error)?;
+        }
+        Ok(())
+    }
+
+    fn navigation_event(&mut self, tab_id: TabId, event: NavigationEvent, out: &mut Vec<String>) {
+        let Some(context) = self.contexts.get_mut(&tab_id) else {
+            return;
+        };
+
+        match event {
+            NavigationEvent::Started { nav_id, url } => {
+                let navigation = nav_id.to_string();
+                context.navigation = Some(navigation.clone());
+                let params = navigation_info(tab_id, Some(&navigation), url.as_str());
+                self.emit(out, "browsingContext.navigationStarted", params);
+            }
+            NavigationEvent::Committed { url, .. } => context.url = url.to_string(),
+            NavigationEvent::Finished { nav_id, url } => {
+                let navigation = nav_id.to_string();
+                context.url = url.to_string();
+                context.navigation = None;
+                let params = navigation_info(tab_id, Some(&navigation), url.as_str());
+                self.emit(out, "browsingContext.domContentLoaded", params.clone());
+                self.emit(out, "browsingContext.load", params);
+
+                let result = json!({ "navigation": navigation, "url": url.as_str() });
+                for pending in self.take_pending(tab_id) {
+                    out.push(protocol::success(pending.command_id, result.clone()));
+                }
+            }
+            NavigationEvent::Failed { nav_id, url, error } => {
+                let navigation = nav_id.map(|id| id.to_string()).or(context.navigation.take());
+                self.navigation_failed(tab_id, navigation, url.as_str(), &error.to_string(), out);
+            }
+            NavigationEvent::FailedUrl { nav_id, url, error } => {
+                let navigation = nav_id.map(|id| id.to_string()).or(context.navigation.take());
+                self.navigation_failed(tab_id, navigation, &url, &error.to_string(), out);
+            }
+            NavigationEvent::Cancelled { nav_id, url, reason } => {
+                context.navigation = None;
+                self.navigation_failed(tab_id, Some(nav_id.to_string()), url.as_str(), &reason.to_string(), out);
+            }
+            NavigationEvent::Progress { .. } | NavigationEvent::DecisionRequired { .. } => {}
+        }
+    }
+
+    fn navigation_failed(
+        &mut self,
+        tab_id: TabId,
+        navigation: Option<String>,
+        url: &str,
+        message: &str,
+        out: &mut Vec<String>,
+    ) {
+        self.emit(
+            out,
+            "browsingContext.navigationFailed",
+            navigation_info(tab_id, navigation.as_deref(), url),
+        );
+        let err = BidiError::new(
+            ErrorCode::UnknownError,
+            format!("navigation to {url} failed: {message}"),
+        );
+        for pending in self.take_pending(tab_id) {
+            out.push(protocol::error(Some(pending.command_id), &err));
+        }
+    }
+
+    fn resource_event(&mut self, tab_id: TabId, event: ResourceEvent, out: &mut Vec<String>) {
+        let Some(context) = self.contexts.get(&tab_id) else {
+            return;
+        };
+        let navigation = context.navigation.clone();
+        let base = |request: &str, url: &str| {
+            json!({
+                "context": tab_id.to_string(),
+                "navigation": navigation,
+                "redirectCount": 0,
+                "request": request_data(request, url),
+                "timestamp": protocol::timestamp(),
+                "isBlocked": false,
+            })
+        };
+
+        match event {
+            ResourceEvent::Started {
+                request_id,
+                url,
+                initiator,
+                ..
+            } => {
+                let mut params = base(&format!("{request_id:?}"), &url);
+                params["initiator"] = json!({ "type": initiator_type(initiator) });
+                self.emit(out, "network.beforeRequestSent", params);
+            }
+            ResourceEvent::Headers {
+                request_id,
+                url,
+                status,
+                content_type,
+                headers,
+                ..
+            } => {
+                let request = format!("{request_id:?}");
+                let mut params = base(&request, &url);
+                params["response"] = response_data(&url, status, content_type.as_deref(), &headers);
+                self.statuses.insert(request, status);
+                self.emit(out, "network.responseStarted", params);
+            }
+            ResourceEvent::Finished {
+                request_id,
+                url,
+                received_bytes,
+                ..
+            } => {
+                let request = format!("{request_id:?}");
+                let status = self.statuses.remove(&request).unwrap_or(200);
+                let mut params = base(&request, url.as_str());
+                params["response"] = response_data(url.as_str(), status, None, &[]);
+                params["response"]["bodySize"] = json!(received_bytes);
+                self.emit(out, "network.responseCompleted", params);
+            }
+            ResourceEvent::Failed {
+                request_id, url, error, ..
+            } => {
+                let request = format!("{request_id:?}");
+                self.statuses.remove(&request);
+                let mut params = base(&request, &url);
+                params["errorText"] = json!(error.to_string());
+                self.emit(out, "network.fetchError", params);
+            }
+            ResourceEvent::Cancelled {
+                request_id,
+                url,
+                reason,
+                ..
+            } => {
+                let request = format!("{request_id:?}");
+                self.statuses.remove(&request);
+                let mut params = base(&request, &url);
+                params["errorText"] = json!(reason.to_string());
+                self.emit(out, "network.fetchError", params);
+            }
+            ResourceEvent::Queued { .. } | ResourceEvent::Redirected { .. } | ResourceEvent::Progress { .. } => {}
+        }
+    }
+
+    fn take_pending(&mut self, tab_id: TabId) -> Vec<PendingNavigation> {
+        let (done, rest) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.tab_id == tab_id);
+        self.pending = rest;
+        done
+    }
+
+    /// Drops a closed context from the session, failing navigations still waiting on it.
+    fn forget(&mut self, tab_id: TabId, out: &mut Vec<String>) {
+        let Some(context) = self.contexts.remove(&tab_id) else {
+            return;
+        };
+        self.order.retain(|id| *id != tab_id);
+        let err = BidiError::new(ErrorCode::NoSuchFrame, "the browsing context was closed");
+        for pending in self.take_pending(tab_id) {
+            out.push(protocol::error(Some(pending.command_id), &err));
+        }
+        self.emit(
+            out,
+            "browsingContext.contextDestroyed",
+            context_info(tab_id, &context.url),
+        );
+    }
+
+    fn context_param(&self, params: &Value) -> BidiResult<TabId> {
+        let id = str_param(params, "context")?;
+        self.contexts
+            .keys()
+            .find(|tab_id| tab_id.to_string() == id)
+            .copied()
+            .ok_or_else(|| BidiError::new(ErrorCode::NoSuchFrame, format!("no browsing context '{id}'")))
+    }
+
+    fn context(&self, tab_id: TabId) -> BidiResult<&Context> {
+        self.contexts
+            .get(&tab_id)
+            .ok_or_else(|| BidiError::new(ErrorCode::NoSuchFrame, format!("no browsing context '{tab_id}'")))
+    }
+
+    fn context_mut(&mut self, tab_id: TabId) -> BidiResult<&mut Context> {
+        self.contexts
+            .get_mut(&tab_id)
+            .ok_or_else(|| BidiError::new(ErrorCode::NoSuchFrame, format!("no browsing context '{tab_id}'")))
+    }
+}
+
+fn unknown_error(error: impl std::fmt::Display) -> BidiError {
+    BidiError::new(ErrorCode::UnknownError, error.to_string())
+}
+
+fn context_info(tab_id: TabId, url: &str) -> Value {
+    json!({
+        "context": tab_id.to_string(),
+        "url": url,
+        "children": [],
+        "parent": Value::Null,
+        "userContext": "default",
+    })
+}
+
+fn navigation_info(tab_id: TabId, navigation: Option<&str>, url: &str) -> Value {
+    json!({
+        "context": tab_id.to_string(),
+        "navigation": navigation,
+        "timestamp": protocol::timestamp(),
+        "url": url,
+    })
+}
+
+fn request_data(request: &str, url: &str) -> Value {
+    json!({
+        "request": request,
+        "url": url,
+        "method": "GET",
+        "headers": [],
+        "cookies": [],
+        "headersSize": 0,
+        "bodySize": Value::Null,
+    })
+}
+
+fn response_data(url: &str, status: u16, content_type: Option<&str>, headers: &[(String, String)]) -> Value {
+    let headers: Vec<Value> = headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": { "type": "string", "value": value } }))
+        .collect();
+    json!({
+        "url": url,
+        "status": status,
+        "statusText": "",
+        "fromCache": false,
+        "headers": headers,
+        "mimeType": content_type.unwrap_or(""),
+        "bytesReceived": 0,
+        "headersSize": Value::Null,
+        "bodySize": Value::Null,
+    })
+}
+
+fn initiator_type(initiator: Initiator) -> &'static str {
+    match initiator {
+        Initiator::Parser | Initiator::CSS => "parser",
+        Initiator::Script => "script",
+        Initiator::Navigation | Initiator::Other => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_headers_use_bidi_string_values() {
+        let response = response_data(
+            "https://example.com/",
+            404,
+            Some("text/html"),
+            &[("content-type".into(), "text/html".into())],
+        );
+        assert_eq!(response["status"], 404);
+        assert_eq!(response["mimeType"], "text/html");
+        assert_eq!(
+            response["headers"][0],
+            json!({"name": "content-type", "value": {"type": "string", "value": "text/html"}})
+        );
+    }
+
+    #[test]
+    fn initiators_map_to_bidi_types() {
+        assert_eq!(initiator_type(Initiator::Parser), "parser");
+        assert_eq!(initiator_type(Initiator::CSS), "parser");
+        assert_eq!(initiator_type(Initiator::Script), "script");
+        assert_eq!(initiator_type(Initiator::Navigation), "other");
+    }
+}
@@ -0,0 +1,336 @@
+//! Minimal RFC 6455 WebSocket server side: the HTTP upgrade handshake and a frame codec.
+//!
+//! Only what a BiDi endpoint needs: text messages (fragmented or not), ping/pong and close.
+//! Client frames must be masked; server frames are never masked or fragmented.
+
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// GUID appended to the client key to derive `Sec-WebSocket-Accept` (RFC 6455 §1.3).
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Upper bound on the upgrade request, so a client cannot make us buffer without limit.
+const MAX_HANDSHAKE_LEN: usize = 16 * 1024;
+
+/// Upper bound on a single (reassembled) message.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// A complete message received from the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+/// Computes the `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut sha = Sha1::new();
+    sha.update(key.trim().as_bytes());
+    sha.update(ACCEPT_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(sha.finalize())
+}
+
+/// Reads the HTTP upgrade request from `stream` and answers it with `101 Switching Protocols`.
+/// Returns the request path. Requests that are not WebSocket upgrades get a `400` and an error.
+///
+/// Upgrades carrying an `Origin` header are refused with a `403`: automation clients do not send
+/// one, browsers always do, so this keeps web pages from driving the engine through localhost.
+pub async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S) -> io::Result<String> {
+    let mut request = Vec::new();
+    let mut byte = [0u8; 1];
+    while !request.ends_with(b"\r\n\r\n") {
+        if request.len() >= MAX_HANDSHAKE_LEN {
+            return Err(invalid("upgrade request too large"));
+        }
+        stream.read_exact(&mut byte).await?;
+        request.push(byte[0]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut lines = request.split("\r\n");
+    let path = lines
+        .next()
+        .and_then(|line| {
+            let mut parts = line.split(' ');
+            match (parts.next(), parts.next()) {
+                (Some("GET"), Some(path)) => Some(path.to_string()),
+                _ => None,
+            }
+        })
+        .ok_or_else(|| invalid("expected a GET request"))?;
+
+    let mut key = None;
+    let mut upgrade = false;
+    let mut origin = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("upgrade") {
+            upgrade = value.eq_ignore_ascii_case("websocket");
+        } else if name.eq_ignore_ascii_case("origin") {
+            origin = true;
+        }
+    }
+
+    let Some(key) = key.filter(|_| upgrade) else {
+        stream
+            .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await?;
+        return Err(invalid("not a WebSocket upgrade request"));
+    };
+
+    if origin {
+        stream
+            .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await?;
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "cross-origin upgrade refused",
+        ));
+    }
+
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(path)
+}
+
+/// Reads the next complete message, reassembling fragmented text/binary messages.
+/// Control frames may arrive between fragments and are returned as they come.
+pub async fn read_message<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    partial: &mut Option<(u8, Vec<u8>)>,
+) -> io::Result<Message> {
+    loop {
+        let (fin, opcode, payload) = read_frame(reader).await?;
+        match opcode {
+            OP_CLOSE => return Ok(Message::Close),
+            OP_PING => return Ok(Message::Ping(payload)),
+            OP_PONG => return Ok(Message::Pong(payload)),
+            OP_TEXT | OP_BINARY => {
+                if partial.is_some() {
+                    return Err(invalid("new message before the previous one finished"));
+                }
+                if fin {
+                    return data_message(opcode, payload);
+                }
+                *partial = Some((opcode, payload));
+            }
+            OP_CONTINUATION => {
+                let Some((first_opcode, mut data)) = partial.take() else {
+                    return Err(invalid("continuation frame without a message"));
+                };
+                if data.len() + payload.len() > MAX_MESSAGE_LEN {
+                    return Err(invalid("message too large"));
+                }
+                data.extend_from_slice(&payload);
+                if fin {
+                    return data_message(first_opcode, data);
+                }
+                *partial = Some((first_opcode, data));
+            }
+            _ => return Err(invalid("unknown opcode")),
+        }
+    }
+}
+
+/// Writes a text message as a single unmasked frame.
+pub async fn write_text<W: AsyncWrite + Unpin>(writer: &mut W, text: &str) -> io::Result<()> {
+    writer.write_all(&encode_frame(OP_TEXT, text.as_bytes())).await?;
+    writer.flush().await
+}
+
+/// Answers a ping with a pong carrying the same payload.
+pub async fn write_pong<W: AsyncWrite + Unpin>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    writer.write_all(&encode_frame(OP_PONG, payload)).await?;
+    writer.flush().await
+}
+
+/// Sends a close frame (normal closure).
+pub async fn write_close<W: AsyncWrite + Unpin>(writer: &mut W) -> io::Result<()> {
+    writer
+        .write_all(&encode_frame(OP_CLOSE, &1000u16.to_be_bytes()))
+        .await?;
+    writer.flush().await
+}
+
+fn data_message(opcode: u8, payload: Vec<u8>) -> io::Result<Message> {
+    if opcode == OP_TEXT {
+        String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| invalid("text message is not UTF-8"))
+    } else {
+        Ok(Message::Binary(payload))
+    }
+}
+
+/// Reads one frame and unmasks its payload. Returns `(fin, opcode, payload)`.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    if head[1] & 0x80 == 0 {
+        return Err(invalid("client frames must be masked"));
+    }
+
+    let len = match head[1] & 0x7F {
+        126 => u64::from(reader.read_u16().await?),
+        127 => reader.read_u64().await?,
+        n => u64::from(n),
+    };
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= MAX_MESSAGE_LEN)
+        .ok_or_else(|| invalid("frame too large"))?;
+    if opcode >= OP_CLOSE && (len > 125 || !fin) {
+        return Err(invalid("invalid control frame"));
+    }
+
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    for (i, b) in payload.iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a masked client frame.
+    fn client_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![if fin { 0x80 | opcode } else { opcode }];
+        assert!(payload.len() < 126);
+        frame.push(0x80 | payload.len() as u8);
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGJRsjxOs+xOo=");
+    }
+
+    #[test]
+    fn server_frames_are_unmasked() {
+        assert_eq!(encode_frame(OP_TEXT, b"Hello"), b"\x81\x05Hello");
+        let long = encode_frame(OP_TEXT, &[b'a'; 300]);
+        assert_eq!(&long[..4], &[0x81, 126, 0x01, 0x2c]);
+        assert_eq!(long.len(), 304);
+    }
+
+    #[tokio::test]
+    async fn reads_masked_and_fragmented_messages() {
+        let mut input = client_frame(true, OP_TEXT, b"Hello");
+        input.extend(client_frame(false, OP_TEXT, b"Hel"));
+        input.extend(client_frame(true, OP_PING, b"p"));
+        input.extend(client_frame(true, OP_CONTINUATION, b"lo"));
+        input.extend(client_frame(true, OP_CLOSE, b""));
+
+        let mut reader = input.as_slice();
+        let mut partial = None;
+        let mut messages = Vec::new();
+        for _ in 0..4 {
+            messages.push(read_message(&mut reader, &mut partial).await.unwrap());
+        }
+        assert_eq!(
+            messages,
+            vec![
+                Message::Text("Hello".into()),
+                Message::Ping(b"p".to_vec()),
+                Message::Text("Hello".into()),
+                Message::Close,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unmasked_client_frames() {
+        let mut reader: &[u8] = b"\x81\x05Hello";
+        assert!(read_message(&mut reader, &mut None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn handshake_answers_upgrade() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        client
+            .write_all(
+                b"GET /session HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(handshake(&mut server).await.unwrap(), "/session");
+
+        let mut response = vec![0u8; 256];
+        let n = client.read(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response[..n]);
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGJRsjxOs+xOo="));
+    }
+
+    #[tokio::test]
+    async fn handshake_refuses_browser_origins() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        client
+            .write_all(
+                b"GET /session HTTP/1.1\r\nUpgrade: websocket\r\nOrigin: https://example.com\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        assert!(handshake(&mut server).await.is_err());
+        let mut response = vec![0u8; 64];
+        let n = client.read(&mut response).await.unwrap();
+        assert!(response[..n].starts_with(b"HTTP/1.1 403"));
+    }
+}
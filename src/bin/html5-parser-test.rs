@@ -1,10 +1,15 @@
 use gosub_css3::system::Css3System;
+use gosub_html5::document::builder::DocumentBuilderImpl;
 use gosub_html5::document::document_impl::DocumentImpl;
+use gosub_html5::parser::errors::render_diagnostic;
 use gosub_html5::parser::Html5Parser;
 use gosub_html5::testing::tree_construction::fixture::{fixture_root_path, read_fixture_from_path};
+use gosub_html5::testing::tree_construction::result::ResultStatus;
 use gosub_html5::testing::tree_construction::Harness;
 use gosub_interface::config::ModuleConfiguration;
-use gosub_shared::types::Result;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+use gosub_shared::types::{Result, Severity};
+use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -18,11 +23,18 @@ impl ModuleConfiguration for Config {
     type HtmlParser = Html5Parser<'static, Self>;
 }
 fn main() -> Result<()> {
+    // With a file argument, parse that file and print its parse errors instead
+    if let Some(path) = std::env::args().nth(1) {
+        return print_diagnostics(&path);
+    }
+
     let mut files = get_files_from_path(fixture_root_path());
     files.sort();
 
     let mut total = 0;
     let mut failed = 0;
+    let mut expected_errors = 0;
+    let mut found_errors = 0;
 
     for file in &files {
         // if file != "math.dat" {
@@ -33,7 +45,7 @@ fn main() -> Result<()> {
         print!("Test: ({:3}) {} [", fixture.tests.len(), file);
         let _ = std::io::stdout().flush();
 
-        let mut harness = Harness::new();
+        let mut harness = Harness::with_error_checks();
 
         // Run tests
         for test in &fixture.tests {
@@ -42,7 +54,15 @@ fn main() -> Result<()> {
 
                 total += 1;
 
-                if result.is_success() {
+                expected_errors += result.error_results.len();
+                found_errors += result
+                    .error_results
+                    .iter()
+                    .filter(|r| r.result == ResultStatus::Success)
+                    .count();
+
+                // Error expectations are reported separately below
+                if result.tree_results.iter().all(|r| r.result == ResultStatus::Success) {
                     print!(".");
                 } else {
                     print!("X");
@@ -61,6 +81,34 @@ fn main() -> Result<()> {
         total,
         (total - failed) as f32 / total as f32 * 100_f32
     );
+    println!(
+        "Parse errors: {}/{} ({:.2}%) of the expected errors reported at the expected position.",
+        found_errors,
+        expected_errors,
+        found_errors as f32 / expected_errors.max(1) as f32 * 100_f32
+    );
+
+    Ok(())
+}
+
+/// Parses the HTML file at `path` and prints every parse error with the source line it occurs on
+fn print_diagnostics(path: &str) -> Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut stream = ByteStream::from_str(&source, Encoding::UTF8);
+    let mut document = DocumentBuilderImpl::new_document::<Config>(None);
+    let errors = Html5Parser::<Config>::parse_document(&mut stream, &mut document, None)?;
+
+    for error in &errors {
+        println!("{}", render_diagnostic(error, &source, path));
+    }
+
+    let warnings = errors.iter().filter(|e| e.severity == Severity::Warning).count();
+    println!(
+        "{}: {} error(s), {} warning(s)",
+        path,
+        errors.len() - warnings,
+        warnings
+    );
 
     Ok(())
 }